@@ -0,0 +1,35 @@
+// Helpers shared by the examples: signing calls and running blocks with annotated
+// output
+
+use code_challenge_1::labels::LabelRegistry;
+use code_challenge_1::{Block, Extrinsic, Header, Runtime, RuntimeCall, RuntimeError};
+
+// Sign `call` as `who` with the account's next nonce
+pub fn signed(runtime: &Runtime, who: u64, call: RuntimeCall) -> Extrinsic {
+    Extrinsic {
+        caller: who,
+        nonce: runtime.system.get_nonce(who),
+        call,
+    }
+}
+
+// Execute the next block and print what happened in it
+pub fn run_block(
+    runtime: &mut Runtime,
+    labels: &LabelRegistry<u64>,
+    extrinsics: Vec<Extrinsic>,
+) -> Result<(), RuntimeError> {
+    let number = runtime.system.block_number() + 1;
+    let results = runtime.execute_block(Block {
+        header: Header { number },
+        extrinsics,
+    })?;
+    for error in results.into_iter().filter_map(Result::err) {
+        let name = RuntimeError::lookup(error.code()).map_or("unknown", |info| info.name);
+        println!("  block {}: extrinsic failed with {}", number, name);
+    }
+    for event in runtime.system.events() {
+        println!("  block {}: {}", number, event.describe(labels));
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+// A proposal from creation to enactment: stakers vote on a proposal carrying a call,
+// the end-of-block hook finalizes it once the voting period is over, and the call is
+// enacted with a Council origin ENACTMENT_DELAY blocks later.
+//
+// Run with `cargo run --example governance_lifecycle`.
+
+mod common;
+
+use code_challenge_1::governance::{self, Conviction};
+use code_challenge_1::labels::LabelRegistry;
+use code_challenge_1::{
+    faucet, payment, staking, GovernanceConfig, Runtime, RuntimeCall, RuntimeError, TreasuryConfig,
+};
+use common::{run_block, signed};
+
+fn main() -> Result<(), RuntimeError> {
+    let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+    let mut labels = LabelRegistry::new();
+    labels.register(alice, "alice");
+    labels.register(bob, "bob");
+    labels.register(charlie, "charlie");
+    labels.register(Runtime::POT_ACCOUNT, "the treasury");
+
+    let mut runtime = Runtime::new();
+    for who in [alice, bob, charlie] {
+        runtime.balances.set_balance(who, 1000)?;
+    }
+
+    println!("Voting power comes from stake, so everyone bonds some first");
+    let stakes = [(alice, 500), (bob, 300), (charlie, 200)];
+    let extrinsics = stakes
+        .iter()
+        .map(|(who, amount)| {
+            signed(
+                &runtime,
+                *who,
+                staking::Call::Stake { amount: *amount }.into(),
+            )
+        })
+        .collect();
+    run_block(&mut runtime, &labels, extrinsics)?;
+
+    println!("alice proposes making faucet claims free");
+    let claim: RuntimeCall = faucet::Call::Claim.into();
+    let discount: RuntimeCall = payment::Call::SetFeeDiscount {
+        call: claim.call_index(),
+        discount: payment::FULL_DISCOUNT,
+    }
+    .into();
+    let propose = governance::Call::CreateProposalWithCall {
+        description: "Free faucet claims".to_string(),
+        call: Box::new(discount),
+    };
+    let extrinsic = signed(&runtime, alice, propose.into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+
+    println!("alice and bob vote aye, charlie nay; bob's second vote is rejected");
+    let vote = |vote_type| -> RuntimeCall {
+        governance::Call::Vote {
+            proposal_id: 0,
+            vote_type,
+            conviction: Conviction::None,
+        }
+        .into()
+    };
+    let extrinsics = vec![
+        signed(&runtime, alice, vote(true)),
+        signed(&runtime, bob, vote(true)),
+        signed(&runtime, charlie, vote(false)),
+    ];
+    run_block(&mut runtime, &labels, extrinsics)?;
+    let extrinsic = signed(&runtime, bob, vote(true));
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+
+    println!("Blocks go by until the voting period ends and the call is enacted");
+    let blocks = Runtime::VOTING_PERIOD + Runtime::ENACTMENT_DELAY;
+    for _ in 0..blocks {
+        run_block(&mut runtime, &labels, Vec::new())?;
+    }
+    println!(
+        "Proposal 0 is {:?}; claims now pay a {} ppm discount",
+        runtime.governance.get_proposal_status(0),
+        runtime.payment.fee_discount(claim.call_index())
+    );
+
+    println!("charlie's faucet claim pays no fee");
+    let extrinsic = signed(&runtime, charlie, claim);
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+    println!(
+        "alice's participation score: {:?} ppm",
+        runtime.governance.participation_score(&alice)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+// One staking era end to end: candidates bond, a nominator backs one of them, the
+// fallback election picks the MAX_VALIDATORS largest stakers and the era reward is
+// paid out. An offence is then previewed with `simulate_slash` before it is reported,
+// and the slashed validator unbonds what is left.
+//
+// Run with `cargo run --example staking_era`.
+
+mod common;
+
+use code_challenge_1::labels::{display_account, LabelRegistry};
+use code_challenge_1::{
+    format_balance, staking, Origin, Runtime, RuntimeError, StakingConfig, TreasuryConfig,
+};
+use common::{run_block, signed};
+
+fn main() -> Result<(), RuntimeError> {
+    let names = ["alice", "bob", "charlie", "dave", "eve", "ferdie"];
+    let mut labels = LabelRegistry::new();
+    for (who, name) in (1u64..).zip(names) {
+        labels.register(who, name);
+    }
+    labels.register(Runtime::POT_ACCOUNT, "the treasury");
+    let (alice, dave, eve, ferdie) = (1u64, 4u64, 5u64, 6u64);
+
+    let mut runtime = Runtime::new();
+    for who in 1..=6 {
+        runtime.balances.set_balance(who, 1000)?;
+    }
+
+    println!("Five candidates bond, and ferdie nominates dave");
+    let mut extrinsics: Vec<_> = (1u64..=5)
+        .zip([500, 400, 300, 200, 150])
+        .map(|(who, amount)| signed(&runtime, who, staking::Call::Stake { amount }.into()))
+        .collect();
+    extrinsics.push(signed(
+        &runtime,
+        ferdie,
+        staking::Call::Nominate {
+            targets: vec![dave],
+            amount: 100,
+        }
+        .into(),
+    ));
+    run_block(&mut runtime, &labels, extrinsics)?;
+    println!("Backing per candidate: {:?}", runtime.staking.backings());
+
+    println!("The era ends after ERA_LENGTH blocks: the reward is paid and an election held");
+    while runtime.system.block_number() < Runtime::ERA_LENGTH {
+        run_block(&mut runtime, &labels, Vec::new())?;
+    }
+    println!(
+        "Era {} validators: {:?}; eve, the smallest staker, sits out",
+        runtime.staking.current_era(),
+        runtime.staking.validators()
+    );
+    for who in [dave, ferdie] {
+        println!(
+            "{} was paid per era: {:?}",
+            display_account(&labels, &who),
+            runtime.staking.payout_history(who)
+        );
+    }
+
+    println!("Before reporting dave, alice previews the slash");
+    let severity = 500_000;
+    let report = runtime
+        .staking
+        .simulate_slash(dave, severity)
+        .map_err(RuntimeError::from)?;
+    println!(
+        "  dave would lose {} and its nominators {:?}; alice's fee {}, the treasury's gain {}",
+        format_balance::<Runtime>(report.validator_loss),
+        report.nominator_losses,
+        format_balance::<Runtime>(report.finder_fee),
+        format_balance::<Runtime>(report.treasury_gain)
+    );
+    println!(
+        "  leaving the set: {:?}, joining: {:?}",
+        report.leaving, report.joining
+    );
+    // Offences are confirmed by the council, which credits alice with the finding
+    let before = runtime.system.events().len();
+    let report = staking::Call::ReportOffence {
+        reporter: alice,
+        offender: dave,
+        severity,
+    };
+    runtime.dispatch_as(Origin::Council, report.into())?;
+    for event in runtime.system.events().iter().skip(before) {
+        println!("  {}", event.describe(&labels));
+    }
+
+    println!("dave unbonds the rest and withdraws it after BONDING_DURATION blocks");
+    let stake = runtime.staking.get_staked_balance(dave);
+    let extrinsic = signed(
+        &runtime,
+        dave,
+        staking::Call::Unbond { amount: stake }.into(),
+    );
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+    for _ in 0..Runtime::BONDING_DURATION {
+        run_block(&mut runtime, &labels, Vec::new())?;
+    }
+    let extrinsic = signed(&runtime, dave, staking::Call::WithdrawUnbonded.into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+    println!(
+        "dave is left with {} free and {} staked; eve has {} staked",
+        format_balance::<Runtime>(runtime.balances.get_free_balance(dave)),
+        format_balance::<Runtime>(runtime.staking.get_staked_balance(dave)),
+        format_balance::<Runtime>(runtime.staking.get_staked_balance(eve))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+// A treasury spend from request to payout: the request opens a governance proposal,
+// the payout is refused while the proposal is open, and goes through once the stakers
+// approve it. The council's direct spend, which skips the referendum, comes last.
+//
+// Run with `cargo run --example treasury_spend`.
+
+mod common;
+
+use code_challenge_1::governance::{self, Conviction};
+use code_challenge_1::labels::LabelRegistry;
+use code_challenge_1::treasury::{self, TreasuryPallet};
+use code_challenge_1::{
+    format_balance, staking, GovernanceConfig, Origin, Runtime, RuntimeError, TreasuryConfig,
+};
+use common::{run_block, signed};
+
+fn main() -> Result<(), RuntimeError> {
+    let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+    let pot = Runtime::POT_ACCOUNT;
+    let mut labels = LabelRegistry::new();
+    labels.register(alice, "alice");
+    labels.register(bob, "bob");
+    labels.register(charlie, "charlie");
+    labels.register(pot, "the treasury");
+
+    let mut runtime = Runtime::new();
+    runtime.balances.set_balance(alice, 1000)?;
+    runtime.balances.set_balance(pot, 500)?;
+    // Enough for bob and charlie to pay their extrinsics' fees
+    runtime.balances.set_balance(bob, 10)?;
+    runtime.balances.set_balance(charlie, 10)?;
+    println!(
+        "The pot starts with {}",
+        format_balance::<Runtime>(TreasuryPallet::<Runtime>::pot(&runtime.balances))
+    );
+
+    println!("alice stakes, then asks the treasury to pay bob for running a workshop");
+    let extrinsic = signed(&runtime, alice, staking::Call::Stake { amount: 600 }.into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+    let propose = treasury::Call::ProposeSpend {
+        beneficiary: bob,
+        amount: 200,
+    };
+    let extrinsic = signed(&runtime, alice, propose.into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+
+    println!("bob can't be paid while the proposal is open");
+    let payout = treasury::Call::Payout { spend_id: 0 };
+    let extrinsic = signed(&runtime, bob, payout.clone().into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+
+    println!("alice votes aye and the proposal is finalized after the voting period");
+    let vote = governance::Call::Vote {
+        proposal_id: 0,
+        vote_type: true,
+        conviction: Conviction::None,
+    };
+    let extrinsic = signed(&runtime, alice, vote.into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+    for _ in 0..Runtime::VOTING_PERIOD {
+        run_block(&mut runtime, &labels, Vec::new())?;
+    }
+
+    println!("Anyone can now trigger the payout, but only once");
+    let extrinsic = signed(&runtime, charlie, payout.clone().into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+    let extrinsic = signed(&runtime, bob, payout.into());
+    run_block(&mut runtime, &labels, vec![extrinsic])?;
+
+    println!("The council pays charlie directly, without a proposal");
+    // Outside a block, so the events are added to the last block's
+    let before = runtime.system.events().len();
+    runtime.dispatch_as(
+        Origin::Council,
+        treasury::Call::ForceSpend {
+            beneficiary: charlie,
+            amount: 50,
+        }
+        .into(),
+    )?;
+    for event in runtime.system.events().iter().skip(before) {
+        println!("  {}", event.describe(&labels));
+    }
+    println!(
+        "bob has {}, and the pot {} after fees flowed back in",
+        format_balance::<Runtime>(runtime.balances.get_free_balance(bob)),
+        format_balance::<Runtime>(TreasuryPallet::<Runtime>::pot(&runtime.balances))
+    );
+
+    Ok(())
+}
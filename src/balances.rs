@@ -0,0 +1,978 @@
+use crate::codec::{Codec, CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::labels::{display_account, AccountLabels};
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+pub trait BalancesConfig: SystemConfig<RuntimeEvent: From<Event<Self>>> + Sized {
+    // Define the Balance type with ability to perform checked arithmetic operations
+    type Balance: Zero
+        + CheckedSub
+        + CheckedAdd
+        + Copy
+        + PartialOrd
+        + Debug
+        + Into<u128>
+        + TryFrom<u128>
+        + Codec;
+    // Free balance below this is dust: an account holding only dust can be reaped
+    const EXISTENTIAL_DEPOSIT: Self::Balance;
+}
+
+// Reasons a pallet can place funds on hold; each reason is tracked separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HoldReason {
+    StakingBond,
+    ProposalDeposit,
+    IdentityDeposit,
+    Escrow,
+    ElectionDeposit,
+    ValidatorMetadata,
+}
+
+// Identifier of a named lock, e.g. *b"vesting "
+pub type LockId = [u8; 8];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BalancesError {
+    InsufficientBalance,
+    // The free balance is there, but locks keep it from being spent
+    LiquidityRestrictions,
+    InsufficientHeldBalance,
+    Overflow,
+    Underflow,
+    // Only an account holding a nonzero free balance below EXISTENTIAL_DEPOSIT can be reaped
+    NotDust,
+    // Part of the balance is held or locked, or another pallet holds state tied to the account
+    StillReferenced,
+}
+
+pallet_error!(BalancesError {
+    0 => InsufficientBalance: "The free balance is too low",
+    1 => LiquidityRestrictions: "The free balance is there, but locks keep it from being spent",
+    2 => InsufficientHeldBalance: "Less is held for this reason than requested",
+    3 => Overflow: "An arithmetic operation overflowed",
+    4 => Underflow: "An arithmetic operation underflowed",
+    5 => NotDust: "Only an account holding a nonzero free balance below EXISTENTIAL_DEPOSIT can be reaped",
+    6 => StillReferenced: "Part of the balance is held or locked, or another pallet holds state tied to the account",
+});
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: BalancesConfig> {
+    Transfer {
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    },
+    Minted {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    Burned {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    Held {
+        reason: HoldReason,
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    Released {
+        reason: HoldReason,
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    Slashed {
+        reason: HoldReason,
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    LockSet {
+        id: LockId,
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    LockRemoved {
+        id: LockId,
+        who: T::AccountId,
+    },
+    Reaped {
+        who: T::AccountId,
+        dust: T::Balance,
+    },
+}
+
+impl<T: BalancesConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice transferred 4 UNIT to bob"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::Transfer { from, to, amount } => format!(
+                "{} transferred {} to {}",
+                display_account(labels, from),
+                format_balance::<T>(*amount),
+                display_account(labels, to)
+            ),
+            Event::Minted { who, amount } => format!(
+                "{} was minted {}",
+                display_account(labels, who),
+                format_balance::<T>(*amount)
+            ),
+            Event::Burned { who, amount } => format!(
+                "{} had {} burned",
+                display_account(labels, who),
+                format_balance::<T>(*amount)
+            ),
+            Event::Held {
+                reason,
+                who,
+                amount,
+            } => format!(
+                "{} had {} held for {:?}",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                reason
+            ),
+            Event::Released {
+                reason,
+                who,
+                amount,
+            } => format!(
+                "{} had {} released from {:?}",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                reason
+            ),
+            Event::Slashed {
+                reason,
+                who,
+                amount,
+            } => format!(
+                "{} had {} slashed from {:?}",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                reason
+            ),
+            Event::LockSet { id, who, amount } => format!(
+                "{} had {} locked by {}",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                String::from_utf8_lossy(id).trim_end()
+            ),
+            Event::LockRemoved { id, who } => format!(
+                "{} had the {} lock removed",
+                display_account(labels, who),
+                String::from_utf8_lossy(id).trim_end()
+            ),
+            Event::Reaped { who, dust } => format!(
+                "{} was reaped, burning {} of dust",
+                display_account(labels, who),
+                format_balance::<T>(*dust)
+            ),
+        }
+    }
+}
+
+// Calls an account can submit to the balances pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: BalancesConfig> {
+    Transfer {
+        to: T::AccountId,
+        amount: T::Balance,
+    },
+    ReapAccount {
+        who: T::AccountId,
+    },
+}
+
+// What keeps part of an account's balance from being transferred
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockSource {
+    Hold(HoldReason),
+    Lock(LockId),
+}
+
+// A portion of an account's balance that can't be transferred, and why
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountLock<Balance> {
+    pub source: LockSource,
+    pub amount: Balance,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BalancesPallet<T: BalancesConfig> {
+    // Track free balances for each account
+    pub free_balances: HashMap<T::AccountId, T::Balance>,
+    // Track held (reserved) balances for each account, keyed by the reason of the hold
+    pub held_balances: HashMap<(T::AccountId, HoldReason), T::Balance>,
+    // Named locks on free balance; locks overlap, so the largest one applies
+    pub locks: HashMap<(T::AccountId, LockId), T::Balance>,
+    // Sum of all free and held balances
+    total_issuance: T::Balance,
+}
+
+impl<T: BalancesConfig> Default for BalancesPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: BalancesConfig> BalancesPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            free_balances: HashMap::new(),
+            held_balances: HashMap::new(),
+            locks: HashMap::new(),
+            total_issuance: T::Balance::zero(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method. The runtime checks the
+    // other pallets' references on an account before routing ReapAccount.
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), BalancesError> {
+        match call {
+            Call::Transfer { to, amount } => self.transfer(system, origin, to, amount),
+            Call::ReapAccount { who } => self.reap_account(system, who),
+        }
+    }
+
+    // Remove an account holding only dust, burning it. Anyone can reap one, as long
+    // as none of its balance is held or locked. Its nonce stays with the system
+    // pallet, so extrinsics it already signed can't be replayed.
+    pub fn reap_account(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+    ) -> Result<(), BalancesError> {
+        let dust = self.get_free_balance(who.clone());
+        if dust.is_zero() || dust >= T::EXISTENTIAL_DEPOSIT {
+            return Err(BalancesError::NotDust);
+        }
+        let held = self.held_balances.keys().any(|(holder, _)| *holder == who);
+        let locked = self.locks.keys().any(|(holder, _)| *holder == who);
+        if held || locked {
+            return Err(BalancesError::StillReferenced);
+        }
+
+        self.total_issuance = self
+            .total_issuance
+            .checked_sub(&dust)
+            .ok_or(BalancesError::Underflow)?;
+        self.write_free_balance(who.clone(), T::Balance::zero());
+        system.deposit_event(Event::Reaped { who, dust });
+
+        Ok(())
+    }
+
+    // Set free balance for an account (genesis/test setup), adjusting total issuance
+    pub fn set_balance(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        let previous = self.get_free_balance(who.clone());
+        let total_issuance = self
+            .total_issuance
+            .checked_sub(&previous)
+            .ok_or(BalancesError::Underflow)?
+            .checked_add(&amount)
+            .ok_or(BalancesError::Overflow)?;
+
+        self.total_issuance = total_issuance;
+        self.write_free_balance(who, amount);
+
+        Ok(())
+    }
+
+    // Move free balance from one account to another
+    pub fn transfer(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        self.ensure_can_spend(from.clone(), amount)?;
+
+        // Both balances are worked out before either is written, so a failure leaves
+        // neither account changed
+        let new_from_balance = self
+            .get_free_balance(from.clone())
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+        let to_balance = if to == from {
+            new_from_balance
+        } else {
+            self.get_free_balance(to.clone())
+        };
+        let new_to_balance = to_balance
+            .checked_add(&amount)
+            .ok_or(BalancesError::Overflow)?;
+        self.write_free_balance(from.clone(), new_from_balance);
+        self.write_free_balance(to.clone(), new_to_balance);
+
+        system.deposit_event(Event::Transfer { from, to, amount });
+
+        Ok(())
+    }
+
+    // Create new tokens in an account's free balance
+    pub fn mint(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        let total_issuance = self
+            .total_issuance
+            .checked_add(&amount)
+            .ok_or(BalancesError::Overflow)?;
+        let new_free_balance = self
+            .get_free_balance(who.clone())
+            .checked_add(&amount)
+            .ok_or(BalancesError::Overflow)?;
+
+        self.total_issuance = total_issuance;
+        self.write_free_balance(who.clone(), new_free_balance);
+        system.deposit_event(Event::Minted { who, amount });
+
+        Ok(())
+    }
+
+    // Destroy tokens from an account's spendable free balance
+    pub fn burn(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        self.ensure_can_spend(who.clone(), amount)?;
+
+        let new_free_balance = self
+            .get_free_balance(who.clone())
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+        let total_issuance = self
+            .total_issuance
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+
+        self.total_issuance = total_issuance;
+        self.write_free_balance(who.clone(), new_free_balance);
+        system.deposit_event(Event::Burned { who, amount });
+
+        Ok(())
+    }
+
+    // Move tokens from free balance into a named hold
+    pub fn hold(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        reason: HoldReason,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        self.ensure_can_spend(who.clone(), amount)?;
+
+        let new_free_balance = self
+            .get_free_balance(who.clone())
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+        let new_held_balance = self
+            .get_held_balance(reason, who.clone())
+            .checked_add(&amount)
+            .ok_or(BalancesError::Overflow)?;
+
+        self.write_free_balance(who.clone(), new_free_balance);
+        self.write_held_balance(reason, who.clone(), new_held_balance);
+        system.deposit_event(Event::Held {
+            reason,
+            who,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Release tokens from a named hold back into free balance
+    pub fn release(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        reason: HoldReason,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        let held_balance = self.get_held_balance(reason, who.clone());
+        if held_balance < amount {
+            return Err(BalancesError::InsufficientHeldBalance);
+        }
+
+        let new_held_balance = held_balance
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+        let new_free_balance = self
+            .get_free_balance(who.clone())
+            .checked_add(&amount)
+            .ok_or(BalancesError::Overflow)?;
+
+        self.write_held_balance(reason, who.clone(), new_held_balance);
+        self.write_free_balance(who.clone(), new_free_balance);
+        system.deposit_event(Event::Released {
+            reason,
+            who,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Move tokens from one account's named hold into another account's free balance.
+    // Locks on the sender don't apply, as held funds were already set aside.
+    pub fn transfer_held(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        reason: HoldReason,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        let held_balance = self.get_held_balance(reason, from.clone());
+        if held_balance < amount {
+            return Err(BalancesError::InsufficientHeldBalance);
+        }
+
+        let new_held_balance = held_balance
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+        let new_to_balance = self
+            .get_free_balance(to.clone())
+            .checked_add(&amount)
+            .ok_or(BalancesError::Overflow)?;
+
+        self.write_held_balance(reason, from.clone(), new_held_balance);
+        self.write_free_balance(to.clone(), new_to_balance);
+        system.deposit_event(Event::Transfer { from, to, amount });
+
+        Ok(())
+    }
+
+    // Burn tokens from a named hold, e.g. as a penalty
+    pub fn slash_held(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        reason: HoldReason,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BalancesError> {
+        let held_balance = self.get_held_balance(reason, who.clone());
+        if held_balance < amount {
+            return Err(BalancesError::InsufficientHeldBalance);
+        }
+
+        let new_held_balance = held_balance
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+        let total_issuance = self
+            .total_issuance
+            .checked_sub(&amount)
+            .ok_or(BalancesError::Underflow)?;
+
+        self.total_issuance = total_issuance;
+        self.write_held_balance(reason, who.clone(), new_held_balance);
+        system.deposit_event(Event::Slashed {
+            reason,
+            who,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Set or replace a named lock on an account's free balance
+    pub fn set_lock(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        id: LockId,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) {
+        self.locks.insert((who.clone(), id), amount);
+        system.deposit_event(Event::LockSet { id, who, amount });
+    }
+
+    // Remove a named lock from an account
+    pub fn remove_lock(&mut self, system: &mut SystemPallet<T>, id: LockId, who: T::AccountId) {
+        if self.locks.remove(&(who.clone(), id)).is_some() {
+            system.deposit_event(Event::LockRemoved { id, who });
+        }
+    }
+
+    // Get free balance for an account
+    pub fn get_free_balance(&self, who: T::AccountId) -> T::Balance {
+        *self.free_balances.get(&who).unwrap_or(&T::Balance::zero())
+    }
+
+    // Get the balance held for a specific reason
+    pub fn get_held_balance(&self, reason: HoldReason, who: T::AccountId) -> T::Balance {
+        *self
+            .held_balances
+            .get(&(who, reason))
+            .unwrap_or(&T::Balance::zero())
+    }
+
+    // Get the total held (reserved) balance of an account across all reasons
+    pub fn get_reserved_balance(&self, who: T::AccountId) -> T::Balance {
+        self.held_balances
+            .iter()
+            .filter(|((account, _), _)| *account == who)
+            .fold(T::Balance::zero(), |total, (_, amount)| {
+                total.checked_add(amount).unwrap_or(total)
+            })
+    }
+
+    // Get the part of the free balance frozen by locks (the largest lock)
+    pub fn get_locked_balance(&self, who: T::AccountId) -> T::Balance {
+        self.locks
+            .iter()
+            .filter(|((account, _), _)| *account == who)
+            .fold(T::Balance::zero(), |largest, (_, amount)| {
+                if *amount > largest {
+                    *amount
+                } else {
+                    largest
+                }
+            })
+    }
+
+    // Get the part of the free balance that can be transferred or held
+    pub fn get_usable_balance(&self, who: T::AccountId) -> T::Balance {
+        self.get_free_balance(who.clone())
+            .checked_sub(&self.get_locked_balance(who))
+            .unwrap_or(T::Balance::zero())
+    }
+
+    pub fn total_issuance(&self) -> T::Balance {
+        self.total_issuance
+    }
+
+    // List every outstanding hold and lock on an account, holds first
+    pub fn locks_of(&self, who: T::AccountId) -> Vec<AccountLock<T::Balance>> {
+        let mut holds: Vec<_> = self
+            .held_balances
+            .iter()
+            .filter(|((account, _), _)| *account == who)
+            .map(|((_, reason), amount)| (*reason, *amount))
+            .collect();
+        holds.sort_by_key(|(reason, _)| *reason);
+
+        let mut locks: Vec<_> = self
+            .locks
+            .iter()
+            .filter(|((account, _), _)| *account == who)
+            .map(|((_, id), amount)| (*id, *amount))
+            .collect();
+        locks.sort_by_key(|(id, _)| *id);
+
+        holds
+            .into_iter()
+            .map(|(reason, amount)| AccountLock {
+                source: LockSource::Hold(reason),
+                amount,
+            })
+            .chain(locks.into_iter().map(|(id, amount)| AccountLock {
+                source: LockSource::Lock(id),
+                amount,
+            }))
+            .collect()
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        StorageUsage::value::<T::Balance>(0)
+            .saturating_add(StorageUsage::map(&self.free_balances))
+            .saturating_add(StorageUsage::map(&self.held_balances))
+            .saturating_add(StorageUsage::map(&self.locks))
+    }
+
+    fn ensure_can_spend(&self, who: T::AccountId, amount: T::Balance) -> Result<(), BalancesError> {
+        if self.get_free_balance(who.clone()) < amount {
+            return Err(BalancesError::InsufficientBalance);
+        }
+        if self.get_usable_balance(who) < amount {
+            return Err(BalancesError::LiquidityRestrictions);
+        }
+
+        Ok(())
+    }
+
+    fn write_held_balance(&mut self, reason: HoldReason, who: T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.held_balances.remove(&(who, reason));
+        } else {
+            self.held_balances.insert((who, reason), amount);
+        }
+    }
+
+    fn write_free_balance(&mut self, who: T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.free_balances.remove(&who);
+        } else {
+            self.free_balances.insert(who, amount);
+        }
+    }
+}
+
+impl Encode for HoldReason {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            HoldReason::StakingBond => 0,
+            HoldReason::ProposalDeposit => 1,
+            HoldReason::IdentityDeposit => 2,
+            HoldReason::Escrow => 3,
+            HoldReason::ElectionDeposit => 4,
+            HoldReason::ValidatorMetadata => 5,
+        };
+        tag.encode_to(dest);
+    }
+}
+
+impl Decode for HoldReason {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(HoldReason::StakingBond),
+            1 => Ok(HoldReason::ProposalDeposit),
+            2 => Ok(HoldReason::IdentityDeposit),
+            3 => Ok(HoldReason::Escrow),
+            4 => Ok(HoldReason::ElectionDeposit),
+            5 => Ok(HoldReason::ValidatorMetadata),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: BalancesConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::Transfer { .. } => 2,
+            Call::ReapAccount { .. } => 2,
+        }
+    }
+}
+
+impl<T: BalancesConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::Transfer { to, amount } => {
+                0u8.encode_to(dest);
+                to.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::ReapAccount { who } => {
+                1u8.encode_to(dest);
+                who.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: BalancesConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::Transfer {
+                to: Decode::decode(input)?,
+                amount: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::ReapAccount {
+                who: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: BalancesConfig> Encode for BalancesPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.free_balances.encode_to(dest);
+        self.held_balances.encode_to(dest);
+        self.locks.encode_to(dest);
+        self.total_issuance.encode_to(dest);
+    }
+}
+
+impl<T: BalancesConfig> Decode for BalancesPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            free_balances: Decode::decode(input)?,
+            held_balances: Decode::decode(input)?,
+            locks: Decode::decode(input)?,
+            total_issuance: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runtime;
+
+    #[test]
+    fn test_transfers_and_issuance() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+        assert_eq!(balances.total_issuance(), 1000);
+
+        balances.transfer(&mut system, alice, bob, 300).unwrap();
+        assert_eq!(balances.get_free_balance(alice), 700u64);
+        assert_eq!(balances.get_free_balance(bob), 300u64);
+
+        let result = balances.transfer(&mut system, bob, alice, 301);
+        assert_eq!(result, Err(BalancesError::InsufficientBalance));
+
+        // Minting and burning keep total issuance in sync
+        balances.mint(&mut system, bob, 50).unwrap();
+        balances.burn(&mut system, alice, 200).unwrap();
+        assert_eq!(balances.total_issuance(), 850);
+
+        assert_eq!(
+            system.events().last(),
+            Some(
+                &Event::Burned {
+                    who: alice,
+                    amount: 200,
+                }
+                .into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_named_holds_are_isolated() {
+        let alice = 1u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+
+        // Hold funds for two different reasons
+        balances
+            .hold(&mut system, HoldReason::StakingBond, alice, 300)
+            .unwrap();
+        balances
+            .hold(&mut system, HoldReason::ProposalDeposit, alice, 200)
+            .unwrap();
+        assert_eq!(balances.get_free_balance(alice), 500u64);
+        assert_eq!(balances.get_reserved_balance(alice), 500u64);
+        assert_eq!(
+            balances.get_held_balance(HoldReason::ProposalDeposit, alice),
+            200u64
+        );
+
+        // One reason can't release funds held by another
+        let result = balances.release(&mut system, HoldReason::Escrow, alice, 100);
+        assert_eq!(result, Err(BalancesError::InsufficientHeldBalance));
+        let result = balances.release(&mut system, HoldReason::ProposalDeposit, alice, 300);
+        assert_eq!(result, Err(BalancesError::InsufficientHeldBalance));
+
+        // Releasing a deposit leaves the staking bond untouched
+        balances
+            .release(&mut system, HoldReason::ProposalDeposit, alice, 200)
+            .unwrap();
+        assert_eq!(balances.get_free_balance(alice), 700u64);
+        assert_eq!(
+            balances.get_held_balance(HoldReason::StakingBond, alice),
+            300u64
+        );
+
+        // Holds move funds but don't change issuance
+        assert_eq!(balances.total_issuance(), 1000);
+    }
+
+    #[test]
+    fn test_only_unencumbered_dust_is_reaped() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        balances.set_balance(alice, 8).unwrap();
+        balances.set_balance(bob, 8).unwrap();
+
+        // Funds held or locked keep the account around, dust or not
+        balances
+            .hold(&mut system, HoldReason::IdentityDeposit, alice, 1)
+            .unwrap();
+        balances.set_lock(&mut system, *b"vesting ", bob, 1);
+        for who in [alice, bob] {
+            assert_eq!(
+                balances.reap_account(&mut system, who),
+                Err(BalancesError::StillReferenced)
+            );
+        }
+
+        balances.remove_lock(&mut system, *b"vesting ", bob);
+        balances.reap_account(&mut system, bob).unwrap();
+        assert_eq!(balances.get_free_balance(bob), 0);
+        assert_eq!(balances.total_issuance(), 8);
+        // Nothing is left to reap
+        assert_eq!(
+            balances.reap_account(&mut system, bob),
+            Err(BalancesError::NotDust)
+        );
+    }
+
+    #[test]
+    fn test_locks_restrict_spending() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+
+        // Locks overlap: the largest one applies
+        balances.set_lock(&mut system, *b"vesting ", alice, 600);
+        balances.set_lock(&mut system, *b"democrac", alice, 400);
+        assert_eq!(balances.get_usable_balance(alice), 400u64);
+
+        let result = balances.transfer(&mut system, alice, bob, 500);
+        assert_eq!(result, Err(BalancesError::LiquidityRestrictions));
+        let result = balances.hold(&mut system, HoldReason::Escrow, alice, 500);
+        assert_eq!(result, Err(BalancesError::LiquidityRestrictions));
+        balances.transfer(&mut system, alice, bob, 400).unwrap();
+
+        balances.remove_lock(&mut system, *b"vesting ", alice);
+        assert_eq!(balances.get_usable_balance(alice), 200u64);
+    }
+
+    #[test]
+    fn test_locks_of_lists_all_holds_and_locks() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+        balances.set_balance(bob, 1000).unwrap();
+        assert!(balances.locks_of(alice).is_empty());
+
+        balances
+            .hold(&mut system, HoldReason::Escrow, alice, 50)
+            .unwrap();
+        balances
+            .hold(&mut system, HoldReason::StakingBond, alice, 300)
+            .unwrap();
+        balances
+            .hold(&mut system, HoldReason::StakingBond, bob, 100)
+            .unwrap();
+        balances.set_lock(&mut system, *b"vesting ", alice, 200);
+
+        assert_eq!(
+            balances.locks_of(alice),
+            vec![
+                AccountLock {
+                    source: LockSource::Hold(HoldReason::StakingBond),
+                    amount: 300u64,
+                },
+                AccountLock {
+                    source: LockSource::Hold(HoldReason::Escrow),
+                    amount: 50u64,
+                },
+                AccountLock {
+                    source: LockSource::Lock(*b"vesting "),
+                    amount: 200u64,
+                },
+            ]
+        );
+
+        // Fully released holds disappear from the list
+        balances
+            .release(&mut system, HoldReason::Escrow, alice, 50)
+            .unwrap();
+        assert_eq!(balances.locks_of(alice).len(), 2);
+    }
+
+    #[test]
+    fn test_overflow_is_an_error() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+
+        balances.set_balance(alice, u64::MAX).unwrap();
+
+        // Total issuance can't grow past the balance type
+        let result = balances.mint(&mut system, bob, 1);
+        assert_eq!(result, Err(BalancesError::Overflow));
+        let result = balances.set_balance(bob, 1);
+        assert_eq!(result, Err(BalancesError::Overflow));
+        assert_eq!(balances.get_free_balance(bob), 0u64);
+        assert_eq!(balances.total_issuance(), u64::MAX);
+
+        // A transfer the recipient can't hold leaves the sender's balance alone too
+        balances.write_free_balance(bob, u64::MAX);
+        let result = balances.transfer(&mut system, alice, bob, 10);
+        assert_eq!(result, Err(BalancesError::Overflow));
+        assert_eq!(balances.get_free_balance(alice), u64::MAX);
+
+        // Sending to oneself changes nothing
+        balances.transfer(&mut system, alice, alice, 10).unwrap();
+        assert_eq!(balances.get_free_balance(alice), u64::MAX);
+    }
+
+    #[test]
+    fn test_random_calls_never_panic() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let reasons = [
+            HoldReason::StakingBond,
+            HoldReason::ProposalDeposit,
+            HoldReason::IdentityDeposit,
+            HoldReason::Escrow,
+            HoldReason::ElectionDeposit,
+            HoldReason::ValidatorMetadata,
+        ];
+        let amounts = [0u64, 1, 500, u64::MAX - 1, u64::MAX];
+
+        // Simple LCG so the sequence is reproducible
+        let mut seed = 0x5eed_u64;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+
+        for _ in 0..10_000 {
+            let who = (next() % 3) as u64;
+            let other = (next() % 3) as u64;
+            let amount = amounts[next() % amounts.len()];
+            let reason = reasons[next() % reasons.len()];
+            let _ = match next() % 9 {
+                0 => balances.set_balance(who, amount),
+                1 => balances.transfer(&mut system, who, other, amount),
+                2 => balances.mint(&mut system, who, amount),
+                3 => balances.burn(&mut system, who, amount),
+                4 => balances.hold(&mut system, reason, who, amount),
+                5 => balances.release(&mut system, reason, who, amount),
+                6 => balances.slash_held(&mut system, reason, who, amount),
+                7 => {
+                    balances.set_lock(&mut system, *b"testlock", who, amount);
+                    Ok(())
+                }
+                _ => {
+                    balances.remove_lock(&mut system, *b"testlock", who);
+                    Ok(())
+                }
+            };
+        }
+    }
+}
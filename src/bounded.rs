@@ -0,0 +1,182 @@
+use crate::codec::{CodecError, Decode, Encode};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+// Returned when an insert would grow a bounded collection past its maximum
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundExceeded;
+
+// A Vec that refuses to grow past a configured number of items
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundedVec<T> {
+    items: Vec<T>,
+    bound: usize,
+}
+
+impl<T> BoundedVec<T> {
+    pub fn with_bound(bound: usize) -> Self {
+        Self {
+            items: Vec::new(),
+            bound,
+        }
+    }
+
+    // Build from existing items, failing if there are too many
+    pub fn try_from_vec(items: Vec<T>, bound: usize) -> Result<Self, BoundExceeded> {
+        if items.len() > bound {
+            return Err(BoundExceeded);
+        }
+
+        Ok(Self { items, bound })
+    }
+
+    // Append an item if there is room left
+    pub fn try_push(&mut self, item: T) -> Result<(), BoundExceeded> {
+        if self.items.len() >= self.bound {
+            return Err(BoundExceeded);
+        }
+
+        self.items.push(item);
+        Ok(())
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        self.items.retain(f);
+    }
+
+    pub fn bound(&self) -> usize {
+        self.bound
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.items
+    }
+}
+
+impl<T> Deref for BoundedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+}
+
+// Only the items are encoded; the bound comes from configuration when decoding
+impl<T: Encode> Encode for BoundedVec<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.items.encode_to(dest);
+    }
+}
+
+impl<T: Decode> BoundedVec<T> {
+    pub fn decode_with_bound(input: &mut &[u8], bound: usize) -> Result<Self, CodecError> {
+        Self::try_from_vec(Vec::decode(input)?, bound).map_err(|_| CodecError::InvalidValue)
+    }
+}
+
+// A BTreeMap that refuses to hold more than a configured number of entries
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundedBTreeMap<K, V> {
+    entries: BTreeMap<K, V>,
+    bound: usize,
+}
+
+impl<K: Ord, V> BoundedBTreeMap<K, V> {
+    pub fn with_bound(bound: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            bound,
+        }
+    }
+
+    // Insert or replace an entry; only new keys count against the bound
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, BoundExceeded> {
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.bound {
+            return Err(BoundExceeded);
+        }
+
+        Ok(self.entries.insert(key, value))
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn bound(&self) -> usize {
+        self.bound
+    }
+}
+
+// Only the entries are encoded; the bound comes from configuration when decoding
+impl<K: Encode, V: Encode> Encode for BoundedBTreeMap<K, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.entries.encode_to(dest);
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> BoundedBTreeMap<K, V> {
+    pub fn decode_with_bound(input: &mut &[u8], bound: usize) -> Result<Self, CodecError> {
+        let entries = BTreeMap::decode(input)?;
+        if entries.len() > bound {
+            return Err(CodecError::InvalidValue);
+        }
+        Ok(Self { entries, bound })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_vec_respects_bound() {
+        let mut items = BoundedVec::with_bound(2);
+        assert!(items.try_push(1u32).is_ok());
+        assert!(items.try_push(2u32).is_ok());
+        assert_eq!(items.try_push(3u32), Err(BoundExceeded));
+        assert_eq!(&items[..], &[1, 2]);
+
+        // Removing items frees up room again
+        items.retain(|item| *item != 1);
+        assert!(items.try_push(3u32).is_ok());
+        assert_eq!(items.into_inner(), vec![2, 3]);
+
+        assert!(BoundedVec::try_from_vec(vec![1, 2, 3], 2).is_err());
+        assert!(BoundedVec::try_from_vec(vec![1, 2], 2).is_ok());
+    }
+
+    #[test]
+    fn test_bounded_map_respects_bound() {
+        let mut map = BoundedBTreeMap::with_bound(1);
+        assert_eq!(map.try_insert("en", 1u32), Ok(None));
+
+        // Replacing an existing key doesn't grow the map
+        assert_eq!(map.try_insert("en", 2u32), Ok(Some(1)));
+        assert_eq!(map.try_insert("id", 3u32), Err(BoundExceeded));
+        assert_eq!(map.get(&"en"), Some(&2));
+
+        map.remove(&"en");
+        assert!(map.is_empty());
+        assert_eq!(map.try_insert("id", 3u32), Ok(None));
+    }
+}
@@ -0,0 +1,449 @@
+use crate::balances::BalancesConfig;
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::StorageMap;
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::{CheckedAdd, Zero};
+
+// Spending limits: an account can cap what its extrinsics spend on transfers and fees
+// in each BUDGET_PERIOD blocks, e.g. to contain the damage of a leaked key. The
+// account may set or lower its own limit; raising or removing it takes a Root or
+// Council origin. The runtime checks the budget before an extrinsic pays its fee.
+pub trait BudgetConfig: BalancesConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Blocks after which the amount spent under a limit starts again from zero
+    const BUDGET_PERIOD: Self::BlockNumber;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetError {
+    // The spend would take the account over its limit for this period
+    BudgetExceeded,
+    // Accounts can only lower their own limit; raising it needs a Root or Council origin
+    CannotRaiseLimit,
+    Overflow,
+}
+
+pallet_error!(BudgetError {
+    0 => BudgetExceeded: "The spend would take the account over its limit for this period",
+    1 => CannotRaiseLimit: "Accounts can only lower their own limit; raising it needs a Root or Council origin",
+    2 => Overflow: "An arithmetic operation overflowed",
+});
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: BudgetConfig> {
+    SpendingLimitSet {
+        who: T::AccountId,
+        limit: Option<T::Balance>,
+    },
+}
+
+impl<T: BudgetConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice may spend 1.00 UNIT per period"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::SpendingLimitSet {
+                who,
+                limit: Some(limit),
+            } => format!(
+                "{} may spend {} per period",
+                display_account(labels, who),
+                format_balance::<T>(*limit)
+            ),
+            Event::SpendingLimitSet { who, limit: None } => {
+                format!(
+                    "{} no longer has a spending limit",
+                    display_account(labels, who)
+                )
+            }
+        }
+    }
+}
+
+// Calls an account can submit to the budget pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: BudgetConfig> {
+    // Set the caller's first limit, or lower its current one
+    SetLimit {
+        limit: T::Balance,
+    },
+    // Set, raise or remove any account's limit; needs a Root or Council origin
+    ForceSetLimit {
+        who: T::AccountId,
+        limit: Option<T::Balance>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpendingLimit<T: BudgetConfig> {
+    pub limit: T::Balance,
+    // Spent since `period_start`
+    pub spent: T::Balance,
+    pub period_start: T::BlockNumber,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetPallet<T: BudgetConfig> {
+    limits: StorageMap<T::AccountId, SpendingLimit<T>>,
+}
+
+impl<T: BudgetConfig> Default for BudgetPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: BudgetConfig> BudgetPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            limits: StorageMap::new(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method. The runtime only
+    // routes ForceSetLimit for a Root or Council origin.
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), BudgetError> {
+        match call {
+            Call::SetLimit { limit } => self.set_limit(system, origin, limit)?,
+            Call::ForceSetLimit { who, limit } => self.force_set_limit(system, who, limit),
+        }
+
+        Ok(())
+    }
+
+    // Set the account's limit, which may only ever go down this way
+    pub fn set_limit(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        limit: T::Balance,
+    ) -> Result<(), BudgetError> {
+        if let Some(current) = self.limits.get(&who) {
+            if limit > current.limit {
+                return Err(BudgetError::CannotRaiseLimit);
+            }
+        }
+        self.force_set_limit(system, who, Some(limit));
+
+        Ok(())
+    }
+
+    // Set, raise or remove the account's limit; what it spent this period still counts
+    pub fn force_set_limit(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        limit: Option<T::Balance>,
+    ) {
+        match limit {
+            Some(limit) => {
+                let entry = self
+                    .limits
+                    .get_or_insert_with(who.clone(), || SpendingLimit {
+                        limit,
+                        spent: T::Balance::zero(),
+                        period_start: system.block_number(),
+                    });
+                entry.limit = limit;
+            }
+            None => {
+                self.limits.remove(&who);
+            }
+        }
+        system.deposit_event(Event::SpendingLimitSet { who, limit });
+    }
+
+    // Check that the account can spend `amount` more in the current period
+    pub fn ensure_can_spend(
+        &self,
+        system: &SystemPallet<T>,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BudgetError> {
+        let Some(entry) = self.limits.get(who) else {
+            return Ok(());
+        };
+        let spent = Self::spent_now(system, entry)?
+            .checked_add(&amount)
+            .ok_or(BudgetError::Overflow)?;
+        if spent > entry.limit {
+            return Err(BudgetError::BudgetExceeded);
+        }
+        Ok(())
+    }
+
+    // Record a spend against the account's limit. A period starts with the first
+    // spend after the previous one is over.
+    pub fn note_spend(
+        &mut self,
+        system: &SystemPallet<T>,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), BudgetError> {
+        self.ensure_can_spend(system, who, amount)?;
+        let Some(entry) = self.limits.get_mut(who) else {
+            return Ok(());
+        };
+        let spent = Self::spent_now(system, entry)?;
+        if spent.is_zero() {
+            entry.period_start = system.block_number();
+        }
+        entry.spent = spent.checked_add(&amount).ok_or(BudgetError::Overflow)?;
+
+        Ok(())
+    }
+
+    // Get an account's spending limit, if it has one
+    pub fn limit_of(&self, who: &T::AccountId) -> Option<&SpendingLimit<T>> {
+        self.limits.get(who)
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.limits.storage_usage()
+    }
+
+    // What counts as spent at the current block: nothing once the period is over
+    fn spent_now(
+        system: &SystemPallet<T>,
+        entry: &SpendingLimit<T>,
+    ) -> Result<T::Balance, BudgetError> {
+        let period_end = entry
+            .period_start
+            .checked_add(&T::BUDGET_PERIOD)
+            .ok_or(BudgetError::Overflow)?;
+        if system.block_number() >= period_end {
+            Ok(T::Balance::zero())
+        } else {
+            Ok(entry.spent)
+        }
+    }
+}
+
+impl<T: BudgetConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::SetLimit { .. } | Call::ForceSetLimit { .. } => 2,
+        }
+    }
+}
+
+impl<T: BudgetConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::SetLimit { limit } => {
+                0u8.encode_to(dest);
+                limit.encode_to(dest);
+            }
+            Call::ForceSetLimit { who, limit } => {
+                1u8.encode_to(dest);
+                who.encode_to(dest);
+                limit.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: BudgetConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::SetLimit {
+                limit: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::ForceSetLimit {
+                who: Decode::decode(input)?,
+                limit: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: BudgetConfig> Encode for SpendingLimit<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.limit.encode_to(dest);
+        self.spent.encode_to(dest);
+        self.period_start.encode_to(dest);
+    }
+}
+
+impl<T: BudgetConfig> Decode for SpendingLimit<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            limit: Decode::decode(input)?,
+            spent: Decode::decode(input)?,
+            period_start: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: BudgetConfig> Encode for BudgetPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.limits.encode_to(dest);
+    }
+}
+
+impl<T: BudgetConfig> Decode for BudgetPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            limits: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances, system, Block, Extrinsic, Header, Origin, Runtime, RuntimeCall, RuntimeError,
+    };
+
+    #[test]
+    fn test_spending_limits_cover_transfers_and_fees() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime
+            .dispatch(alice, Call::SetLimit { limit: 100 }.into())
+            .unwrap();
+        assert_eq!(
+            runtime.dispatch(alice, Call::SetLimit { limit: 200 }.into()),
+            Err(RuntimeError::Budget(BudgetError::CannotRaiseLimit))
+        );
+
+        let transfer = |nonce, amount| Extrinsic {
+            caller: alice,
+            nonce,
+            call: balances::Call::Transfer { to: bob, amount }.into(),
+        };
+        // A transfer's fee is BASE_FEE plus a weight of 2
+        runtime.apply_extrinsic(transfer(0, 90)).unwrap();
+        assert_eq!(runtime.budget.limit_of(&alice).unwrap().spent, 93);
+        assert_eq!(
+            runtime.apply_extrinsic(transfer(1, 5)),
+            Err(RuntimeError::Budget(BudgetError::BudgetExceeded))
+        );
+        assert_eq!(runtime.system.get_nonce(alice), 1);
+
+        // The council can raise the limit
+        let raise: RuntimeCall = Call::ForceSetLimit {
+            who: alice,
+            limit: Some(200),
+        }
+        .into();
+        assert_eq!(
+            runtime.dispatch(alice, raise.clone()),
+            Err(crate::system::SystemError::BadOrigin.into())
+        );
+        runtime.dispatch_as(Origin::Council, raise).unwrap();
+        runtime.apply_extrinsic(transfer(1, 5)).unwrap();
+
+        // A new period starts from zero
+        let period = <Runtime as BudgetConfig>::BUDGET_PERIOD;
+        for number in 1..=period {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+        runtime.apply_extrinsic(transfer(2, 190)).unwrap();
+        assert_eq!(runtime.budget.limit_of(&alice).unwrap().spent, 193);
+        assert_eq!(runtime.balances.get_free_balance(bob), 285u64);
+    }
+
+    #[test]
+    fn test_session_transfers_spend_from_the_principals_budget() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(bob, 100).unwrap();
+        runtime
+            .dispatch(alice, Call::SetLimit { limit: 100 }.into())
+            .unwrap();
+        let transfer: RuntimeCall = balances::Call::Transfer {
+            to: charlie,
+            amount: 500,
+        }
+        .into();
+        runtime
+            .dispatch(
+                alice,
+                system::Call::AuthorizeSession {
+                    delegate: bob,
+                    calls: vec![transfer.call_index()],
+                    expires: 10,
+                }
+                .into(),
+            )
+            .unwrap();
+
+        // bob signs and pays the fee, but the transfer leaves alice's account
+        let as_alice = |nonce, amount| Extrinsic {
+            caller: bob,
+            nonce,
+            call: system::Call::DispatchAsPrincipal {
+                principal: alice,
+                call: Box::new(
+                    balances::Call::Transfer {
+                        to: charlie,
+                        amount,
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        };
+        assert_eq!(
+            runtime.apply_extrinsic(as_alice(0, 500)),
+            Err(RuntimeError::Budget(BudgetError::BudgetExceeded))
+        );
+        assert_eq!(runtime.balances.get_free_balance(charlie), 0u64);
+        assert_eq!(runtime.budget.limit_of(&alice).unwrap().spent, 0);
+        runtime.apply_extrinsic(as_alice(1, 60)).unwrap();
+        assert_eq!(runtime.budget.limit_of(&alice).unwrap().spent, 60);
+    }
+
+    #[test]
+    fn test_multisig_transfers_spend_from_the_multisigs_budget() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut runtime = Runtime::new();
+        let multisig = runtime.system.create_multisig(vec![alice, bob], 2).unwrap();
+        runtime.balances.set_balance(multisig, 1000).unwrap();
+        runtime
+            .dispatch(multisig, Call::SetLimit { limit: 100 }.into())
+            .unwrap();
+        let approve = |amount| -> RuntimeCall {
+            system::Call::ApproveAsMulti {
+                multisig,
+                call: Box::new(
+                    balances::Call::Transfer {
+                        to: charlie,
+                        amount,
+                    }
+                    .into(),
+                ),
+            }
+            .into()
+        };
+
+        // The approval reaching the threshold runs the transfer, within the limit
+        runtime.dispatch(alice, approve(500)).unwrap();
+        assert_eq!(
+            runtime.dispatch(bob, approve(500)),
+            Err(RuntimeError::Budget(BudgetError::BudgetExceeded))
+        );
+        assert_eq!(runtime.balances.get_free_balance(charlie), 0u64);
+        runtime.dispatch(alice, approve(80)).unwrap();
+        runtime.dispatch(bob, approve(80)).unwrap();
+        assert_eq!(runtime.budget.limit_of(&multisig).unwrap().spent, 80);
+        assert_eq!(runtime.balances.get_free_balance(charlie), 80u64);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::codec::{CodecError, Decode, Encode};
+use crate::labels::{display_account, AccountLabels};
+use crate::staking::{StakingConfig, StakingPallet};
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::CheckedAdd;
+
+// State checkpoints: every CHECKPOINT_INTERVAL blocks the active validators attest
+// the state root they computed at the end of that block (`Runtime::state_root`).
+// Once more than two thirds of them agree, the checkpoint is stored, and a syncing
+// node can start from a state export matching it instead of replaying every block.
+pub trait CheckpointConfig: StakingConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Blocks between checkpoints
+    const CHECKPOINT_INTERVAL: Self::BlockNumber;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointError {
+    // Only the validators of the current era attest checkpoints
+    NotValidator,
+    // Attestations are for the pending checkpoint block, once it has been executed
+    UnexpectedBlock,
+    AlreadyAttested,
+    Overflow,
+}
+
+pallet_error!(CheckpointError {
+    0 => NotValidator: "Only the validators of the current era attest checkpoints",
+    1 => UnexpectedBlock: "Attestations are for the pending checkpoint block, once it has been executed",
+    2 => AlreadyAttested: "The validator already attested the pending checkpoint",
+    3 => Overflow: "An arithmetic operation overflowed",
+});
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: CheckpointConfig> {
+    CheckpointAttested {
+        who: T::AccountId,
+        block: T::BlockNumber,
+        state_root: u64,
+    },
+    CheckpointFinalized {
+        block: T::BlockNumber,
+        state_root: u64,
+    },
+    // No root won a supermajority before the next checkpoint block was executed
+    CheckpointMissed {
+        block: T::BlockNumber,
+    },
+}
+
+impl<T: CheckpointConfig> Event<T> {
+    // Render the event as a sentence, e.g. "checkpoint at block 100 finalized with root 0x…"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::CheckpointAttested {
+                who,
+                block,
+                state_root,
+            } => format!(
+                "{} attested state root {:#018x} at block {:?}",
+                display_account(labels, who),
+                state_root,
+                block
+            ),
+            Event::CheckpointFinalized { block, state_root } => format!(
+                "checkpoint at block {:?} finalized with state root {:#018x}",
+                block, state_root
+            ),
+            Event::CheckpointMissed { block } => {
+                format!("checkpoint at block {:?} was missed", block)
+            }
+        }
+    }
+}
+
+// Calls an account can submit to the checkpoint pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: CheckpointConfig> {
+    AttestCheckpoint {
+        block: T::BlockNumber,
+        state_root: u64,
+    },
+}
+
+// A state root the validators agreed on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint<BlockNumber> {
+    pub block: BlockNumber,
+    pub state_root: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckpointPallet<T: CheckpointConfig> {
+    // Block the next checkpoint is taken at
+    pending: T::BlockNumber,
+    // Attestations for the pending checkpoint, as (validator, state root) in order
+    attestations: Vec<(T::AccountId, u64)>,
+    latest: Option<Checkpoint<T::BlockNumber>>,
+}
+
+impl<T: CheckpointConfig> Default for CheckpointPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: CheckpointConfig> CheckpointPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            pending: T::CHECKPOINT_INTERVAL,
+            attestations: Vec::new(),
+            latest: None,
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &StakingPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), CheckpointError> {
+        match call {
+            Call::AttestCheckpoint { block, state_root } => {
+                self.attest(system, staking, origin, block, state_root)
+            }
+        }
+    }
+
+    // Record a validator's state root for the pending checkpoint. The root with more
+    // than two thirds of the validators behind it becomes the latest checkpoint.
+    pub fn attest(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &StakingPallet<T>,
+        who: T::AccountId,
+        block: T::BlockNumber,
+        state_root: u64,
+    ) -> Result<(), CheckpointError> {
+        let validators = staking.validators();
+        if !validators.contains(&who) {
+            return Err(CheckpointError::NotValidator);
+        }
+        if block != self.pending || system.block_number() <= block {
+            return Err(CheckpointError::UnexpectedBlock);
+        }
+        if self
+            .attestations
+            .iter()
+            .any(|(validator, _)| *validator == who)
+        {
+            return Err(CheckpointError::AlreadyAttested);
+        }
+
+        self.attestations.push((who.clone(), state_root));
+        system.deposit_event(Event::CheckpointAttested {
+            who,
+            block,
+            state_root,
+        });
+
+        let agreeing = self
+            .attestations
+            .iter()
+            .filter(|(validator, root)| *root == state_root && validators.contains(validator))
+            .count();
+        let supermajority = agreeing
+            .checked_mul(3)
+            .zip(validators.len().checked_mul(2))
+            .map(|(agreeing, needed)| agreeing > needed)
+            .ok_or(CheckpointError::Overflow)?;
+        if supermajority {
+            self.pending = block
+                .checked_add(&T::CHECKPOINT_INTERVAL)
+                .ok_or(CheckpointError::Overflow)?;
+            self.attestations.clear();
+            self.latest = Some(Checkpoint { block, state_root });
+            system.deposit_event(Event::CheckpointFinalized { block, state_root });
+        }
+
+        Ok(())
+    }
+
+    // Start-of-block hook. A checkpoint still short of a supermajority once the next
+    // interval block has been executed is given up: attestations move on to the
+    // latest interval block, and those for the missed one are dropped.
+    pub fn on_initialize(&mut self, system: &mut SystemPallet<T>, now: T::BlockNumber) {
+        let mut next = self.pending;
+        while let Some(after) = next
+            .checked_add(&T::CHECKPOINT_INTERVAL)
+            .filter(|after| *after > next && *after < now)
+        {
+            next = after;
+        }
+        if next != self.pending {
+            let block = std::mem::replace(&mut self.pending, next);
+            self.attestations.clear();
+            system.deposit_event(Event::CheckpointMissed { block });
+        }
+    }
+
+    // Get the most recent checkpoint the validators agreed on
+    pub fn latest(&self) -> Option<Checkpoint<T::BlockNumber>> {
+        self.latest
+    }
+
+    // Get the block the next checkpoint is taken at
+    pub fn pending(&self) -> T::BlockNumber {
+        self.pending
+    }
+
+    // Whether the validator attested a root for the pending checkpoint
+    pub fn has_attested(&self, who: &T::AccountId) -> bool {
+        self.attestations.iter().any(|(signer, _)| signer == who)
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        StorageUsage::value::<T::BlockNumber>(0)
+            .saturating_add(StorageUsage::value::<Option<Checkpoint<T::BlockNumber>>>(0))
+            .saturating_add(StorageUsage {
+                items: self.attestations.len(),
+                bytes: self
+                    .attestations
+                    .len()
+                    .saturating_mul(std::mem::size_of::<(T::AccountId, u64)>()),
+            })
+    }
+}
+
+impl<T: CheckpointConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::AttestCheckpoint { .. } => 2,
+        }
+    }
+}
+
+impl<T: CheckpointConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::AttestCheckpoint { block, state_root } => {
+                0u8.encode_to(dest);
+                block.encode_to(dest);
+                state_root.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: CheckpointConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::AttestCheckpoint {
+                block: Decode::decode(input)?,
+                state_root: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<BlockNumber: Encode> Encode for Checkpoint<BlockNumber> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block.encode_to(dest);
+        self.state_root.encode_to(dest);
+    }
+}
+
+impl<BlockNumber: Decode> Decode for Checkpoint<BlockNumber> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            block: Decode::decode(input)?,
+            state_root: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: CheckpointConfig> Encode for CheckpointPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.pending.encode_to(dest);
+        self.attestations.encode_to(dest);
+        self.latest.encode_to(dest);
+    }
+}
+
+impl<T: CheckpointConfig> Decode for CheckpointPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            pending: Decode::decode(input)?,
+            attestations: Decode::decode(input)?,
+            latest: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{staking, Block, Header, Runtime, RuntimeError};
+
+    #[test]
+    fn test_validators_finalize_checkpoints_and_nodes_sync_from_them() {
+        let validators = [1u64, 2, 3, 4];
+        let mut runtime = Runtime::new();
+        for who in validators {
+            runtime.balances.set_balance(who, 1000).unwrap();
+            runtime
+                .dispatch(who, staking::Call::Stake { amount: 100 }.into())
+                .unwrap();
+        }
+        let interval = <Runtime as CheckpointConfig>::CHECKPOINT_INTERVAL;
+        let mut snapshot = Vec::new();
+        for number in 1..=interval + 1 {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+            if number == interval {
+                snapshot = runtime.export_state();
+            }
+        }
+        assert_eq!(runtime.staking.validators(), &validators);
+        let root = Runtime::state_root_of(&snapshot);
+        let attest = |block, state_root| -> crate::RuntimeCall {
+            Call::AttestCheckpoint { block, state_root }.into()
+        };
+
+        assert_eq!(
+            runtime.dispatch(9, attest(interval, root)),
+            Err(RuntimeError::Checkpoint(CheckpointError::NotValidator))
+        );
+        assert_eq!(
+            runtime.dispatch(1, attest(interval + 1, root)),
+            Err(RuntimeError::Checkpoint(CheckpointError::UnexpectedBlock))
+        );
+        // One dissenting validator can't stop the other three
+        runtime.dispatch(1, attest(interval, root)).unwrap();
+        runtime.dispatch(2, attest(interval, root ^ 1)).unwrap();
+        runtime.dispatch(3, attest(interval, root)).unwrap();
+        assert_eq!(runtime.checkpoint.latest(), None);
+        runtime.dispatch(4, attest(interval, root)).unwrap();
+        assert_eq!(
+            runtime.checkpoint.latest(),
+            Some(Checkpoint {
+                block: interval,
+                state_root: root
+            })
+        );
+        assert_eq!(runtime.checkpoint.pending(), 2 * interval);
+
+        // A node syncs from the export matching the checkpoint, and only that one
+        let checkpoint = runtime.checkpoint.latest().unwrap();
+        let synced = Runtime::sync_from_checkpoint(&snapshot, checkpoint).unwrap();
+        assert_eq!(synced.system.block_number(), interval);
+        assert_eq!(
+            Runtime::sync_from_checkpoint(&runtime.export_state(), checkpoint).map(|_| ()),
+            Err(crate::codec::CodecError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn test_a_missed_checkpoint_moves_on_to_the_next() {
+        let validators = [1u64, 2, 3, 4];
+        let mut runtime = Runtime::new();
+        for who in validators {
+            runtime.balances.set_balance(who, 1000).unwrap();
+            runtime
+                .dispatch(who, staking::Call::Stake { amount: 100 }.into())
+                .unwrap();
+        }
+        let interval = <Runtime as CheckpointConfig>::CHECKPOINT_INTERVAL;
+        let attest = |block, state_root| -> crate::RuntimeCall {
+            Call::AttestCheckpoint { block, state_root }.into()
+        };
+        let execute = |runtime: &mut Runtime, number| {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        };
+
+        // Only half the validators attest the first checkpoint
+        for number in 1..=interval + 1 {
+            execute(&mut runtime, number);
+        }
+        runtime.dispatch(1, attest(interval, 7)).unwrap();
+        runtime.dispatch(2, attest(interval, 7)).unwrap();
+        for number in interval + 2..=2 * interval {
+            execute(&mut runtime, number);
+        }
+        let snapshot = runtime.export_state();
+        assert_eq!(runtime.checkpoint.pending(), interval);
+
+        // Once the next interval block has been executed, the missed one is given up
+        execute(&mut runtime, 2 * interval + 1);
+        assert_eq!(runtime.checkpoint.pending(), 2 * interval);
+        assert!(runtime
+            .system
+            .events()
+            .contains(&Event::CheckpointMissed { block: interval }.into()));
+        assert_eq!(
+            runtime.dispatch(3, attest(interval, 7)),
+            Err(RuntimeError::Checkpoint(CheckpointError::UnexpectedBlock))
+        );
+
+        // The earlier attestations were dropped, so validator 1 attests again
+        let root = Runtime::state_root_of(&snapshot);
+        for who in [1, 2, 3] {
+            runtime.dispatch(who, attest(2 * interval, root)).unwrap();
+        }
+        assert_eq!(
+            runtime.checkpoint.latest(),
+            Some(Checkpoint {
+                block: 2 * interval,
+                state_root: root
+            })
+        );
+        assert_eq!(runtime.checkpoint.pending(), 3 * interval);
+    }
+}
@@ -0,0 +1,364 @@
+// A small SCALE-style codec: fixed-width little-endian integers, compact-prefixed
+// lengths, one tag byte per enum variant. Maps are written sorted by encoded key, so
+// equal state always produces equal bytes.
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodecError {
+    // The input ended in the middle of a value
+    UnexpectedEnd,
+    // An enum tag or bool byte that no variant uses
+    InvalidTag,
+    InvalidUtf8,
+    // The same map key was written twice
+    DuplicateKey,
+    // A decoded value breaks an invariant, e.g. a bounded map holding too much
+    InvalidValue,
+    // Bytes were left over after the value
+    TrailingBytes,
+    // A state snapshot or extrinsic written by a format version this build can't read
+    UnsupportedVersion(u8),
+}
+
+pub trait Encode {
+    fn encode_to(&self, dest: &mut Vec<u8>);
+
+    fn encode(&self) -> Vec<u8> {
+        let mut dest = Vec::new();
+        self.encode_to(&mut dest);
+        dest
+    }
+}
+
+pub trait Decode: Sized {
+    // Decode a value from the front of `input`, advancing it past the value
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError>;
+
+    // Decode a value that must take up the whole input
+    fn decode_all(mut input: &[u8]) -> Result<Self, CodecError> {
+        let value = Self::decode(&mut input)?;
+        if !input.is_empty() {
+            return Err(CodecError::TrailingBytes);
+        }
+        Ok(value)
+    }
+}
+
+// Types that can be both encoded and decoded, as required of storage types
+pub trait Codec: Encode + Decode {}
+
+impl<T: Encode + Decode> Codec for T {}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], CodecError> {
+    if input.len() < len {
+        return Err(CodecError::UnexpectedEnd);
+    }
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+fn take_array<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], CodecError> {
+    let mut array = [0u8; N];
+    array.copy_from_slice(take(input, N)?);
+    Ok(array)
+}
+
+macro_rules! impl_int {
+    ($($int:ty),*) => {$(
+        impl Encode for $int {
+            fn encode_to(&self, dest: &mut Vec<u8>) {
+                dest.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl Decode for $int {
+            fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+                Ok(<$int>::from_le_bytes(take_array(input)?))
+            }
+        }
+    )*};
+}
+
+impl_int!(u8, u16, u32, u64, u128);
+
+// Compact length prefix: the low two bits of the first byte pick a 1, 2 or 4 byte
+// form, or (3) say how many bytes of plain little-endian follow
+pub fn encode_compact(value: u64, dest: &mut Vec<u8>) {
+    let shifted = value.checked_shl(2).filter(|_| value < 1 << 30);
+    match shifted {
+        Some(shifted) if value < 1 << 6 => dest.push(shifted.to_le_bytes()[0]),
+        Some(shifted) if value < 1 << 14 => {
+            dest.extend_from_slice(&(shifted | 0b01).to_le_bytes()[..2])
+        }
+        Some(shifted) => dest.extend_from_slice(&(shifted | 0b10).to_le_bytes()[..4]),
+        None => {
+            let bytes = value.to_le_bytes();
+            let len = bytes
+                .iter()
+                .rposition(|byte| *byte != 0)
+                .map_or(0, |last| last.saturating_add(1));
+            let len = len.max(4);
+            let header = u8::try_from(len.saturating_sub(4)).unwrap_or(0);
+            dest.push(header.checked_shl(2).unwrap_or(0) | 0b11);
+            dest.extend_from_slice(&bytes[..len]);
+        }
+    }
+}
+
+pub fn decode_compact(input: &mut &[u8]) -> Result<u64, CodecError> {
+    let first = *input.first().ok_or(CodecError::UnexpectedEnd)?;
+    let mut bytes = [0u8; 8];
+    let len = match first & 0b11 {
+        0b00 => 1,
+        0b01 => 2,
+        0b10 => 4,
+        _ => {
+            let len = usize::from(first.checked_shr(2).unwrap_or(0)).saturating_add(4);
+            take(input, 1)?;
+            if len > bytes.len() {
+                return Err(CodecError::InvalidValue);
+            }
+            bytes[..len].copy_from_slice(take(input, len)?);
+            return Ok(u64::from_le_bytes(bytes));
+        }
+    };
+    bytes[..len].copy_from_slice(take(input, len)?);
+    Ok(u64::from_le_bytes(bytes).checked_shr(2).unwrap_or(0))
+}
+
+fn encode_len(len: usize, dest: &mut Vec<u8>) {
+    encode_compact(u64::try_from(len).unwrap_or(u64::MAX), dest);
+}
+
+fn decode_len(input: &mut &[u8]) -> Result<usize, CodecError> {
+    usize::try_from(decode_compact(input)?).map_err(|_| CodecError::InvalidValue)
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+impl Encode for bool {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(u8::from(*self));
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        dest.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidUtf8)
+    }
+}
+
+impl<const N: usize> Encode for [u8; N] {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> Decode for [u8; N] {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        take_array(input)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            None => dest.push(0),
+            Some(value) => {
+                dest.push(1);
+                value.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_ref().encode_to(dest);
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Box::new(T::decode(input)?))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        for item in self {
+            item.encode_to(dest);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        // Don't trust the length for the allocation; every item takes at least a byte
+        let mut items = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+        self.1.encode_to(dest);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+// Write map entries sorted by encoded key, for maps without an order of their own
+pub fn encode_unordered_map<'a, K: Encode + 'a, V: Encode + 'a>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+    dest: &mut Vec<u8>,
+) {
+    let mut entries: Vec<_> = entries.map(|(key, value)| (key.encode(), value)).collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    encode_len(entries.len(), dest);
+    for (key, value) in entries {
+        dest.extend_from_slice(&key);
+        value.encode_to(dest);
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for HashMap<K, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_unordered_map(self.iter(), dest);
+    }
+}
+
+impl<K: Decode + Eq + Hash, V: Decode> Decode for HashMap<K, V> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        let mut map = HashMap::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            let key = K::decode(input)?;
+            let value = V::decode(input)?;
+            if map.insert(key, value).is_some() {
+                return Err(CodecError::DuplicateKey);
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        for (key, value) in self {
+            key.encode_to(dest);
+            value.encode_to(dest);
+        }
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let key = K::decode(input)?;
+            let value = V::decode(input)?;
+            if map.insert(key, value).is_some() {
+                return Err(CodecError::DuplicateKey);
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_lengths() {
+        let cases: [(u64, &[u8]); 5] = [
+            (0, &[0x00]),
+            (63, &[0xfc]),
+            (64, &[0x01, 0x01]),
+            (16_384, &[0x02, 0x00, 0x01, 0x00]),
+            (1 << 30, &[0x03, 0x00, 0x00, 0x00, 0x40]),
+        ];
+        for (value, bytes) in cases {
+            let mut encoded = Vec::new();
+            encode_compact(value, &mut encoded);
+            assert_eq!(encoded, bytes);
+            assert_eq!(decode_compact(&mut &encoded[..]), Ok(value));
+        }
+
+        let mut encoded = Vec::new();
+        encode_compact(u64::MAX, &mut encoded);
+        assert_eq!(decode_compact(&mut &encoded[..]), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn test_round_trip_and_errors() {
+        let map: HashMap<u64, Vec<Option<String>>> = [
+            (2, vec![None, Some("é".to_string())]),
+            (1, vec![Some(String::new())]),
+        ]
+        .into_iter()
+        .collect();
+        let encoded = map.encode();
+        assert_eq!(HashMap::decode_all(&encoded), Ok(map));
+
+        // Keys are written in order, whatever order the map iterates in
+        assert_eq!(&encoded[..9], &[0x08, 1, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(u32::decode_all(&[1, 2, 3]), Err(CodecError::UnexpectedEnd));
+        assert_eq!(bool::decode_all(&[2]), Err(CodecError::InvalidTag));
+        assert_eq!(u8::decode_all(&[1, 2]), Err(CodecError::TrailingBytes));
+        assert_eq!(
+            <HashMap<u8, u8>>::decode_all(&[0x08, 1, 1, 1, 2]),
+            Err(CodecError::DuplicateKey)
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use crate::codec::{CodecError, Decode, Encode};
+use crate::governance::{GovernanceConfig, GovernanceError, GovernancePallet, ProposalStatus};
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::{StorageMap, StorageValue};
+use crate::support::{pallet_error, weight_of, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use std::collections::BTreeSet;
+
+// A council whose members can fast-track or veto governance proposals. The
+// membership is set by a Root or Council origin, so governance manages it.
+pub trait CouncilConfig: GovernanceConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Most members the council can have
+    const MAX_MEMBERS: u32;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CouncilError {
+    NotMember,
+    TooManyMembers,
+    DuplicateMember,
+    AlreadyEndorsed,
+    AlreadyVetoed,
+    // The proposal couldn't be fast-tracked or vetoed
+    Governance(GovernanceError),
+}
+
+pallet_error!(CouncilError {
+    0 => NotMember: "Only council members may do this",
+    1 => TooManyMembers: "The council would have more than MAX_MEMBERS members",
+    2 => DuplicateMember: "An account is listed as a member more than once",
+    3 => AlreadyEndorsed: "The member already endorsed fast-tracking this proposal",
+    4 => AlreadyVetoed: "The member already vetoed this proposal",
+    5 => Governance(_): "The proposal couldn't be fast-tracked or vetoed",
+});
+
+impl From<GovernanceError> for CouncilError {
+    fn from(error: GovernanceError) -> Self {
+        CouncilError::Governance(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: CouncilConfig> {
+    MembersSet {
+        members: Vec<T::AccountId>,
+    },
+    Endorsed {
+        member: T::AccountId,
+        proposal_id: u32,
+        endorsements: u32,
+    },
+    Vetoed {
+        member: T::AccountId,
+        proposal_id: u32,
+        vetoes: u32,
+    },
+}
+
+impl<T: CouncilConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice vetoed proposal 0 (1 so far)"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::MembersSet { members } => format!(
+                "council members set to {}",
+                members
+                    .iter()
+                    .map(|member| display_account(labels, member))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Event::Endorsed {
+                member,
+                proposal_id,
+                endorsements,
+            } => format!(
+                "{} endorsed fast-tracking proposal {} ({} so far)",
+                display_account(labels, member),
+                proposal_id,
+                endorsements
+            ),
+            Event::Vetoed {
+                member,
+                proposal_id,
+                vetoes,
+            } => format!(
+                "{} vetoed proposal {} ({} so far)",
+                display_account(labels, member),
+                proposal_id,
+                vetoes
+            ),
+        }
+    }
+}
+
+// Calls an account can submit to the council pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: CouncilConfig> {
+    // Replace the whole membership; needs a Root or Council origin
+    SetMembers { members: Vec<T::AccountId> },
+    // Once a majority of members endorse, the proposal's voting period ends
+    FastTrack { proposal_id: u32 },
+    // Once every member vetoes, the proposal is rejected
+    Veto { proposal_id: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CouncilPallet<T: CouncilConfig> {
+    // Sorted by account
+    members: StorageValue<Vec<T::AccountId>>,
+    // Members that endorsed fast-tracking each proposal
+    endorsements: StorageMap<u32, Vec<T::AccountId>>,
+    // Members that vetoed each proposal
+    vetoes: StorageMap<u32, Vec<T::AccountId>>,
+}
+
+impl<T: CouncilConfig> Default for CouncilPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: CouncilConfig> CouncilPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            members: StorageValue::new(Vec::new()),
+            endorsements: StorageMap::new(),
+            vetoes: StorageMap::new(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method. The runtime only
+    // routes SetMembers for a Root or Council origin.
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        governance: &mut GovernancePallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), CouncilError> {
+        match call {
+            Call::SetMembers { members } => self.set_members(system, members),
+            Call::FastTrack { proposal_id } => {
+                self.fast_track(system, governance, origin, proposal_id)
+            }
+            Call::Veto { proposal_id } => self.veto(system, governance, origin, proposal_id),
+        }
+    }
+
+    // Replace the membership. Pending endorsements and vetoes are dropped, as they
+    // were counted against the old council.
+    pub fn set_members(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        mut members: Vec<T::AccountId>,
+    ) -> Result<(), CouncilError> {
+        members.sort();
+        let distinct: BTreeSet<_> = members.iter().collect();
+        if distinct.len() != members.len() {
+            return Err(CouncilError::DuplicateMember);
+        }
+        if u32::try_from(members.len()).map_or(true, |len| len > T::MAX_MEMBERS) {
+            return Err(CouncilError::TooManyMembers);
+        }
+
+        self.members.put(members.clone());
+        self.endorsements = StorageMap::new();
+        self.vetoes = StorageMap::new();
+        system.deposit_event(Event::MembersSet { members });
+
+        Ok(())
+    }
+
+    // Endorse fast-tracking a proposal; the endorsement that makes a majority
+    // ends its voting period
+    pub fn fast_track(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        governance: &mut GovernancePallet<T>,
+        member: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), CouncilError> {
+        let endorsements = Self::record(
+            &self.members.get(),
+            &mut self.endorsements,
+            governance,
+            member.clone(),
+            proposal_id,
+        )?
+        .ok_or(CouncilError::AlreadyEndorsed)?;
+
+        system.deposit_event(Event::Endorsed {
+            member,
+            proposal_id,
+            endorsements,
+        });
+        if usize::try_from(endorsements)
+            .is_ok_and(|count| count > self.members.get().len().saturating_div(2))
+        {
+            self.endorsements.remove(&proposal_id);
+            governance.fast_track(system, proposal_id)?;
+        }
+
+        Ok(())
+    }
+
+    // Veto a proposal; the veto that makes the council unanimous rejects it
+    pub fn veto(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        governance: &mut GovernancePallet<T>,
+        member: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), CouncilError> {
+        let vetoes = Self::record(
+            &self.members.get(),
+            &mut self.vetoes,
+            governance,
+            member.clone(),
+            proposal_id,
+        )?
+        .ok_or(CouncilError::AlreadyVetoed)?;
+
+        system.deposit_event(Event::Vetoed {
+            member,
+            proposal_id,
+            vetoes,
+        });
+        if usize::try_from(vetoes).is_ok_and(|count| count >= self.members.get().len()) {
+            self.vetoes.remove(&proposal_id);
+            self.endorsements.remove(&proposal_id);
+            governance.veto(system, proposal_id)?;
+        }
+
+        Ok(())
+    }
+
+    // Get the council members, sorted by account
+    pub fn members(&self) -> Vec<T::AccountId> {
+        self.members.get()
+    }
+
+    // Get the members that vetoed a proposal so far
+    pub fn vetoes(&self, proposal_id: u32) -> &[T::AccountId] {
+        self.vetoes
+            .get(&proposal_id)
+            .map_or(&[], |vetoes| vetoes.as_slice())
+    }
+
+    // Add `member` to a proposal's list in `motions`. Returns the new count, or None
+    // if the member was already listed.
+    fn record(
+        members: &[T::AccountId],
+        motions: &mut StorageMap<u32, Vec<T::AccountId>>,
+        governance: &GovernancePallet<T>,
+        member: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<Option<u32>, CouncilError> {
+        if members.binary_search(&member).is_err() {
+            return Err(CouncilError::NotMember);
+        }
+        match governance.get_proposal_status(proposal_id) {
+            None => return Err(GovernanceError::ProposalNotFound.into()),
+            Some(ProposalStatus::Active) => {}
+            Some(_) => return Err(GovernanceError::ProposalNotActive.into()),
+        }
+
+        let listed = motions.get_or_insert_with(proposal_id, Vec::new);
+        if listed.contains(&member) {
+            return Ok(None);
+        }
+        listed.push(member);
+
+        Ok(Some(u32::try_from(listed.len()).unwrap_or(u32::MAX)))
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.members
+            .storage_usage()
+            .saturating_add(self.endorsements.storage_usage())
+            .saturating_add(self.vetoes.storage_usage())
+    }
+}
+
+impl<T: CouncilConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::SetMembers { members } => weight_of(members.len(), 1).saturating_add(2),
+            Call::FastTrack { .. } | Call::Veto { .. } => 4,
+        }
+    }
+}
+
+impl<T: CouncilConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::SetMembers { members } => {
+                0u8.encode_to(dest);
+                members.encode_to(dest);
+            }
+            Call::FastTrack { proposal_id } => {
+                1u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+            }
+            Call::Veto { proposal_id } => {
+                2u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: CouncilConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::SetMembers {
+                members: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::FastTrack {
+                proposal_id: Decode::decode(input)?,
+            }),
+            2 => Ok(Call::Veto {
+                proposal_id: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: CouncilConfig> Encode for CouncilPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.members.encode_to(dest);
+        self.endorsements.encode_to(dest);
+        self.vetoes.encode_to(dest);
+    }
+}
+
+impl<T: CouncilConfig> Decode for CouncilPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            members: Decode::decode(input)?,
+            endorsements: Decode::decode(input)?,
+            vetoes: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{governance, staking, Origin, Runtime, RuntimeCall, RuntimeError};
+
+    fn council_runtime(members: Vec<u64>) -> Runtime {
+        let mut runtime = Runtime::new();
+        for who in 1..=4u64 {
+            runtime.balances.set_balance(who, 1000).unwrap();
+            runtime
+                .dispatch(who, staking::Call::Stake { amount: 500 }.into())
+                .unwrap();
+        }
+        runtime
+            .dispatch_as(Origin::Root, Call::SetMembers { members }.into())
+            .unwrap();
+        for description in ["Fast", "Slow"] {
+            runtime
+                .dispatch(
+                    4,
+                    governance::Call::CreateProposal {
+                        description: description.to_string(),
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+        runtime
+    }
+
+    #[test]
+    fn test_majority_fast_tracks_proposal() {
+        let mut runtime = council_runtime(vec![3, 1, 2]);
+        assert_eq!(runtime.council.members(), vec![1, 2, 3]);
+        runtime
+            .dispatch(
+                4,
+                governance::Call::Vote {
+                    proposal_id: 0,
+                    vote_type: true,
+                    conviction: governance::Conviction::None,
+                }
+                .into(),
+            )
+            .unwrap();
+
+        let fast_track: RuntimeCall = Call::FastTrack { proposal_id: 0 }.into();
+        assert_eq!(
+            runtime.dispatch(4, fast_track.clone()),
+            Err(RuntimeError::Council(CouncilError::NotMember))
+        );
+        runtime.dispatch(1, fast_track.clone()).unwrap();
+        assert_eq!(
+            runtime.dispatch(1, fast_track.clone()),
+            Err(RuntimeError::Council(CouncilError::AlreadyEndorsed))
+        );
+        runtime.on_finalize(0);
+        assert_eq!(
+            runtime.governance.get_proposal_status(0),
+            Some(&ProposalStatus::Active)
+        );
+
+        // Two of three is a majority, so the proposal closes at the end of the block
+        runtime.dispatch(2, fast_track).unwrap();
+        runtime.on_finalize(0);
+        assert_eq!(
+            runtime.governance.get_proposal_status(0),
+            Some(&ProposalStatus::Approved)
+        );
+        assert_eq!(
+            runtime.governance.get_proposal_status(1),
+            Some(&ProposalStatus::Active)
+        );
+    }
+
+    #[test]
+    fn test_unanimous_veto_rejects_proposal() {
+        let mut runtime = council_runtime(vec![1, 2]);
+        let veto: RuntimeCall = Call::Veto { proposal_id: 1 }.into();
+        runtime.dispatch(1, veto.clone()).unwrap();
+        assert_eq!(runtime.council.vetoes(1), &[1]);
+        assert_eq!(
+            runtime.governance.get_proposal_status(1),
+            Some(&ProposalStatus::Active)
+        );
+
+        runtime.dispatch(2, veto.clone()).unwrap();
+        assert_eq!(
+            runtime.governance.get_proposal_status(1),
+            Some(&ProposalStatus::Rejected)
+        );
+        assert!(runtime
+            .system
+            .events()
+            .contains(&governance::Event::ProposalVetoed { proposal_id: 1 }.into()));
+        assert_eq!(
+            runtime.dispatch(1, veto),
+            Err(RuntimeError::Council(CouncilError::Governance(
+                GovernanceError::ProposalNotActive
+            )))
+        );
+    }
+
+    #[test]
+    fn test_membership_needs_privileged_origin() {
+        let mut runtime = council_runtime(vec![1, 2]);
+        runtime
+            .dispatch(1, Call::Veto { proposal_id: 0 }.into())
+            .unwrap();
+
+        let set_members =
+            |members: Vec<u64>| -> RuntimeCall { Call::SetMembers { members }.into() };
+        assert_eq!(
+            runtime.dispatch(1, set_members(vec![1])),
+            Err(crate::system::SystemError::BadOrigin.into())
+        );
+        assert_eq!(
+            runtime.dispatch_as(Origin::Council, set_members(vec![1, 1])),
+            Err(RuntimeError::Council(CouncilError::DuplicateMember))
+        );
+
+        // A new council starts without the old one's vetoes
+        runtime
+            .dispatch_as(Origin::Council, set_members(vec![2, 3]))
+            .unwrap();
+        assert!(runtime.council.vetoes(0).is_empty());
+    }
+}
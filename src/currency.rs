@@ -0,0 +1,94 @@
+use crate::balances::BalancesConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurrencyError {
+    InvalidAmount,
+    TooManyDecimals,
+    AmountTooLarge,
+}
+
+// Render a raw balance with the runtime's decimals and symbol, e.g. "12.5 UNIT"
+pub fn format_balance<T: BalancesConfig>(amount: T::Balance) -> String {
+    let raw: u128 = amount.into();
+    let decimals = usize::from(T::TOKEN_DECIMALS);
+
+    // Left-pad so there is always at least one integer digit
+    let digits = format!("{:0>width$}", raw, width = decimals.saturating_add(1));
+    let (integer, fraction) = digits.split_at(digits.len().saturating_sub(decimals));
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        format!("{} {}", integer, T::TOKEN_SYMBOL)
+    } else {
+        format!("{}.{} {}", integer, fraction, T::TOKEN_SYMBOL)
+    }
+}
+
+// Parse user input such as "12.5" or "12.5 UNIT" into a raw balance
+pub fn parse_balance<T: BalancesConfig>(input: &str) -> Result<T::Balance, CurrencyError> {
+    let input = input.trim();
+    let amount = input
+        .strip_suffix(T::TOKEN_SYMBOL)
+        .map(str::trim_end)
+        .unwrap_or(input);
+
+    let (integer, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let decimals = usize::from(T::TOKEN_DECIMALS);
+
+    if integer.is_empty() || !integer.chars().all(|c| c.is_ascii_digit()) {
+        return Err(CurrencyError::InvalidAmount);
+    }
+    if amount.contains('.') && fraction.is_empty() {
+        return Err(CurrencyError::InvalidAmount);
+    }
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(CurrencyError::InvalidAmount);
+    }
+    if fraction.len() > decimals {
+        return Err(CurrencyError::TooManyDecimals);
+    }
+
+    let digits = format!("{}{:0<width$}", integer, fraction, width = decimals);
+    let raw: u128 = digits.parse().map_err(|_| CurrencyError::AmountTooLarge)?;
+    T::Balance::try_from(raw).map_err(|_| CurrencyError::AmountTooLarge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runtime;
+
+    #[test]
+    fn test_format_balance() {
+        assert_eq!(format_balance::<Runtime>(1250), "12.5 UNIT");
+        assert_eq!(format_balance::<Runtime>(1200), "12 UNIT");
+        assert_eq!(format_balance::<Runtime>(5), "0.05 UNIT");
+        assert_eq!(format_balance::<Runtime>(0), "0 UNIT");
+    }
+
+    #[test]
+    fn test_parse_balance() {
+        assert_eq!(parse_balance::<Runtime>("12.5"), Ok(1250));
+        assert_eq!(parse_balance::<Runtime>("12.5 UNIT"), Ok(1250));
+        assert_eq!(parse_balance::<Runtime>("0.05"), Ok(5));
+        assert_eq!(parse_balance::<Runtime>("7"), Ok(700));
+
+        assert!(parse_balance::<Runtime>("").is_err());
+        assert!(parse_balance::<Runtime>("1.").is_err());
+        assert!(parse_balance::<Runtime>(".5").is_err());
+        assert!(parse_balance::<Runtime>("-1").is_err());
+        assert_eq!(
+            parse_balance::<Runtime>("1.234"),
+            Err(CurrencyError::TooManyDecimals)
+        );
+        assert!(parse_balance::<Runtime>("1e3").is_err());
+        assert_eq!(
+            parse_balance::<Runtime>("184467440737095516.16"),
+            Err(CurrencyError::AmountTooLarge)
+        );
+
+        // Formatting round-trips through parsing
+        let amount = parse_balance::<Runtime>("42.07").unwrap();
+        assert_eq!(format_balance::<Runtime>(amount), "42.07 UNIT");
+    }
+}
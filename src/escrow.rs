@@ -0,0 +1,528 @@
+use crate::balances::{BalancesConfig, BalancesError, BalancesPallet, HoldReason};
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::{StorageMap, StorageValue};
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::{CheckedAdd, Zero};
+
+// Protected transfers: the amount is held from the sender for a dispute window, in
+// which the sender can cancel or the council can adjudicate. Once the window is
+// over, the transfer completes on its own.
+pub trait EscrowConfig: BalancesConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Blocks a protected transfer stays open to disputes
+    const DISPUTE_WINDOW: Self::BlockNumber;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowError {
+    TransferNotFound,
+    NotSender,
+    ZeroAmount,
+    SelfTransfer,
+    // The dispute window is over; the transfer completes at the end of the block
+    DisputeWindowOver,
+    Overflow,
+    // Holding or moving the funds failed
+    Balances(BalancesError),
+}
+
+pallet_error!(EscrowError {
+    0 => TransferNotFound: "No protected transfer has this id",
+    1 => NotSender: "Only the sender of the transfer may do this",
+    2 => ZeroAmount: "A protected transfer must be for a non-zero amount",
+    3 => SelfTransfer: "Accounts cannot send protected transfers to themselves",
+    4 => DisputeWindowOver: "The dispute window is over; the transfer completes at the end of the block",
+    5 => Overflow: "An arithmetic operation overflowed",
+    6 => Balances(_): "Holding or moving the funds failed",
+});
+
+impl From<BalancesError> for EscrowError {
+    fn from(error: BalancesError) -> Self {
+        EscrowError::Balances(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: EscrowConfig> {
+    TransferProtected {
+        transfer_id: u32,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        completes_at: T::BlockNumber,
+    },
+    TransferCancelled {
+        transfer_id: u32,
+    },
+    TransferCompleted {
+        transfer_id: u32,
+    },
+    TransferAdjudicated {
+        transfer_id: u32,
+        to_recipient: bool,
+    },
+}
+
+impl<T: EscrowConfig> Event<T> {
+    // Render the event as a sentence, e.g. "protected transfer 0 was cancelled"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::TransferProtected {
+                transfer_id,
+                from,
+                to,
+                amount,
+                completes_at,
+            } => format!(
+                "{} sent {} to {} as protected transfer {}, completing at block {:?}",
+                display_account(labels, from),
+                format_balance::<T>(*amount),
+                display_account(labels, to),
+                transfer_id,
+                completes_at
+            ),
+            Event::TransferCancelled { transfer_id } => {
+                format!("protected transfer {} was cancelled", transfer_id)
+            }
+            Event::TransferCompleted { transfer_id } => {
+                format!("protected transfer {} completed", transfer_id)
+            }
+            Event::TransferAdjudicated {
+                transfer_id,
+                to_recipient,
+            } => format!(
+                "the council settled protected transfer {} in favour of the {}",
+                transfer_id,
+                if *to_recipient { "recipient" } else { "sender" }
+            ),
+        }
+    }
+}
+
+// Calls an account can submit to the escrow pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: EscrowConfig> {
+    Transfer {
+        to: T::AccountId,
+        amount: T::Balance,
+    },
+    Cancel {
+        transfer_id: u32,
+    },
+    // Settle a disputed transfer; needs a Root or Council origin
+    Adjudicate {
+        transfer_id: u32,
+        to_recipient: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProtectedTransfer<T: EscrowConfig> {
+    pub from: T::AccountId,
+    pub to: T::AccountId,
+    // Held from the sender under HoldReason::Escrow
+    pub amount: T::Balance,
+    // First block at whose end the transfer completes
+    pub completes_at: T::BlockNumber,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowPallet<T: EscrowConfig> {
+    transfers: StorageMap<u32, ProtectedTransfer<T>>,
+    next_transfer_id: StorageValue<u32>,
+}
+
+impl<T: EscrowConfig> Default for EscrowPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: EscrowConfig> EscrowPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            transfers: StorageMap::new(),
+            next_transfer_id: StorageValue::new(0),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method. The runtime only
+    // routes Adjudicate for a Root or Council origin.
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), EscrowError> {
+        match call {
+            Call::Transfer { to, amount } => {
+                self.transfer(system, balances, origin, to, amount)?;
+            }
+            Call::Cancel { transfer_id } => self.cancel(system, balances, origin, transfer_id)?,
+            Call::Adjudicate {
+                transfer_id,
+                to_recipient,
+            } => self.adjudicate(system, balances, transfer_id, to_recipient)?,
+        }
+
+        Ok(())
+    }
+
+    // Hold `amount` from the sender until the dispute window is over
+    pub fn transfer(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<u32, EscrowError> {
+        if amount.is_zero() {
+            return Err(EscrowError::ZeroAmount);
+        }
+        if from == to {
+            return Err(EscrowError::SelfTransfer);
+        }
+        let transfer_id = self.next_transfer_id.get();
+        let next_transfer_id = transfer_id.checked_add(1).ok_or(EscrowError::Overflow)?;
+        let completes_at = system
+            .block_number()
+            .checked_add(&T::DISPUTE_WINDOW)
+            .ok_or(EscrowError::Overflow)?;
+
+        balances.hold(system, HoldReason::Escrow, from.clone(), amount)?;
+        self.next_transfer_id.put(next_transfer_id);
+        self.transfers.insert(
+            transfer_id,
+            ProtectedTransfer {
+                from: from.clone(),
+                to: to.clone(),
+                amount,
+                completes_at,
+            },
+        );
+        system.deposit_event(Event::TransferProtected {
+            transfer_id,
+            from,
+            to,
+            amount,
+            completes_at,
+        });
+
+        Ok(transfer_id)
+    }
+
+    // Call off a transfer within its dispute window, releasing the funds to the sender
+    pub fn cancel(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        transfer_id: u32,
+    ) -> Result<(), EscrowError> {
+        let transfer = self.open_transfer(system, transfer_id)?;
+        if transfer.from != who {
+            return Err(EscrowError::NotSender);
+        }
+
+        balances.release(system, HoldReason::Escrow, who, transfer.amount)?;
+        self.transfers.remove(&transfer_id);
+        system.deposit_event(Event::TransferCancelled { transfer_id });
+
+        Ok(())
+    }
+
+    // Settle a transfer within its dispute window, paying either the recipient or
+    // back to the sender
+    pub fn adjudicate(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        transfer_id: u32,
+        to_recipient: bool,
+    ) -> Result<(), EscrowError> {
+        let transfer = self.open_transfer(system, transfer_id)?;
+
+        if to_recipient {
+            Self::pay(system, balances, transfer)?;
+        } else {
+            balances.release(
+                system,
+                HoldReason::Escrow,
+                transfer.from.clone(),
+                transfer.amount,
+            )?;
+        }
+        self.transfers.remove(&transfer_id);
+        system.deposit_event(Event::TransferAdjudicated {
+            transfer_id,
+            to_recipient,
+        });
+
+        Ok(())
+    }
+
+    // End-of-block hook: complete every transfer whose dispute window is over
+    pub fn on_finalize(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        now: T::BlockNumber,
+    ) {
+        let mut due: Vec<u32> = self
+            .transfers
+            .iter()
+            .filter(|(_, transfer)| transfer.completes_at <= now)
+            .map(|(transfer_id, _)| *transfer_id)
+            .collect();
+        due.sort_unstable();
+
+        for transfer_id in due {
+            let Some(transfer) = self.transfers.get(&transfer_id) else {
+                continue;
+            };
+            // The held funds can't have gone anywhere else, so a failure would be a
+            // bug; the transfer is then kept to be retried in later blocks
+            if Self::pay(system, balances, transfer).is_ok() {
+                self.transfers.remove(&transfer_id);
+                system.deposit_event(Event::TransferCompleted { transfer_id });
+            }
+        }
+    }
+
+    // Get a pending protected transfer
+    pub fn get_transfer(&self, transfer_id: u32) -> Option<&ProtectedTransfer<T>> {
+        self.transfers.get(&transfer_id)
+    }
+
+    // List the pending transfers an account sends or receives, in id order
+    pub fn transfers_of(&self, who: &T::AccountId) -> Vec<u32> {
+        let mut transfer_ids: Vec<u32> = self
+            .transfers
+            .iter()
+            .filter(|(_, transfer)| transfer.from == *who || transfer.to == *who)
+            .map(|(transfer_id, _)| *transfer_id)
+            .collect();
+        transfer_ids.sort_unstable();
+        transfer_ids
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.next_transfer_id
+            .storage_usage()
+            .saturating_add(self.transfers.storage_usage())
+    }
+
+    fn open_transfer(
+        &self,
+        system: &SystemPallet<T>,
+        transfer_id: u32,
+    ) -> Result<&ProtectedTransfer<T>, EscrowError> {
+        let transfer = self
+            .transfers
+            .get(&transfer_id)
+            .ok_or(EscrowError::TransferNotFound)?;
+        if system.block_number() >= transfer.completes_at {
+            return Err(EscrowError::DisputeWindowOver);
+        }
+        Ok(transfer)
+    }
+
+    fn pay(
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        transfer: &ProtectedTransfer<T>,
+    ) -> Result<(), BalancesError> {
+        balances.transfer_held(
+            system,
+            HoldReason::Escrow,
+            transfer.from.clone(),
+            transfer.to.clone(),
+            transfer.amount,
+        )
+    }
+}
+
+impl<T: EscrowConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::Transfer { .. } | Call::Adjudicate { .. } => 4,
+            Call::Cancel { .. } => 3,
+        }
+    }
+}
+
+impl<T: EscrowConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::Transfer { to, amount } => {
+                0u8.encode_to(dest);
+                to.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::Cancel { transfer_id } => {
+                1u8.encode_to(dest);
+                transfer_id.encode_to(dest);
+            }
+            Call::Adjudicate {
+                transfer_id,
+                to_recipient,
+            } => {
+                2u8.encode_to(dest);
+                transfer_id.encode_to(dest);
+                to_recipient.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: EscrowConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::Transfer {
+                to: Decode::decode(input)?,
+                amount: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::Cancel {
+                transfer_id: Decode::decode(input)?,
+            }),
+            2 => Ok(Call::Adjudicate {
+                transfer_id: Decode::decode(input)?,
+                to_recipient: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: EscrowConfig> Encode for ProtectedTransfer<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.from.encode_to(dest);
+        self.to.encode_to(dest);
+        self.amount.encode_to(dest);
+        self.completes_at.encode_to(dest);
+    }
+}
+
+impl<T: EscrowConfig> Decode for ProtectedTransfer<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            from: Decode::decode(input)?,
+            to: Decode::decode(input)?,
+            amount: Decode::decode(input)?,
+            completes_at: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: EscrowConfig> Encode for EscrowPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.transfers.encode_to(dest);
+        self.next_transfer_id.encode_to(dest);
+    }
+}
+
+impl<T: EscrowConfig> Decode for EscrowPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            transfers: Decode::decode(input)?,
+            next_transfer_id: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Block, Header, Origin, Runtime, RuntimeCall, RuntimeError};
+
+    fn run_to(runtime: &mut Runtime, number: u32) {
+        for number in runtime.system.block_number() + 1..=number {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_transfer_completes_after_dispute_window() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        runtime
+            .dispatch(
+                alice,
+                Call::Transfer {
+                    to: bob,
+                    amount: 300,
+                }
+                .into(),
+            )
+            .unwrap();
+        assert_eq!(
+            runtime.balances.get_held_balance(HoldReason::Escrow, alice),
+            300u64
+        );
+        assert_eq!(
+            runtime.dispatch(bob, Call::Cancel { transfer_id: 0 }.into()),
+            Err(RuntimeError::Escrow(EscrowError::NotSender))
+        );
+
+        let window = <Runtime as EscrowConfig>::DISPUTE_WINDOW;
+        run_to(&mut runtime, window - 1);
+        assert_eq!(runtime.balances.get_free_balance(bob), 0u64);
+        run_to(&mut runtime, window);
+        assert_eq!(runtime.balances.get_free_balance(bob), 300u64);
+        assert_eq!(runtime.balances.get_reserved_balance(alice), 0u64);
+        assert!(runtime.escrow.get_transfer(0).is_none());
+    }
+
+    #[test]
+    fn test_sender_cancels_and_council_adjudicates() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        for amount in [100, 200, 300] {
+            runtime
+                .dispatch(alice, Call::Transfer { to: bob, amount }.into())
+                .unwrap();
+        }
+
+        runtime
+            .dispatch(alice, Call::Cancel { transfer_id: 0 }.into())
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(alice), 500u64);
+
+        let adjudicate = |transfer_id, to_recipient| -> RuntimeCall {
+            Call::Adjudicate {
+                transfer_id,
+                to_recipient,
+            }
+            .into()
+        };
+        assert_eq!(
+            runtime.dispatch(alice, adjudicate(1, false)),
+            Err(crate::system::SystemError::BadOrigin.into())
+        );
+        runtime
+            .dispatch_as(Origin::Council, adjudicate(1, true))
+            .unwrap();
+        runtime
+            .dispatch_as(Origin::Council, adjudicate(2, false))
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(bob), 200u64);
+        assert_eq!(runtime.balances.get_free_balance(alice), 800u64);
+        assert_eq!(
+            runtime.dispatch_as(Origin::Council, adjudicate(2, true)),
+            Err(RuntimeError::Escrow(EscrowError::TransferNotFound))
+        );
+    }
+}
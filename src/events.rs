@@ -0,0 +1,289 @@
+// Bounded event history: the events of the last `retain` blocks stay in memory and
+// older blocks are handed to an archival sink, so long runs such as simulations keep
+// their history without holding all of it. Account queries cover both sides.
+
+use crate::codec::{CodecError, Decode, Encode};
+use crate::labels::AccountLabels;
+use crate::storage::StorageError;
+use crate::RuntimeEvent;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+// An event as kept by a sink: described rather than structured, with the accounts
+// it mentions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedEvent {
+    pub block: u32,
+    pub description: String,
+    // Ordered and without duplicates
+    pub accounts: Vec<u64>,
+}
+
+impl ArchivedEvent {
+    pub fn new(block: u32, event: &RuntimeEvent) -> Self {
+        let mentions = Mentions(RefCell::new(BTreeSet::new()));
+        let description = event.describe(&mentions);
+        Self {
+            block,
+            description,
+            accounts: mentions.0.into_inner().into_iter().collect(),
+        }
+    }
+}
+
+// Notes every account an event's description shows, without labelling any
+struct Mentions(RefCell<BTreeSet<u64>>);
+
+impl AccountLabels<u64> for Mentions {
+    fn label(&self, who: &u64) -> Option<String> {
+        self.0.borrow_mut().insert(*who);
+        None
+    }
+}
+
+// Where events go once they leave the in-memory window
+pub trait EventSink {
+    fn archive(&mut self, events: Vec<ArchivedEvent>) -> Result<(), StorageError>;
+
+    // Archived events mentioning `who`, oldest first. Sinks that don't keep an index
+    // return nothing.
+    fn events_for_account(&self, who: u64) -> Result<Vec<ArchivedEvent>, StorageError>;
+}
+
+// Sink appending events to a file, with an in-memory index by account that is
+// rebuilt from the file on opening
+pub struct FileSink {
+    path: PathBuf,
+    // Offset of each event's record in the file, by account
+    index: HashMap<u64, Vec<u64>>,
+    len: u64,
+}
+
+impl FileSink {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let path = path.into();
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut sink = Self {
+            path,
+            index: HashMap::new(),
+            len: 0,
+        };
+        let mut input = &bytes[..];
+        while !input.is_empty() {
+            let event = ArchivedEvent::decode(&mut input)?;
+            let next = offset(bytes.len().saturating_sub(input.len()))?;
+            sink.note(&event, sink.len);
+            sink.len = next;
+        }
+        Ok(sink)
+    }
+
+    fn note(&mut self, event: &ArchivedEvent, at: u64) {
+        for who in &event.accounts {
+            self.index.entry(*who).or_default().push(at);
+        }
+    }
+}
+
+fn offset(len: usize) -> Result<u64, StorageError> {
+    u64::try_from(len).map_err(|_| StorageError::Codec(CodecError::InvalidValue))
+}
+
+impl EventSink for FileSink {
+    fn archive(&mut self, events: Vec<ArchivedEvent>) -> Result<(), StorageError> {
+        let mut records = Vec::new();
+        let mut offsets = Vec::new();
+        for event in &events {
+            let at = self
+                .len
+                .checked_add(offset(records.len())?)
+                .ok_or(StorageError::Codec(CodecError::InvalidValue))?;
+            offsets.push(at);
+            event.encode_to(&mut records);
+        }
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&records)?;
+        for (event, at) in events.iter().zip(offsets) {
+            self.note(event, at);
+        }
+        self.len = self
+            .len
+            .checked_add(offset(records.len())?)
+            .ok_or(StorageError::Codec(CodecError::InvalidValue))?;
+        Ok(())
+    }
+
+    fn events_for_account(&self, who: u64) -> Result<Vec<ArchivedEvent>, StorageError> {
+        let Some(offsets) = self.index.get(&who) else {
+            return Ok(Vec::new());
+        };
+        let bytes = std::fs::read(&self.path)?;
+        offsets
+            .iter()
+            .map(|at| {
+                let start = usize::try_from(*at)
+                    .map_err(|_| StorageError::Codec(CodecError::InvalidValue))?;
+                let mut input = bytes
+                    .get(start..)
+                    .ok_or(StorageError::Codec(CodecError::UnexpectedEnd))?;
+                Ok(ArchivedEvent::decode(&mut input)?)
+            })
+            .collect()
+    }
+}
+
+// Sink forwarding events to a channel, e.g. for another thread to store or display.
+// It keeps no index, so archived events drop out of account queries.
+pub struct ChannelSink(pub Sender<ArchivedEvent>);
+
+impl EventSink for ChannelSink {
+    fn archive(&mut self, events: Vec<ArchivedEvent>) -> Result<(), StorageError> {
+        for event in events {
+            // A closed receiver just means nobody is listening any more
+            let _ = self.0.send(event);
+        }
+        Ok(())
+    }
+
+    fn events_for_account(&self, _who: u64) -> Result<Vec<ArchivedEvent>, StorageError> {
+        Ok(Vec::new())
+    }
+}
+
+pub struct EventLog<S: EventSink> {
+    // Blocks kept in memory
+    retain: usize,
+    // Events of the most recent blocks, oldest block first
+    recent: VecDeque<(u32, Vec<RuntimeEvent>)>,
+    sink: S,
+}
+
+impl<S: EventSink> EventLog<S> {
+    pub fn new(retain: usize, sink: S) -> Self {
+        Self {
+            retain,
+            recent: VecDeque::new(),
+            sink,
+        }
+    }
+
+    // Record a block's events, e.g. `runtime.system.take_events()` after executing
+    // it. Blocks that fall out of the window go to the sink.
+    pub fn record_block(
+        &mut self,
+        block: u32,
+        events: Vec<RuntimeEvent>,
+    ) -> Result<(), StorageError> {
+        self.recent.push_back((block, events));
+        while self.recent.len() > self.retain {
+            if let Some((block, events)) = self.recent.pop_front() {
+                let archived = events
+                    .iter()
+                    .map(|event| ArchivedEvent::new(block, event))
+                    .collect();
+                self.sink.archive(archived)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Events still held in memory, oldest first
+    pub fn recent(&self) -> impl Iterator<Item = (u32, &RuntimeEvent)> + '_ {
+        self.recent
+            .iter()
+            .flat_map(|(block, events)| events.iter().map(move |event| (*block, event)))
+    }
+
+    // Every event mentioning `who`, archived and recent, oldest first
+    pub fn events_for_account(&self, who: u64) -> Result<Vec<ArchivedEvent>, StorageError> {
+        let mut events = self.sink.events_for_account(who)?;
+        events.extend(
+            self.recent()
+                .map(|(block, event)| ArchivedEvent::new(block, event))
+                .filter(|event| event.accounts.contains(&who)),
+        );
+        Ok(events)
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+}
+
+impl Encode for ArchivedEvent {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block.encode_to(dest);
+        self.description.encode_to(dest);
+        self.accounts.encode_to(dest);
+    }
+}
+
+impl Decode for ArchivedEvent {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            block: Decode::decode(input)?,
+            description: Decode::decode(input)?,
+            accounts: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{balances, Block, Extrinsic, Header, Runtime};
+
+    #[test]
+    fn test_old_events_move_to_the_sink() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let path = std::env::temp_dir().join(format!("events-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        let mut log = EventLog::new(2, FileSink::open(&path).unwrap());
+        for (number, to) in [(1, bob), (2, charlie), (3, bob)] {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: vec![Extrinsic {
+                        caller: alice,
+                        nonce: number - 1,
+                        call: balances::Call::Transfer { to, amount: 10 }.into(),
+                    }],
+                })
+                .unwrap();
+            log.record_block(number, runtime.system.take_events())
+                .unwrap();
+        }
+        assert_eq!(log.recent().map(|(block, _)| block).min(), Some(2));
+
+        // Block 1 is only in the file, block 3 only in memory
+        let blocks = |events: Vec<ArchivedEvent>| {
+            events
+                .iter()
+                .filter(|event| event.description.contains("transferred"))
+                .map(|event| event.block)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(blocks(log.events_for_account(bob).unwrap()), vec![1, 3]);
+        assert_eq!(blocks(log.events_for_account(charlie).unwrap()), vec![2]);
+
+        // The index is rebuilt from the file
+        let reopened = FileSink::open(&path).unwrap();
+        assert_eq!(blocks(reopened.events_for_account(bob).unwrap()), vec![1]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::balances::{BalancesError, BalancesPallet};
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::governance::{GovernanceConfig, GovernancePallet};
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::StorageMap;
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::CheckedAdd;
+
+// Test-token faucet for classroom chains: any account can mint FAUCET_AMOUNT once.
+// Claiming again takes FAUCET_COOLDOWN blocks and a vote on one of the
+// PARTICIPATION_WINDOW most recent proposals, so the tokens go to people taking part;
+// accounts with a governance participation score also need MIN_PARTICIPATION_SCORE.
+pub trait FaucetConfig: GovernanceConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Minted into the claimer's free balance
+    const FAUCET_AMOUNT: Self::Balance;
+    // Blocks between two claims by the same account
+    const FAUCET_COOLDOWN: Self::BlockNumber;
+    // How many of the latest proposals a repeat claimer must have voted among
+    const PARTICIPATION_WINDOW: u32;
+    // Lowest governance participation score, in parts per million, a repeat claimer
+    // may have once it has one; zero turns the check off
+    const MIN_PARTICIPATION_SCORE: u32;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaucetError {
+    // The account claimed less than FAUCET_COOLDOWN blocks ago
+    CoolingDown,
+    // Repeat claims need a vote on one of the recent proposals
+    NoRecentVote,
+    Balances(BalancesError),
+    Overflow,
+    // The account's participation score is below MIN_PARTICIPATION_SCORE
+    LowParticipation,
+}
+
+pallet_error!(FaucetError {
+    0 => CoolingDown: "The account claimed less than FAUCET_COOLDOWN blocks ago",
+    1 => NoRecentVote: "Repeat claims need a vote on one of the recent proposals",
+    2 => Balances(_): "Minting the claimed tokens failed",
+    3 => Overflow: "An arithmetic operation overflowed",
+    4 => LowParticipation: "The account's participation score is below MIN_PARTICIPATION_SCORE",
+});
+
+impl From<BalancesError> for FaucetError {
+    fn from(error: BalancesError) -> Self {
+        FaucetError::Balances(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: FaucetConfig> {
+    Claimed {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+}
+
+impl<T: FaucetConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice claimed 1.00 UNIT from the faucet"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::Claimed { who, amount } => format!(
+                "{} claimed {} from the faucet",
+                display_account(labels, who),
+                format_balance::<T>(*amount)
+            ),
+        }
+    }
+}
+
+// Calls an account can submit to the faucet pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+    Claim,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaucetPallet<T: FaucetConfig> {
+    // Block of each account's last claim
+    last_claim: StorageMap<T::AccountId, T::BlockNumber>,
+}
+
+impl<T: FaucetConfig> Default for FaucetPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FaucetConfig> FaucetPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            last_claim: StorageMap::new(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        governance: &GovernancePallet<T>,
+        origin: T::AccountId,
+        call: Call,
+    ) -> Result<(), FaucetError> {
+        match call {
+            Call::Claim => self.claim(system, balances, governance, origin),
+        }
+    }
+
+    // Mint FAUCET_AMOUNT to the account, if it may claim at this block
+    pub fn claim(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        governance: &GovernancePallet<T>,
+        who: T::AccountId,
+    ) -> Result<(), FaucetError> {
+        let now = system.block_number();
+        if let Some(last) = self.last_claim.get(&who) {
+            let next = last
+                .checked_add(&T::FAUCET_COOLDOWN)
+                .ok_or(FaucetError::Overflow)?;
+            if now < next {
+                return Err(FaucetError::CoolingDown);
+            }
+            if !governance.voted_recently(&who, T::PARTICIPATION_WINDOW) {
+                return Err(FaucetError::NoRecentVote);
+            }
+            if governance
+                .participation_score(&who)
+                .is_some_and(|score| score < T::MIN_PARTICIPATION_SCORE)
+            {
+                return Err(FaucetError::LowParticipation);
+            }
+        }
+
+        balances.mint(system, who.clone(), T::FAUCET_AMOUNT)?;
+        self.last_claim.insert(who.clone(), now);
+        system.deposit_event(Event::Claimed {
+            who,
+            amount: T::FAUCET_AMOUNT,
+        });
+
+        Ok(())
+    }
+
+    // Get the block of the account's last claim
+    pub fn last_claim(&self, who: &T::AccountId) -> Option<T::BlockNumber> {
+        self.last_claim.get(who).copied()
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.last_claim.storage_usage()
+    }
+}
+
+impl GetWeight for Call {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::Claim => 2,
+        }
+    }
+}
+
+impl Encode for Call {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::Claim => 0u8.encode_to(dest),
+        }
+    }
+}
+
+impl Decode for Call {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::Claim),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: FaucetConfig> Encode for FaucetPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.last_claim.encode_to(dest);
+    }
+}
+
+impl<T: FaucetConfig> Decode for FaucetPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            last_claim: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{governance, Block, Header, Runtime, RuntimeError};
+
+    #[test]
+    fn test_repeat_claims_need_a_recent_vote() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.dispatch(alice, Call::Claim.into()).unwrap();
+        assert_eq!(runtime.balances.get_free_balance(alice), 100u64);
+        assert_eq!(
+            runtime.dispatch(alice, Call::Claim.into()),
+            Err(RuntimeError::Faucet(FaucetError::CoolingDown))
+        );
+
+        let cooldown = <Runtime as FaucetConfig>::FAUCET_COOLDOWN;
+        for number in 1..=cooldown {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+        assert_eq!(
+            runtime.dispatch(alice, Call::Claim.into()),
+            Err(RuntimeError::Faucet(FaucetError::NoRecentVote))
+        );
+
+        // Voting with a stake earns the next claim
+        runtime.balances.set_balance(bob, 1000).unwrap();
+        runtime
+            .dispatch(
+                bob,
+                governance::Call::CreateProposal {
+                    description: "Faucet".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+        runtime
+            .dispatch(alice, crate::staking::Call::Stake { amount: 50 }.into())
+            .unwrap();
+        runtime
+            .dispatch(
+                alice,
+                governance::Call::Vote {
+                    proposal_id: 0,
+                    vote_type: true,
+                    conviction: governance::Conviction::None,
+                }
+                .into(),
+            )
+            .unwrap();
+        runtime.dispatch(alice, Call::Claim.into()).unwrap();
+        assert_eq!(runtime.faucet.last_claim(&alice), Some(cooldown));
+    }
+
+    #[test]
+    fn test_repeat_claims_need_a_participation_score() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.dispatch(alice, Call::Claim.into()).unwrap();
+        runtime.balances.set_balance(bob, 1000).unwrap();
+        for who in [alice, bob] {
+            runtime
+                .dispatch(who, crate::staking::Call::Stake { amount: 50 }.into())
+                .unwrap();
+        }
+        let propose = |runtime: &mut Runtime| {
+            runtime
+                .dispatch(
+                    bob,
+                    governance::Call::CreateProposal {
+                        description: "Faucet".to_string(),
+                    }
+                    .into(),
+                )
+                .unwrap();
+        };
+        let vote = |runtime: &mut Runtime, who, proposal_id| {
+            runtime
+                .dispatch(
+                    who,
+                    governance::Call::Vote {
+                        proposal_id,
+                        vote_type: true,
+                        conviction: governance::Conviction::None,
+                    }
+                    .into(),
+                )
+                .unwrap();
+        };
+
+        // alice votes on the first proposal, then sits out the next eight
+        for proposal_id in 0..9 {
+            propose(&mut runtime);
+            vote(&mut runtime, bob, proposal_id);
+        }
+        vote(&mut runtime, alice, 0);
+        let voting_end = <Runtime as governance::GovernanceConfig>::VOTING_PERIOD;
+        for number in 1..=voting_end + 2 {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+        let score = runtime.governance.participation_score(&alice).unwrap();
+        assert!(score < <Runtime as FaucetConfig>::MIN_PARTICIPATION_SCORE);
+
+        // A fresh vote isn't enough to make up for it
+        propose(&mut runtime);
+        vote(&mut runtime, alice, 9);
+        assert_eq!(
+            runtime.dispatch(alice, Call::Claim.into()),
+            Err(RuntimeError::Faucet(FaucetError::LowParticipation))
+        );
+    }
+}
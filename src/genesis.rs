@@ -0,0 +1,219 @@
+use crate::balances::{BalancesError, BalancesPallet};
+use crate::currency::{parse_balance, CurrencyError};
+use crate::staking::{StakingConfig, StakingError, StakingPallet};
+use crate::system::SystemPallet;
+use num::traits::Zero;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenesisError {
+    // The file couldn't be read
+    Io(std::io::ErrorKind),
+    // The first line must be `account,balance,bond`
+    InvalidHeader,
+    WrongColumnCount { line: usize },
+    InvalidAccount { line: usize },
+    InvalidAmount { line: usize, error: CurrencyError },
+    DuplicateAccount { line: usize },
+    BondExceedsBalance { line: usize },
+    Balances(BalancesError),
+    Staking(StakingError),
+}
+
+impl From<BalancesError> for GenesisError {
+    fn from(error: BalancesError) -> Self {
+        GenesisError::Balances(error)
+    }
+}
+
+impl From<StakingError> for GenesisError {
+    fn from(error: StakingError) -> Self {
+        GenesisError::Staking(error)
+    }
+}
+
+// One account's starting state
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenesisAccount<T: StakingConfig> {
+    pub who: T::AccountId,
+    // Total balance, including the bond
+    pub balance: T::Balance,
+    pub bond: T::Balance,
+}
+
+// Initial chain state, e.g. for setting up a classroom chain
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenesisConfig<T: StakingConfig> {
+    pub accounts: Vec<GenesisAccount<T>>,
+}
+
+impl<T: StakingConfig> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self {
+            accounts: Vec::new(),
+        }
+    }
+}
+
+impl<T: StakingConfig> GenesisConfig<T> {
+    // Write the accounts into fresh pallets. Setup events are dropped, so the chain
+    // starts with an empty event log.
+    pub fn assimilate_storage(
+        &self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        staking: &mut StakingPallet<T>,
+    ) -> Result<(), GenesisError> {
+        for account in &self.accounts {
+            balances.set_balance(account.who.clone(), account.balance)?;
+            if !account.bond.is_zero() {
+                staking.stake(system, balances, account.who.clone(), account.bond)?;
+            }
+        }
+        system.take_events();
+        Ok(())
+    }
+}
+
+// Read a genesis config from a CSV file; see `parse_genesis_csv` for the format
+pub fn genesis_from_csv<T: StakingConfig>(
+    path: impl AsRef<Path>,
+) -> Result<GenesisConfig<T>, GenesisError>
+where
+    T::AccountId: FromStr,
+{
+    let contents = std::fs::read_to_string(path).map_err(|error| GenesisError::Io(error.kind()))?;
+    parse_genesis_csv(&contents)
+}
+
+// Parse CSV with an `account,balance,bond` header and one account per line. Amounts
+// are in tokens, e.g. "12.5", and an empty bond means nothing is bonded. Blank lines
+// and lines starting with '#' are skipped. Line numbers in errors start at 1.
+pub fn parse_genesis_csv<T: StakingConfig>(contents: &str) -> Result<GenesisConfig<T>, GenesisError>
+where
+    T::AccountId: FromStr,
+{
+    let mut lines = contents
+        .lines()
+        .zip(1usize..)
+        .map(|(text, line)| (text.trim(), line))
+        .filter(|(text, _)| !text.is_empty() && !text.starts_with('#'));
+
+    let header = lines.next().map(|(text, _)| columns(text));
+    if header != Some(vec!["account", "balance", "bond"]) {
+        return Err(GenesisError::InvalidHeader);
+    }
+
+    let mut seen = HashSet::new();
+    let mut config = GenesisConfig::default();
+    for (text, line) in lines {
+        let [who, balance, bond] = columns(text)[..] else {
+            return Err(GenesisError::WrongColumnCount { line });
+        };
+        let who: T::AccountId = who
+            .parse()
+            .map_err(|_| GenesisError::InvalidAccount { line })?;
+        let amount = |input: &str| {
+            if input.is_empty() {
+                return Ok(T::Balance::zero());
+            }
+            parse_balance::<T>(input).map_err(|error| GenesisError::InvalidAmount { line, error })
+        };
+        let balance = amount(balance)?;
+        let bond = amount(bond)?;
+
+        if bond > balance {
+            return Err(GenesisError::BondExceedsBalance { line });
+        }
+        if !seen.insert(who.clone()) {
+            return Err(GenesisError::DuplicateAccount { line });
+        }
+        config.accounts.push(GenesisAccount { who, balance, bond });
+    }
+
+    Ok(config)
+}
+
+fn columns(text: &str) -> Vec<&str> {
+    text.split(',').map(str::trim).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runtime;
+
+    #[test]
+    fn test_parse_genesis_csv() {
+        let csv = "account,balance,bond\n\
+                   # instructor\n\
+                   1, 100, 40.5\n\
+                   \n\
+                   2,12.25,\n";
+        let config = parse_genesis_csv::<Runtime>(csv).unwrap();
+        assert_eq!(
+            config.accounts,
+            vec![
+                GenesisAccount {
+                    who: 1,
+                    balance: 10_000,
+                    bond: 4_050,
+                },
+                GenesisAccount {
+                    who: 2,
+                    balance: 1_225,
+                    bond: 0,
+                },
+            ]
+        );
+
+        let mut runtime = Runtime::new();
+        config
+            .assimilate_storage(
+                &mut runtime.system,
+                &mut runtime.balances,
+                &mut runtime.staking,
+            )
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(1), 5_950);
+        assert_eq!(runtime.staking.get_staked_balance(1), 4_050);
+        assert!(runtime.system.events().is_empty());
+    }
+
+    #[test]
+    fn test_parse_genesis_csv_errors() {
+        let parse = |csv: &str| parse_genesis_csv::<Runtime>(csv).map(|_| ());
+        assert_eq!(parse("who,balance\n1,2"), Err(GenesisError::InvalidHeader));
+        assert_eq!(
+            parse("account,balance,bond\n1,2"),
+            Err(GenesisError::WrongColumnCount { line: 2 })
+        );
+        assert_eq!(
+            parse("account,balance,bond\nalice,2,0"),
+            Err(GenesisError::InvalidAccount { line: 2 })
+        );
+        assert_eq!(
+            parse("account,balance,bond\n1,2,3"),
+            Err(GenesisError::BondExceedsBalance { line: 2 })
+        );
+        assert_eq!(
+            parse("account,balance,bond\n1,2,0\n1,3,0"),
+            Err(GenesisError::DuplicateAccount { line: 3 })
+        );
+        assert_eq!(
+            parse("account,balance,bond\n1,0.001,0"),
+            Err(GenesisError::InvalidAmount {
+                line: 2,
+                error: CurrencyError::TooManyDecimals,
+            })
+        );
+
+        let missing = std::env::temp_dir().join("no-such-genesis.csv");
+        assert_eq!(
+            genesis_from_csv::<Runtime>(missing).map(|_| ()),
+            Err(GenesisError::Io(std::io::ErrorKind::NotFound))
+        );
+    }
+}
@@ -0,0 +1,155 @@
+// Golden tests: record every dispatch of a scenario and the events it caused, then
+// compare the transcript with a checked-in file under `golden/`. Any behavior change
+// shows up as a failing test. After an intended change, rerun with UPDATE_GOLDEN=1
+// to rewrite the files; missing files are written on the first run.
+
+use crate::{Block, Runtime, RuntimeCall};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+// Wraps a runtime and writes down everything dispatched through it
+pub struct Recorder {
+    pub runtime: Runtime,
+    transcript: String,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            runtime: Runtime::new(),
+            transcript: String::new(),
+        }
+    }
+
+    // Dispatch a call and record it, its result and the events it deposited
+    pub fn dispatch(&mut self, origin: u64, call: impl Into<RuntimeCall>) {
+        let call = call.into();
+        let seen = self.runtime.system.events().len();
+        let result = self.runtime.dispatch(origin, call.clone());
+
+        let _ = writeln!(self.transcript, "{} dispatched {:?}", origin, call);
+        let _ = writeln!(self.transcript, "  -> {:?}", result);
+        self.record_events(seen);
+    }
+
+    // Execute a block and record each extrinsic result and the block's events
+    pub fn execute_block(&mut self, block: Block) {
+        let number = block.header.number;
+        let _ = writeln!(self.transcript, "block {}", number);
+        match self.runtime.execute_block(block.clone()) {
+            Ok(results) => {
+                for (extrinsic, result) in block.extrinsics.iter().zip(results) {
+                    let _ = writeln!(
+                        self.transcript,
+                        "{} dispatched {:?}",
+                        extrinsic.caller, extrinsic.call
+                    );
+                    let _ = writeln!(self.transcript, "  -> {:?}", result);
+                }
+                self.record_events(0);
+            }
+            Err(error) => {
+                let _ = writeln!(self.transcript, "  -> {:?}", error);
+            }
+        }
+    }
+
+    fn record_events(&mut self, from: usize) {
+        for event in &self.runtime.system.events()[from..] {
+            let _ = writeln!(self.transcript, "  event {:?}", event);
+        }
+    }
+
+    // Compare the transcript with `golden/<name>.golden`, writing it when asked to
+    pub fn assert_golden(&self, name: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("golden")
+            .join(format!("{}.golden", name));
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() || !path.exists() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &self.transcript).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(&path).unwrap();
+        if expected != self.transcript {
+            let line = expected
+                .lines()
+                .zip(self.transcript.lines())
+                .position(|(expected, actual)| expected != actual)
+                .unwrap_or(
+                    expected
+                        .lines()
+                        .count()
+                        .min(self.transcript.lines().count()),
+                );
+            panic!(
+                "{} no longer matches {} (first difference on line {}); \
+                 rerun with UPDATE_GOLDEN=1 if the change is intended\n\nactual:\n{}",
+                name,
+                path.display(),
+                line + 1,
+                self.transcript
+            );
+        }
+    }
+}
+
+// Define a #[test] that runs a scenario against a fresh recorder and checks it
+// against the golden file named after the test
+macro_rules! golden_test {
+    ($name:ident, |$recorder:ident| $body:block) => {
+        #[test]
+        fn $name() {
+            let mut $recorder = $crate::golden::Recorder::new();
+            $body
+            $recorder.assert_golden(stringify!($name));
+        }
+    };
+}
+
+mod scenarios {
+    use crate::{balances, governance, staking, Block, Header};
+
+    golden_test!(stake_vote_and_finalize, |recorder| {
+        let alice = 1u64;
+        let bob = 2u64;
+        recorder.runtime.balances.set_balance(alice, 1000).unwrap();
+        recorder.runtime.balances.set_balance(bob, 500).unwrap();
+
+        recorder.dispatch(
+            alice,
+            balances::Call::Transfer {
+                to: bob,
+                amount: 100,
+            },
+        );
+        recorder.dispatch(alice, staking::Call::Stake { amount: 400 });
+        recorder.dispatch(bob, staking::Call::Stake { amount: 700 });
+        recorder.dispatch(bob, staking::Call::Stake { amount: 600 });
+        recorder.dispatch(
+            alice,
+            governance::Call::CreateProposal {
+                description: "Fund the explorer".to_string(),
+            },
+        );
+        recorder.dispatch(bob, governance::Call::Delegate { to: alice });
+        recorder.dispatch(
+            alice,
+            governance::Call::Vote {
+                proposal_id: 0,
+                vote_type: true,
+                conviction: governance::Conviction::None,
+            },
+        );
+
+        // Voting closes at the end of the voting period
+        for number in 1..=<crate::Runtime as crate::GovernanceConfig>::VOTING_PERIOD {
+            recorder.execute_block(Block {
+                header: Header { number },
+                extrinsics: Vec::new(),
+            });
+        }
+    });
+}
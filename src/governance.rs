@@ -1,168 +1,4733 @@
-use crate::staking::StakingConfig;
-use crate::system::SystemConfig;
-use std::collections::HashMap;
+use crate::balances::{BalancesConfig, BalancesError, BalancesPallet, HoldReason};
+use crate::bounded::{BoundedBTreeMap, BoundedVec};
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::labels::{display_account, AccountLabels};
+use crate::staking::{StakingConfig, StakingPallet};
+use crate::storage::{StorageMap, StorageValue};
+use crate::support::{pallet_error, weight_of, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::{CheckedAdd, CheckedSub, One, Zero};
 
-pub trait GovernanceConfig: StakingConfig + SystemConfig {}
+pub trait GovernanceConfig: StakingConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Maximum number of localized descriptions per proposal
+    const MAX_LOCALIZATIONS: usize;
+    // Maximum length in bytes of a localized description
+    const MAX_LOCALIZED_DESCRIPTION_LEN: usize;
+    // Deposit held from the creator for each localized description
+    const LOCALIZATION_DEPOSIT: Self::Balance;
+    // Number of blocks a proposal stays open before it is finalized automatically
+    const VOTING_PERIOD: Self::BlockNumber;
+    // Share of the total stake that must vote, in parts per million
+    const QUORUM: u32;
+    // Share of the votes cast that must be Aye, in parts per million; the proposal
+    // passes only with strictly more than this (500_000 is a simple majority)
+    const APPROVAL_THRESHOLD: u32;
+    // What happens when the Aye share lands exactly on the approval threshold
+    const TIE_POLICY: TiePolicy<Self::BlockNumber>;
+    // Blocks a voter's stake stays locked after finalization per conviction step
+    const CONVICTION_LOCK_PERIOD: Self::BlockNumber;
+    // Offences at least this severe, in parts per million of the bond, bar the
+    // offender from governance
+    const OFFENCE_BAR_SEVERITY: u32;
+    // Eras an offender stays barred from creating proposals and voting
+    const OFFENCE_BAR_ERAS: u32;
+    // Maximum number of tags per proposal
+    const MAX_TAGS: usize;
+    // Maximum length in bytes of a tag
+    const MAX_TAG_LEN: usize;
+    // Blocks between an approved proposal's finalization and the dispatch of its calls
+    const ENACTMENT_DELAY: Self::BlockNumber;
+    // Maximum number of calls a proposal can carry
+    const MAX_PROPOSAL_CALLS: usize;
+    // Expired proposals finalized per block; any beyond it wait for the next blocks
+    const MAX_FINALIZATIONS_PER_BLOCK: u32;
+    // Weight a vote keeps in an account's participation score for each proposal
+    // finalized after it, in parts per million
+    const PARTICIPATION_DECAY: u32;
+    // Runtime behavior run at each step of a proposal's life; `()` does nothing
+    type ProposalHooks: ProposalHooks<Self>;
+}
+
+// Lets a runtime plug in its own behavior, such as notifications or reputation
+// updates, as proposals are created, voted on and closed. Each hook runs after the
+// pallet's own changes and events.
+pub trait ProposalHooks<T: GovernanceConfig> {
+    fn on_created(_system: &mut SystemPallet<T>, _proposal_id: u32, _creator: &T::AccountId) {}
+
+    // `weight` includes the stake of delegators the vote was cast for
+    fn on_vote(
+        _system: &mut SystemPallet<T>,
+        _proposal_id: u32,
+        _voter: &T::AccountId,
+        _vote_type: bool,
+        _weight: T::Balance,
+    ) {
+    }
+
+    // Called once a proposal stops being active: finalized, vetoed or cancelled
+    fn on_finalized(_system: &mut SystemPallet<T>, _proposal_id: u32, _status: &ProposalStatus) {}
+}
+
+impl<T: GovernanceConfig> ProposalHooks<T> for () {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GovernanceError {
+    ProposalNotFound,
+    ProposalNotActive,
+    AlreadyVoted,
+    NoVotingPower,
+    DependencyNotFound,
+    DependencyNotFinalized,
+    InvalidParameterKey,
+    ZeroSpendAmount,
+    NotProposalCreator,
+    DescriptionTooLong,
+    TooManyLocalizations,
+    InvalidLanguageTag,
+    LocalizationNotFound,
+    SelfDelegation,
+    DelegationCycle,
+    AlreadyDelegating,
+    NotDelegating,
+    InvalidVoteCap,
+    VotesAlreadyCast,
+    CannotAmendTemplate,
+    NotVoted,
+    // The account was slashed for an offence and is temporarily barred
+    ParticipationBarred,
+    TooManyTags,
+    // Tags are up to MAX_TAG_LEN letters, digits and dashes
+    InvalidTag,
+    // A proposal carries between one and MAX_PROPOSAL_CALLS calls
+    InvalidCallCount,
+    // The voting period is over, though the proposal may still await finalization
+    VotingClosed,
+    // Only proposal creation calls can be validated ahead of submission
+    NotAProposal,
+    // Only allowed once the proposal is finalized or cancelled
+    ProposalStillActive,
+    Overflow,
+    // A balances operation (e.g. holding a deposit) failed
+    Balances(BalancesError),
+}
+
+pallet_error!(GovernanceError {
+    0 => ProposalNotFound: "No proposal has this id",
+    1 => ProposalNotActive: "The proposal is no longer open for voting",
+    2 => AlreadyVoted: "The account already voted on this proposal",
+    3 => NoVotingPower: "The voter has no stake, directly or through delegations",
+    4 => DependencyNotFound: "The proposal this one depends on does not exist",
+    5 => DependencyNotFinalized: "The proposal this one depends on is still open",
+    6 => InvalidParameterKey: "The template names an unknown parameter",
+    7 => ZeroSpendAmount: "A treasury spend must be for a non-zero amount",
+    8 => NotProposalCreator: "Only the creator of the proposal may do this",
+    9 => DescriptionTooLong: "The localized description exceeds MAX_LOCALIZED_DESCRIPTION_LEN",
+    10 => TooManyLocalizations: "The proposal already has MAX_LOCALIZATIONS descriptions",
+    11 => InvalidLanguageTag: "The language tag is not well formed",
+    12 => LocalizationNotFound: "The proposal has no description in this language",
+    13 => SelfDelegation: "Accounts cannot delegate to themselves",
+    14 => DelegationCycle: "The delegation would lead back to the delegator",
+    15 => AlreadyDelegating: "The account is already delegating",
+    16 => NotDelegating: "The account is not delegating",
+    17 => Overflow: "An arithmetic operation overflowed",
+    18 => Balances(_): "A balances operation (e.g. holding a deposit) failed",
+    19 => InvalidVoteCap: "The vote cap is zero or more than the whole electorate",
+    20 => VotesAlreadyCast: "This can only be done before the first vote",
+    21 => CannotAmendTemplate: "A template proposal's description is derived from its arguments",
+    22 => NotVoted: "The account has not voted on this proposal",
+    23 => ParticipationBarred: "The account was slashed for an offence and is temporarily barred",
+    24 => TooManyTags: "The proposal would have more than MAX_TAGS tags",
+    25 => InvalidTag: "Tags are up to MAX_TAG_LEN letters, digits and dashes",
+    26 => InvalidCallCount: "A proposal carries between one and MAX_PROPOSAL_CALLS calls",
+    27 => VotingClosed: "The voting period is over, though the proposal may still await finalization",
+    28 => NotAProposal: "Only proposal creation calls can be validated ahead of submission",
+    29 => ProposalStillActive: "This can only be done once the proposal is closed",
+});
+
+impl From<BalancesError> for GovernanceError {
+    fn from(error: BalancesError) -> Self {
+        GovernanceError::Balances(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: GovernanceConfig> {
+    ProposalCreated {
+        proposal_id: u32,
+        creator: T::AccountId,
+    },
+    Voted {
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+        weight: T::Balance,
+    },
+    ProposalFinalized {
+        proposal_id: u32,
+        status: ProposalStatus,
+    },
+    LocalizedDescriptionSet {
+        proposal_id: u32,
+        lang: String,
+    },
+    LocalizedDescriptionRemoved {
+        proposal_id: u32,
+        lang: String,
+    },
+    ProposalAmended {
+        proposal_id: u32,
+    },
+    ProposalCancelled {
+        proposal_id: u32,
+    },
+    Delegated {
+        from: T::AccountId,
+        to: T::AccountId,
+    },
+    Undelegated {
+        from: T::AccountId,
+    },
+    VoteCapSet {
+        proposal_id: u32,
+        cap: T::Balance,
+    },
+    VoteChanged {
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+    },
+    VoteRetracted {
+        voter: T::AccountId,
+        proposal_id: u32,
+    },
+    VotingExtended {
+        proposal_id: u32,
+        end: T::BlockNumber,
+    },
+    ParticipationBarred {
+        who: T::AccountId,
+        until_era: u32,
+    },
+    ParticipationRestored {
+        who: T::AccountId,
+    },
+    ProposalFastTracked {
+        proposal_id: u32,
+        end: T::BlockNumber,
+    },
+    ProposalVetoed {
+        proposal_id: u32,
+    },
+    TagsSet {
+        proposal_id: u32,
+        tags: Vec<String>,
+    },
+    EnactmentScheduled {
+        proposal_id: u32,
+        at: T::BlockNumber,
+    },
+    // Calls run all-or-nothing: if one fails, the ones before it are rolled back
+    // and the ones after it never run
+    ProposalEnacted {
+        proposal_id: u32,
+        failed_call: Option<u32>,
+    },
+    // Dropped because the calls of the proposal it depends on failed
+    EnactmentCancelled {
+        proposal_id: u32,
+        dependency: u32,
+    },
+}
+
+impl<T: GovernanceConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice voted Aye on proposal 0 with 1 UNIT"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::ProposalCreated {
+                proposal_id,
+                creator,
+            } => format!(
+                "{} created proposal {}",
+                display_account(labels, creator),
+                proposal_id
+            ),
+            Event::Voted {
+                voter,
+                proposal_id,
+                vote_type,
+                weight,
+            } => format!(
+                "{} voted {} on proposal {} with {}",
+                display_account(labels, voter),
+                if *vote_type { "Aye" } else { "Nay" },
+                proposal_id,
+                format_balance::<T>(*weight)
+            ),
+            Event::ProposalFinalized {
+                proposal_id,
+                status,
+            } => format!("proposal {} was finalized as {:?}", proposal_id, status),
+            Event::LocalizedDescriptionSet { proposal_id, lang } => {
+                format!("proposal {} got a description in {}", proposal_id, lang)
+            }
+            Event::LocalizedDescriptionRemoved { proposal_id, lang } => {
+                format!("proposal {} lost its description in {}", proposal_id, lang)
+            }
+            Event::ProposalAmended { proposal_id } => {
+                format!("proposal {} was amended", proposal_id)
+            }
+            Event::ProposalCancelled { proposal_id } => {
+                format!("proposal {} was cancelled", proposal_id)
+            }
+            Event::VotingExtended { proposal_id, end } => format!(
+                "voting on proposal {} was extended to block {:?} after a tie",
+                proposal_id, end
+            ),
+            Event::Delegated { from, to } => format!(
+                "{} delegated their votes to {}",
+                display_account(labels, from),
+                display_account(labels, to)
+            ),
+            Event::Undelegated { from } => {
+                format!("{} stopped delegating", display_account(labels, from))
+            }
+            Event::VoteChanged {
+                voter,
+                proposal_id,
+                vote_type,
+            } => format!(
+                "{} changed their vote on proposal {} to {}",
+                display_account(labels, voter),
+                proposal_id,
+                if *vote_type { "Aye" } else { "Nay" }
+            ),
+            Event::VoteRetracted { voter, proposal_id } => format!(
+                "{} retracted their vote on proposal {}",
+                display_account(labels, voter),
+                proposal_id
+            ),
+            Event::VoteCapSet { proposal_id, cap } => format!(
+                "proposal {} capped each account's vote at {}",
+                proposal_id,
+                format_balance::<T>(*cap)
+            ),
+            Event::ParticipationBarred { who, until_era } => format!(
+                "{} is barred from governance until era {}",
+                display_account(labels, who),
+                until_era
+            ),
+            Event::ParticipationRestored { who } => format!(
+                "{} may take part in governance again",
+                display_account(labels, who)
+            ),
+            Event::ProposalFastTracked { proposal_id, end } => format!(
+                "proposal {} was fast-tracked to close at block {:?}",
+                proposal_id, end
+            ),
+            Event::ProposalVetoed { proposal_id } => {
+                format!("proposal {} was vetoed by the council", proposal_id)
+            }
+            Event::TagsSet { proposal_id, tags } => {
+                format!("proposal {} tagged [{}]", proposal_id, tags.join(", "))
+            }
+            Event::EnactmentScheduled { proposal_id, at } => {
+                format!("proposal {} will be enacted at block {:?}", proposal_id, at)
+            }
+            Event::ProposalEnacted {
+                proposal_id,
+                failed_call,
+            } => match failed_call {
+                None => format!("proposal {} was enacted", proposal_id),
+                Some(index) => format!(
+                    "proposal {} was not enacted: call {} failed",
+                    proposal_id, index
+                ),
+            },
+            Event::EnactmentCancelled {
+                proposal_id,
+                dependency,
+            } => format!(
+                "proposal {} was not enacted: proposal {}, which it depends on, failed",
+                proposal_id, dependency
+            ),
+        }
+    }
+}
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Proposal<T: GovernanceConfig> {
     description: String,
-    yes_votes: u32,
-    no_votes: u32,
+    // Stake-weighted tallies
+    yes_votes: T::Balance,
+    no_votes: T::Balance,
     status: ProposalStatus,
     creator: T::AccountId,
+    depends_on: Option<u32>,
+    template: Option<ProposalTemplateOf<T>>,
+    // Language tag -> description
+    localized_descriptions: BoundedBTreeMap<String, String>,
+    // Lowercase, sorted and distinct
+    tags: BoundedVec<String>,
+    // Dispatched in order with a Council origin once the proposal is approved
+    calls: BoundedVec<T::RuntimeCall>,
+    // Most weight any single account's stake can add to the tally
+    vote_cap: Option<T::Balance>,
+    // Block at which voting closes and the proposal is finalized
+    end: T::BlockNumber,
+}
+
+impl<T: GovernanceConfig> Proposal<T> {
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn creator(&self) -> &T::AccountId {
+        &self.creator
+    }
+
+    pub fn status(&self) -> &ProposalStatus {
+        &self.status
+    }
+
+    // Get the stake-weighted (Aye, Nay) tallies
+    pub fn tally(&self) -> (T::Balance, T::Balance) {
+        (self.yes_votes, self.no_votes)
+    }
+
+    // Get the block at which voting closes
+    pub fn end(&self) -> T::BlockNumber {
+        self.end
+    }
+}
+
+// How finalization settles an Aye share exactly equal to the approval threshold
+#[derive(Clone, Debug, PartialEq)]
+pub enum TiePolicy<BlockNumber> {
+    RejectOnTie,
+    ApproveOnTie,
+    // Keep the proposal open for this many more blocks
+    ExtendPeriod(BlockNumber),
+}
+
+// Limit on a single account's vote weight, set per proposal before voting starts
+#[derive(Clone, Debug, PartialEq)]
+pub enum VoteCap<Balance> {
+    Absolute(Balance),
+    // Share of the total stake when the cap is set, e.g. 50_000 = 5%
+    FractionOfElectorate { parts_per_million: u32 },
+}
+
+// Structured proposal kinds with validated arguments and a canonical description
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProposalTemplate<AccountId, Balance> {
+    SetParameter { key: String, value: u128 },
+    TreasurySpend { to: AccountId, amount: Balance },
+}
+
+impl<AccountId, Balance: Zero> ProposalTemplate<AccountId, Balance> {
+    // Check the template arguments are well formed
+    pub fn validate(&self) -> Result<(), GovernanceError> {
+        match self {
+            ProposalTemplate::SetParameter { key, .. } => {
+                if key.is_empty()
+                    || !key
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+                {
+                    return Err(GovernanceError::InvalidParameterKey);
+                }
+            }
+            ProposalTemplate::TreasurySpend { amount, .. } => {
+                if amount.is_zero() {
+                    return Err(GovernanceError::ZeroSpendAmount);
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Clone, PartialEq)]
+pub type ProposalTemplateOf<T> =
+    ProposalTemplate<<T as SystemConfig>::AccountId, <T as BalancesConfig>::Balance>;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum ProposalStatus {
     Active,
     Approved,
     Rejected,
+    // Too little of the total stake voted for the result to count
+    QuorumNotMet,
+    // Withdrawn by the creator before finalization
+    Cancelled,
+}
+
+// A page of listing results; pass `next_cursor` back in to fetch the following page
+pub struct Page<K, V> {
+    pub items: Vec<(K, V)>,
+    pub next_cursor: Option<K>,
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Outcome of `GovernancePallet::verify_tally`: the (Aye, Nay) counters stored on a
+// proposal next to the ones recomputed from its vote records
+#[derive(Clone, Debug, PartialEq)]
+pub struct TallyReport<T: GovernanceConfig> {
+    pub proposal_id: u32,
+    pub stored: (T::Balance, T::Balance),
+    // None if summing the vote records overflows
+    pub recomputed: Option<(T::Balance, T::Balance)>,
+}
+
+impl<T: GovernanceConfig> TallyReport<T> {
+    pub fn is_consistent(&self) -> bool {
+        self.recomputed == Some(self.stored)
+    }
+}
+
+// Block a conviction vote's stake lock runs to, counting its periods from `from`
+fn conviction_lock_until<T: GovernanceConfig>(
+    from: T::BlockNumber,
+    conviction: Conviction,
+) -> Result<T::BlockNumber, GovernanceError> {
+    (0..conviction.lock_periods())
+        .try_fold(from, |until, _| {
+            until.checked_add(&T::CONVICTION_LOCK_PERIOD)
+        })
+        .ok_or(GovernanceError::Overflow)
+}
+
+// Votes are accepted on an active proposal up to and including its end block
+fn ensure_open<T: GovernanceConfig>(
+    proposal: &Proposal<T>,
+    now: T::BlockNumber,
+) -> Result<(), GovernanceError> {
+    if proposal.status != ProposalStatus::Active {
+        return Err(GovernanceError::ProposalNotActive);
+    }
+    if now > proposal.end {
+        return Err(GovernanceError::VotingClosed);
+    }
+    Ok(())
+}
+
+fn add_to_tally<B: CheckedAdd>(
+    (yes, no): (B, B),
+    vote_type: bool,
+    weight: B,
+) -> Result<(B, B), GovernanceError> {
+    if vote_type {
+        Ok((
+            yes.checked_add(&weight).ok_or(GovernanceError::Overflow)?,
+            no,
+        ))
+    } else {
+        Ok((
+            yes,
+            no.checked_add(&weight).ok_or(GovernanceError::Overflow)?,
+        ))
+    }
+}
+
+// Settle the Aye share against the approval threshold; None means the tie policy
+// asks for a longer voting period
+fn approval_outcome<B>(
+    yes: u128,
+    turnout: u128,
+    threshold: u32,
+    tie_policy: &TiePolicy<B>,
+) -> Result<Option<ProposalStatus>, GovernanceError> {
+    // Compare shares in parts per million by cross-multiplying, so nothing is rounded
+    let aye_share = yes
+        .checked_mul(1_000_000)
+        .ok_or(GovernanceError::Overflow)?;
+    let needed = turnout
+        .checked_mul(u128::from(threshold))
+        .ok_or(GovernanceError::Overflow)?;
+
+    Ok(match aye_share.cmp(&needed) {
+        std::cmp::Ordering::Greater => Some(ProposalStatus::Approved),
+        std::cmp::Ordering::Less => Some(ProposalStatus::Rejected),
+        std::cmp::Ordering::Equal => match tie_policy {
+            TiePolicy::RejectOnTie => Some(ProposalStatus::Rejected),
+            TiePolicy::ApproveOnTie => Some(ProposalStatus::Approved),
+            TiePolicy::ExtendPeriod(_) => None,
+        },
+    })
+}
+
+// Sum of every account's stake, i.e. the weight that could vote
+fn total_stake<T: GovernanceConfig>(staking: &StakingPallet<T>) -> Result<u128, GovernanceError> {
+    staking
+        .stakers()
+        .into_iter()
+        .try_fold(0u128, |total, (_, amount)| total.checked_add(amount.into()))
+        .ok_or(GovernanceError::Overflow)
+}
+
+fn remove_from_tally<B: CheckedSub>(
+    (yes, no): (B, B),
+    vote_type: bool,
+    weight: B,
+) -> Result<(B, B), GovernanceError> {
+    if vote_type {
+        Ok((
+            yes.checked_sub(&weight).ok_or(GovernanceError::Overflow)?,
+            no,
+        ))
+    } else {
+        Ok((
+            yes,
+            no.checked_sub(&weight).ok_or(GovernanceError::Overflow)?,
+        ))
+    }
+}
+
+// Keys are ordered, so a cursor stays valid when entries are inserted or removed
+fn paginate<K: Ord + Clone, V>(items: Vec<(K, V)>, after: Option<K>, limit: usize) -> Page<K, V> {
+    let mut items: Vec<_> = items
+        .into_iter()
+        .filter(|(key, _)| after.as_ref().is_none_or(|after| key > after))
+        .collect();
+    items.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+    let next_cursor = if has_more {
+        items.last().map(|(key, _)| key.clone())
+    } else {
+        None
+    };
+
+    Page { items, next_cursor }
+}
+
+// Lowercase alphanumeric words of `text`, each listed once
+fn tokenize(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+// Add `proposal_id` to the ids listed under `key`, keeping them sorted
+fn index(index: &mut StorageMap<String, Vec<u32>>, key: String, proposal_id: u32) {
+    let ids = index.get_or_insert_with(key, Vec::new);
+    if let Err(position) = ids.binary_search(&proposal_id) {
+        ids.insert(position, proposal_id);
+    }
+}
+
+fn unindex(index: &mut StorageMap<String, Vec<u32>>, key: &String, proposal_id: u32) {
+    let Some(ids) = index.get_mut(key) else {
+        return;
+    };
+    ids.retain(|id| *id != proposal_id);
+    if ids.is_empty() {
+        index.remove(key);
+    }
+}
+
+fn index_words(word_index: &mut StorageMap<String, Vec<u32>>, text: &str, proposal_id: u32) {
+    for word in tokenize(text) {
+        index(word_index, word, proposal_id);
+    }
+}
+
+fn unindex_words(word_index: &mut StorageMap<String, Vec<u32>>, text: &str, proposal_id: u32) {
+    for word in tokenize(text) {
+        unindex(word_index, &word, proposal_id);
+    }
+}
+
+// How strongly a voter backs their vote: each step above None multiplies the
+// voter's own stake once more and locks it for another CONVICTION_LOCK_PERIOD
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Conviction {
+    #[default]
+    None,
+    Locked2x,
+    Locked3x,
+    Locked4x,
+    Locked5x,
+    Locked6x,
+}
+
+impl Conviction {
+    // Factor the voter's own stake is multiplied by
+    pub fn multiplier(self) -> u8 {
+        match self {
+            Conviction::None => 1,
+            Conviction::Locked2x => 2,
+            Conviction::Locked3x => 3,
+            Conviction::Locked4x => 4,
+            Conviction::Locked5x => 5,
+            Conviction::Locked6x => 6,
+        }
+    }
+
+    // Number of CONVICTION_LOCK_PERIODs the stake stays locked after finalization
+    pub fn lock_periods(self) -> u8 {
+        self.multiplier().saturating_sub(1)
+    }
+}
+
+// A vote cast directly by an account
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoteRecord<T: GovernanceConfig> {
+    pub vote_type: bool,
+    pub conviction: Conviction,
+    // The voter's own weight after conviction and cap; weight carried for
+    // delegators is tracked with each delegator
+    pub weight: T::Balance,
+    // The voter's own stake, locked after finalization according to the conviction
+    pub stake: T::Balance,
+}
+
+// Weight cast on a delegator's behalf by the voter its delegation resolved to
+#[derive(Clone, Debug, PartialEq)]
+struct DelegatedVote<T: GovernanceConfig> {
+    delegate: T::AccountId,
+    vote_type: bool,
+    weight: T::Balance,
+}
+
+// Calls an account can submit to the governance pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: GovernanceConfig> {
+    CreateProposal {
+        description: String,
+    },
+    CreateProposalFromTemplate {
+        template: ProposalTemplateOf<T>,
+    },
+    CreateDependentProposal {
+        description: String,
+        depends_on: u32,
+    },
+    Vote {
+        proposal_id: u32,
+        vote_type: bool,
+        conviction: Conviction,
+    },
+    Delegate {
+        to: T::AccountId,
+    },
+    Undelegate,
+    SetVoteCap {
+        proposal_id: u32,
+        cap: VoteCap<T::Balance>,
+    },
+    // Finalize without waiting for the voting period to end; needs a Root or
+    // Council origin, as expired proposals are finalized by the end-of-block hook
+    FinalizeProposal {
+        proposal_id: u32,
+    },
+    AmendProposal {
+        proposal_id: u32,
+        description: String,
+    },
+    CancelProposal {
+        proposal_id: u32,
+    },
+    SetLocalizedDescription {
+        proposal_id: u32,
+        lang: String,
+        description: String,
+    },
+    RemoveLocalizedDescription {
+        proposal_id: u32,
+        lang: String,
+    },
+    // Drop a closed proposal's localized descriptions, releasing their deposits
+    ReleaseLocalizationDeposits {
+        proposal_id: u32,
+    },
+    ChangeVote {
+        proposal_id: u32,
+        vote_type: bool,
+    },
+    RetractVote {
+        proposal_id: u32,
+    },
+    // Replace the proposal's tags; only its creator may
+    SetTags {
+        proposal_id: u32,
+        tags: Vec<String>,
+    },
+    CreateProposalWithCall {
+        description: String,
+        call: Box<T::RuntimeCall>,
+    },
+    // An omnibus proposal whose calls are enacted together or not at all
+    CreateProposalWithCalls {
+        description: String,
+        calls: Vec<T::RuntimeCall>,
+    },
+    // A dependent proposal with calls, enacted only once its dependency's calls were
+    CreateDependentProposalWithCalls {
+        description: String,
+        depends_on: u32,
+        calls: Vec<T::RuntimeCall>,
+    },
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct GovernancePallet<T: GovernanceConfig> {
-    pub proposals: HashMap<u32, Proposal<T>>,
-    pub votes: HashMap<(T::AccountId, u32), bool>, // (voter, proposal_id) -> vote_type
-    next_proposal_id: u32,
+    proposals: StorageMap<u32, Proposal<T>>,
+    votes: StorageMap<(T::AccountId, u32), VoteRecord<T>>, // (voter, proposal_id)
+    delegations: StorageMap<T::AccountId, T::AccountId>,   // delegator -> delegate
+    delegated_votes: StorageMap<(T::AccountId, u32), DelegatedVote<T>>, // (delegator, proposal_id)
+    next_proposal_id: StorageValue<u32>,
+    // Offenders barred from proposing and voting, with the era the bar is lifted in
+    barred: StorageMap<T::AccountId, u32>,
+    // Search indexes, tag -> ids and description word -> ids, with ids sorted. They
+    // aren't state: they're left out of the encoding and storage usage, and rebuilt
+    // from the proposals when decoding.
+    tag_index: StorageMap<String, Vec<u32>>,
+    word_index: StorageMap<String, Vec<u32>>,
+    // Approved proposals whose call is still to be dispatched, with the block it's due
+    enactments: StorageMap<u32, T::BlockNumber>,
+    // Whether each enacted proposal's calls all went through
+    enacted: StorageMap<u32, bool>,
+    // Proposals finalized so far, which participation scores are brought up to
+    finalized_count: StorageValue<u32>,
+    participation: StorageMap<T::AccountId, Participation>,
+}
+
+// An account's recency-weighted record of the finalized proposals it voted on, out
+// of those finalized since its first vote. Both sums are in millionths and decay by
+// PARTICIPATION_DECAY per finalized proposal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Participation {
+    voted: u128,
+    eligible: u128,
+    // `finalized_count` the sums are up to date with
+    as_of: u32,
+}
+
+impl Participation {
+    // Bring the sums up to `count` finalized proposals, each of which the account
+    // was eligible for
+    fn decayed(mut self, count: u32, decay: u32) -> Self {
+        // Each proposal scales both sums by `decay` and adds one to `eligible`, an
+        // affine step (scale, added); n proposals are its nth power, by squaring
+        let compose = |(scale, added): (u128, u128), (inner_scale, inner_added): (u128, u128)| {
+            (
+                scale.saturating_mul(inner_scale) / 1_000_000,
+                (scale.saturating_mul(inner_added) / 1_000_000).saturating_add(added),
+            )
+        };
+        let mut step = (u128::from(decay), 1_000_000);
+        let mut total = (1_000_000, 0);
+        let mut remaining = count.saturating_sub(self.as_of);
+        while remaining > 0 {
+            if remaining % 2 == 1 {
+                total = compose(total, step);
+            }
+            step = compose(step, step);
+            remaining /= 2;
+        }
+
+        let (scale, added) = total;
+        self.voted = self.voted.saturating_mul(scale) / 1_000_000;
+        self.eligible = (self.eligible.saturating_mul(scale) / 1_000_000).saturating_add(added);
+        self.as_of = self.as_of.max(count);
+        self
+    }
+}
+
+impl<T: GovernanceConfig> Default for GovernancePallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: GovernanceConfig> GovernancePallet<T> {
     pub fn new() -> Self {
         Self {
-            proposals: HashMap::new(),
-            votes: HashMap::new(),
-            next_proposal_id: 0,
+            proposals: StorageMap::new(),
+            votes: StorageMap::new(),
+            delegations: StorageMap::new(),
+            delegated_votes: StorageMap::new(),
+            next_proposal_id: StorageValue::new(0),
+            barred: StorageMap::new(),
+            tag_index: StorageMap::new(),
+            word_index: StorageMap::new(),
+            enactments: StorageMap::new(),
+            enacted: StorageMap::new(),
+            finalized_count: StorageValue::new(0),
+            participation: StorageMap::new(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method.
+    // Return values (e.g. new proposal ids) are reported through events.
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        staking: &mut StakingPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), GovernanceError> {
+        match call {
+            Call::CreateProposal { description } => {
+                self.create_proposal(system, origin, description)?;
+            }
+            Call::CreateProposalFromTemplate { template } => {
+                self.create_proposal_from_template(system, origin, template)?;
+            }
+            Call::CreateDependentProposal {
+                description,
+                depends_on,
+            } => {
+                self.create_dependent_proposal(system, origin, description, depends_on)?;
+            }
+            Call::Vote {
+                proposal_id,
+                vote_type,
+                conviction,
+            } => self.vote_with_conviction(
+                system,
+                staking,
+                origin,
+                proposal_id,
+                vote_type,
+                conviction,
+            )?,
+            Call::Delegate { to } => self.delegate(system, origin, to)?,
+            Call::Undelegate => self.undelegate(system, origin)?,
+            Call::SetVoteCap { proposal_id, cap } => {
+                self.set_vote_cap(system, staking, origin, proposal_id, cap)?
+            }
+            Call::FinalizeProposal { proposal_id } => {
+                self.finalize_proposal(system, staking, proposal_id)?;
+            }
+            Call::AmendProposal {
+                proposal_id,
+                description,
+            } => self.amend_proposal(system, origin, proposal_id, description)?,
+            Call::CancelProposal { proposal_id } => {
+                self.cancel_proposal(system, balances, origin, proposal_id)?
+            }
+            Call::SetLocalizedDescription {
+                proposal_id,
+                lang,
+                description,
+            } => self.set_localized_description(
+                system,
+                balances,
+                origin,
+                proposal_id,
+                &lang,
+                description,
+            )?,
+            Call::RemoveLocalizedDescription { proposal_id, lang } => {
+                self.remove_localized_description(system, balances, origin, proposal_id, &lang)?
+            }
+            Call::ReleaseLocalizationDeposits { proposal_id } => {
+                self.release_localization_deposits(system, balances, origin, proposal_id)?
+            }
+            Call::ChangeVote {
+                proposal_id,
+                vote_type,
+            } => self.change_vote(system, origin, proposal_id, vote_type)?,
+            Call::RetractVote { proposal_id } => self.retract_vote(system, origin, proposal_id)?,
+            Call::SetTags { proposal_id, tags } => {
+                self.set_tags(system, origin, proposal_id, tags)?
+            }
+            Call::CreateProposalWithCall { description, call } => {
+                self.create_proposal_with_call(system, origin, description, *call)?;
+            }
+            Call::CreateProposalWithCalls { description, calls } => {
+                self.create_proposal_with_calls(system, origin, description, calls)?;
+            }
+            Call::CreateDependentProposalWithCalls {
+                description,
+                depends_on,
+                calls,
+            } => {
+                self.create_dependent_proposal_with_calls(
+                    system,
+                    origin,
+                    description,
+                    depends_on,
+                    calls,
+                )?;
+            }
         }
+
+        Ok(())
     }
 
     // Create a new proposal
     pub fn create_proposal(
         &mut self,
+        system: &mut SystemPallet<T>,
         creator: T::AccountId,
         description: String,
-    ) -> Result<u32, &'static str> {
-        let proposal_id = self.next_proposal_id;
-        self.next_proposal_id += 1;
+    ) -> Result<u32, GovernanceError> {
+        self.insert_proposal(system, creator, description, None, None, Vec::new())
+    }
 
-        let proposal = Proposal {
-            description,
-            yes_votes: 0,
-            no_votes: 0,
-            status: ProposalStatus::Active,
-            creator,
-        };
+    // Create a proposal whose call is dispatched ENACTMENT_DELAY blocks after it is
+    // approved, with a Council origin
+    pub fn create_proposal_with_call(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        creator: T::AccountId,
+        description: String,
+        call: T::RuntimeCall,
+    ) -> Result<u32, GovernanceError> {
+        self.create_proposal_with_calls(system, creator, description, vec![call])
+    }
 
-        self.proposals.insert(proposal_id, proposal);
+    // Create a proposal whose calls are dispatched in order ENACTMENT_DELAY blocks
+    // after it is approved. The calls are enacted atomically: if one fails, none of
+    // them take effect.
+    pub fn create_proposal_with_calls(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        creator: T::AccountId,
+        description: String,
+        calls: Vec<T::RuntimeCall>,
+    ) -> Result<u32, GovernanceError> {
+        if calls.is_empty() || calls.len() > T::MAX_PROPOSAL_CALLS {
+            return Err(GovernanceError::InvalidCallCount);
+        }
 
-        Ok(proposal_id)
+        self.insert_proposal(system, creator, description, None, None, calls)
     }
 
-    // Vote on a proposal (true = yes, false = no)
-    pub fn vote(
+    // Create a proposal from a template, validating its arguments first
+    pub fn create_proposal_from_template(
         &mut self,
-        voter: T::AccountId,
-        proposal_id: u32,
-        vote_type: bool,
-    ) -> Result<(), &'static str> {
-        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
-            if proposal.status != ProposalStatus::Active {
-                return Err("Proposal is not active");
-            }
+        system: &mut SystemPallet<T>,
+        creator: T::AccountId,
+        template: ProposalTemplateOf<T>,
+    ) -> Result<u32, GovernanceError> {
+        template.validate()?;
+        let description = Self::describe_template(&template);
 
-            if self.votes.contains_key(&(voter.clone(), proposal_id)) {
-                return Err("Voter has already voted");
-            }
+        self.insert_proposal(
+            system,
+            creator,
+            description,
+            None,
+            Some(template),
+            Vec::new(),
+        )
+    }
 
-            self.votes.insert((voter, proposal_id), vote_type);
+    // Create a proposal that can only pass once another proposal is approved
+    pub fn create_dependent_proposal(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        creator: T::AccountId,
+        description: String,
+        depends_on: u32,
+    ) -> Result<u32, GovernanceError> {
+        // Dependencies must already exist, so a new proposal can never close a cycle
+        if !self.proposals.contains_key(&depends_on) {
+            return Err(GovernanceError::DependencyNotFound);
+        }
 
-            if vote_type {
-                proposal.yes_votes += 1;
-            } else {
-                proposal.no_votes += 1;
-            }
+        self.insert_proposal(
+            system,
+            creator,
+            description,
+            Some(depends_on),
+            None,
+            Vec::new(),
+        )
+    }
 
-            Ok(())
-        } else {
-            Err("Proposal does not exist")
+    // Create a dependent proposal carrying calls. Once approved, they're enacted only
+    // after its dependency's calls were, and dropped if those failed.
+    pub fn create_dependent_proposal_with_calls(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        creator: T::AccountId,
+        description: String,
+        depends_on: u32,
+        calls: Vec<T::RuntimeCall>,
+    ) -> Result<u32, GovernanceError> {
+        if !self.proposals.contains_key(&depends_on) {
+            return Err(GovernanceError::DependencyNotFound);
+        }
+        if calls.is_empty() || calls.len() > T::MAX_PROPOSAL_CALLS {
+            return Err(GovernanceError::InvalidCallCount);
         }
-    }
 
-    // Get proposal details
-    pub fn get_proposal(&self, proposal_id: u32) -> Option<&Proposal<T>> {
-        self.proposals.get(&proposal_id)
+        self.insert_proposal(system, creator, description, Some(depends_on), None, calls)
     }
 
-    // Finalize a proposal (changes status based on votes)
-    pub fn finalize_proposal(&mut self, proposal_id: u32) -> Result<ProposalStatus, &'static str> {
-        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
-            if proposal.status != ProposalStatus::Active {
-                return Err("Proposal is not active");
+    // Run every check creating a proposal with `call` would, without changing any
+    // state, so a client can report the error before submitting it
+    pub fn validate_proposal(
+        &self,
+        system: &SystemPallet<T>,
+        creator: &T::AccountId,
+        call: &Call<T>,
+    ) -> Result<(), GovernanceError> {
+        match call {
+            Call::CreateProposal { .. } | Call::CreateProposalWithCall { .. } => {}
+            Call::CreateProposalFromTemplate { template } => template.validate()?,
+            Call::CreateDependentProposal { depends_on, .. } => {
+                if !self.proposals.contains_key(depends_on) {
+                    return Err(GovernanceError::DependencyNotFound);
+                }
             }
-
-            if proposal.yes_votes > proposal.no_votes {
-                proposal.status = ProposalStatus::Approved;
-            } else {
-                proposal.status = ProposalStatus::Rejected;
+            Call::CreateProposalWithCalls { calls, .. } => {
+                if calls.is_empty() || calls.len() > T::MAX_PROPOSAL_CALLS {
+                    return Err(GovernanceError::InvalidCallCount);
+                }
             }
-
-            Ok(proposal.status.clone())
-        } else {
-            Err("Proposal does not exist")
+            Call::CreateDependentProposalWithCalls {
+                depends_on, calls, ..
+            } => {
+                if !self.proposals.contains_key(depends_on) {
+                    return Err(GovernanceError::DependencyNotFound);
+                }
+                if calls.is_empty() || calls.len() > T::MAX_PROPOSAL_CALLS {
+                    return Err(GovernanceError::InvalidCallCount);
+                }
+            }
+            _ => return Err(GovernanceError::NotAProposal),
         }
+        self.ensure_can_insert(system, creator)?;
+
+        Ok(())
     }
 
-    pub fn get_proposal_details(
-        &self,
-        proposal_id: u32,
-    ) -> Result<(String, T::AccountId), &'static str> {
-        if let Some(proposal) = self.proposals.get(&proposal_id) {
-            Ok((proposal.description.clone(), proposal.creator.clone()))
-        } else {
-            Err("Proposal does not exist")
+    // Render the canonical description for a template
+    fn describe_template(template: &ProposalTemplateOf<T>) -> String {
+        match template {
+            ProposalTemplate::SetParameter { key, value } => {
+                format!("Set parameter `{}` to {}", key, value)
+            }
+            ProposalTemplate::TreasurySpend { to, amount } => {
+                format!(
+                    "Spend {} from the treasury to account {:?}",
+                    format_balance::<T>(*amount),
+                    to
+                )
+            }
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Runtime;
+    // Checks shared by every way of creating a proposal. Returns the end of the new
+    // proposal's voting period and its id.
+    fn ensure_can_insert(
+        &self,
+        system: &SystemPallet<T>,
+        creator: &T::AccountId,
+    ) -> Result<(T::BlockNumber, u32), GovernanceError> {
+        self.ensure_not_barred(creator)?;
+        let end = system
+            .block_number()
+            .checked_add(&T::VOTING_PERIOD)
+            .ok_or(GovernanceError::Overflow)?;
+        let proposal_id = self.next_proposal_id.get();
+        proposal_id
+            .checked_add(1)
+            .ok_or(GovernanceError::Overflow)?;
 
-    #[test]
-    fn test_governance_should_work() {
-        let alice = 1u64;
+        Ok((end, proposal_id))
+    }
+
+    fn insert_proposal(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        creator: T::AccountId,
+        description: String,
+        depends_on: Option<u32>,
+        template: Option<ProposalTemplateOf<T>>,
+        calls: Vec<T::RuntimeCall>,
+    ) -> Result<u32, GovernanceError> {
+        let (end, proposal_id) = self.ensure_can_insert(system, &creator)?;
+        let calls = BoundedVec::try_from_vec(calls, T::MAX_PROPOSAL_CALLS)
+            .map_err(|_| GovernanceError::InvalidCallCount)?;
+        let next_proposal_id = proposal_id
+            .checked_add(1)
+            .ok_or(GovernanceError::Overflow)?;
+        self.next_proposal_id.put(next_proposal_id);
+
+        let proposal = Proposal {
+            description,
+            yes_votes: T::Balance::zero(),
+            no_votes: T::Balance::zero(),
+            status: ProposalStatus::Active,
+            creator: creator.clone(),
+            depends_on,
+            template,
+            localized_descriptions: BoundedBTreeMap::with_bound(T::MAX_LOCALIZATIONS),
+            tags: BoundedVec::with_bound(T::MAX_TAGS),
+            calls,
+            vote_cap: None,
+            end,
+        };
+
+        index_words(&mut self.word_index, &proposal.description, proposal_id);
+        self.proposals.insert(proposal_id, proposal);
+        system.deposit_event(Event::ProposalCreated {
+            proposal_id,
+            creator: creator.clone(),
+        });
+        T::ProposalHooks::on_created(system, proposal_id, &creator);
+
+        Ok(proposal_id)
+    }
+
+    // Vote on a proposal (true = yes, false = no), weighted by the voter's staked balance
+    // plus the stake of every account delegating to them that hasn't voted itself
+    pub fn vote(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &mut StakingPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+    ) -> Result<(), GovernanceError> {
+        self.vote_with_conviction(
+            system,
+            staking,
+            voter,
+            proposal_id,
+            vote_type,
+            Conviction::None,
+        )
+    }
+
+    // Vote with the voter's own stake multiplied by the conviction. Delegators' stake
+    // always counts once, since they never agreed to the lock.
+    pub fn vote_with_conviction(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &mut StakingPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+        conviction: Conviction,
+    ) -> Result<(), GovernanceError> {
+        self.ensure_not_barred(&voter)?;
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        ensure_open(proposal, system.block_number())?;
+        // Votes are still accepted at the end block, so locks run through it
+        let closes = proposal
+            .end
+            .checked_add(&T::BlockNumber::one())
+            .ok_or(GovernanceError::Overflow)?;
+
+        if self.votes.contains_key(&(voter.clone(), proposal_id)) {
+            return Err(GovernanceError::AlreadyVoted);
+        }
+
+        let cap = |stake: T::Balance| match proposal.vote_cap {
+            Some(cap) if stake > cap => cap,
+            _ => stake,
+        };
+
+        // Stake above the cap is ignored, both for the voter and each delegator
+        let stake = staking.get_staked_balance(voter.clone());
+        let boosted = u128::from(conviction.multiplier())
+            .checked_mul(stake.into())
+            .and_then(|boosted| T::Balance::try_from(boosted).ok())
+            .ok_or(GovernanceError::Overflow)?;
+        let own_weight = cap(boosted);
+        let mut weight = own_weight;
+        let mut carried = Vec::new();
+        for delegator in self.delegators_of(&voter, proposal_id) {
+            let delegator_weight = cap(staking.get_staked_balance(delegator.clone()));
+            weight = weight
+                .checked_add(&delegator_weight)
+                .ok_or(GovernanceError::Overflow)?;
+            carried.push((delegator, delegator_weight));
+        }
+        if weight.is_zero() {
+            return Err(GovernanceError::NoVotingPower);
+        }
+
+        // Voting directly overrides weight a delegate already cast for the voter or its delegators
+        let overridden: Vec<T::AccountId> = std::iter::once(voter.clone())
+            .chain(carried.iter().map(|(delegator, _)| delegator.clone()))
+            .filter(|who| {
+                self.delegated_votes
+                    .contains_key(&(who.clone(), proposal_id))
+            })
+            .collect();
+
+        let mut tally = (proposal.yes_votes, proposal.no_votes);
+        for who in &overridden {
+            if let Some(previous) = self.delegated_votes.get(&(who.clone(), proposal_id)) {
+                tally = remove_from_tally(tally, previous.vote_type, previous.weight)?;
+            }
+        }
+        tally = add_to_tally(tally, vote_type, weight)?;
+
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            (proposal.yes_votes, proposal.no_votes) = tally;
+        }
+        for who in overridden {
+            self.delegated_votes.remove(&(who, proposal_id));
+        }
+        // The counted stake stays bonded until voting closes, so it can't be unstaked,
+        // moved to another account and counted again. A conviction vote's lock also
+        // covers its periods from then on, and is extended again at finalization.
+        if !stake.is_zero() {
+            let until = conviction_lock_until::<T>(closes, conviction)?;
+            staking.lock_stake(system, voter.clone(), stake, until);
+        }
+        for (delegator, delegator_weight) in carried {
+            if !delegator_weight.is_zero() {
+                staking.lock_stake(system, delegator.clone(), delegator_weight, closes);
+            }
+            self.delegated_votes.insert(
+                (delegator, proposal_id),
+                DelegatedVote {
+                    delegate: voter.clone(),
+                    vote_type,
+                    weight: delegator_weight,
+                },
+            );
+        }
+
+        self.votes.insert(
+            (voter.clone(), proposal_id),
+            VoteRecord {
+                vote_type,
+                conviction,
+                weight: own_weight,
+                stake,
+            },
+        );
+        system.deposit_event(Event::Voted {
+            voter: voter.clone(),
+            proposal_id,
+            vote_type,
+            weight,
+        });
+        T::ProposalHooks::on_vote(system, proposal_id, &voter, vote_type, weight);
+
+        Ok(())
+    }
+
+    // Switch a vote to the other side while the proposal is open. Weight and conviction
+    // stay as cast, and weight carried for delegators moves along with it.
+    pub fn change_vote(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+    ) -> Result<(), GovernanceError> {
+        self.ensure_not_barred(&voter)?;
+        let (previous, own_weight) = self.cast_vote(system, &voter, proposal_id)?;
+        let carried = self.carried_by(&voter, proposal_id);
+        if previous != vote_type {
+            let proposal = self
+                .proposals
+                .get(&proposal_id)
+                .ok_or(GovernanceError::ProposalNotFound)?;
+            let mut tally = (proposal.yes_votes, proposal.no_votes);
+            for weight in std::iter::once(own_weight).chain(carried.iter().map(|(_, w)| *w)) {
+                tally = remove_from_tally(tally, previous, weight)?;
+                tally = add_to_tally(tally, vote_type, weight)?;
+            }
+
+            if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                (proposal.yes_votes, proposal.no_votes) = tally;
+            }
+            for (delegator, _) in carried {
+                if let Some(delegated) = self.delegated_votes.get_mut(&(delegator, proposal_id)) {
+                    delegated.vote_type = vote_type;
+                }
+            }
+            if let Some(record) = self.votes.get_mut(&(voter.clone(), proposal_id)) {
+                record.vote_type = vote_type;
+            }
+        }
+
+        system.deposit_event(Event::VoteChanged {
+            voter,
+            proposal_id,
+            vote_type,
+        });
+        Ok(())
+    }
+
+    // Withdraw a vote while the proposal is open, along with the weight it carried for
+    // delegators. They count again if their delegate or they themselves vote later.
+    // The lock taken when voting stays, conviction periods included, so a conviction
+    // vote can't sway others and then be withdrawn for free.
+    pub fn retract_vote(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), GovernanceError> {
+        let (previous, own_weight) = self.cast_vote(system, &voter, proposal_id)?;
+        let carried = self.carried_by(&voter, proposal_id);
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        let mut tally = (proposal.yes_votes, proposal.no_votes);
+        for weight in std::iter::once(own_weight).chain(carried.iter().map(|(_, w)| *w)) {
+            tally = remove_from_tally(tally, previous, weight)?;
+        }
+
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            (proposal.yes_votes, proposal.no_votes) = tally;
+        }
+        for (delegator, _) in carried {
+            self.delegated_votes.remove(&(delegator, proposal_id));
+        }
+        self.votes.remove(&(voter.clone(), proposal_id));
+
+        system.deposit_event(Event::VoteRetracted { voter, proposal_id });
+        Ok(())
+    }
+
+    // Stake behind the votes on a proposal, for its quorum: each voter's own stake and
+    // the stake carried for delegators, capped like their weight but without conviction
+    fn turnout(
+        &self,
+        proposal_id: u32,
+        vote_cap: Option<T::Balance>,
+    ) -> Result<u128, GovernanceError> {
+        let own = self
+            .votes
+            .iter()
+            .filter(|((_, id), _)| *id == proposal_id)
+            .map(|(_, record)| match vote_cap {
+                Some(cap) if record.stake > cap => cap,
+                _ => record.stake,
+            });
+        let carried = self
+            .delegated_votes
+            .iter()
+            .filter(|((_, id), _)| *id == proposal_id)
+            .map(|(_, delegated)| delegated.weight);
+        own.chain(carried).try_fold(0u128, |turnout, stake| {
+            turnout
+                .checked_add(stake.into())
+                .ok_or(GovernanceError::Overflow)
+        })
+    }
+
+    // The direction and own weight of the voter's vote on an open proposal
+    fn cast_vote(
+        &self,
+        system: &SystemPallet<T>,
+        voter: &T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(bool, T::Balance), GovernanceError> {
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        ensure_open(proposal, system.block_number())?;
+        self.votes
+            .get(&(voter.clone(), proposal_id))
+            .map(|record| (record.vote_type, record.weight))
+            .ok_or(GovernanceError::NotVoted)
+    }
+
+    // Weight the voter's vote still carries for each delegator
+    fn carried_by(
+        &self,
+        voter: &T::AccountId,
+        proposal_id: u32,
+    ) -> Vec<(T::AccountId, T::Balance)> {
+        self.delegated_votes
+            .iter()
+            .filter(|((_, id), delegated)| *id == proposal_id && delegated.delegate == *voter)
+            .map(|((delegator, _), delegated)| (delegator.clone(), delegated.weight))
+            .collect()
+    }
+
+    // Cap the weight any single account can add to a proposal; only the creator may
+    // set it, and only before the first vote so every vote is tallied the same way
+    pub fn set_vote_cap(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &StakingPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+        cap: VoteCap<T::Balance>,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.creator != who {
+            return Err(GovernanceError::NotProposalCreator);
+        }
+        if proposal.status != ProposalStatus::Active {
+            return Err(GovernanceError::ProposalNotActive);
+        }
+        if self.votes.keys().any(|(_, id)| *id == proposal_id) {
+            return Err(GovernanceError::VotesAlreadyCast);
+        }
+
+        let cap = match cap {
+            VoteCap::Absolute(amount) => amount,
+            VoteCap::FractionOfElectorate { parts_per_million } => {
+                if parts_per_million == 0 || parts_per_million > 1_000_000 {
+                    return Err(GovernanceError::InvalidVoteCap);
+                }
+                // Snapshot the electorate now, so later stake changes don't move the cap
+                let electorate = total_stake(staking)?;
+                let share = electorate
+                    .checked_mul(u128::from(parts_per_million))
+                    .and_then(|scaled| scaled.checked_div(1_000_000))
+                    .ok_or(GovernanceError::Overflow)?;
+                T::Balance::try_from(share).map_err(|_| GovernanceError::Overflow)?
+            }
+        };
+        if cap.is_zero() {
+            return Err(GovernanceError::InvalidVoteCap);
+        }
+
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.vote_cap = Some(cap);
+        }
+        system.deposit_event(Event::VoteCapSet { proposal_id, cap });
+
+        Ok(())
+    }
+
+    // Delegate voting power to another account; delegations may chain but not loop
+    pub fn delegate(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        from: T::AccountId,
+        to: T::AccountId,
+    ) -> Result<(), GovernanceError> {
+        self.ensure_not_barred(&from)?;
+        if from == to {
+            return Err(GovernanceError::SelfDelegation);
+        }
+        if self.delegations.contains_key(&from) {
+            return Err(GovernanceError::AlreadyDelegating);
+        }
+
+        // Follow the chain from the new delegate; reaching `from` would close a loop
+        let mut current = &to;
+        while let Some(next) = self.delegations.get(current) {
+            if *next == from {
+                return Err(GovernanceError::DelegationCycle);
+            }
+            current = next;
+        }
+
+        self.delegations.insert(from.clone(), to.clone());
+        system.deposit_event(Event::Delegated { from, to });
+
+        Ok(())
+    }
+
+    // Stop delegating; votes already cast by the delegate keep the carried weight
+    pub fn undelegate(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        from: T::AccountId,
+    ) -> Result<(), GovernanceError> {
+        if self.delegations.remove(&from).is_none() {
+            return Err(GovernanceError::NotDelegating);
+        }
+
+        system.deposit_event(Event::Undelegated { from });
+
+        Ok(())
+    }
+
+    // Get the account whose vote carried this account's weight on a proposal, if any
+    pub fn vote_carried_by(&self, who: T::AccountId, proposal_id: u32) -> Option<&T::AccountId> {
+        self.delegated_votes
+            .get(&(who, proposal_id))
+            .map(|record| &record.delegate)
+    }
+
+    // Every account whose delegation chain ends at `delegate` without passing a direct
+    // voter. Barred accounts carry no weight, though the chain still runs through them.
+    fn delegators_of(&self, delegate: &T::AccountId, proposal_id: u32) -> Vec<T::AccountId> {
+        let mut found = Vec::new();
+        let mut queue = vec![delegate.clone()];
+
+        while let Some(current) = queue.pop() {
+            for (from, to) in &self.delegations {
+                if *to == current && !self.votes.contains_key(&(from.clone(), proposal_id)) {
+                    if !self.barred.contains_key(from) {
+                        found.push(from.clone());
+                    }
+                    queue.push(from.clone());
+                }
+            }
+        }
+
+        found
+    }
+
+    // Get proposal details
+    pub fn get_proposal(&self, proposal_id: u32) -> Option<&Proposal<T>> {
+        self.proposals.get(&proposal_id)
+    }
+
+    // Get the current status of a proposal
+    pub fn get_proposal_status(&self, proposal_id: u32) -> Option<&ProposalStatus> {
+        self.proposals
+            .get(&proposal_id)
+            .map(|proposal| &proposal.status)
+    }
+
+    // Finalize a proposal: it needs the quorum of the total stake to vote, and then
+    // more than the approval threshold of the votes cast to be Aye. Quorum counts the
+    // stake behind the votes; conviction only weighs in on the approval share. When
+    // the policy extends a tied vote, the proposal stays Active and Active is returned.
+    pub fn finalize_proposal(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &mut StakingPallet<T>,
+        proposal_id: u32,
+    ) -> Result<ProposalStatus, GovernanceError> {
+        let electorate = total_stake(staking)?;
+        let turnout = match self.proposals.get(&proposal_id) {
+            Some(proposal) => self.turnout(proposal_id, proposal.vote_cap)?,
+            None => 0,
+        };
+        let dependency_status = self
+            .proposals
+            .get(&proposal_id)
+            .and_then(|proposal| proposal.depends_on)
+            .and_then(|dependency| self.proposals.get(&dependency))
+            .map(|dependency| dependency.status.clone());
+
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            if proposal.status != ProposalStatus::Active {
+                return Err(GovernanceError::ProposalNotActive);
+            }
+
+            // A dependent proposal waits for its dependency and fails unless it passed
+            if dependency_status == Some(ProposalStatus::Active) {
+                return Err(GovernanceError::DependencyNotFinalized);
+            }
+
+            let yes: u128 = proposal.yes_votes.into();
+            let cast = yes
+                .checked_add(proposal.no_votes.into())
+                .ok_or(GovernanceError::Overflow)?;
+            let quorum_met = turnout
+                .checked_mul(1_000_000)
+                .zip(electorate.checked_mul(u128::from(T::QUORUM)))
+                .map(|(turnout, quorum)| turnout >= quorum)
+                .ok_or(GovernanceError::Overflow)?;
+
+            if dependency_status.is_some_and(|status| status != ProposalStatus::Approved) {
+                proposal.status = ProposalStatus::Rejected;
+            } else if !quorum_met {
+                proposal.status = ProposalStatus::QuorumNotMet;
+            } else {
+                match approval_outcome(yes, cast, T::APPROVAL_THRESHOLD, &T::TIE_POLICY)? {
+                    Some(status) => proposal.status = status,
+                    None => {
+                        if let TiePolicy::ExtendPeriod(blocks) = T::TIE_POLICY {
+                            proposal.end = system
+                                .block_number()
+                                .max(proposal.end)
+                                .checked_add(&blocks)
+                                .ok_or(GovernanceError::Overflow)?;
+                        }
+                        system.deposit_event(Event::VotingExtended {
+                            proposal_id,
+                            end: proposal.end,
+                        });
+
+                        // The counted stake stays bonded through the extension too
+                        let closes = proposal
+                            .end
+                            .checked_add(&One::one())
+                            .ok_or(GovernanceError::Overflow)?;
+                        let mut counted: Vec<(T::AccountId, T::Balance)> = self
+                            .votes
+                            .iter()
+                            .map(|((voter, id), record)| (voter, *id, record.stake))
+                            .chain(self.delegated_votes.iter().map(
+                                |((delegator, id), delegated)| (delegator, *id, delegated.weight),
+                            ))
+                            .filter(|(_, id, stake)| *id == proposal_id && !stake.is_zero())
+                            .map(|(who, _, stake)| (who.clone(), stake))
+                            .collect();
+                        counted.sort_by(|(a, _), (b, _)| a.cmp(b));
+                        for (who, stake) in counted {
+                            staking.lock_stake(system, who, stake, closes);
+                        }
+                        return Ok(ProposalStatus::Active);
+                    }
+                }
+            }
+
+            system.deposit_event(Event::ProposalFinalized {
+                proposal_id,
+                status: proposal.status.clone(),
+            });
+
+            // Every tracked account was eligible for this proposal; its voters took part
+            let count = self.finalized_count.get().saturating_add(1);
+            self.finalized_count.put(count);
+            for (voter, _) in self.votes.keys().filter(|(_, id)| *id == proposal_id) {
+                let previous = count.saturating_sub(1);
+                let entry =
+                    self.participation
+                        .get_or_insert_with(voter.clone(), || Participation {
+                            voted: 0,
+                            eligible: 0,
+                            as_of: previous,
+                        });
+                *entry = entry.decayed(count, T::PARTICIPATION_DECAY);
+                entry.voted = entry.voted.saturating_add(1_000_000);
+            }
+
+            let now = system.block_number();
+            if proposal.status == ProposalStatus::Approved && !proposal.calls.is_empty() {
+                let at = now
+                    .checked_add(&T::ENACTMENT_DELAY)
+                    .ok_or(GovernanceError::Overflow)?;
+                self.enactments.insert(proposal_id, at);
+                system.deposit_event(Event::EnactmentScheduled { proposal_id, at });
+            }
+
+            // Voters with conviction now keep their stake bonded for the promised time
+            let mut convicted: Vec<(T::AccountId, T::Balance, Conviction)> = self
+                .votes
+                .iter()
+                .filter(|((_, id), record)| *id == proposal_id && !record.stake.is_zero())
+                .map(|((voter, _), record)| (voter.clone(), record.stake, record.conviction))
+                .collect();
+            convicted.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+            for (voter, stake, conviction) in convicted {
+                let until = conviction_lock_until::<T>(now, conviction).ok();
+                if let Some(until) = until.filter(|until| *until > now) {
+                    staking.lock_stake(system, voter, stake, until);
+                }
+            }
+
+            T::ProposalHooks::on_finalized(system, proposal_id, &proposal.status);
+            Ok(proposal.status.clone())
+        } else {
+            Err(GovernanceError::ProposalNotFound)
+        }
+    }
+
+    // Replace a proposal's description; only before anyone has voted on it, so no
+    // vote was cast on different wording
+    pub fn amend_proposal(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+        description: String,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self.ensure_unvoted(&who, proposal_id)?;
+        // A template's description is derived from its arguments
+        if proposal.template.is_some() {
+            return Err(GovernanceError::CannotAmendTemplate);
+        }
+
+        let previous = std::mem::replace(&mut proposal.description, description.clone());
+        unindex_words(&mut self.word_index, &previous, proposal_id);
+        index_words(&mut self.word_index, &description, proposal_id);
+        system.deposit_event(Event::ProposalAmended { proposal_id });
+
+        Ok(())
+    }
+
+    // Withdraw a proposal before it is finalized, releasing the deposits of its
+    // localized descriptions
+    pub fn cancel_proposal(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.creator != who {
+            return Err(GovernanceError::NotProposalCreator);
+        }
+        if proposal.status != ProposalStatus::Active {
+            return Err(GovernanceError::ProposalNotActive);
+        }
+
+        Self::release_localizations(system, balances, proposal, who)?;
+        proposal.status = ProposalStatus::Cancelled;
+        system.deposit_event(Event::ProposalCancelled { proposal_id });
+        T::ProposalHooks::on_finalized(system, proposal_id, &ProposalStatus::Cancelled);
+
+        Ok(())
+    }
+
+    // Close voting on an active proposal now; the end-of-block hook then finalizes
+    // it under the usual quorum and approval rules. Called by the council pallet.
+    pub fn fast_track(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        proposal_id: u32,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.status != ProposalStatus::Active {
+            return Err(GovernanceError::ProposalNotActive);
+        }
+
+        let now = system.block_number();
+        proposal.end = proposal.end.min(now);
+        system.deposit_event(Event::ProposalFastTracked {
+            proposal_id,
+            end: proposal.end,
+        });
+
+        Ok(())
+    }
+
+    // Reject an active proposal without a tally. Called by the council pallet. Voters
+    // keep the locks taken when they voted, conviction periods included, but as the
+    // votes never counted they aren't extended from here.
+    pub fn veto(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        proposal_id: u32,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.status != ProposalStatus::Active {
+            return Err(GovernanceError::ProposalNotActive);
+        }
+
+        proposal.status = ProposalStatus::Rejected;
+        system.deposit_event(Event::ProposalVetoed { proposal_id });
+        T::ProposalHooks::on_finalized(system, proposal_id, &ProposalStatus::Rejected);
+
+        Ok(())
+    }
+
+    // End-of-block hook: finalize every active proposal whose voting period is over.
+    // Proposals still waiting on a dependency are retried in later blocks.
+    pub fn on_finalize(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &mut StakingPallet<T>,
+        now: T::BlockNumber,
+    ) {
+        let mut expired: Vec<u32> = self
+            .proposals
+            .iter()
+            .filter(|(_, proposal)| {
+                proposal.status == ProposalStatus::Active && proposal.end <= now
+            })
+            .map(|(proposal_id, _)| *proposal_id)
+            .collect();
+        // Dependencies always have lower ids, so they are finalized first. Proposals
+        // over the cap stay expired and are picked up by the next block, in id order.
+        // Only finalizations count towards the cap: a dependent still waiting on its
+        // dependency is skipped without taking a later proposal's turn.
+        expired.sort_unstable();
+        let cap = usize::try_from(T::MAX_FINALIZATIONS_PER_BLOCK).unwrap_or(usize::MAX);
+        let mut finalized = 0usize;
+        for proposal_id in expired {
+            if finalized >= cap {
+                break;
+            }
+            if self.finalize_proposal(system, staking, proposal_id).is_ok() {
+                finalized = finalized.saturating_add(1);
+            }
+        }
+
+        let era = staking.current_era();
+        let mut lifted: Vec<T::AccountId> = self
+            .barred
+            .iter()
+            .filter(|(_, until_era)| **until_era <= era)
+            .map(|(who, _)| who.clone())
+            .collect();
+        lifted.sort();
+        for who in lifted {
+            self.barred.remove(&who);
+            system.deposit_event(Event::ParticipationRestored { who });
+        }
+    }
+
+    // Take the calls of approved proposals that are due for enactment by `now`, in
+    // proposal order. The runtime dispatches them and reports back with `note_enacted`.
+    // A dependent proposal stays queued until its dependency's calls went through,
+    // and is dropped if they failed.
+    pub fn take_due_enactments(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        now: T::BlockNumber,
+    ) -> Vec<(u32, Vec<T::RuntimeCall>)> {
+        let mut due: Vec<u32> = self
+            .enactments
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(proposal_id, _)| *proposal_id)
+            .collect();
+        due.sort_unstable();
+
+        let mut taken = Vec::new();
+        for proposal_id in due {
+            let Some(proposal) = self.proposals.get(&proposal_id) else {
+                self.enactments.remove(&proposal_id);
+                continue;
+            };
+            if let Some(dependency) = proposal.depends_on {
+                match self.dependency_enacted(dependency) {
+                    // Due in this block or later; retried in the next one
+                    None => continue,
+                    Some(false) => {
+                        self.enactments.remove(&proposal_id);
+                        self.enacted.insert(proposal_id, false);
+                        system.deposit_event(Event::EnactmentCancelled {
+                            proposal_id,
+                            dependency,
+                        });
+                        continue;
+                    }
+                    Some(true) => {}
+                }
+            }
+            taken.push((proposal_id, proposal.calls.to_vec()));
+            self.enactments.remove(&proposal_id);
+        }
+
+        taken
+    }
+
+    // Whether a dependency's calls went through, or None while they're still queued.
+    // An approved proposal without calls has nothing to enact.
+    fn dependency_enacted(&self, dependency: u32) -> Option<bool> {
+        if let Some(succeeded) = self.enacted.get(&dependency) {
+            return Some(*succeeded);
+        }
+        let proposal = self.proposals.get(&dependency)?;
+        match proposal.status {
+            ProposalStatus::Approved if proposal.calls.is_empty() => Some(true),
+            ProposalStatus::Approved => None,
+            _ => Some(false),
+        }
+    }
+
+    // Whether an approved proposal's calls were enacted, once it's been attempted
+    pub fn get_enactment_outcome(&self, proposal_id: u32) -> Option<bool> {
+        self.enacted.get(&proposal_id).copied()
+    }
+
+    // Record the outcome of enacting an approved proposal: the index of the call
+    // that failed, if any
+    pub fn note_enacted(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        proposal_id: u32,
+        failed_call: Option<u32>,
+    ) {
+        self.enacted.insert(proposal_id, failed_call.is_none());
+        system.deposit_event(Event::ProposalEnacted {
+            proposal_id,
+            failed_call,
+        });
+    }
+
+    // Get the calls an approved proposal dispatches, in order
+    pub fn get_proposal_calls(&self, proposal_id: u32) -> &[T::RuntimeCall] {
+        self.proposals
+            .get(&proposal_id)
+            .map_or(&[], |proposal| &proposal.calls)
+    }
+
+    // Called when an offence is reported against `offender`. A severe enough offence
+    // bars them for OFFENCE_BAR_ERAS eras after the current one; a later offence
+    // can extend the bar but never shortens it.
+    pub fn note_offence(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &StakingPallet<T>,
+        offender: T::AccountId,
+        severity: u32,
+    ) {
+        if severity < T::OFFENCE_BAR_SEVERITY || T::OFFENCE_BAR_ERAS == 0 {
+            return;
+        }
+        let until_era = staking
+            .current_era()
+            .saturating_add(1)
+            .saturating_add(T::OFFENCE_BAR_ERAS);
+        if self
+            .barred
+            .get(&offender)
+            .is_some_and(|until| *until >= until_era)
+        {
+            return;
+        }
+
+        self.barred.insert(offender.clone(), until_era);
+        system.deposit_event(Event::ParticipationBarred {
+            who: offender,
+            until_era,
+        });
+    }
+
+    // Get the era in which an account's bar from governance is lifted, if barred
+    pub fn barred_until(&self, who: &T::AccountId) -> Option<u32> {
+        self.barred.get(who).copied()
+    }
+
+    fn ensure_not_barred(&self, who: &T::AccountId) -> Result<(), GovernanceError> {
+        if self.barred.contains_key(who) {
+            return Err(GovernanceError::ParticipationBarred);
+        }
+        Ok(())
+    }
+
+    // Get a proposal's description and creator, along with the creator's name if
+    // `labels` knows it (e.g. a registered identity)
+    pub fn get_proposal_details(
+        &self,
+        proposal_id: u32,
+        labels: &impl AccountLabels<T::AccountId>,
+    ) -> Result<(String, T::AccountId, Option<String>), GovernanceError> {
+        if let Some(proposal) = self.proposals.get(&proposal_id) {
+            Ok((
+                proposal.description.clone(),
+                proposal.creator.clone(),
+                labels.label(&proposal.creator),
+            ))
+        } else {
+            Err(GovernanceError::ProposalNotFound)
+        }
+    }
+
+    // Attach or replace a localized description, holding a deposit for new entries.
+    // Like amending, only before anyone has voted on the proposal.
+    pub fn set_localized_description(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+        lang: &str,
+        description: String,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self.ensure_unvoted(&who, proposal_id)?;
+        if description.len() > T::MAX_LOCALIZED_DESCRIPTION_LEN {
+            return Err(GovernanceError::DescriptionTooLong);
+        }
+
+        let lang = Self::normalize_language_tag(lang)?;
+        if !proposal.localized_descriptions.contains_key(&lang) {
+            if proposal.localized_descriptions.len() >= T::MAX_LOCALIZATIONS {
+                return Err(GovernanceError::TooManyLocalizations);
+            }
+            balances.hold(
+                system,
+                HoldReason::ProposalDeposit,
+                who,
+                T::LOCALIZATION_DEPOSIT,
+            )?;
+        }
+
+        proposal
+            .localized_descriptions
+            .try_insert(lang.clone(), description)
+            .map_err(|_| GovernanceError::TooManyLocalizations)?;
+        system.deposit_event(Event::LocalizedDescriptionSet { proposal_id, lang });
+
+        Ok(())
+    }
+
+    // Remove a localized description and release its deposit, again only before
+    // anyone has voted on the proposal
+    pub fn remove_localized_description(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+        lang: &str,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self.ensure_unvoted(&who, proposal_id)?;
+
+        let lang = Self::normalize_language_tag(lang)?;
+        if proposal.localized_descriptions.remove(&lang).is_none() {
+            return Err(GovernanceError::LocalizationNotFound);
+        }
+        balances.release(
+            system,
+            HoldReason::ProposalDeposit,
+            who,
+            T::LOCALIZATION_DEPOSIT,
+        )?;
+        system.deposit_event(Event::LocalizedDescriptionRemoved { proposal_id, lang });
+
+        Ok(())
+    }
+
+    // Drop a closed proposal's localized descriptions and release their deposits,
+    // which can no longer be removed one by one
+    pub fn release_localization_deposits(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.creator != who {
+            return Err(GovernanceError::NotProposalCreator);
+        }
+        if proposal.status == ProposalStatus::Active {
+            return Err(GovernanceError::ProposalStillActive);
+        }
+        if proposal.localized_descriptions.is_empty() {
+            return Err(GovernanceError::LocalizationNotFound);
+        }
+
+        Self::release_localizations(system, balances, proposal, who)
+    }
+
+    // Release the deposit held for each of a proposal's localized descriptions and
+    // drop them
+    fn release_localizations(
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        proposal: &mut Proposal<T>,
+        who: T::AccountId,
+    ) -> Result<(), GovernanceError> {
+        let deposit = proposal
+            .localized_descriptions
+            .iter()
+            .try_fold(T::Balance::zero(), |total, _| {
+                total.checked_add(&T::LOCALIZATION_DEPOSIT)
+            })
+            .ok_or(GovernanceError::Overflow)?;
+        if !deposit.is_zero() {
+            balances.release(system, HoldReason::ProposalDeposit, who, deposit)?;
+        }
+        proposal.localized_descriptions = BoundedBTreeMap::with_bound(T::MAX_LOCALIZATIONS);
+
+        Ok(())
+    }
+
+    // The checks for changing a proposal's wording: only its creator may, and only
+    // while it's open and nobody has voted on it yet
+    fn ensure_unvoted(
+        &mut self,
+        who: &T::AccountId,
+        proposal_id: u32,
+    ) -> Result<&mut Proposal<T>, GovernanceError> {
+        let has_votes = self.votes.keys().any(|(_, id)| *id == proposal_id);
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.creator != *who {
+            return Err(GovernanceError::NotProposalCreator);
+        }
+        if proposal.status != ProposalStatus::Active {
+            return Err(GovernanceError::ProposalNotActive);
+        }
+        if has_votes {
+            return Err(GovernanceError::VotesAlreadyCast);
+        }
+
+        Ok(proposal)
+    }
+
+    // Get a proposal description in the given language, falling back to the default
+    pub fn get_proposal_description(
+        &self,
+        proposal_id: u32,
+        lang: &str,
+    ) -> Result<String, GovernanceError> {
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+
+        let localized = Self::normalize_language_tag(lang)
+            .ok()
+            .and_then(|lang| proposal.localized_descriptions.get(&lang));
+        Ok(localized.unwrap_or(&proposal.description).clone())
+    }
+
+    // Language tags are matched case-insensitively, e.g. "pt-BR" and "pt-br"
+    fn normalize_language_tag(lang: &str) -> Result<String, GovernanceError> {
+        if lang.is_empty()
+            || lang.len() > 35
+            || !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(GovernanceError::InvalidLanguageTag);
+        }
+
+        Ok(lang.to_ascii_lowercase())
+    }
+
+    // Replace a proposal's tags. Tags are lowercased and deduplicated.
+    pub fn set_tags(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+        tags: Vec<String>,
+    ) -> Result<(), GovernanceError> {
+        let mut tags = tags
+            .iter()
+            .map(|tag| Self::normalize_tag(tag))
+            .collect::<Result<Vec<_>, _>>()?;
+        tags.sort();
+        tags.dedup();
+        let tags = BoundedVec::try_from_vec(tags, T::MAX_TAGS)
+            .map_err(|_| GovernanceError::TooManyTags)?;
+
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.creator != who {
+            return Err(GovernanceError::NotProposalCreator);
+        }
+
+        let previous = std::mem::replace(&mut proposal.tags, tags.clone());
+        for tag in previous.iter() {
+            unindex(&mut self.tag_index, tag, proposal_id);
+        }
+        for tag in tags.iter() {
+            index(&mut self.tag_index, tag.clone(), proposal_id);
+        }
+        system.deposit_event(Event::TagsSet {
+            proposal_id,
+            tags: tags.into_inner(),
+        });
+
+        Ok(())
+    }
+
+    // Find proposals matching every word of `query`, each word matching either a
+    // tag or a word of the description, case-insensitively. Ids come back in order.
+    pub fn search_proposals(&self, query: &str) -> Vec<u32> {
+        let mut matches: Option<Vec<u32>> = None;
+        for token in tokenize(query) {
+            let mut ids: Vec<u32> = self
+                .tag_index
+                .get(&token)
+                .into_iter()
+                .chain(self.word_index.get(&token))
+                .flatten()
+                .copied()
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            matches = Some(match matches {
+                Some(previous) => previous
+                    .into_iter()
+                    .filter(|id| ids.binary_search(id).is_ok())
+                    .collect(),
+                None => ids,
+            });
+        }
+
+        matches.unwrap_or_default()
+    }
+
+    // Get a proposal's tags, sorted
+    pub fn get_proposal_tags(&self, proposal_id: u32) -> Option<&[String]> {
+        self.proposals
+            .get(&proposal_id)
+            .map(|proposal| &proposal.tags[..])
+    }
+
+    fn normalize_tag(tag: &str) -> Result<String, GovernanceError> {
+        if tag.is_empty()
+            || tag.len() > T::MAX_TAG_LEN
+            || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(GovernanceError::InvalidTag);
+        }
+
+        Ok(tag.to_ascii_lowercase())
+    }
+
+    // Get the structured template a proposal was created from, if any
+    pub fn get_proposal_template(&self, proposal_id: u32) -> Option<&ProposalTemplateOf<T>> {
+        self.proposals
+            .get(&proposal_id)
+            .and_then(|proposal| proposal.template.as_ref())
+    }
+
+    // Recompute a proposal's tally from the vote records cast on it, using the weights
+    // snapshotted when each vote was cast, and compare it with the stored counters
+    pub fn verify_tally(&self, proposal_id: u32) -> Result<TallyReport<T>, GovernanceError> {
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        let mut cast = self
+            .votes
+            .iter()
+            .filter(|((_, id), _)| *id == proposal_id)
+            .map(|(_, record)| (record.vote_type, record.weight))
+            .chain(
+                self.delegated_votes
+                    .iter()
+                    .filter(|((_, id), _)| *id == proposal_id)
+                    .map(|(_, vote)| (vote.vote_type, vote.weight)),
+            );
+        let recomputed = cast
+            .try_fold(
+                (T::Balance::zero(), T::Balance::zero()),
+                |tally, (vote_type, weight)| add_to_tally(tally, vote_type, weight),
+            )
+            .ok();
+
+        Ok(TallyReport {
+            proposal_id,
+            stored: proposal.tally(),
+            recomputed,
+        })
+    }
+
+    // Verify every proposal's tally, returning the reports that show a discrepancy,
+    // in id order
+    pub fn check_tallies(&self) -> Vec<TallyReport<T>> {
+        let mut proposal_ids: Vec<u32> = self.proposals.keys().copied().collect();
+        proposal_ids.sort_unstable();
+        proposal_ids
+            .into_iter()
+            .filter_map(|proposal_id| self.verify_tally(proposal_id).ok())
+            .filter(|report| !report.is_consistent())
+            .collect()
+    }
+
+    // Approximate state held by this pallet, counting proposal text as well
+    pub fn storage_usage(&self) -> StorageUsage {
+        let proposals = self
+            .proposals
+            .values()
+            .map(|proposal| {
+                let text = proposal.localized_descriptions.iter().fold(
+                    proposal.description.len(),
+                    |total, (lang, description)| {
+                        total
+                            .saturating_add(lang.len())
+                            .saturating_add(description.len())
+                    },
+                );
+                StorageUsage::value::<(u32, Proposal<T>)>(text)
+            })
+            .fold(StorageUsage::default(), StorageUsage::saturating_add);
+
+        StorageUsage::value::<u32>(0)
+            .saturating_add(StorageUsage::value::<u32>(0))
+            .saturating_add(proposals)
+            .saturating_add(self.votes.storage_usage())
+            .saturating_add(self.delegations.storage_usage())
+            .saturating_add(self.delegated_votes.storage_usage())
+            .saturating_add(self.barred.storage_usage())
+            .saturating_add(self.enactments.storage_usage())
+            .saturating_add(self.enacted.storage_usage())
+            .saturating_add(self.participation.storage_usage())
+    }
+
+    // Export the votes counted on a proposal as JSON for off-chain tallying: one entry
+    // per account whose stake counted, direct voters and delegators alike, with the
+    // weight the tally used. Summing "voting_power" by "vote" gives the proposal's
+    // tally, and summing "stake" gives the turnout its quorum is measured on.
+    //
+    // Schema (amounts are raw integers encoded as strings; "delegate" is null for a
+    // direct vote and the voter who carried the weight otherwise):
+    // {"symbol": "UNIT", "decimals": 2, "proposal": 0, "end": 10,
+    //  "accounts": [{"account": "1", "vote": "aye", "conviction": 3, "stake": "100",
+    //                "voting_power": "300", "delegate": null}, ...]}
+    pub fn export_voting_snapshot(&self, proposal_id: u32) -> Result<String, GovernanceError> {
+        let proposal = self
+            .proposals
+            .get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        let cap = |stake: T::Balance| match proposal.vote_cap {
+            Some(cap) if stake > cap => cap,
+            _ => stake,
+        };
+
+        // (account, vote, conviction multiplier, stake, weight, delegate)
+        let mut counted: Vec<_> = self
+            .votes
+            .iter()
+            .filter(|((_, id), _)| *id == proposal_id)
+            .map(|((voter, _), record)| {
+                let conviction = record.conviction.multiplier();
+                let stake = cap(record.stake);
+                (
+                    voter,
+                    record.vote_type,
+                    conviction,
+                    stake,
+                    record.weight,
+                    None,
+                )
+            })
+            .chain(
+                self.delegated_votes
+                    .iter()
+                    .filter(|((_, id), _)| *id == proposal_id)
+                    .map(|((delegator, _), delegated)| {
+                        let weight = delegated.weight;
+                        let delegate = Some(&delegated.delegate);
+                        (delegator, delegated.vote_type, 1, weight, weight, delegate)
+                    }),
+            )
+            .collect();
+        counted.sort_by_key(|(who, ..)| *who);
+
+        let account = |who: &T::AccountId| format!("\"{}\"", json_escape(&format!("{:?}", who)));
+        let accounts: Vec<String> = counted
+            .into_iter()
+            .map(|(who, vote_type, conviction, stake, weight, delegate)| {
+                let stake: u128 = stake.into();
+                let voting_power: u128 = weight.into();
+                format!(
+                    "{{\"account\": {}, \"vote\": \"{}\", \"conviction\": {}, \"stake\": \"{}\", \"voting_power\": \"{}\", \"delegate\": {}}}",
+                    account(who),
+                    if vote_type { "aye" } else { "nay" },
+                    conviction,
+                    stake,
+                    voting_power,
+                    delegate.map_or_else(|| "null".to_string(), account)
+                )
+            })
+            .collect();
+
+        Ok(format!(
+            "{{\"symbol\": \"{}\", \"decimals\": {}, \"proposal\": {}, \"end\": {:?}, \"accounts\": [{}]}}",
+            json_escape(T::TOKEN_SYMBOL),
+            T::TOKEN_DECIMALS,
+            proposal_id,
+            proposal.end,
+            accounts.join(", ")
+        ))
+    }
+
+    // List proposals in id order, starting after the given cursor
+    pub fn proposals_page(&self, after: Option<u32>, limit: usize) -> Page<u32, &Proposal<T>> {
+        let items = self
+            .proposals
+            .iter()
+            .map(|(id, proposal)| (*id, proposal))
+            .collect();
+        paginate(items, after, limit)
+    }
+
+    // List proposals with the given status in id order, starting after the given cursor
+    pub fn proposals_by_status(
+        &self,
+        status: &ProposalStatus,
+        after: Option<u32>,
+        limit: usize,
+    ) -> Page<u32, &Proposal<T>> {
+        self.proposals_where(|proposal| proposal.status == *status, after, limit)
+    }
+
+    // List the proposals an account created in id order, starting after the given cursor
+    pub fn proposals_by_creator(
+        &self,
+        creator: &T::AccountId,
+        after: Option<u32>,
+        limit: usize,
+    ) -> Page<u32, &Proposal<T>> {
+        self.proposals_where(|proposal| proposal.creator == *creator, after, limit)
+    }
+
+    // List the proposals still open for voting in id order
+    pub fn active_proposals(&self, after: Option<u32>, limit: usize) -> Page<u32, &Proposal<T>> {
+        self.proposals_by_status(&ProposalStatus::Active, after, limit)
+    }
+
+    // List an account's own votes in proposal order, starting after the given cursor.
+    // Weight carried for delegators isn't included.
+    pub fn votes_of(
+        &self,
+        who: &T::AccountId,
+        after: Option<u32>,
+        limit: usize,
+    ) -> Page<u32, &VoteRecord<T>> {
+        let items = self
+            .votes
+            .iter()
+            .filter(|((voter, _), _)| voter == who)
+            .map(|((_, proposal_id), record)| (*proposal_id, record))
+            .collect();
+        paginate(items, after, limit)
+    }
+
+    // Whether the account has open business in governance: an active proposal it
+    // created or voted on, or a delegation of its voting power
+    pub fn is_participating(&self, who: &T::AccountId) -> bool {
+        let active = |proposal_id: &u32| {
+            self.proposals
+                .get(proposal_id)
+                .is_some_and(|proposal| proposal.status == ProposalStatus::Active)
+        };
+        self.delegations.contains_key(who)
+            || self.proposals.iter().any(|(_, proposal)| {
+                proposal.creator == *who && proposal.status == ProposalStatus::Active
+            })
+            || self
+                .votes
+                .keys()
+                .any(|(voter, proposal_id)| voter == who && active(proposal_id))
+    }
+
+    // Whether the account voted directly on one of the `window` most recently created
+    // proposals, whatever their status
+    pub fn voted_recently(&self, who: &T::AccountId, window: u32) -> bool {
+        let next = self.next_proposal_id.get();
+        (next.saturating_sub(window)..next)
+            .any(|proposal_id| self.votes.contains_key(&(who.clone(), proposal_id)))
+    }
+
+    // Share of the finalized proposals the account voted on directly since its first
+    // vote, in parts per million and weighted towards recent proposals. None until a
+    // proposal it voted on is finalized.
+    pub fn participation_score(&self, who: &T::AccountId) -> Option<u32> {
+        let participation = self
+            .participation
+            .get(who)?
+            .decayed(self.finalized_count.get(), T::PARTICIPATION_DECAY);
+        let score = participation
+            .voted
+            .saturating_mul(1_000_000)
+            .checked_div(participation.eligible)?;
+        u32::try_from(score).ok()
+    }
+
+    // Count the proposals each account voted on directly, ordered by account
+    pub fn vote_counts(&self) -> Vec<(T::AccountId, u32)> {
+        let mut counts = std::collections::BTreeMap::new();
+        for (voter, _) in self.votes.keys() {
+            let count: &mut u32 = counts.entry(voter.clone()).or_default();
+            *count = count.saturating_add(1);
+        }
+        counts.into_iter().collect()
+    }
+
+    fn proposals_where(
+        &self,
+        filter: impl Fn(&Proposal<T>) -> bool,
+        after: Option<u32>,
+        limit: usize,
+    ) -> Page<u32, &Proposal<T>> {
+        let items = self
+            .proposals
+            .iter()
+            .filter(|(_, proposal)| filter(proposal))
+            .map(|(id, proposal)| (*id, proposal))
+            .collect();
+        paginate(items, after, limit)
+    }
+
+    // List the voters of a proposal in account order, starting after the given cursor
+    pub fn voters_page(
+        &self,
+        proposal_id: u32,
+        after: Option<T::AccountId>,
+        limit: usize,
+    ) -> Page<T::AccountId, bool> {
+        let items = self
+            .votes
+            .iter()
+            .filter(|((_, id), _)| *id == proposal_id)
+            .map(|((voter, _), record)| (voter.clone(), record.vote_type))
+            .collect();
+        paginate(items, after, limit)
+    }
+}
+
+impl Encode for ProposalStatus {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            ProposalStatus::Active => 0,
+            ProposalStatus::Approved => 1,
+            ProposalStatus::Rejected => 2,
+            ProposalStatus::QuorumNotMet => 3,
+            ProposalStatus::Cancelled => 4,
+        };
+        tag.encode_to(dest);
+    }
+}
+
+impl Decode for ProposalStatus {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(ProposalStatus::Active),
+            1 => Ok(ProposalStatus::Approved),
+            2 => Ok(ProposalStatus::Rejected),
+            3 => Ok(ProposalStatus::QuorumNotMet),
+            4 => Ok(ProposalStatus::Cancelled),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl Encode for Conviction {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            Conviction::None => 0,
+            Conviction::Locked2x => 1,
+            Conviction::Locked3x => 2,
+            Conviction::Locked4x => 3,
+            Conviction::Locked5x => 4,
+            Conviction::Locked6x => 5,
+        };
+        tag.encode_to(dest);
+    }
+}
+
+impl Decode for Conviction {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Conviction::None),
+            1 => Ok(Conviction::Locked2x),
+            2 => Ok(Conviction::Locked3x),
+            3 => Ok(Conviction::Locked4x),
+            4 => Ok(Conviction::Locked5x),
+            5 => Ok(Conviction::Locked6x),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for ProposalTemplate<AccountId, Balance> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            ProposalTemplate::SetParameter { key, value } => {
+                0u8.encode_to(dest);
+                key.encode_to(dest);
+                value.encode_to(dest);
+            }
+            ProposalTemplate::TreasurySpend { to, amount } => {
+                1u8.encode_to(dest);
+                to.encode_to(dest);
+                amount.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for ProposalTemplate<AccountId, Balance> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(ProposalTemplate::SetParameter {
+                key: Decode::decode(input)?,
+                value: Decode::decode(input)?,
+            }),
+            1 => Ok(ProposalTemplate::TreasurySpend {
+                to: Decode::decode(input)?,
+                amount: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<Balance: Encode> Encode for VoteCap<Balance> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            VoteCap::Absolute(amount) => {
+                0u8.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            VoteCap::FractionOfElectorate { parts_per_million } => {
+                1u8.encode_to(dest);
+                parts_per_million.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<Balance: Decode> Decode for VoteCap<Balance> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(VoteCap::Absolute(Decode::decode(input)?)),
+            1 => Ok(VoteCap::FractionOfElectorate {
+                parts_per_million: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: GovernanceConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::CreateProposal { .. } | Call::CreateProposalFromTemplate { .. } => 4,
+            Call::CreateDependentProposal { .. } | Call::CreateProposalWithCall { .. } => 5,
+            Call::CreateProposalWithCalls { calls, .. } => {
+                weight_of(calls.len(), 1).saturating_add(4)
+            }
+            Call::CreateDependentProposalWithCalls { calls, .. } => {
+                weight_of(calls.len(), 1).saturating_add(5)
+            }
+            Call::Vote { .. } => 8,
+            Call::ChangeVote { .. } | Call::RetractVote { .. } => 6,
+            Call::Delegate { .. } | Call::Undelegate => 2,
+            Call::SetVoteCap { .. }
+            | Call::AmendProposal { .. }
+            | Call::CancelProposal { .. }
+            | Call::SetLocalizedDescription { .. }
+            | Call::RemoveLocalizedDescription { .. }
+            | Call::ReleaseLocalizationDeposits { .. } => 3,
+            Call::SetTags { tags, .. } => weight_of(tags.len(), 1).saturating_add(3),
+            Call::FinalizeProposal { .. } => 10,
+        }
+    }
+}
+
+impl<T: GovernanceConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::CreateProposal { description } => {
+                0u8.encode_to(dest);
+                description.encode_to(dest);
+            }
+            Call::CreateProposalFromTemplate { template } => {
+                1u8.encode_to(dest);
+                template.encode_to(dest);
+            }
+            Call::CreateDependentProposal {
+                description,
+                depends_on,
+            } => {
+                2u8.encode_to(dest);
+                description.encode_to(dest);
+                depends_on.encode_to(dest);
+            }
+            Call::Vote {
+                proposal_id,
+                vote_type,
+                conviction,
+            } => {
+                3u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+                vote_type.encode_to(dest);
+                conviction.encode_to(dest);
+            }
+            Call::Delegate { to } => {
+                4u8.encode_to(dest);
+                to.encode_to(dest);
+            }
+            Call::Undelegate => 5u8.encode_to(dest),
+            Call::SetVoteCap { proposal_id, cap } => {
+                6u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+                cap.encode_to(dest);
+            }
+            Call::FinalizeProposal { proposal_id } => {
+                7u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+            }
+            Call::AmendProposal {
+                proposal_id,
+                description,
+            } => {
+                8u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+                description.encode_to(dest);
+            }
+            Call::CancelProposal { proposal_id } => {
+                9u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+            }
+            Call::SetLocalizedDescription {
+                proposal_id,
+                lang,
+                description,
+            } => {
+                10u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+                lang.encode_to(dest);
+                description.encode_to(dest);
+            }
+            Call::RemoveLocalizedDescription { proposal_id, lang } => {
+                11u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+                lang.encode_to(dest);
+            }
+            Call::ReleaseLocalizationDeposits { proposal_id } => {
+                18u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+            }
+            Call::ChangeVote {
+                proposal_id,
+                vote_type,
+            } => {
+                12u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+                vote_type.encode_to(dest);
+            }
+            Call::RetractVote { proposal_id } => {
+                13u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+            }
+            Call::SetTags { proposal_id, tags } => {
+                14u8.encode_to(dest);
+                proposal_id.encode_to(dest);
+                tags.encode_to(dest);
+            }
+            Call::CreateProposalWithCall { description, call } => {
+                15u8.encode_to(dest);
+                description.encode_to(dest);
+                call.encode_to(dest);
+            }
+            Call::CreateProposalWithCalls { description, calls } => {
+                16u8.encode_to(dest);
+                description.encode_to(dest);
+                calls.encode_to(dest);
+            }
+            Call::CreateDependentProposalWithCalls {
+                description,
+                depends_on,
+                calls,
+            } => {
+                17u8.encode_to(dest);
+                description.encode_to(dest);
+                depends_on.encode_to(dest);
+                calls.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: GovernanceConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::CreateProposal {
+                description: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::CreateProposalFromTemplate {
+                template: Decode::decode(input)?,
+            }),
+            2 => Ok(Call::CreateDependentProposal {
+                description: Decode::decode(input)?,
+                depends_on: Decode::decode(input)?,
+            }),
+            3 => Ok(Call::Vote {
+                proposal_id: Decode::decode(input)?,
+                vote_type: Decode::decode(input)?,
+                conviction: Decode::decode(input)?,
+            }),
+            4 => Ok(Call::Delegate {
+                to: Decode::decode(input)?,
+            }),
+            5 => Ok(Call::Undelegate),
+            6 => Ok(Call::SetVoteCap {
+                proposal_id: Decode::decode(input)?,
+                cap: Decode::decode(input)?,
+            }),
+            7 => Ok(Call::FinalizeProposal {
+                proposal_id: Decode::decode(input)?,
+            }),
+            8 => Ok(Call::AmendProposal {
+                proposal_id: Decode::decode(input)?,
+                description: Decode::decode(input)?,
+            }),
+            9 => Ok(Call::CancelProposal {
+                proposal_id: Decode::decode(input)?,
+            }),
+            10 => Ok(Call::SetLocalizedDescription {
+                proposal_id: Decode::decode(input)?,
+                lang: Decode::decode(input)?,
+                description: Decode::decode(input)?,
+            }),
+            11 => Ok(Call::RemoveLocalizedDescription {
+                proposal_id: Decode::decode(input)?,
+                lang: Decode::decode(input)?,
+            }),
+            18 => Ok(Call::ReleaseLocalizationDeposits {
+                proposal_id: Decode::decode(input)?,
+            }),
+            12 => Ok(Call::ChangeVote {
+                proposal_id: Decode::decode(input)?,
+                vote_type: Decode::decode(input)?,
+            }),
+            13 => Ok(Call::RetractVote {
+                proposal_id: Decode::decode(input)?,
+            }),
+            14 => Ok(Call::SetTags {
+                proposal_id: Decode::decode(input)?,
+                tags: Decode::decode(input)?,
+            }),
+            15 => Ok(Call::CreateProposalWithCall {
+                description: Decode::decode(input)?,
+                call: Decode::decode(input)?,
+            }),
+            16 => Ok(Call::CreateProposalWithCalls {
+                description: Decode::decode(input)?,
+                calls: Decode::decode(input)?,
+            }),
+            17 => Ok(Call::CreateDependentProposalWithCalls {
+                description: Decode::decode(input)?,
+                depends_on: Decode::decode(input)?,
+                calls: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: GovernanceConfig> Encode for Proposal<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.description.encode_to(dest);
+        self.yes_votes.encode_to(dest);
+        self.no_votes.encode_to(dest);
+        self.status.encode_to(dest);
+        self.creator.encode_to(dest);
+        self.depends_on.encode_to(dest);
+        self.template.encode_to(dest);
+        self.localized_descriptions.encode_to(dest);
+        self.tags.encode_to(dest);
+        self.calls.encode_to(dest);
+        self.vote_cap.encode_to(dest);
+        self.end.encode_to(dest);
+    }
+}
+
+impl<T: GovernanceConfig> Decode for Proposal<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            description: Decode::decode(input)?,
+            yes_votes: Decode::decode(input)?,
+            no_votes: Decode::decode(input)?,
+            status: Decode::decode(input)?,
+            creator: Decode::decode(input)?,
+            depends_on: Decode::decode(input)?,
+            template: Decode::decode(input)?,
+            localized_descriptions: BoundedBTreeMap::decode_with_bound(
+                input,
+                T::MAX_LOCALIZATIONS,
+            )?,
+            tags: BoundedVec::decode_with_bound(input, T::MAX_TAGS)?,
+            calls: BoundedVec::decode_with_bound(input, T::MAX_PROPOSAL_CALLS)?,
+            vote_cap: Decode::decode(input)?,
+            end: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: GovernanceConfig> Encode for VoteRecord<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.vote_type.encode_to(dest);
+        self.conviction.encode_to(dest);
+        self.weight.encode_to(dest);
+        self.stake.encode_to(dest);
+    }
+}
+
+impl<T: GovernanceConfig> Decode for VoteRecord<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            vote_type: Decode::decode(input)?,
+            conviction: Decode::decode(input)?,
+            weight: Decode::decode(input)?,
+            stake: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: GovernanceConfig> Encode for DelegatedVote<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.delegate.encode_to(dest);
+        self.vote_type.encode_to(dest);
+        self.weight.encode_to(dest);
+    }
+}
+
+impl<T: GovernanceConfig> Decode for DelegatedVote<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            delegate: Decode::decode(input)?,
+            vote_type: Decode::decode(input)?,
+            weight: Decode::decode(input)?,
+        })
+    }
+}
+
+impl Encode for Participation {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.voted.encode_to(dest);
+        self.eligible.encode_to(dest);
+        self.as_of.encode_to(dest);
+    }
+}
+
+impl Decode for Participation {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            voted: Decode::decode(input)?,
+            eligible: Decode::decode(input)?,
+            as_of: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: GovernanceConfig> Encode for GovernancePallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.proposals.encode_to(dest);
+        self.votes.encode_to(dest);
+        self.delegations.encode_to(dest);
+        self.delegated_votes.encode_to(dest);
+        self.next_proposal_id.encode_to(dest);
+        self.barred.encode_to(dest);
+        self.enactments.encode_to(dest);
+        self.enacted.encode_to(dest);
+        self.finalized_count.encode_to(dest);
+        self.participation.encode_to(dest);
+    }
+}
+
+impl<T: GovernanceConfig> Decode for GovernancePallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let mut pallet = Self {
+            proposals: Decode::decode(input)?,
+            votes: Decode::decode(input)?,
+            delegations: Decode::decode(input)?,
+            delegated_votes: Decode::decode(input)?,
+            next_proposal_id: Decode::decode(input)?,
+            barred: Decode::decode(input)?,
+            tag_index: StorageMap::new(),
+            word_index: StorageMap::new(),
+            enactments: Decode::decode(input)?,
+            enacted: Decode::decode(input)?,
+            finalized_count: Decode::decode(input)?,
+            participation: Decode::decode(input)?,
+        };
+        for (proposal_id, proposal) in pallet.proposals.iter() {
+            index_words(&mut pallet.word_index, &proposal.description, *proposal_id);
+            for tag in proposal.tags.iter() {
+                index(&mut pallet.tag_index, tag.clone(), *proposal_id);
+            }
+        }
+        Ok(pallet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::{LabelRegistry, NoLabels};
+    use crate::{Runtime, RuntimeEvent};
+
+    // Build a staking pallet where each account has bonded the given amount
+    fn staked(stakes: &[(u64, u64)]) -> StakingPallet<Runtime> {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for (who, amount) in stakes {
+            balances.set_balance(*who, *amount).unwrap();
+            staking
+                .stake(&mut system, &mut balances, *who, *amount)
+                .unwrap();
+        }
+        staking
+    }
+
+    #[test]
+    fn test_governance_should_work() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+
+        let mut staking = staked(&[(alice, 100), (bob, 50), (charlie, 120)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        // Create a proposal
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Increase validator rewards".to_string())
+            .unwrap();
+
+        let (description, creator, _) = governance
+            .get_proposal_details(proposal_id, &NoLabels)
+            .unwrap();
+        assert_eq!(description, "Increase validator rewards");
+        assert_eq!(creator, alice);
+
+        // Cast votes
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap(); // Yes vote
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, true)
+            .unwrap(); // Yes vote
+        governance
+            .vote(&mut system, &mut staking, charlie, proposal_id, false)
+            .unwrap(); // No vote
+
+        // Check proposal status before finalization
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.yes_votes, 150);
+        assert_eq!(proposal.no_votes, 120);
+
+        // Finalize proposal
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, proposal_id)
+            .unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+
+        // Check proposal is now approved
+        let finalized_proposal = governance.get_proposal(proposal_id).unwrap();
+        assert!(matches!(
+            finalized_proposal.status,
+            ProposalStatus::Approved
+        ));
+    }
+
+    #[test]
+    fn test_governance_emits_events() {
+        let alice = 1u64;
+        let bob = 2u64;
+
+        let mut staking = staked(&[(bob, 70)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Fund the bootcamp".to_string())
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, true)
+            .unwrap();
+
+        // Failed calls don't emit anything
+        let _ = governance.vote(&mut system, &mut staking, bob, proposal_id, true);
+
+        governance
+            .finalize_proposal(&mut system, &mut staking, proposal_id)
+            .unwrap();
+
+        assert_eq!(
+            system.take_events(),
+            vec![
+                Event::ProposalCreated {
+                    proposal_id,
+                    creator: alice,
+                }
+                .into(),
+                crate::staking::Event::StakeLocked {
+                    who: bob,
+                    amount: 70,
+                    until: 11,
+                }
+                .into(),
+                Event::Voted {
+                    voter: bob,
+                    proposal_id,
+                    vote_type: true,
+                    weight: 70,
+                }
+                .into(),
+                Event::ProposalFinalized {
+                    proposal_id,
+                    status: ProposalStatus::Approved,
+                }
+                .into(),
+            ]
+        );
+        assert!(system.events().is_empty());
+    }
+
+    #[test]
+    fn test_events_are_described_with_labels() {
+        let mut labels = LabelRegistry::new();
+        labels.register(1u64, "alice");
+
+        let event = Event::<Runtime>::Voted {
+            voter: 1,
+            proposal_id: 0,
+            vote_type: true,
+            weight: 100,
+        };
+        assert_eq!(
+            event.describe(&labels),
+            "alice voted Aye on proposal 0 with 1 UNIT"
+        );
+        assert_eq!(
+            event.describe(&NoLabels),
+            "account 1 voted Aye on proposal 0 with 1 UNIT"
+        );
+
+        let event: RuntimeEvent = Event::<Runtime>::ProposalFinalized {
+            proposal_id: 0,
+            status: ProposalStatus::Rejected,
+        }
+        .into();
+        assert_eq!(
+            event.describe(&labels),
+            "proposal 0 was finalized as Rejected"
+        );
+    }
+
+    #[test]
+    fn test_votes_are_weighted_by_stake() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+        let dave = 4u64;
+
+        let mut staking = staked(&[(alice, 10), (bob, 20), (charlie, 500)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Lower the minimum bond".to_string())
+            .unwrap();
+
+        // Accounts without stake have no voting power
+        let result = governance.vote(&mut system, &mut staking, dave, proposal_id, true);
+        assert_eq!(result, Err(GovernanceError::NoVotingPower));
+
+        // Two small stakers are outweighed by one large staker
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, charlie, proposal_id, false)
+            .unwrap();
+
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.yes_votes, 30);
+        assert_eq!(proposal.no_votes, 500);
+
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, proposal_id)
+            .unwrap();
+        assert!(matches!(status, ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_quorum_and_approval_threshold() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+
+        // 1000 staked in total, so the 20% quorum is 200
+        let mut staking = staked(&[(alice, 100), (bob, 100), (charlie, 800)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let quiet = governance
+            .create_proposal(&mut system, alice, "Low turnout".to_string())
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, alice, quiet, true)
+            .unwrap();
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, quiet)
+            .unwrap();
+        assert_eq!(status, ProposalStatus::QuorumNotMet);
+
+        // Conviction boosts the weight, not the stake that turned out
+        let boosted = governance
+            .create_proposal(&mut system, alice, "Loud but low turnout".to_string())
+            .unwrap();
+        governance
+            .vote_with_conviction(
+                &mut system,
+                &mut staking,
+                alice,
+                boosted,
+                true,
+                Conviction::Locked6x,
+            )
+            .unwrap();
+        assert_eq!(governance.get_proposal(boosted).unwrap().tally(), (600, 0));
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, boosted)
+            .unwrap();
+        assert_eq!(status, ProposalStatus::QuorumNotMet);
+
+        // Exactly half Aye doesn't clear the simple-majority threshold
+        let split = governance
+            .create_proposal(&mut system, alice, "Even split".to_string())
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, alice, split, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, split, false)
+            .unwrap();
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, split)
+            .unwrap();
+        assert_eq!(status, ProposalStatus::Rejected);
+    }
+
+    #[test]
+    fn test_change_and_retract_vote() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut staking = staked(&[(alice, 100), (bob, 50), (charlie, 120)]);
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let tally = |governance: &GovernancePallet<Runtime>, id| {
+            let proposal = governance.get_proposal(id).unwrap();
+            (proposal.yes_votes, proposal.no_votes)
+        };
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Upgrade".to_string())
+            .unwrap();
+        let result = governance.retract_vote(&mut system, alice, proposal_id);
+        assert_eq!(result, Err(GovernanceError::NotVoted));
+
+        // alice votes Aye carrying bob's delegated stake
+        governance.delegate(&mut system, bob, alice).unwrap();
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, charlie, proposal_id, false)
+            .unwrap();
+        assert_eq!(tally(&governance, proposal_id), (150, 120));
+
+        // Changing moves the carried weight too
+        governance
+            .change_vote(&mut system, alice, proposal_id, false)
+            .unwrap();
+        assert_eq!(tally(&governance, proposal_id), (0, 270));
+
+        // Retracting frees bob to vote on his own
+        governance
+            .retract_vote(&mut system, alice, proposal_id)
+            .unwrap();
+        assert_eq!(tally(&governance, proposal_id), (0, 120));
+        assert_eq!(governance.vote_carried_by(bob, proposal_id), None);
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, true)
+            .unwrap();
+        assert_eq!(tally(&governance, proposal_id), (50, 120));
+
+        // Once finalized, the votes are settled
+        governance
+            .finalize_proposal(&mut system, &mut staking, proposal_id)
+            .unwrap();
+        let result = governance.change_vote(&mut system, bob, proposal_id, false);
+        assert_eq!(result, Err(GovernanceError::ProposalNotActive));
+    }
+
+    #[test]
+    fn test_retracted_conviction_vote_stays_locked() {
+        let alice = 1u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut staking = staked(&[(alice, 100)]);
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Upgrade".to_string())
+            .unwrap();
+        governance
+            .vote_with_conviction(
+                &mut system,
+                &mut staking,
+                alice,
+                proposal_id,
+                true,
+                Conviction::Locked3x,
+            )
+            .unwrap();
+        governance
+            .retract_vote(&mut system, alice, proposal_id)
+            .unwrap();
+
+        // The vote no longer counts, but its conviction lock still runs its course
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().tally(),
+            (0, 0)
+        );
+        let closes = governance.get_proposal(proposal_id).unwrap().end() + 1;
+        let until = closes + 2 * <Runtime as GovernanceConfig>::CONVICTION_LOCK_PERIOD;
+        assert_eq!(staking.get_locked_stake(alice, until - 1), 100);
+        assert_eq!(staking.get_locked_stake(alice, until), 0);
+    }
+
+    #[test]
+    fn test_verify_tally_recomputes_from_vote_records() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut staking = staked(&[(alice, 100), (bob, 50), (charlie, 120)]);
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Upgrade".to_string())
+            .unwrap();
+        governance.delegate(&mut system, bob, alice).unwrap();
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, charlie, proposal_id, false)
+            .unwrap();
+
+        let report = governance.verify_tally(proposal_id).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.recomputed, Some((150, 120)));
+        assert!(governance.check_tallies().is_empty());
+
+        // A counter that drifted from the records is reported
+        governance
+            .proposals
+            .get_mut(&proposal_id)
+            .unwrap()
+            .yes_votes = 100;
+        let report = governance.verify_tally(proposal_id).unwrap();
+        assert_eq!(
+            (report.stored, report.recomputed),
+            ((100, 120), Some((150, 120)))
+        );
+        assert_eq!(governance.check_tallies(), vec![report]);
+        assert_eq!(
+            governance.verify_tally(proposal_id + 1),
+            Err(GovernanceError::ProposalNotFound)
+        );
+    }
+
+    #[test]
+    fn test_voted_stake_cannot_be_moved_and_counted_again() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        balances.set_balance(alice, 1000).unwrap();
+        staking
+            .stake(&mut system, &mut balances, alice, 1000)
+            .unwrap();
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Upgrade".to_string())
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap();
+
+        // The stake behind the vote can't leave to back bob's vote too
+        let locked = Err(crate::staking::StakingError::StakeLocked);
+        assert_eq!(
+            staking.unstake(&mut system, &mut balances, alice, 1000),
+            locked
+        );
+        assert_eq!(staking.unbond(&mut system, alice, 1000), locked);
+        assert_eq!(
+            governance.vote(&mut system, &mut staking, bob, proposal_id, true),
+            Err(GovernanceError::NoVotingPower)
+        );
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().yes_votes,
+            1000
+        );
+
+        // Once voting closes the stake is free again
+        let end = governance.get_proposal(proposal_id).unwrap().end();
+        for number in 1..end {
+            system.initialize(number).unwrap();
+        }
+        assert_eq!(
+            staking.unstake(&mut system, &mut balances, alice, 1000),
+            locked
+        );
+        for number in end..=end + 1 {
+            system.initialize(number).unwrap();
+        }
+        staking
+            .unstake(&mut system, &mut balances, alice, 1000)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_conviction_multiplies_weight_and_locks_stake() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        for (who, amount) in [(alice, 100), (bob, 250)] {
+            balances.set_balance(who, amount).unwrap();
+            staking
+                .stake(&mut system, &mut balances, who, amount)
+                .unwrap();
+        }
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Upgrade".to_string())
+            .unwrap();
+        governance
+            .vote_with_conviction(
+                &mut system,
+                &mut staking,
+                alice,
+                proposal_id,
+                true,
+                Conviction::Locked3x,
+            )
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, false)
+            .unwrap();
+
+        // 3 x 100 outweighs 250
+        assert_eq!(
+            governance
+                .votes
+                .get(&(alice, proposal_id))
+                .map(|v| v.weight),
+            Some(300)
+        );
+        // Unstaking before finalization can't dodge the promised lock: it holds once
+        // voting has closed, when bob's stake is free again
+        let locked = Err(crate::staking::StakingError::StakeLocked);
+        let closed = governance.get_proposal(proposal_id).unwrap().end() + 1;
+        for number in 1..=closed {
+            system.initialize(number).unwrap();
+        }
+        assert_eq!(
+            staking.unstake(&mut system, &mut balances, alice, 1),
+            locked
+        );
+        staking
+            .unstake(&mut system, &mut balances, bob, 250)
+            .unwrap();
+
+        // Finalization extends alice's lock to two periods from then
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, proposal_id)
+            .unwrap();
+        assert_eq!(status, ProposalStatus::Approved);
+        for number in closed + 1..closed + 40 {
+            system.initialize(number).unwrap();
+        }
+        assert_eq!(
+            staking.unstake(&mut system, &mut balances, alice, 1),
+            locked
+        );
+        system.initialize(closed + 40).unwrap();
+        staking
+            .unstake(&mut system, &mut balances, alice, 100)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_auto_finalization_is_spread_across_blocks() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut staking = staked(&[(bob, 100)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let cap = <Runtime as GovernanceConfig>::MAX_FINALIZATIONS_PER_BLOCK;
+        let ids: Vec<u32> = (0..cap + 2)
+            .map(|_| {
+                governance
+                    .create_proposal(&mut system, alice, "Upgrade".to_string())
+                    .unwrap()
+            })
+            .collect();
+        let end = governance.get_proposal(ids[0]).unwrap().end();
+        let active = |governance: &GovernancePallet<Runtime>| {
+            ids.iter()
+                .filter(|id| {
+                    governance.get_proposal(**id).unwrap().status == ProposalStatus::Active
+                })
+                .count()
+        };
+
+        // Voting stays open through the end block
+        for number in 1..=end {
+            system.initialize(number).unwrap();
+        }
+        governance
+            .vote(&mut system, &mut staking, bob, ids[0], true)
+            .unwrap();
+        governance.on_finalize(&mut system, &mut staking, end);
+        assert_eq!(active(&governance), 2);
+
+        // The rest wait for the next block, but no longer take votes
+        system.initialize(end + 1).unwrap();
+        assert_eq!(
+            governance.vote(&mut system, &mut staking, bob, ids[cap as usize], true),
+            Err(GovernanceError::VotingClosed)
+        );
+        governance.on_finalize(&mut system, &mut staking, end + 1);
+        assert_eq!(active(&governance), 0);
+    }
+
+    #[test]
+    fn test_tie_policy() {
+        let tie = |policy: &TiePolicy<u32>| approval_outcome(50, 100, 500_000, policy).unwrap();
+        assert_eq!(tie(&TiePolicy::RejectOnTie), Some(ProposalStatus::Rejected));
+        assert_eq!(
+            tie(&TiePolicy::ApproveOnTie),
+            Some(ProposalStatus::Approved)
+        );
+        assert_eq!(tie(&TiePolicy::ExtendPeriod(5)), None);
+
+        // Only an exact tie consults the policy
+        let policy = TiePolicy::ExtendPeriod(5);
+        assert_eq!(
+            approval_outcome(49, 100, 500_000, &policy).unwrap(),
+            Some(ProposalStatus::Rejected)
+        );
+        assert_eq!(
+            approval_outcome(51, 100, 500_000, &policy).unwrap(),
+            Some(ProposalStatus::Approved)
+        );
+    }
+
+    #[test]
+    fn test_vote_cap_limits_whales() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+
+        let mut staking = staked(&[(alice, 100), (bob, 100), (charlie, 800)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Cap the whale".to_string())
+            .unwrap();
+
+        // Only the creator sets the cap, and the fraction must be in range
+        let cap = VoteCap::FractionOfElectorate {
+            parts_per_million: 150_000,
+        };
+        let result = governance.set_vote_cap(&mut system, &staking, bob, proposal_id, cap.clone());
+        assert_eq!(result, Err(GovernanceError::NotProposalCreator));
+        let result = governance.set_vote_cap(
+            &mut system,
+            &staking,
+            alice,
+            proposal_id,
+            VoteCap::FractionOfElectorate {
+                parts_per_million: 1_000_001,
+            },
+        );
+        assert_eq!(result, Err(GovernanceError::InvalidVoteCap));
+
+        // 15% of the 1000 staked caps every account at 150
+        governance
+            .set_vote_cap(&mut system, &staking, alice, proposal_id, cap)
+            .unwrap();
+        assert_eq!(
+            system.events().last(),
+            Some(
+                &Event::VoteCapSet {
+                    proposal_id,
+                    cap: 150,
+                }
+                .into()
+            )
+        );
+
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, charlie, proposal_id, false)
+            .unwrap();
+
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.yes_votes, 200);
+        assert_eq!(proposal.no_votes, 150);
+
+        // The cap can't change once voting has started
+        let result = governance.set_vote_cap(
+            &mut system,
+            &staking,
+            alice,
+            proposal_id,
+            VoteCap::Absolute(1000),
+        );
+        assert_eq!(result, Err(GovernanceError::VotesAlreadyCast));
+    }
+
+    #[test]
+    fn test_delegated_voting() {
+        let alice = 1u64;
         let bob = 2u64;
         let charlie = 3u64;
+        let dave = 4u64;
 
+        let mut staking = staked(&[(alice, 10), (bob, 20), (charlie, 40), (dave, 100)]);
+        let mut system = SystemPallet::<Runtime>::new();
         let mut governance = GovernancePallet::<Runtime>::new();
 
-        // Create a proposal
+        // alice -> bob -> charlie
+        governance.delegate(&mut system, alice, bob).unwrap();
+        governance.delegate(&mut system, bob, charlie).unwrap();
+
+        // Delegations can't loop or target oneself
+        let result = governance.delegate(&mut system, charlie, alice);
+        assert_eq!(result, Err(GovernanceError::DelegationCycle));
+        let result = governance.delegate(&mut system, dave, dave);
+        assert_eq!(result, Err(GovernanceError::SelfDelegation));
+        let result = governance.delegate(&mut system, alice, dave);
+        assert_eq!(result, Err(GovernanceError::AlreadyDelegating));
+
         let proposal_id = governance
-            .create_proposal(alice, "Increase validator rewards".to_string())
+            .create_proposal(&mut system, dave, "Adopt delegation".to_string())
             .unwrap();
 
-        let (description, creator) = governance.get_proposal_details(proposal_id).unwrap();
-        assert_eq!(description, "Increase validator rewards");
-        assert_eq!(creator, alice);
+        // charlie carries the weight of the whole chain
+        governance
+            .vote(&mut system, &mut staking, charlie, proposal_id, true)
+            .unwrap();
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.yes_votes, 70);
+        assert_eq!(
+            governance.vote_carried_by(alice, proposal_id),
+            Some(&charlie)
+        );
 
-        // Cast votes
-        governance.vote(alice, proposal_id, true).unwrap(); // Yes vote
-        governance.vote(bob, proposal_id, true).unwrap(); // Yes vote
-        governance.vote(charlie, proposal_id, false).unwrap(); // No vote
+        // bob overrides by voting directly, taking alice's weight along
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, false)
+            .unwrap();
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.yes_votes, 40);
+        assert_eq!(proposal.no_votes, 30);
+        assert_eq!(governance.vote_carried_by(alice, proposal_id), Some(&bob));
+        assert_eq!(governance.vote_carried_by(bob, proposal_id), None);
 
-        // Check proposal status before finalization
+        // alice overrides as well
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap();
         let proposal = governance.get_proposal(proposal_id).unwrap();
-        assert_eq!(proposal.yes_votes, 2);
-        assert_eq!(proposal.no_votes, 1);
+        assert_eq!(proposal.yes_votes, 50);
+        assert_eq!(proposal.no_votes, 20);
 
-        // Finalize proposal
-        let status = governance.finalize_proposal(proposal_id).unwrap();
-        assert!(matches!(status, ProposalStatus::Approved));
+        // After undelegating, dave votes with only his own stake
+        governance.delegate(&mut system, dave, charlie).unwrap();
+        governance.undelegate(&mut system, dave).unwrap();
+        let result = governance.undelegate(&mut system, dave);
+        assert_eq!(result, Err(GovernanceError::NotDelegating));
+        governance
+            .vote(&mut system, &mut staking, dave, proposal_id, false)
+            .unwrap();
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.yes_votes, 50);
+        assert_eq!(proposal.no_votes, 120);
+    }
 
-        // Check proposal is now approved
-        let finalized_proposal = governance.get_proposal(proposal_id).unwrap();
+    #[test]
+    fn test_dependent_proposals() {
+        let alice = 1u64;
+        let bob = 2u64;
+
+        let mut staking = staked(&[(bob, 100)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        // Dependencies must exist
+        let result =
+            governance.create_dependent_proposal(&mut system, alice, "Step 2".to_string(), 42);
+        assert_eq!(result, Err(GovernanceError::DependencyNotFound));
+
+        let first = governance
+            .create_proposal(&mut system, alice, "Step 1".to_string())
+            .unwrap();
+        let second = governance
+            .create_dependent_proposal(&mut system, alice, "Step 2".to_string(), first)
+            .unwrap();
+        let third = governance
+            .create_dependent_proposal(&mut system, alice, "Step 3".to_string(), second)
+            .unwrap();
+
+        governance
+            .vote(&mut system, &mut staking, bob, first, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, second, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, third, true)
+            .unwrap();
+
+        // Can't finalize before the dependency is finalized
+        let result = governance.finalize_proposal(&mut system, &mut staking, second);
         assert!(matches!(
-            finalized_proposal.status,
-            ProposalStatus::Approved
+            result,
+            Err(GovernanceError::DependencyNotFinalized)
         ));
+
+        // Once the dependency is approved the dependent proposal can pass
+        governance
+            .finalize_proposal(&mut system, &mut staking, first)
+            .unwrap();
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, second)
+            .unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+
+        // A rejected dependency rejects its dependents regardless of votes
+        let fourth = governance
+            .create_proposal(&mut system, alice, "Alternative step 1".to_string())
+            .unwrap();
+        let fifth = governance
+            .create_dependent_proposal(&mut system, alice, "Alternative step 2".to_string(), fourth)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, fifth, true)
+            .unwrap();
+        governance
+            .finalize_proposal(&mut system, &mut staking, fourth)
+            .unwrap();
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, fifth)
+            .unwrap();
+        assert!(matches!(status, ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_pagination_cursors_are_stable() {
+        let alice = 1u64;
+        let mut staking = staked(&[(3, 10), (5, 10), (7, 10)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        for i in 0..5 {
+            governance
+                .create_proposal(&mut system, alice, format!("Proposal {}", i))
+                .unwrap();
+        }
+
+        let page = governance.proposals_page(None, 2);
+        let ids: Vec<u32> = page.items.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(page.next_cursor, Some(1));
+
+        // Removing an already seen entry doesn't shift the next page
+        governance.proposals.remove(&0);
+        let page = governance.proposals_page(Some(1), 2);
+        let ids: Vec<u32> = page.items.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        let cursor = page.next_cursor;
+
+        // Insertions while paginating show up at the end, never twice
+        governance
+            .create_proposal(&mut system, alice, "Late proposal".to_string())
+            .unwrap();
+        let page = governance.proposals_page(cursor, 10);
+        let ids: Vec<u32> = page.items.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![4, 5]);
+        assert_eq!(page.next_cursor, None);
+
+        // Voters are paginated by account id
+        for voter in [7u64, 3, 5] {
+            governance
+                .vote(&mut system, &mut staking, voter, 2, voter != 5)
+                .unwrap();
+        }
+        let page = governance.voters_page(2, None, 2);
+        assert_eq!(page.items, vec![(3, true), (5, false)]);
+        let page = governance.voters_page(2, page.next_cursor, 2);
+        assert_eq!(page.items, vec![(7, true)]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_proposal_and_vote_queries() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut staking = staked(&[(alice, 10), (bob, 10)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        for (creator, description) in [(alice, "A"), (bob, "B"), (alice, "C"), (bob, "D")] {
+            governance
+                .create_proposal(&mut system, creator, description.to_string())
+                .unwrap();
+        }
+        for proposal_id in [3, 1] {
+            governance
+                .vote(&mut system, &mut staking, alice, proposal_id, true)
+                .unwrap();
+        }
+        governance
+            .finalize_proposal(&mut system, &mut staking, 1)
+            .unwrap();
+
+        let ids = |page: Page<u32, &Proposal<Runtime>>| -> Vec<u32> {
+            page.items.iter().map(|(id, _)| *id).collect()
+        };
+        assert_eq!(
+            ids(governance.proposals_by_creator(&bob, None, 10)),
+            vec![1, 3]
+        );
+        assert_eq!(ids(governance.active_proposals(None, 10)), vec![0, 2, 3]);
+        let page = governance.active_proposals(Some(0), 1);
+        assert_eq!(ids(page), vec![2]);
+        let approved = governance.proposals_by_status(&ProposalStatus::Approved, None, 10);
+        assert_eq!(approved.items[0].1.description(), "B");
+        assert_eq!(approved.items[0].1.tally(), (10, 0));
+
+        // Votes come back in proposal order, whatever order they were cast in
+        let page = governance.votes_of(&alice, None, 1);
+        assert_eq!(page.items[0].0, 1);
+        let page = governance.votes_of(&alice, page.next_cursor, 10);
+        assert_eq!(page.items[0].0, 3);
+        assert!(page.items[0].1.vote_type);
+        assert_eq!(page.next_cursor, None);
+        assert!(governance.votes_of(&bob, None, 10).items.is_empty());
+    }
+
+    #[test]
+    fn test_proposal_templates() {
+        let alice = 1u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let template = ProposalTemplate::SetParameter {
+            key: "min_bond".to_string(),
+            value: 100,
+        };
+        let proposal_id = governance
+            .create_proposal_from_template(&mut system, alice, template.clone())
+            .unwrap();
+        let (description, ..) = governance
+            .get_proposal_details(proposal_id, &NoLabels)
+            .unwrap();
+        assert_eq!(description, "Set parameter `min_bond` to 100");
+        assert_eq!(
+            governance.get_proposal_template(proposal_id),
+            Some(&template)
+        );
+
+        let proposal_id = governance
+            .create_proposal_from_template(
+                &mut system,
+                alice,
+                ProposalTemplate::TreasurySpend {
+                    to: 7,
+                    amount: 1250,
+                },
+            )
+            .unwrap();
+        let (description, ..) = governance
+            .get_proposal_details(proposal_id, &NoLabels)
+            .unwrap();
+        assert_eq!(
+            description,
+            "Spend 12.5 UNIT from the treasury to account 7"
+        );
+
+        // Malformed arguments are rejected before a proposal is created
+        let result = governance.create_proposal_from_template(
+            &mut system,
+            alice,
+            ProposalTemplate::SetParameter {
+                key: "Min Bond".to_string(),
+                value: 100,
+            },
+        );
+        assert_eq!(result, Err(GovernanceError::InvalidParameterKey));
+        let result = governance.create_proposal_from_template(
+            &mut system,
+            alice,
+            ProposalTemplate::TreasurySpend { to: 7, amount: 0 },
+        );
+        assert_eq!(result, Err(GovernanceError::ZeroSpendAmount));
+        assert_eq!(governance.proposals.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_proposal_without_creating() {
+        let (alice, bob) = (1u64, 2u64);
+        let system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        governance.barred.insert(bob, 1);
+
+        let spend = Call::CreateProposalFromTemplate {
+            template: ProposalTemplate::TreasurySpend { to: 7, amount: 0 },
+        };
+        let dependent = Call::CreateDependentProposal {
+            description: "Follow-up".to_string(),
+            depends_on: 0,
+        };
+        let plain = Call::CreateProposal {
+            description: "Plain".to_string(),
+        };
+        let cases = [
+            (alice, &spend, Err(GovernanceError::ZeroSpendAmount)),
+            (alice, &dependent, Err(GovernanceError::DependencyNotFound)),
+            (bob, &plain, Err(GovernanceError::ParticipationBarred)),
+            (alice, &plain, Ok(())),
+            (alice, &Call::Undelegate, Err(GovernanceError::NotAProposal)),
+        ];
+        for (creator, call, expected) in cases {
+            assert_eq!(
+                governance.validate_proposal(&system, &creator, call),
+                expected
+            );
+        }
+        assert!(governance.proposals.is_empty());
+    }
+
+    #[test]
+    fn test_localized_descriptions() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        balances.set_balance(alice, 100).unwrap();
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Increase validator rewards".to_string())
+            .unwrap();
+
+        // Only the creator may localize
+        let result = governance.set_localized_description(
+            &mut system,
+            &mut balances,
+            bob,
+            proposal_id,
+            "id",
+            "Naikkan hadiah validator".to_string(),
+        );
+        assert_eq!(result, Err(GovernanceError::NotProposalCreator));
+
+        governance
+            .set_localized_description(
+                &mut system,
+                &mut balances,
+                alice,
+                proposal_id,
+                "id",
+                "Naikkan hadiah validator".to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            balances.get_held_balance(HoldReason::ProposalDeposit, alice),
+            <Runtime as GovernanceConfig>::LOCALIZATION_DEPOSIT
+        );
+
+        // Lookups are case-insensitive and fall back to the default description
+        assert_eq!(
+            governance
+                .get_proposal_description(proposal_id, "ID")
+                .unwrap(),
+            "Naikkan hadiah validator"
+        );
+        assert_eq!(
+            governance
+                .get_proposal_description(proposal_id, "vi")
+                .unwrap(),
+            "Increase validator rewards"
+        );
+
+        // Oversized descriptions and unaffordable deposits are rejected
+        let result = governance.set_localized_description(
+            &mut system,
+            &mut balances,
+            alice,
+            proposal_id,
+            "vi",
+            "x".repeat(<Runtime as GovernanceConfig>::MAX_LOCALIZED_DESCRIPTION_LEN + 1),
+        );
+        assert_eq!(result, Err(GovernanceError::DescriptionTooLong));
+        balances.set_balance(alice, 0).unwrap();
+        let result = governance.set_localized_description(
+            &mut system,
+            &mut balances,
+            alice,
+            proposal_id,
+            "vi",
+            "Tang phan thuong".to_string(),
+        );
+        assert_eq!(
+            result,
+            Err(GovernanceError::Balances(
+                BalancesError::InsufficientBalance
+            ))
+        );
+
+        // Removing the entry releases its deposit
+        governance
+            .remove_localized_description(&mut system, &mut balances, alice, proposal_id, "id")
+            .unwrap();
+        assert_eq!(
+            balances.get_free_balance(alice),
+            <Runtime as GovernanceConfig>::LOCALIZATION_DEPOSIT
+        );
+        assert_eq!(
+            governance
+                .get_proposal_description(proposal_id, "id")
+                .unwrap(),
+            "Increase validator rewards"
+        );
+    }
+
+    #[test]
+    fn test_amend_and_cancel_proposals() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut staking = staked(&[(bob, 100)]);
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        balances.set_balance(alice, 100).unwrap();
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Teh typo".to_string())
+            .unwrap();
+
+        // Only the creator amends, and only before any vote
+        let result = governance.amend_proposal(&mut system, bob, proposal_id, "Spam".to_string());
+        assert_eq!(result, Err(GovernanceError::NotProposalCreator));
+        governance
+            .amend_proposal(&mut system, alice, proposal_id, "The fix".to_string())
+            .unwrap();
+        assert_eq!(
+            governance
+                .get_proposal_details(proposal_id, &NoLabels)
+                .unwrap()
+                .0,
+            "The fix"
+        );
+
+        governance
+            .set_localized_description(
+                &mut system,
+                &mut balances,
+                alice,
+                proposal_id,
+                "id",
+                "Perbaikan".to_string(),
+            )
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, true)
+            .unwrap();
+        let result =
+            governance.amend_proposal(&mut system, alice, proposal_id, "Switcheroo".to_string());
+        assert_eq!(result, Err(GovernanceError::VotesAlreadyCast));
+
+        // Cancelling still works after votes and hands back the localization deposit
+        governance
+            .cancel_proposal(&mut system, &mut balances, alice, proposal_id)
+            .unwrap();
+        assert_eq!(balances.get_free_balance(alice), 100u64);
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().status,
+            ProposalStatus::Cancelled
+        );
+
+        let result = governance.finalize_proposal(&mut system, &mut staking, proposal_id);
+        assert_eq!(result, Err(GovernanceError::ProposalNotActive));
+        let result = governance.cancel_proposal(&mut system, &mut balances, alice, proposal_id);
+        assert_eq!(result, Err(GovernanceError::ProposalNotActive));
+    }
+
+    #[test]
+    fn test_localized_descriptions_are_frozen_once_voted() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut staking = staked(&[(bob, 100)]);
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        balances.set_balance(alice, 100).unwrap();
+        let deposit = <Runtime as GovernanceConfig>::LOCALIZATION_DEPOSIT;
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Raise the era reward".to_string())
+            .unwrap();
+        governance
+            .set_localized_description(
+                &mut system,
+                &mut balances,
+                alice,
+                proposal_id,
+                "id",
+                "Naikkan hadiah era".to_string(),
+            )
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, true)
+            .unwrap();
+
+        // Voters read the translations too, so they can't change after a vote
+        let result = governance.set_localized_description(
+            &mut system,
+            &mut balances,
+            alice,
+            proposal_id,
+            "id",
+            "Turunkan hadiah era".to_string(),
+        );
+        assert_eq!(result, Err(GovernanceError::VotesAlreadyCast));
+        let result = governance.remove_localized_description(
+            &mut system,
+            &mut balances,
+            alice,
+            proposal_id,
+            "id",
+        );
+        assert_eq!(result, Err(GovernanceError::VotesAlreadyCast));
+        let result = governance.release_localization_deposits(
+            &mut system,
+            &mut balances,
+            alice,
+            proposal_id,
+        );
+        assert_eq!(result, Err(GovernanceError::ProposalStillActive));
+
+        // Nor once the proposal is closed, when the deposit is released in one go
+        governance
+            .finalize_proposal(&mut system, &mut staking, proposal_id)
+            .unwrap();
+        let result = governance.set_localized_description(
+            &mut system,
+            &mut balances,
+            alice,
+            proposal_id,
+            "vi",
+            "Tang phan thuong".to_string(),
+        );
+        assert_eq!(result, Err(GovernanceError::ProposalNotActive));
+        assert_eq!(
+            balances.get_held_balance(HoldReason::ProposalDeposit, alice),
+            deposit
+        );
+        let result =
+            governance.release_localization_deposits(&mut system, &mut balances, bob, proposal_id);
+        assert_eq!(result, Err(GovernanceError::NotProposalCreator));
+        governance
+            .release_localization_deposits(&mut system, &mut balances, alice, proposal_id)
+            .unwrap();
+        assert_eq!(balances.get_free_balance(alice), 100u64);
+        let result = governance.release_localization_deposits(
+            &mut system,
+            &mut balances,
+            alice,
+            proposal_id,
+        );
+        assert_eq!(result, Err(GovernanceError::LocalizationNotFound));
+    }
+
+    #[test]
+    fn test_tags_and_search() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        for description in [
+            "Fund the block explorer",
+            "Raise the era reward",
+            "Fund a wallet audit",
+        ] {
+            governance
+                .create_proposal(&mut system, alice, description.to_string())
+                .unwrap();
+        }
+
+        governance
+            .set_tags(
+                &mut system,
+                alice,
+                0,
+                vec!["Tooling".into(), "treasury".into()],
+            )
+            .unwrap();
+        governance
+            .set_tags(
+                &mut system,
+                alice,
+                2,
+                vec!["treasury".into(), "treasury".into()],
+            )
+            .unwrap();
+        assert_eq!(
+            governance.get_proposal_tags(0).unwrap(),
+            &["tooling", "treasury"]
+        );
+        let result = governance.set_tags(&mut system, bob, 1, vec!["spam".into()]);
+        assert_eq!(result, Err(GovernanceError::NotProposalCreator));
+        let result = governance.set_tags(&mut system, alice, 1, vec!["two words".into()]);
+        assert_eq!(result, Err(GovernanceError::InvalidTag));
+        let too_many = (0..6).map(|tag| tag.to_string()).collect();
+        let result = governance.set_tags(&mut system, alice, 1, too_many);
+        assert_eq!(result, Err(GovernanceError::TooManyTags));
+
+        // Every query word has to match a tag or a description word
+        assert_eq!(governance.search_proposals("treasury"), vec![0, 2]);
+        assert_eq!(governance.search_proposals("FUND treasury"), vec![0, 2]);
+        assert_eq!(governance.search_proposals("fund tooling"), vec![0]);
+        assert_eq!(
+            governance.search_proposals("fund reward"),
+            Vec::<u32>::new()
+        );
+        assert_eq!(governance.search_proposals(""), Vec::<u32>::new());
+
+        // Amended text and replaced tags are reindexed
+        governance
+            .amend_proposal(&mut system, alice, 2, "Fund a node audit".to_string())
+            .unwrap();
+        governance
+            .set_tags(&mut system, alice, 0, Vec::new())
+            .unwrap();
+        assert_eq!(governance.search_proposals("wallet"), Vec::<u32>::new());
+        assert_eq!(governance.search_proposals("node"), vec![2]);
+        assert_eq!(governance.search_proposals("treasury"), vec![2]);
+
+        // The indexes are rebuilt when decoding
+        let decoded = GovernancePallet::<Runtime>::decode(&mut &governance.encode()[..]).unwrap();
+        assert_eq!(decoded, governance);
+    }
+
+    #[test]
+    fn test_participation_decays_many_proposals_at_once() {
+        let fresh = Participation {
+            voted: 1_000_000,
+            eligible: 1_000_000,
+            as_of: 0,
+        };
+        let step = |participation: Participation, count| participation.decayed(count, 900_000);
+
+        assert_eq!(
+            step(fresh, 1),
+            Participation {
+                voted: 900_000,
+                eligible: 1_900_000,
+                as_of: 1,
+            }
+        );
+        assert_eq!(step(step(fresh, 1), 2), step(fresh, 2));
+        assert_eq!(step(fresh, 0), fresh);
+
+        // Squaring keeps this to a few dozen steps; the sums settle at their limits
+        let settled = step(fresh, u32::MAX);
+        assert_eq!(settled.voted, 0);
+        assert!((9_999_000..=10_000_000).contains(&settled.eligible));
+        assert_eq!(settled.as_of, u32::MAX);
+    }
+
+    #[test]
+    fn test_participation_score_favours_recent_votes() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut staking = staked(&[(alice, 100), (bob, 100)]);
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        for proposal_id in 0..3 {
+            governance
+                .create_proposal(&mut system, charlie, "Proposal".to_string())
+                .unwrap();
+            governance
+                .vote(&mut system, &mut staking, alice, proposal_id, true)
+                .unwrap();
+            if proposal_id == 0 {
+                governance
+                    .vote(&mut system, &mut staking, bob, proposal_id, true)
+                    .unwrap();
+            }
+            assert_eq!(governance.participation_score(&charlie), None);
+            governance
+                .finalize_proposal(&mut system, &mut staking, proposal_id)
+                .unwrap();
+        }
+
+        // bob's one vote decays by 0.9 per later proposal: 0.81 / (0.81 + 0.9 + 1)
+        assert_eq!(governance.participation_score(&alice), Some(1_000_000));
+        assert_eq!(governance.participation_score(&bob), Some(298_892));
+        let decoded = GovernancePallet::<Runtime>::decode(&mut &governance.encode()[..]).unwrap();
+        assert_eq!(decoded.participation_score(&bob), Some(298_892));
+    }
+
+    #[test]
+    fn test_export_voting_snapshot() {
+        let (alice, bob, charlie, dave) = (1u64, 2u64, 3u64, 4u64);
+        let mut staking = staked(&[(alice, 100), (bob, 40), (charlie, 120), (dave, 500)]);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Upgrade".to_string())
+            .unwrap();
+        assert_eq!(
+            governance.export_voting_snapshot(proposal_id + 1),
+            Err(GovernanceError::ProposalNotFound)
+        );
+
+        // alice votes with conviction, carrying bob's stake; dave doesn't vote
+        governance.delegate(&mut system, bob, alice).unwrap();
+        governance
+            .vote_with_conviction(
+                &mut system,
+                &mut staking,
+                alice,
+                proposal_id,
+                true,
+                Conviction::Locked3x,
+            )
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, charlie, proposal_id, false)
+            .unwrap();
+
+        // The weights add up to the tally
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().tally(),
+            (340, 120)
+        );
+        assert_eq!(
+            governance.export_voting_snapshot(proposal_id).unwrap(),
+            "{\"symbol\": \"UNIT\", \"decimals\": 2, \"proposal\": 0, \"end\": 10, \"accounts\": [\
+             {\"account\": \"1\", \"vote\": \"aye\", \"conviction\": 3, \"stake\": \"100\", \"voting_power\": \"300\", \"delegate\": null}, \
+             {\"account\": \"2\", \"vote\": \"aye\", \"conviction\": 1, \"stake\": \"40\", \"voting_power\": \"40\", \"delegate\": \"1\"}, \
+             {\"account\": \"3\", \"vote\": \"nay\", \"conviction\": 1, \"stake\": \"120\", \"voting_power\": \"120\", \"delegate\": null}]}"
+        );
+        assert_eq!(json_escape("a\"b\\c\n"), "a\\\"b\\\\c\\u000a");
+    }
+
+    // A runtime whose proposal hooks leave a trace among the events, so tests can see
+    // when each hook runs and with what, and whose ties extend the voting period
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestRuntime;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TestEvent {
+        System(crate::system::Event<TestRuntime>),
+        Balances(crate::balances::Event<TestRuntime>),
+        Staking(crate::staking::Event<TestRuntime>),
+        Governance(Event<TestRuntime>),
+        Hook(HookCall),
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum HookCall {
+        Created(u32, u64),
+        Voted(u32, u64, bool, u64),
+        Finalized(u32, ProposalStatus),
+    }
+
+    impl From<crate::system::Event<TestRuntime>> for TestEvent {
+        fn from(event: crate::system::Event<TestRuntime>) -> Self {
+            TestEvent::System(event)
+        }
+    }
+
+    impl From<crate::balances::Event<TestRuntime>> for TestEvent {
+        fn from(event: crate::balances::Event<TestRuntime>) -> Self {
+            TestEvent::Balances(event)
+        }
+    }
+
+    impl From<crate::staking::Event<TestRuntime>> for TestEvent {
+        fn from(event: crate::staking::Event<TestRuntime>) -> Self {
+            TestEvent::Staking(event)
+        }
+    }
+
+    impl From<Event<TestRuntime>> for TestEvent {
+        fn from(event: Event<TestRuntime>) -> Self {
+            TestEvent::Governance(event)
+        }
+    }
+
+    struct RecordingHooks;
+
+    impl ProposalHooks<TestRuntime> for RecordingHooks {
+        fn on_created(system: &mut SystemPallet<TestRuntime>, proposal_id: u32, creator: &u64) {
+            system.deposit_event(TestEvent::Hook(HookCall::Created(proposal_id, *creator)));
+        }
+
+        fn on_vote(
+            system: &mut SystemPallet<TestRuntime>,
+            proposal_id: u32,
+            voter: &u64,
+            vote_type: bool,
+            weight: u64,
+        ) {
+            let call = HookCall::Voted(proposal_id, *voter, vote_type, weight);
+            system.deposit_event(TestEvent::Hook(call));
+        }
+
+        fn on_finalized(
+            system: &mut SystemPallet<TestRuntime>,
+            proposal_id: u32,
+            status: &ProposalStatus,
+        ) {
+            let call = HookCall::Finalized(proposal_id, status.clone());
+            system.deposit_event(TestEvent::Hook(call));
+        }
+    }
+
+    impl SystemConfig for TestRuntime {
+        type AccountId = u64;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = TestEvent;
+        type RuntimeCall = crate::RuntimeCall;
+        const TOKEN_DECIMALS: u8 = <Runtime as SystemConfig>::TOKEN_DECIMALS;
+        const TOKEN_SYMBOL: &'static str = <Runtime as SystemConfig>::TOKEN_SYMBOL;
+    }
+
+    impl BalancesConfig for TestRuntime {
+        type Balance = u64;
+        const EXISTENTIAL_DEPOSIT: u64 = <Runtime as BalancesConfig>::EXISTENTIAL_DEPOSIT;
+    }
+
+    // Everything but the hooks and the tie policy is the runtime's configuration
+    impl StakingConfig for TestRuntime {
+        const ERA_LENGTH: u32 = <Runtime as StakingConfig>::ERA_LENGTH;
+        const ERA_REWARD: u64 = <Runtime as StakingConfig>::ERA_REWARD;
+        const MAX_VALIDATORS: u32 = <Runtime as StakingConfig>::MAX_VALIDATORS;
+        const SIGNED_PHASE: u32 = <Runtime as StakingConfig>::SIGNED_PHASE;
+        const ROTATION_GRACE_PERIOD: u32 = <Runtime as StakingConfig>::ROTATION_GRACE_PERIOD;
+        const SOLUTION_DEPOSIT: u64 = <Runtime as StakingConfig>::SOLUTION_DEPOSIT;
+        const BONDING_DURATION: u32 = <Runtime as StakingConfig>::BONDING_DURATION;
+        const FINDER_FEE: u32 = <Runtime as StakingConfig>::FINDER_FEE;
+        const VALIDATOR_COMMISSION: u32 = <Runtime as StakingConfig>::VALIDATOR_COMMISSION;
+        const MAX_NOMINATIONS: u32 = <Runtime as StakingConfig>::MAX_NOMINATIONS;
+        const MIN_VALIDATOR_BOND: u64 = <Runtime as StakingConfig>::MIN_VALIDATOR_BOND;
+        const MAX_EXITS_PER_ERA: u32 = <Runtime as StakingConfig>::MAX_EXITS_PER_ERA;
+        const MAX_UNLOCKS_PER_BLOCK: u32 = <Runtime as StakingConfig>::MAX_UNLOCKS_PER_BLOCK;
+        const VALIDATOR_METADATA_DEPOSIT: u64 =
+            <Runtime as StakingConfig>::VALIDATOR_METADATA_DEPOSIT;
+        const MAX_PROVIDER_TAGS: u32 = <Runtime as StakingConfig>::MAX_PROVIDER_TAGS;
+        const MAX_METADATA_TAG_LEN: usize = <Runtime as StakingConfig>::MAX_METADATA_TAG_LEN;
+        const DIVERSITY_TIE_BREAK: bool = <Runtime as StakingConfig>::DIVERSITY_TIE_BREAK;
+        const SLASH_ACCOUNT: u64 = <Runtime as StakingConfig>::SLASH_ACCOUNT;
+    }
+
+    impl GovernanceConfig for TestRuntime {
+        const MAX_LOCALIZATIONS: usize = <Runtime as GovernanceConfig>::MAX_LOCALIZATIONS;
+        const MAX_LOCALIZED_DESCRIPTION_LEN: usize =
+            <Runtime as GovernanceConfig>::MAX_LOCALIZED_DESCRIPTION_LEN;
+        const LOCALIZATION_DEPOSIT: u64 = <Runtime as GovernanceConfig>::LOCALIZATION_DEPOSIT;
+        const VOTING_PERIOD: u32 = <Runtime as GovernanceConfig>::VOTING_PERIOD;
+        const QUORUM: u32 = <Runtime as GovernanceConfig>::QUORUM;
+        const APPROVAL_THRESHOLD: u32 = <Runtime as GovernanceConfig>::APPROVAL_THRESHOLD;
+        const TIE_POLICY: TiePolicy<u32> = TiePolicy::ExtendPeriod(5);
+        const CONVICTION_LOCK_PERIOD: u32 = <Runtime as GovernanceConfig>::CONVICTION_LOCK_PERIOD;
+        const OFFENCE_BAR_SEVERITY: u32 = <Runtime as GovernanceConfig>::OFFENCE_BAR_SEVERITY;
+        const OFFENCE_BAR_ERAS: u32 = <Runtime as GovernanceConfig>::OFFENCE_BAR_ERAS;
+        const MAX_TAGS: usize = <Runtime as GovernanceConfig>::MAX_TAGS;
+        const MAX_TAG_LEN: usize = <Runtime as GovernanceConfig>::MAX_TAG_LEN;
+        const ENACTMENT_DELAY: u32 = <Runtime as GovernanceConfig>::ENACTMENT_DELAY;
+        const MAX_PROPOSAL_CALLS: usize = <Runtime as GovernanceConfig>::MAX_PROPOSAL_CALLS;
+        const MAX_FINALIZATIONS_PER_BLOCK: u32 =
+            <Runtime as GovernanceConfig>::MAX_FINALIZATIONS_PER_BLOCK;
+        const PARTICIPATION_DECAY: u32 = <Runtime as GovernanceConfig>::PARTICIPATION_DECAY;
+        type ProposalHooks = RecordingHooks;
+    }
+
+    #[test]
+    fn test_proposal_hooks_run_with_each_outcome() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut system = SystemPallet::<TestRuntime>::new();
+        let mut balances = BalancesPallet::<TestRuntime>::new();
+        let mut staking = StakingPallet::<TestRuntime>::new();
+        let mut governance = GovernancePallet::<TestRuntime>::new();
+        balances.set_balance(bob, 100).unwrap();
+        staking.stake(&mut system, &mut balances, bob, 100).unwrap();
+        system.take_events();
+
+        // Approved after bob's vote
+        let approved = governance
+            .create_proposal(&mut system, alice, "Approve me".to_string())
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, approved, true)
+            .unwrap();
+        governance
+            .finalize_proposal(&mut system, &mut staking, approved)
+            .unwrap();
+
+        // Cancelled by its creator, and vetoed by the council
+        let cancelled = governance
+            .create_proposal(&mut system, alice, "Cancel me".to_string())
+            .unwrap();
+        governance
+            .cancel_proposal(&mut system, &mut balances, alice, cancelled)
+            .unwrap();
+        // Each hook runs after the pallet's own event
+        assert_eq!(
+            system.events()[system.events().len() - 2..],
+            [
+                TestEvent::Governance(Event::ProposalCancelled {
+                    proposal_id: cancelled
+                }),
+                TestEvent::Hook(HookCall::Finalized(cancelled, ProposalStatus::Cancelled)),
+            ]
+        );
+        let vetoed = governance
+            .create_proposal(&mut system, alice, "Veto me".to_string())
+            .unwrap();
+        governance.veto(&mut system, vetoed).unwrap();
+
+        let hooks: Vec<_> = system
+            .events()
+            .iter()
+            .filter_map(|event| match event {
+                TestEvent::Hook(call) => Some(call.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            hooks,
+            vec![
+                HookCall::Created(approved, alice),
+                HookCall::Voted(approved, bob, true, 100),
+                HookCall::Finalized(approved, ProposalStatus::Approved),
+                HookCall::Created(cancelled, alice),
+                HookCall::Finalized(cancelled, ProposalStatus::Cancelled),
+                HookCall::Created(vetoed, alice),
+                HookCall::Finalized(vetoed, ProposalStatus::Rejected),
+            ]
+        );
+
+        // Failed calls run no hook
+        let result = governance.veto(&mut system, vetoed);
+        assert_eq!(result, Err(GovernanceError::ProposalNotActive));
+        assert_eq!(
+            system.events().last(),
+            Some(&TestEvent::Hook(HookCall::Finalized(
+                vetoed,
+                ProposalStatus::Rejected
+            )))
+        );
+    }
+
+    #[test]
+    fn test_tied_votes_stay_locked_through_the_extension() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut system = SystemPallet::<TestRuntime>::new();
+        let mut balances = BalancesPallet::<TestRuntime>::new();
+        let mut staking = StakingPallet::<TestRuntime>::new();
+        let mut governance = GovernancePallet::<TestRuntime>::new();
+        for who in [alice, bob] {
+            balances.set_balance(who, 50).unwrap();
+            staking.stake(&mut system, &mut balances, who, 50).unwrap();
+        }
+
+        let proposal_id = governance
+            .create_proposal(&mut system, alice, "Split".to_string())
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, alice, proposal_id, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, proposal_id, false)
+            .unwrap();
+
+        let end = <TestRuntime as GovernanceConfig>::VOTING_PERIOD;
+        for number in 1..=end {
+            system.initialize(number).unwrap();
+        }
+        let status = governance
+            .finalize_proposal(&mut system, &mut staking, proposal_id)
+            .unwrap();
+        assert_eq!(status, ProposalStatus::Active);
+        assert_eq!(governance.get_proposal(proposal_id).unwrap().end, end + 5);
+
+        // Past the original end, the stake counted in the tie can't leave to vote again
+        for number in end + 1..=end + 2 {
+            system.initialize(number).unwrap();
+        }
+        let result = staking.unstake(&mut system, &mut balances, alice, 50);
+        assert_eq!(result, Err(crate::staking::StakingError::StakeLocked));
+        assert_eq!(staking.get_locked_stake(bob, system.block_number()), 50u64);
+    }
+
+    #[test]
+    fn test_blocked_dependents_dont_hold_up_other_finalizations() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut system = SystemPallet::<TestRuntime>::new();
+        let mut balances = BalancesPallet::<TestRuntime>::new();
+        let mut staking = StakingPallet::<TestRuntime>::new();
+        let mut governance = GovernancePallet::<TestRuntime>::new();
+        for who in [alice, bob] {
+            balances.set_balance(who, 50).unwrap();
+            staking.stake(&mut system, &mut balances, who, 50).unwrap();
+        }
+
+        // The dependency ties and stays open; more dependents wait on it than the cap
+        let dependency = governance
+            .create_proposal(&mut system, alice, "Dependency".to_string())
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, alice, dependency, true)
+            .unwrap();
+        governance
+            .vote(&mut system, &mut staking, bob, dependency, false)
+            .unwrap();
+        let cap = <TestRuntime as GovernanceConfig>::MAX_FINALIZATIONS_PER_BLOCK;
+        for _ in 0..=cap {
+            governance
+                .create_dependent_proposal(&mut system, alice, "Blocked".to_string(), dependency)
+                .unwrap();
+        }
+        let independent = governance
+            .create_proposal(&mut system, alice, "Independent".to_string())
+            .unwrap();
+
+        let end = <TestRuntime as GovernanceConfig>::VOTING_PERIOD;
+        for number in 1..=end {
+            system.initialize(number).unwrap();
+        }
+        governance.on_finalize(&mut system, &mut staking, end);
+        assert_eq!(
+            governance.get_proposal_status(dependency),
+            Some(&ProposalStatus::Active)
+        );
+        assert_eq!(
+            governance.get_proposal_status(1),
+            Some(&ProposalStatus::Active)
+        );
+        assert_eq!(
+            governance.get_proposal_status(independent),
+            Some(&ProposalStatus::QuorumNotMet)
+        );
     }
 }
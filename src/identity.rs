@@ -0,0 +1,436 @@
+use crate::balances::{BalancesConfig, BalancesError, BalancesPallet, HoldReason};
+use crate::codec::{CodecError, Decode, Encode};
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::StorageMap;
+use crate::support::{pallet_error, weight_of, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+
+// On-chain identities, similar to `pallet_identity`: an account registers a display
+// name and free-form metadata, holding IDENTITY_DEPOSIT while it's set. Root or the
+// council can mark an identity as verified; changing it clears the mark again.
+pub trait IdentityConfig: BalancesConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Held from the account for as long as it has an identity
+    const IDENTITY_DEPOSIT: Self::Balance;
+    const MAX_DISPLAY_NAME_LEN: usize;
+    const MAX_METADATA_LEN: usize;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdentityError {
+    NoIdentity,
+    EmptyDisplayName,
+    DisplayNameTooLong,
+    MetadataTooLong,
+    // Holding or releasing the deposit failed
+    Balances(BalancesError),
+}
+
+pallet_error!(IdentityError {
+    0 => NoIdentity: "The account has no identity",
+    1 => EmptyDisplayName: "The display name must not be empty",
+    2 => DisplayNameTooLong: "The display name exceeds MAX_DISPLAY_NAME_LEN",
+    3 => MetadataTooLong: "The metadata exceeds MAX_METADATA_LEN",
+    4 => Balances(_): "Holding or releasing the deposit failed",
+});
+
+impl From<BalancesError> for IdentityError {
+    fn from(error: BalancesError) -> Self {
+        IdentityError::Balances(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: IdentityConfig> {
+    IdentitySet {
+        who: T::AccountId,
+        display_name: String,
+    },
+    IdentityCleared {
+        who: T::AccountId,
+    },
+    IdentityVerified {
+        who: T::AccountId,
+    },
+}
+
+impl<T: IdentityConfig> Event<T> {
+    // Render the event as a sentence, e.g. "account 1 is now known as alice"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::IdentitySet { who, display_name } => format!(
+                "{} is now known as {}",
+                display_account(labels, who),
+                display_name
+            ),
+            Event::IdentityCleared { who } => {
+                format!("{} cleared its identity", display_account(labels, who))
+            }
+            Event::IdentityVerified { who } => {
+                format!(
+                    "the identity of {} was verified",
+                    display_account(labels, who)
+                )
+            }
+        }
+    }
+}
+
+// Calls an account can submit to the identity pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: IdentityConfig> {
+    SetIdentity {
+        display_name: String,
+        metadata: String,
+    },
+    ClearIdentity,
+    // Mark an account's identity as verified; needs a Root or Council origin
+    VerifyIdentity {
+        who: T::AccountId,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identity<T: IdentityConfig> {
+    pub display_name: String,
+    pub metadata: String,
+    // Held under HoldReason::IdentityDeposit
+    pub deposit: T::Balance,
+    pub verified: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdentityPallet<T: IdentityConfig> {
+    identities: StorageMap<T::AccountId, Identity<T>>,
+}
+
+impl<T: IdentityConfig> Default for IdentityPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: IdentityConfig> IdentityPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            identities: StorageMap::new(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method. The runtime only
+    // routes VerifyIdentity for a Root or Council origin.
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), IdentityError> {
+        match call {
+            Call::SetIdentity {
+                display_name,
+                metadata,
+            } => self.set_identity(system, balances, origin, display_name, metadata)?,
+            Call::ClearIdentity => self.clear_identity(system, balances, origin)?,
+            Call::VerifyIdentity { who } => self.verify_identity(system, who)?,
+        }
+
+        Ok(())
+    }
+
+    // Set or replace the caller's identity. The deposit is held the first time only;
+    // a replaced identity has to be verified again.
+    pub fn set_identity(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        display_name: String,
+        metadata: String,
+    ) -> Result<(), IdentityError> {
+        if display_name.trim().is_empty() {
+            return Err(IdentityError::EmptyDisplayName);
+        }
+        if display_name.len() > T::MAX_DISPLAY_NAME_LEN {
+            return Err(IdentityError::DisplayNameTooLong);
+        }
+        if metadata.len() > T::MAX_METADATA_LEN {
+            return Err(IdentityError::MetadataTooLong);
+        }
+
+        let deposit = match self.identities.get(&who) {
+            Some(identity) => identity.deposit,
+            None => {
+                balances.hold(
+                    system,
+                    HoldReason::IdentityDeposit,
+                    who.clone(),
+                    T::IDENTITY_DEPOSIT,
+                )?;
+                T::IDENTITY_DEPOSIT
+            }
+        };
+        self.identities.insert(
+            who.clone(),
+            Identity {
+                display_name: display_name.clone(),
+                metadata,
+                deposit,
+                verified: false,
+            },
+        );
+        system.deposit_event(Event::IdentitySet { who, display_name });
+
+        Ok(())
+    }
+
+    // Remove the caller's identity and release its deposit
+    pub fn clear_identity(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+    ) -> Result<(), IdentityError> {
+        let deposit = self
+            .identities
+            .get(&who)
+            .ok_or(IdentityError::NoIdentity)?
+            .deposit;
+
+        balances.release(system, HoldReason::IdentityDeposit, who.clone(), deposit)?;
+        self.identities.remove(&who);
+        system.deposit_event(Event::IdentityCleared { who });
+
+        Ok(())
+    }
+
+    // Mark an identity as verified, e.g. after the council has checked it
+    pub fn verify_identity(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+    ) -> Result<(), IdentityError> {
+        let identity = self
+            .identities
+            .get_mut(&who)
+            .ok_or(IdentityError::NoIdentity)?;
+        identity.verified = true;
+        system.deposit_event(Event::IdentityVerified { who });
+
+        Ok(())
+    }
+
+    // Get an account's identity, if it registered one
+    pub fn identity_of(&self, who: &T::AccountId) -> Option<&Identity<T>> {
+        self.identities.get(who)
+    }
+
+    // Approximate state held by this pallet, counting names and metadata as well
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.identities
+            .values()
+            .map(|identity| {
+                let text = identity
+                    .display_name
+                    .len()
+                    .saturating_add(identity.metadata.len());
+                StorageUsage::value::<(T::AccountId, Identity<T>)>(text)
+            })
+            .fold(StorageUsage::default(), StorageUsage::saturating_add)
+    }
+}
+
+// Registered display names label accounts in event descriptions and listings
+impl<T: IdentityConfig> AccountLabels<T::AccountId> for IdentityPallet<T> {
+    fn label(&self, who: &T::AccountId) -> Option<String> {
+        self.identities
+            .get(who)
+            .map(|identity| identity.display_name.clone())
+    }
+}
+
+impl<T: IdentityConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::SetIdentity {
+                display_name,
+                metadata,
+            } => weight_of(display_name.len().saturating_add(metadata.len()), 1).saturating_add(3),
+            Call::ClearIdentity => 3,
+            Call::VerifyIdentity { .. } => 2,
+        }
+    }
+}
+
+impl<T: IdentityConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::SetIdentity {
+                display_name,
+                metadata,
+            } => {
+                0u8.encode_to(dest);
+                display_name.encode_to(dest);
+                metadata.encode_to(dest);
+            }
+            Call::ClearIdentity => 1u8.encode_to(dest),
+            Call::VerifyIdentity { who } => {
+                2u8.encode_to(dest);
+                who.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: IdentityConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::SetIdentity {
+                display_name: Decode::decode(input)?,
+                metadata: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::ClearIdentity),
+            2 => Ok(Call::VerifyIdentity {
+                who: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: IdentityConfig> Encode for Identity<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.display_name.encode_to(dest);
+        self.metadata.encode_to(dest);
+        self.deposit.encode_to(dest);
+        self.verified.encode_to(dest);
+    }
+}
+
+impl<T: IdentityConfig> Decode for Identity<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            display_name: Decode::decode(input)?,
+            metadata: Decode::decode(input)?,
+            deposit: Decode::decode(input)?,
+            verified: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: IdentityConfig> Encode for IdentityPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.identities.encode_to(dest);
+    }
+}
+
+impl<T: IdentityConfig> Decode for IdentityPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            identities: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{governance, Origin, Runtime, RuntimeCall, RuntimeError};
+
+    #[test]
+    fn test_identities_hold_a_deposit_and_can_be_verified() {
+        let (alice, bob) = (1u64, 2u64);
+        let deposit = <Runtime as IdentityConfig>::IDENTITY_DEPOSIT;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        let set = |display_name: &str| -> RuntimeCall {
+            Call::SetIdentity {
+                display_name: display_name.to_string(),
+                metadata: "alice@example.org".to_string(),
+            }
+            .into()
+        };
+        assert_eq!(
+            runtime.dispatch(alice, set(" ")),
+            Err(RuntimeError::Identity(IdentityError::EmptyDisplayName))
+        );
+        assert_eq!(
+            runtime.dispatch(bob, set("bob")),
+            Err(RuntimeError::Identity(IdentityError::Balances(
+                BalancesError::InsufficientBalance
+            )))
+        );
+        runtime.dispatch(alice, set("alice")).unwrap();
+        assert_eq!(
+            runtime
+                .balances
+                .get_held_balance(HoldReason::IdentityDeposit, alice),
+            deposit
+        );
+
+        // Only Root or the council may verify
+        let verify: RuntimeCall = Call::VerifyIdentity { who: alice }.into();
+        assert_eq!(
+            runtime.dispatch(alice, verify.clone()),
+            Err(crate::system::SystemError::BadOrigin.into())
+        );
+        runtime.dispatch_as(Origin::Council, verify).unwrap();
+        assert!(runtime.identity.identity_of(&alice).unwrap().verified);
+
+        // Renaming keeps the deposit but needs a new verification
+        runtime.dispatch(alice, set("Alice")).unwrap();
+        let identity = runtime.identity.identity_of(&alice).unwrap();
+        assert_eq!(identity.display_name, "Alice");
+        assert!(!identity.verified);
+        assert_eq!(runtime.balances.get_free_balance(alice), 1000 - deposit);
+
+        runtime.dispatch(alice, Call::ClearIdentity.into()).unwrap();
+        assert_eq!(runtime.balances.get_free_balance(alice), 1000u64);
+        assert!(runtime.identity.identity_of(&alice).is_none());
+    }
+
+    #[test]
+    fn test_proposal_details_show_the_creators_name() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(bob, 1000).unwrap();
+        runtime
+            .dispatch(
+                alice,
+                Call::SetIdentity {
+                    display_name: "alice".to_string(),
+                    metadata: String::new(),
+                }
+                .into(),
+            )
+            .unwrap();
+        for who in [alice, bob] {
+            runtime
+                .dispatch(
+                    who,
+                    governance::Call::CreateProposal {
+                        description: "Fund the library".to_string(),
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        let details = |proposal_id| {
+            runtime
+                .governance
+                .get_proposal_details(proposal_id, &runtime.identity)
+                .unwrap()
+        };
+        assert_eq!(
+            details(0),
+            (
+                "Fund the library".to_string(),
+                alice,
+                Some("alice".to_string())
+            )
+        );
+        assert_eq!(details(1), ("Fund the library".to_string(), bob, None));
+    }
+}
@@ -0,0 +1,533 @@
+use crate::balances::{BalancesConfig, BalancesError, BalancesPallet};
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::labels::{display_account, AccountLabels};
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::{CheckedAdd, Zero};
+use std::collections::HashMap;
+
+// A dead-man's switch: once an account has been inactive for its chosen timeout, its
+// beneficiary can start a claim, and after a challenge period take the free balance
+pub trait InheritanceConfig:
+    BalancesConfig + SystemConfig<RuntimeEvent: From<Event<Self>>>
+{
+    // Number of blocks the owner has to cancel a claim before it can be completed
+    const CHALLENGE_PERIOD: Self::BlockNumber;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InheritanceError {
+    NoWill,
+    SelfInheritance,
+    ZeroTimeout,
+    NotBeneficiary,
+    OwnerStillActive,
+    ClaimAlreadyStarted,
+    NoPendingClaim,
+    ChallengePeriodNotOver,
+    Overflow,
+    // Moving the inherited balance failed
+    Balances(BalancesError),
+}
+
+pallet_error!(InheritanceError {
+    0 => NoWill: "The owner has not named an heir",
+    1 => SelfInheritance: "Owners cannot name themselves as heir",
+    2 => ZeroTimeout: "The inactivity timeout must be at least one block",
+    3 => NotBeneficiary: "Only the named heir may do this",
+    4 => OwnerStillActive: "The owner's inactivity timeout has not passed",
+    5 => ClaimAlreadyStarted: "A claim is already in progress",
+    6 => NoPendingClaim: "No claim is in progress",
+    7 => ChallengePeriodNotOver: "The claim is still in its challenge period",
+    8 => Overflow: "An arithmetic operation overflowed",
+    9 => Balances(_): "Moving the inherited balance failed",
+});
+
+impl From<BalancesError> for InheritanceError {
+    fn from(error: BalancesError) -> Self {
+        InheritanceError::Balances(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: InheritanceConfig> {
+    HeirSet {
+        owner: T::AccountId,
+        beneficiary: T::AccountId,
+        timeout: T::BlockNumber,
+    },
+    HeirRemoved {
+        owner: T::AccountId,
+    },
+    ClaimStarted {
+        owner: T::AccountId,
+        beneficiary: T::AccountId,
+    },
+    ClaimCancelled {
+        owner: T::AccountId,
+    },
+    Inherited {
+        owner: T::AccountId,
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    },
+}
+
+impl<T: InheritanceConfig> Event<T> {
+    // Render the event as a sentence, e.g. "bob inherited 4 UNIT from alice"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::HeirSet {
+                owner,
+                beneficiary,
+                timeout,
+            } => format!(
+                "{} named {} as heir after {:?} inactive blocks",
+                display_account(labels, owner),
+                display_account(labels, beneficiary),
+                timeout
+            ),
+            Event::HeirRemoved { owner } => {
+                format!("{} removed their heir", display_account(labels, owner))
+            }
+            Event::ClaimStarted { owner, beneficiary } => format!(
+                "{} started claiming the balance of {}",
+                display_account(labels, beneficiary),
+                display_account(labels, owner)
+            ),
+            Event::ClaimCancelled { owner } => format!(
+                "{} cancelled the claim on their balance",
+                display_account(labels, owner)
+            ),
+            Event::Inherited {
+                owner,
+                beneficiary,
+                amount,
+            } => format!(
+                "{} inherited {} from {}",
+                display_account(labels, beneficiary),
+                format_balance::<T>(*amount),
+                display_account(labels, owner)
+            ),
+        }
+    }
+}
+
+// Calls an account can submit to the inheritance pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: InheritanceConfig> {
+    SetHeir {
+        beneficiary: T::AccountId,
+        timeout: T::BlockNumber,
+    },
+    RemoveHeir,
+    CancelClaim,
+    StartClaim {
+        owner: T::AccountId,
+    },
+    FinishClaim {
+        owner: T::AccountId,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Will<T: InheritanceConfig> {
+    pub beneficiary: T::AccountId,
+    // Inactive blocks after which the beneficiary may start a claim
+    pub timeout: T::BlockNumber,
+    // Block of the owner's latest dispatch
+    pub last_active: T::BlockNumber,
+    // Block the pending claim was started at, if any
+    pub claim_started: Option<T::BlockNumber>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InheritancePallet<T: InheritanceConfig> {
+    // owner -> will
+    pub wills: HashMap<T::AccountId, Will<T>>,
+}
+
+impl<T: InheritanceConfig> Default for InheritancePallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: InheritanceConfig> InheritancePallet<T> {
+    pub fn new() -> Self {
+        Self {
+            wills: HashMap::new(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), InheritanceError> {
+        match call {
+            Call::SetHeir {
+                beneficiary,
+                timeout,
+            } => self.set_heir(system, origin, beneficiary, timeout),
+            Call::RemoveHeir => self.remove_heir(system, origin),
+            Call::CancelClaim => self.cancel_claim(system, origin),
+            Call::StartClaim { owner } => self.start_claim(system, origin, owner),
+            Call::FinishClaim { owner } => self.finish_claim(system, balances, origin, owner),
+        }
+    }
+
+    // Name (or replace) the account that inherits after `timeout` inactive blocks
+    pub fn set_heir(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        owner: T::AccountId,
+        beneficiary: T::AccountId,
+        timeout: T::BlockNumber,
+    ) -> Result<(), InheritanceError> {
+        if owner == beneficiary {
+            return Err(InheritanceError::SelfInheritance);
+        }
+        if timeout.is_zero() {
+            return Err(InheritanceError::ZeroTimeout);
+        }
+
+        self.wills.insert(
+            owner.clone(),
+            Will {
+                beneficiary: beneficiary.clone(),
+                timeout,
+                last_active: system.block_number(),
+                claim_started: None,
+            },
+        );
+        system.deposit_event(Event::HeirSet {
+            owner,
+            beneficiary,
+            timeout,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_heir(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        owner: T::AccountId,
+    ) -> Result<(), InheritanceError> {
+        if self.wills.remove(&owner).is_none() {
+            return Err(InheritanceError::NoWill);
+        }
+
+        system.deposit_event(Event::HeirRemoved { owner });
+
+        Ok(())
+    }
+
+    // Record that the owner dispatched something, resetting their inactivity timer
+    pub fn note_activity(&mut self, who: T::AccountId, now: T::BlockNumber) {
+        if let Some(will) = self.wills.get_mut(&who) {
+            will.last_active = now;
+        }
+    }
+
+    // Cancel a pending claim on the owner's balance
+    pub fn cancel_claim(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        owner: T::AccountId,
+    ) -> Result<(), InheritanceError> {
+        let will = self.wills.get_mut(&owner).ok_or(InheritanceError::NoWill)?;
+        if will.claim_started.take().is_none() {
+            return Err(InheritanceError::NoPendingClaim);
+        }
+
+        system.deposit_event(Event::ClaimCancelled { owner });
+
+        Ok(())
+    }
+
+    // Start a claim once the owner has been inactive for the whole timeout
+    pub fn start_claim(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        beneficiary: T::AccountId,
+        owner: T::AccountId,
+    ) -> Result<(), InheritanceError> {
+        let now = system.block_number();
+        let will = self.wills.get_mut(&owner).ok_or(InheritanceError::NoWill)?;
+        if will.beneficiary != beneficiary {
+            return Err(InheritanceError::NotBeneficiary);
+        }
+        if will.claim_started.is_some() {
+            return Err(InheritanceError::ClaimAlreadyStarted);
+        }
+        let inactive_until = will
+            .last_active
+            .checked_add(&will.timeout)
+            .ok_or(InheritanceError::Overflow)?;
+        if now < inactive_until {
+            return Err(InheritanceError::OwnerStillActive);
+        }
+
+        will.claim_started = Some(now);
+        system.deposit_event(Event::ClaimStarted { owner, beneficiary });
+
+        Ok(())
+    }
+
+    // Complete a claim after the challenge period, moving the owner's usable balance.
+    // Any dispatch by the owner after the claim started also counts as a cancellation.
+    pub fn finish_claim(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        beneficiary: T::AccountId,
+        owner: T::AccountId,
+    ) -> Result<(), InheritanceError> {
+        let will = self.wills.get(&owner).ok_or(InheritanceError::NoWill)?;
+        if will.beneficiary != beneficiary {
+            return Err(InheritanceError::NotBeneficiary);
+        }
+        let started = will.claim_started.ok_or(InheritanceError::NoPendingClaim)?;
+        if will.last_active >= started {
+            return Err(InheritanceError::OwnerStillActive);
+        }
+        let claimable_at = started
+            .checked_add(&T::CHALLENGE_PERIOD)
+            .ok_or(InheritanceError::Overflow)?;
+        if system.block_number() < claimable_at {
+            return Err(InheritanceError::ChallengePeriodNotOver);
+        }
+
+        // Held and locked funds stay with the owner until released
+        let amount = balances.get_usable_balance(owner.clone());
+        balances.transfer(system, owner.clone(), beneficiary.clone(), amount)?;
+        self.wills.remove(&owner);
+        system.deposit_event(Event::Inherited {
+            owner,
+            beneficiary,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        StorageUsage::map(&self.wills)
+    }
+}
+
+impl<T: InheritanceConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::SetHeir { .. } | Call::RemoveHeir | Call::CancelClaim => 2,
+            Call::StartClaim { .. } => 3,
+            Call::FinishClaim { .. } => 5,
+        }
+    }
+}
+
+impl<T: InheritanceConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::SetHeir {
+                beneficiary,
+                timeout,
+            } => {
+                0u8.encode_to(dest);
+                beneficiary.encode_to(dest);
+                timeout.encode_to(dest);
+            }
+            Call::RemoveHeir => 1u8.encode_to(dest),
+            Call::CancelClaim => 2u8.encode_to(dest),
+            Call::StartClaim { owner } => {
+                3u8.encode_to(dest);
+                owner.encode_to(dest);
+            }
+            Call::FinishClaim { owner } => {
+                4u8.encode_to(dest);
+                owner.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: InheritanceConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::SetHeir {
+                beneficiary: Decode::decode(input)?,
+                timeout: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::RemoveHeir),
+            2 => Ok(Call::CancelClaim),
+            3 => Ok(Call::StartClaim {
+                owner: Decode::decode(input)?,
+            }),
+            4 => Ok(Call::FinishClaim {
+                owner: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: InheritanceConfig> Encode for Will<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.beneficiary.encode_to(dest);
+        self.timeout.encode_to(dest);
+        self.last_active.encode_to(dest);
+        self.claim_started.encode_to(dest);
+    }
+}
+
+impl<T: InheritanceConfig> Decode for Will<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            beneficiary: Decode::decode(input)?,
+            timeout: Decode::decode(input)?,
+            last_active: Decode::decode(input)?,
+            claim_started: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: InheritanceConfig> Encode for InheritancePallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.wills.encode_to(dest);
+    }
+}
+
+impl<T: InheritanceConfig> Decode for InheritancePallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            wills: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::Header;
+    use crate::{Block, Runtime, RuntimeError};
+
+    // Execute empty blocks until the runtime reaches `number`
+    fn run_to_block(runtime: &mut Runtime, number: u32) {
+        while runtime.system.block_number() < number {
+            let next = runtime.system.block_number() + 1;
+            runtime
+                .execute_block(Block {
+                    header: Header { number: next },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_inactive_balance_is_inherited() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        runtime
+            .dispatch(
+                alice,
+                Call::SetHeir {
+                    beneficiary: bob,
+                    timeout: 20,
+                }
+                .into(),
+            )
+            .unwrap();
+
+        // Too early: alice was active at block 0
+        run_to_block(&mut runtime, 19);
+        let result = runtime.dispatch(bob, Call::StartClaim { owner: alice }.into());
+        assert_eq!(
+            result,
+            Err(RuntimeError::Inheritance(
+                InheritanceError::OwnerStillActive
+            ))
+        );
+
+        run_to_block(&mut runtime, 20);
+        runtime
+            .dispatch(bob, Call::StartClaim { owner: alice }.into())
+            .unwrap();
+        let result = runtime.dispatch(bob, Call::FinishClaim { owner: alice }.into());
+        assert_eq!(
+            result,
+            Err(RuntimeError::Inheritance(
+                InheritanceError::ChallengePeriodNotOver
+            ))
+        );
+
+        run_to_block(
+            &mut runtime,
+            20 + <Runtime as InheritanceConfig>::CHALLENGE_PERIOD,
+        );
+        runtime
+            .dispatch(bob, Call::FinishClaim { owner: alice }.into())
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(bob), 1000u64);
+        assert!(runtime.inheritance.wills.is_empty());
+    }
+
+    #[test]
+    fn test_owner_can_cancel_claim() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        runtime
+            .dispatch(
+                alice,
+                Call::SetHeir {
+                    beneficiary: bob,
+                    timeout: 5,
+                }
+                .into(),
+            )
+            .unwrap();
+        run_to_block(&mut runtime, 5);
+
+        // Only the named beneficiary can claim
+        let result = runtime.dispatch(charlie, Call::StartClaim { owner: alice }.into());
+        assert_eq!(
+            result,
+            Err(RuntimeError::Inheritance(InheritanceError::NotBeneficiary))
+        );
+        runtime
+            .dispatch(bob, Call::StartClaim { owner: alice }.into())
+            .unwrap();
+
+        // Alice shows up during the challenge period and cancels
+        run_to_block(&mut runtime, 6);
+        runtime.dispatch(alice, Call::CancelClaim.into()).unwrap();
+        assert_eq!(
+            runtime.system.events().last(),
+            Some(&Event::ClaimCancelled { owner: alice }.into())
+        );
+
+        // Her activity also restarts the inactivity timer
+        let result = runtime.dispatch(bob, Call::StartClaim { owner: alice }.into());
+        assert_eq!(
+            result,
+            Err(RuntimeError::Inheritance(
+                InheritanceError::OwnerStillActive
+            ))
+        );
+        assert_eq!(runtime.balances.get_free_balance(alice), 1000u64);
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+// Source of human-readable account names used when formatting output
+pub trait AccountLabels<AccountId> {
+    fn label(&self, who: &AccountId) -> Option<String>;
+}
+
+// Disables labelling, so accounts are always shown by id
+pub struct NoLabels;
+
+impl<AccountId> AccountLabels<AccountId> for NoLabels {
+    fn label(&self, _who: &AccountId) -> Option<String> {
+        None
+    }
+}
+
+// Node-local registry of labels, e.g. for tests and classroom demos
+pub struct LabelRegistry<AccountId> {
+    labels: HashMap<AccountId, String>,
+}
+
+impl<AccountId: Eq + Hash> Default for LabelRegistry<AccountId> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<AccountId: Eq + Hash> LabelRegistry<AccountId> {
+    pub fn new() -> Self {
+        Self {
+            labels: HashMap::new(),
+        }
+    }
+
+    // Assign or replace the label of an account
+    pub fn register(&mut self, who: AccountId, label: impl Into<String>) {
+        self.labels.insert(who, label.into());
+    }
+
+    pub fn unregister(&mut self, who: &AccountId) {
+        self.labels.remove(who);
+    }
+}
+
+impl<AccountId: Eq + Hash> AccountLabels<AccountId> for LabelRegistry<AccountId> {
+    fn label(&self, who: &AccountId) -> Option<String> {
+        self.labels.get(who).cloned()
+    }
+}
+
+// Show an account by its label, falling back to its id
+pub fn display_account<AccountId: Debug>(
+    labels: &impl AccountLabels<AccountId>,
+    who: &AccountId,
+) -> String {
+    labels
+        .label(who)
+        .unwrap_or_else(|| format!("account {:?}", who))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_fall_back_to_ids() {
+        let mut labels = LabelRegistry::new();
+        labels.register(1u64, "alice");
+
+        assert_eq!(display_account(&labels, &1), "alice");
+        assert_eq!(display_account(&labels, &2), "account 2");
+        assert_eq!(display_account(&NoLabels, &1u64), "account 1");
+
+        labels.unregister(&1);
+        assert_eq!(display_account(&labels, &1), "account 1");
+    }
+}
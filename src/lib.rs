@@ -1,24 +1,2363 @@
+// Dispatchable code must never panic, so runtime code uses checked math and returns errors
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::arithmetic_side_effects
+    )
+)]
+
+pub use balances::BalancesConfig;
+pub use budget::BudgetConfig;
+pub use checkpoint::CheckpointConfig;
+pub use codec::{Decode, Encode};
+pub use council::CouncilConfig;
+pub use currency::{format_balance, parse_balance};
+pub use escrow::EscrowConfig;
+pub use faucet::FaucetConfig;
 pub use governance::GovernanceConfig;
+pub use identity::IdentityConfig;
+pub use inheritance::InheritanceConfig;
+pub use payment::PaymentConfig;
 pub use staking::StakingConfig;
-pub use system::SystemConfig;
+pub use support::{GetWeight, Origin, PalletError, Weight};
+pub use system::{SystemConfig, SystemPallet};
+pub use treasury::TreasuryConfig;
 
+pub mod airdrop;
+pub mod balances;
+pub mod bounded;
+pub mod budget;
+pub mod checkpoint;
+pub mod codec;
+pub mod council;
+pub mod currency;
+pub mod escrow;
+pub mod events;
+pub mod faucet;
+pub mod genesis;
+#[cfg(test)]
+mod golden;
 pub mod governance;
+pub mod identity;
+pub mod inheritance;
+pub mod labels;
+pub mod payment;
+pub mod simulation;
 pub mod staking;
+pub mod storage;
+pub mod support;
 pub mod system;
+#[cfg(feature = "testnet")]
+pub mod testnet;
+pub mod treasury;
+
+// The runtime ties the pallets together and routes calls to them
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Runtime {
+    pub system: SystemPallet<Runtime>,
+    pub balances: balances::BalancesPallet<Runtime>,
+    pub staking: staking::StakingPallet<Runtime>,
+    pub governance: governance::GovernancePallet<Runtime>,
+    pub inheritance: inheritance::InheritancePallet<Runtime>,
+    pub treasury: treasury::TreasuryPallet<Runtime>,
+    pub council: council::CouncilPallet<Runtime>,
+    pub escrow: escrow::EscrowPallet<Runtime>,
+    pub identity: identity::IdentityPallet<Runtime>,
+    pub budget: budget::BudgetPallet<Runtime>,
+    pub checkpoint: checkpoint::CheckpointPallet<Runtime>,
+    pub payment: payment::PaymentPallet<Runtime>,
+    pub faucet: faucet::FaucetPallet<Runtime>,
+    // Where each executed block's state is committed, if the chain is kept on disk
+    journal: Option<storage::Journal>,
+}
+
+impl Runtime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Open a chain kept on disk at `path`, recovering the state of the last block
+    // committed there, or starting empty if there is none
+    pub fn open(path: impl Into<std::path::PathBuf>) -> Result<Self, storage::StorageError> {
+        let (journal, state) = storage::Journal::open(path)?;
+        let runtime = match state {
+            Some(state) => Self::import_state(&state)?,
+            None => Self::new(),
+        };
+        Ok(runtime.with_journal(journal))
+    }
+
+    // Commit the state to `journal` after every block this runtime executes
+    pub fn with_journal(mut self, journal: storage::Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    // Build a runtime whose accounts start with the given balances and bonds
+    pub fn from_genesis(
+        config: &genesis::GenesisConfig<Runtime>,
+    ) -> Result<Self, genesis::GenesisError> {
+        let mut runtime = Self::new();
+        config.assimilate_storage(
+            &mut runtime.system,
+            &mut runtime.balances,
+            &mut runtime.staking,
+        )?;
+        Ok(runtime)
+    }
+
+    // Execute a block: start it in the system pallet, dispatch each extrinsic, then run
+    // the end-of-block hooks. A failed extrinsic doesn't stop the block; its error is
+    // returned in the extrinsic's slot of the result. A runtime with a journal then
+    // commits the block's state to it; if that fails, the block is executed in memory
+    // but a restart comes back to the block before it.
+    pub fn execute_block(&mut self, block: Block) -> Result<Vec<DispatchResult>, RuntimeError> {
+        let number = block.header.number;
+        self.system.initialize(number)?;
+        self.on_initialize(number);
+
+        let results = block
+            .extrinsics
+            .into_iter()
+            .map(|extrinsic| self.apply_extrinsic(extrinsic))
+            .collect();
+
+        self.on_finalize(number);
+
+        // One frame for the whole block, so a restart never sees half of it
+        if let Some(mut journal) = self.journal.take() {
+            let committed = journal.commit(&self.export_state());
+            self.journal = Some(journal);
+            committed.map_err(|_| system::SystemError::CommitFailed)?;
+        }
+
+        Ok(results)
+    }
+
+    // Start-of-block hooks, run before any extrinsic
+    fn on_initialize(&mut self, now: BlockNumber) {
+        self.staking.on_initialize(&mut self.system, now);
+        self.checkpoint.on_initialize(&mut self.system, now);
+    }
+
+    // Storage migrations, run right after a runtime upgrade with the spec version it
+    // upgraded from. Each pallet whose encoding changes adds its step here; none has
+    // needed one yet.
+    fn on_runtime_upgrade(&mut self, _from: u32) {}
+
+    // End-of-block hooks, run after every extrinsic. Approved proposals that are due
+    // are enacted last, dispatching their calls with a Council origin. A proposal's
+    // calls are enacted atomically, so a failing call rolls back the ones before it.
+    fn on_finalize(&mut self, now: BlockNumber) {
+        self.staking
+            .on_finalize(&mut self.system, &mut self.balances, now);
+        self.governance
+            .on_finalize(&mut self.system, &mut self.staking, now);
+        self.escrow
+            .on_finalize(&mut self.system, &mut self.balances, now);
+        for (proposal_id, calls) in self.governance.take_due_enactments(&mut self.system, now) {
+            let failed_call = self.dispatch_all_as(Origin::Council, calls).err();
+            self.governance
+                .note_enacted(&mut self.system, proposal_id, failed_call);
+        }
+    }
+
+    // Dispatch calls in order, all-or-nothing: if one fails, the state (events
+    // included) is rolled back to before the first, and its index is returned
+    fn dispatch_all_as(
+        &mut self,
+        origin: Origin<AccountId>,
+        calls: Vec<RuntimeCall>,
+    ) -> Result<(), u32> {
+        let checkpoint = self.clone();
+        for (index, call) in (0u32..).zip(calls) {
+            if self.dispatch_as(origin.clone(), call).is_err() {
+                *self = checkpoint;
+                return Err(index);
+            }
+        }
+        Ok(())
+    }
+
+    // Run the pre-dispatch checks in order (nonce, spending limit, fee), then dispatch
+    // the call. An extrinsic failing a check is rejected outright. Otherwise the nonce
+    // and fee are used up even if the call fails, so the same signed extrinsic can
+    // never be applied twice.
+    pub fn apply_extrinsic(&mut self, extrinsic: Extrinsic) -> DispatchResult {
+        let support::Extrinsic {
+            caller,
+            nonce,
+            call,
+        } = extrinsic;
+        self.system.check_nonce(caller, nonce)?;
+        // The fee and the amount transferred both count against the spending limit
+        let fee = self.payment.fee_for(call.call_index(), call.weight())?;
+        let transferred = call.transferred_amount();
+        let spend = fee
+            .checked_add(transferred)
+            .ok_or(budget::BudgetError::Overflow)?;
+        self.budget.ensure_can_spend(&self.system, &caller, spend)?;
+        payment::charge_fee(&mut self.system, &mut self.balances, caller, fee)?;
+        self.budget.note_spend(&self.system, &caller, fee)?;
+        self.system.inc_nonce(caller)?;
+
+        self.dispatch_spending(caller, call)
+    }
+
+    // Dispatch a call signed by `who`, counting what it transfers against the
+    // account's spending limit. Only a transfer that went through is spent.
+    fn dispatch_spending(&mut self, who: AccountId, call: RuntimeCall) -> DispatchResult {
+        let transferred = call.transferred_amount();
+        self.budget
+            .ensure_can_spend(&self.system, &who, transferred)?;
+        self.dispatch(who, call)?;
+        self.budget.note_spend(&self.system, &who, transferred)?;
+        Ok(())
+    }
+
+    // Approximate state held by each pallet, to see which one a workload makes grow
+    pub fn storage_report(&self) -> Vec<(&'static str, support::StorageUsage)> {
+        vec![
+            ("System", self.system.storage_usage()),
+            ("Balances", self.balances.storage_usage()),
+            ("Staking", self.staking.storage_usage()),
+            ("Governance", self.governance.storage_usage()),
+            ("Inheritance", self.inheritance.storage_usage()),
+            ("Treasury", self.treasury.storage_usage()),
+            ("Council", self.council.storage_usage()),
+            ("Escrow", self.escrow.storage_usage()),
+            ("Payment", self.payment.storage_usage()),
+            ("Identity", self.identity.storage_usage()),
+            ("Budget", self.budget.storage_usage()),
+            ("Checkpoint", self.checkpoint.storage_usage()),
+            ("Faucet", self.faucet.storage_usage()),
+        ]
+    }
+
+    // List the pallets holding a reference on an account, i.e. state that would be
+    // left dangling if the account went away: multisigs and sessions, bonds,
+    // nominations or funds unbonding, open proposals, votes or delegations, wills,
+    // treasury spends and investments, council seats, pending protected transfers,
+    // identities, spending limits, checkpoint attestations and faucet claims. An
+    // account with any of these can't be reaped.
+    pub fn account_references(&self, who: AccountId) -> Vec<&'static str> {
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let references = [
+            ("System", self.system.is_referenced(&who)),
+            (
+                "Staking",
+                self.staking.get_staked_balance(who) != 0
+                    || !self.staking.nominations_of(who).is_empty()
+                    || !self.staking.unbonding(who).is_empty()
+                    || self.staking.validator_metadata(&who).is_some(),
+            ),
+            ("Governance", self.governance.is_participating(&who)),
+            (
+                "Inheritance",
+                self.inheritance
+                    .wills
+                    .iter()
+                    .any(|(owner, will)| *owner == who || will.beneficiary == who),
+            ),
+            (
+                "Treasury",
+                // A spend can still be paid until its proposal fails
+                self.treasury.spends.values().any(|spend| {
+                    let payable = matches!(
+                        self.governance.get_proposal_status(spend.proposal_id),
+                        Some(
+                            governance::ProposalStatus::Active
+                                | governance::ProposalStatus::Approved
+                        )
+                    );
+                    payable && !spend.paid && (spend.proposer == who || spend.beneficiary == who)
+                }) || self
+                    .staking
+                    .nominations_of(pot)
+                    .iter()
+                    .any(|(validator, _)| *validator == who),
+            ),
+            ("Council", self.council.members().contains(&who)),
+            ("Escrow", !self.escrow.transfers_of(&who).is_empty()),
+            ("Identity", self.identity.identity_of(&who).is_some()),
+            ("Budget", self.budget.limit_of(&who).is_some()),
+            ("Checkpoint", self.checkpoint.has_attested(&who)),
+            ("Faucet", self.faucet.last_claim(&who).is_some()),
+        ];
+        references
+            .into_iter()
+            .filter(|(_, referenced)| *referenced)
+            .map(|(pallet, _)| pallet)
+            .collect()
+    }
+
+    // Snapshot every pallet's storage, so the chain can be saved and restored later.
+    // Events aren't included; they only describe the block being executed.
+    pub fn export_state(&self) -> Vec<u8> {
+        let mut bytes = vec![STATE_VERSION];
+        self.system.encode_to(&mut bytes);
+        self.balances.encode_to(&mut bytes);
+        self.staking.encode_to(&mut bytes);
+        self.governance.encode_to(&mut bytes);
+        self.inheritance.encode_to(&mut bytes);
+        self.treasury.encode_to(&mut bytes);
+        self.council.encode_to(&mut bytes);
+        self.escrow.encode_to(&mut bytes);
+        self.identity.encode_to(&mut bytes);
+        self.budget.encode_to(&mut bytes);
+        self.checkpoint.encode_to(&mut bytes);
+        self.payment.encode_to(&mut bytes);
+        self.faucet.encode_to(&mut bytes);
+        bytes
+    }
+
+    // Hash of the state as exported, which validators attest at each checkpoint. A
+    // 64-bit FNV-1a hash, so it catches diverging state but isn't collision resistant.
+    pub fn state_root(&self) -> u64 {
+        Self::state_root_of(&self.export_state())
+    }
+
+    // State root of `export_state` output
+    pub fn state_root_of(state: &[u8]) -> u64 {
+        system::fnv1a(state)
+    }
+
+    // Start from a state export instead of replaying every block, as long as it is
+    // the state the validators agreed on at the checkpoint
+    pub fn sync_from_checkpoint(
+        state: &[u8],
+        checkpoint: checkpoint::Checkpoint<BlockNumber>,
+    ) -> Result<Self, codec::CodecError> {
+        if Self::state_root_of(state) != checkpoint.state_root {
+            return Err(codec::CodecError::InvalidValue);
+        }
+        let runtime = Self::import_state(state)?;
+        if runtime.system.block_number() != checkpoint.block {
+            return Err(codec::CodecError::InvalidValue);
+        }
+        Ok(runtime)
+    }
+
+    // Restore a runtime from `export_state` output
+    pub fn import_state(mut bytes: &[u8]) -> Result<Self, codec::CodecError> {
+        let input = &mut bytes;
+        let version = u8::decode(input)?;
+        if version != STATE_VERSION {
+            return Err(codec::CodecError::UnsupportedVersion(version));
+        }
+        let runtime = Self {
+            system: Decode::decode(input)?,
+            balances: Decode::decode(input)?,
+            staking: Decode::decode(input)?,
+            governance: Decode::decode(input)?,
+            inheritance: Decode::decode(input)?,
+            treasury: Decode::decode(input)?,
+            council: Decode::decode(input)?,
+            escrow: Decode::decode(input)?,
+            identity: Decode::decode(input)?,
+            budget: Decode::decode(input)?,
+            checkpoint: Decode::decode(input)?,
+            payment: Decode::decode(input)?,
+            faucet: Decode::decode(input)?,
+            journal: None,
+        };
+        if !input.is_empty() {
+            return Err(codec::CodecError::TrailingBytes);
+        }
+        Ok(runtime)
+    }
+
+    // Route a call signed by `origin` to its pallet
+    pub fn dispatch(&mut self, origin: AccountId, call: RuntimeCall) -> DispatchResult {
+        self.dispatch_as(Origin::Signed(origin), call)
+    }
+
+    // Route a call to its pallet, if `origin` may make it: privileged calls need
+    // Root or Council, and every other call needs a signed origin
+    pub fn dispatch_as(&mut self, origin: Origin<AccountId>, call: RuntimeCall) -> DispatchResult {
+        let result = match (&origin, call.is_privileged()) {
+            (Origin::Signed(who), false) => self.route(*who, call),
+            (Origin::Council, true) if call.requires_root() => {
+                Err(system::SystemError::BadOrigin.into())
+            }
+            (Origin::Root | Origin::Council, true) => self.route_privileged(call),
+            _ => Err(system::SystemError::BadOrigin.into()),
+        };
+        // Any signed call, even a failed one, shows the account is still active
+        if let Origin::Signed(who) = origin {
+            self.inheritance
+                .note_activity(who, self.system.block_number());
+        }
+
+        result
+    }
+
+    fn route_privileged(&mut self, call: RuntimeCall) -> DispatchResult {
+        match call {
+            RuntimeCall::System(system::Call::SetCode { code }) => {
+                let from = self.system.set_code(code)?;
+                self.on_runtime_upgrade(from);
+            }
+            RuntimeCall::Governance(governance::Call::FinalizeProposal { proposal_id }) => {
+                self.governance.finalize_proposal(
+                    &mut self.system,
+                    &mut self.staking,
+                    proposal_id,
+                )?;
+            }
+            RuntimeCall::Treasury(treasury::Call::ForceSpend {
+                beneficiary,
+                amount,
+            }) => treasury::TreasuryPallet::force_spend(
+                &mut self.system,
+                &mut self.balances,
+                beneficiary,
+                amount,
+            )?,
+            RuntimeCall::Treasury(treasury::Call::Invest { targets, amount }) => {
+                self.treasury.invest(
+                    &mut self.system,
+                    &mut self.balances,
+                    &mut self.staking,
+                    targets,
+                    amount,
+                )?
+            }
+            RuntimeCall::Treasury(treasury::Call::Divest) => {
+                self.treasury.divest(&mut self.system, &mut self.staking)?
+            }
+            RuntimeCall::Council(council::Call::SetMembers { members }) => {
+                self.council.set_members(&mut self.system, members)?
+            }
+            RuntimeCall::Escrow(escrow::Call::Adjudicate {
+                transfer_id,
+                to_recipient,
+            }) => self.escrow.adjudicate(
+                &mut self.system,
+                &mut self.balances,
+                transfer_id,
+                to_recipient,
+            )?,
+            RuntimeCall::Identity(identity::Call::VerifyIdentity { who }) => {
+                self.identity.verify_identity(&mut self.system, who)?
+            }
+            RuntimeCall::Budget(budget::Call::ForceSetLimit { who, limit }) => {
+                self.budget.force_set_limit(&mut self.system, who, limit)
+            }
+            RuntimeCall::Payment(call) => self.payment.dispatch(&mut self.system, call)?,
+            RuntimeCall::Staking(staking::Call::ReportOffence {
+                reporter,
+                offender,
+                severity,
+            }) => {
+                self.staking.report_offence(
+                    &mut self.system,
+                    &mut self.balances,
+                    reporter,
+                    offender,
+                    severity,
+                )?;
+                // Governance bars offenders once the slash has gone through
+                self.governance
+                    .note_offence(&mut self.system, &self.staking, offender, severity);
+            }
+            _ => return Err(system::SystemError::BadOrigin.into()),
+        }
 
-pub struct Runtime;
+        Ok(())
+    }
+
+    fn route(&mut self, origin: AccountId, call: RuntimeCall) -> DispatchResult {
+        match call {
+            RuntimeCall::System(call) => {
+                // A multisig call that reached its threshold, or a session delegate's
+                // call, runs as the account it was made for, and spends from its budget
+                if let Some((account, call)) = self.system.dispatch(origin, call)? {
+                    return self.dispatch_spending(account, call);
+                }
+            }
+            RuntimeCall::Balances(balances::Call::ReapAccount { who }) => {
+                // Reaping would leave other pallets' state about the account dangling
+                if !self.account_references(who).is_empty() {
+                    return Err(balances::BalancesError::StillReferenced.into());
+                }
+                self.balances.reap_account(&mut self.system, who)?
+            }
+            RuntimeCall::Balances(call) => {
+                self.balances.dispatch(&mut self.system, origin, call)?
+            }
+            RuntimeCall::Staking(call) => {
+                self.staking
+                    .dispatch(&mut self.system, &mut self.balances, origin, call)?
+            }
+            RuntimeCall::Governance(call) => self.governance.dispatch(
+                &mut self.system,
+                &mut self.balances,
+                &mut self.staking,
+                origin,
+                call,
+            )?,
+            RuntimeCall::Inheritance(call) => {
+                self.inheritance
+                    .dispatch(&mut self.system, &mut self.balances, origin, call)?
+            }
+            RuntimeCall::Treasury(call) => self.treasury.dispatch(
+                &mut self.system,
+                &mut self.balances,
+                &mut self.staking,
+                &mut self.governance,
+                origin,
+                call,
+            )?,
+            RuntimeCall::Council(call) => {
+                self.council
+                    .dispatch(&mut self.system, &mut self.governance, origin, call)?
+            }
+            RuntimeCall::Escrow(call) => {
+                self.escrow
+                    .dispatch(&mut self.system, &mut self.balances, origin, call)?
+            }
+            RuntimeCall::Payment(call) => self.payment.dispatch(&mut self.system, call)?,
+            RuntimeCall::Identity(call) => {
+                self.identity
+                    .dispatch(&mut self.system, &mut self.balances, origin, call)?
+            }
+            RuntimeCall::Budget(call) => self.budget.dispatch(&mut self.system, origin, call)?,
+            RuntimeCall::Checkpoint(call) => {
+                self.checkpoint
+                    .dispatch(&mut self.system, &self.staking, origin, call)?
+            }
+            RuntimeCall::Faucet(call) => self.faucet.dispatch(
+                &mut self.system,
+                &mut self.balances,
+                &self.governance,
+                origin,
+                call,
+            )?,
+        }
+
+        Ok(())
+    }
+}
+
+type AccountId = <Runtime as SystemConfig>::AccountId;
+type BlockNumber = <Runtime as SystemConfig>::BlockNumber;
+type Nonce = <Runtime as SystemConfig>::Nonce;
+type Balance = <Runtime as BalancesConfig>::Balance;
+
+pub type Header = support::Header<BlockNumber>;
+pub type Extrinsic = support::Extrinsic<AccountId, Nonce, RuntimeCall>;
+pub type Block = support::Block<Header, Extrinsic>;
+
+pub type DispatchResult = Result<(), RuntimeError>;
+
+// Format version written at the start of `Runtime::export_state`; bump it whenever
+// the encoding of any pallet's storage changes
+pub const STATE_VERSION: u8 = 23;
+
+// Calls for every pallet, as submitted to `Runtime::dispatch`
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeCall {
+    System(system::Call<Runtime>),
+    Balances(balances::Call<Runtime>),
+    Staking(staking::Call<Runtime>),
+    Governance(governance::Call<Runtime>),
+    Inheritance(inheritance::Call<Runtime>),
+    Treasury(treasury::Call<Runtime>),
+    Council(council::Call<Runtime>),
+    Escrow(escrow::Call<Runtime>),
+    Payment(payment::Call),
+    Identity(identity::Call<Runtime>),
+    Budget(budget::Call<Runtime>),
+    Checkpoint(checkpoint::Call<Runtime>),
+    Faucet(faucet::Call),
+}
+
+impl From<system::Call<Runtime>> for RuntimeCall {
+    fn from(call: system::Call<Runtime>) -> Self {
+        RuntimeCall::System(call)
+    }
+}
+
+impl From<balances::Call<Runtime>> for RuntimeCall {
+    fn from(call: balances::Call<Runtime>) -> Self {
+        RuntimeCall::Balances(call)
+    }
+}
+
+impl From<staking::Call<Runtime>> for RuntimeCall {
+    fn from(call: staking::Call<Runtime>) -> Self {
+        RuntimeCall::Staking(call)
+    }
+}
+
+impl From<governance::Call<Runtime>> for RuntimeCall {
+    fn from(call: governance::Call<Runtime>) -> Self {
+        RuntimeCall::Governance(call)
+    }
+}
+
+impl From<inheritance::Call<Runtime>> for RuntimeCall {
+    fn from(call: inheritance::Call<Runtime>) -> Self {
+        RuntimeCall::Inheritance(call)
+    }
+}
+
+impl From<treasury::Call<Runtime>> for RuntimeCall {
+    fn from(call: treasury::Call<Runtime>) -> Self {
+        RuntimeCall::Treasury(call)
+    }
+}
+
+impl From<council::Call<Runtime>> for RuntimeCall {
+    fn from(call: council::Call<Runtime>) -> Self {
+        RuntimeCall::Council(call)
+    }
+}
+
+impl From<escrow::Call<Runtime>> for RuntimeCall {
+    fn from(call: escrow::Call<Runtime>) -> Self {
+        RuntimeCall::Escrow(call)
+    }
+}
+
+impl From<payment::Call> for RuntimeCall {
+    fn from(call: payment::Call) -> Self {
+        RuntimeCall::Payment(call)
+    }
+}
+
+impl From<identity::Call<Runtime>> for RuntimeCall {
+    fn from(call: identity::Call<Runtime>) -> Self {
+        RuntimeCall::Identity(call)
+    }
+}
+
+impl From<budget::Call<Runtime>> for RuntimeCall {
+    fn from(call: budget::Call<Runtime>) -> Self {
+        RuntimeCall::Budget(call)
+    }
+}
+
+impl From<checkpoint::Call<Runtime>> for RuntimeCall {
+    fn from(call: checkpoint::Call<Runtime>) -> Self {
+        RuntimeCall::Checkpoint(call)
+    }
+}
+
+impl From<faucet::Call> for RuntimeCall {
+    fn from(call: faucet::Call) -> Self {
+        RuntimeCall::Faucet(call)
+    }
+}
+
+impl GetWeight for RuntimeCall {
+    fn weight(&self) -> Weight {
+        match self {
+            RuntimeCall::System(call) => call.weight(),
+            RuntimeCall::Balances(call) => call.weight(),
+            RuntimeCall::Staking(call) => call.weight(),
+            RuntimeCall::Governance(call) => call.weight(),
+            RuntimeCall::Inheritance(call) => call.weight(),
+            RuntimeCall::Treasury(call) => call.weight(),
+            RuntimeCall::Council(call) => call.weight(),
+            RuntimeCall::Escrow(call) => call.weight(),
+            RuntimeCall::Payment(call) => call.weight(),
+            RuntimeCall::Identity(call) => call.weight(),
+            RuntimeCall::Budget(call) => call.weight(),
+            RuntimeCall::Checkpoint(call) => call.weight(),
+            RuntimeCall::Faucet(call) => call.weight(),
+        }
+    }
+}
+
+// Calls are tagged with their pallet's index, as in the error registry
+impl Encode for RuntimeCall {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            RuntimeCall::System(call) => {
+                0u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Balances(call) => {
+                1u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Staking(call) => {
+                2u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Governance(call) => {
+                3u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Inheritance(call) => {
+                4u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Treasury(call) => {
+                5u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Council(call) => {
+                6u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Escrow(call) => {
+                7u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Payment(call) => {
+                8u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Identity(call) => {
+                9u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Budget(call) => {
+                10u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Checkpoint(call) => {
+                11u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+            RuntimeCall::Faucet(call) => {
+                12u8.encode_to(dest);
+                call.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl Decode for RuntimeCall {
+    fn decode(input: &mut &[u8]) -> Result<Self, codec::CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(RuntimeCall::System(Decode::decode(input)?)),
+            1 => Ok(RuntimeCall::Balances(Decode::decode(input)?)),
+            2 => Ok(RuntimeCall::Staking(Decode::decode(input)?)),
+            3 => Ok(RuntimeCall::Governance(Decode::decode(input)?)),
+            4 => Ok(RuntimeCall::Inheritance(Decode::decode(input)?)),
+            5 => Ok(RuntimeCall::Treasury(Decode::decode(input)?)),
+            6 => Ok(RuntimeCall::Council(Decode::decode(input)?)),
+            7 => Ok(RuntimeCall::Escrow(Decode::decode(input)?)),
+            8 => Ok(RuntimeCall::Payment(Decode::decode(input)?)),
+            9 => Ok(RuntimeCall::Identity(Decode::decode(input)?)),
+            10 => Ok(RuntimeCall::Budget(Decode::decode(input)?)),
+            11 => Ok(RuntimeCall::Checkpoint(Decode::decode(input)?)),
+            12 => Ok(RuntimeCall::Faucet(Decode::decode(input)?)),
+            _ => Err(codec::CodecError::InvalidTag),
+        }
+    }
+}
+
+impl RuntimeCall {
+    // Whether the call needs a Root or Council origin rather than a signed one
+    pub fn is_privileged(&self) -> bool {
+        self.requires_root()
+            || matches!(
+                self,
+                RuntimeCall::Governance(governance::Call::FinalizeProposal { .. })
+                    | RuntimeCall::Staking(staking::Call::ReportOffence { .. })
+                    | RuntimeCall::Treasury(treasury::Call::ForceSpend { .. })
+                    | RuntimeCall::Treasury(treasury::Call::Invest { .. })
+                    | RuntimeCall::Treasury(treasury::Call::Divest)
+                    | RuntimeCall::Council(council::Call::SetMembers { .. })
+                    | RuntimeCall::Escrow(escrow::Call::Adjudicate { .. })
+                    | RuntimeCall::Identity(identity::Call::VerifyIdentity { .. })
+                    | RuntimeCall::Budget(budget::Call::ForceSetLimit { .. })
+                    | RuntimeCall::Payment(_)
+            )
+    }
+
+    // The call's pallet and call tags, which fee discounts are keyed by
+    pub fn call_index(&self) -> payment::CallIndex {
+        support::call_index_of(self)
+    }
+
+    // Whether the call needs a Root origin; the council's origin isn't enough
+    pub fn requires_root(&self) -> bool {
+        matches!(self, RuntimeCall::System(system::Call::SetCode { .. }))
+    }
+
+    // The amount the call pays out of the signer's balance to someone else, which
+    // counts against the signer's spending limit
+    pub fn transferred_amount(&self) -> Balance {
+        match self {
+            RuntimeCall::Balances(balances::Call::Transfer { amount, .. })
+            | RuntimeCall::Escrow(escrow::Call::Transfer { amount, .. }) => *amount,
+            _ => 0,
+        }
+    }
+}
+
+// Events from every pallet, as recorded by the system pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeEvent {
+    System(system::Event<Runtime>),
+    Balances(balances::Event<Runtime>),
+    Staking(staking::Event<Runtime>),
+    Governance(governance::Event<Runtime>),
+    Inheritance(inheritance::Event<Runtime>),
+    Treasury(treasury::Event<Runtime>),
+    Council(council::Event<Runtime>),
+    Escrow(escrow::Event<Runtime>),
+    Payment(payment::Event<Runtime>),
+    Identity(identity::Event<Runtime>),
+    Budget(budget::Event<Runtime>),
+    Checkpoint(checkpoint::Event<Runtime>),
+    Faucet(faucet::Event<Runtime>),
+}
+
+impl From<system::Event<Runtime>> for RuntimeEvent {
+    fn from(event: system::Event<Runtime>) -> Self {
+        RuntimeEvent::System(event)
+    }
+}
+
+impl From<balances::Event<Runtime>> for RuntimeEvent {
+    fn from(event: balances::Event<Runtime>) -> Self {
+        RuntimeEvent::Balances(event)
+    }
+}
+
+impl From<staking::Event<Runtime>> for RuntimeEvent {
+    fn from(event: staking::Event<Runtime>) -> Self {
+        RuntimeEvent::Staking(event)
+    }
+}
+
+impl From<governance::Event<Runtime>> for RuntimeEvent {
+    fn from(event: governance::Event<Runtime>) -> Self {
+        RuntimeEvent::Governance(event)
+    }
+}
+
+impl From<inheritance::Event<Runtime>> for RuntimeEvent {
+    fn from(event: inheritance::Event<Runtime>) -> Self {
+        RuntimeEvent::Inheritance(event)
+    }
+}
+
+impl From<treasury::Event<Runtime>> for RuntimeEvent {
+    fn from(event: treasury::Event<Runtime>) -> Self {
+        RuntimeEvent::Treasury(event)
+    }
+}
+
+impl From<council::Event<Runtime>> for RuntimeEvent {
+    fn from(event: council::Event<Runtime>) -> Self {
+        RuntimeEvent::Council(event)
+    }
+}
+
+impl From<escrow::Event<Runtime>> for RuntimeEvent {
+    fn from(event: escrow::Event<Runtime>) -> Self {
+        RuntimeEvent::Escrow(event)
+    }
+}
+
+impl From<payment::Event<Runtime>> for RuntimeEvent {
+    fn from(event: payment::Event<Runtime>) -> Self {
+        RuntimeEvent::Payment(event)
+    }
+}
+
+impl From<identity::Event<Runtime>> for RuntimeEvent {
+    fn from(event: identity::Event<Runtime>) -> Self {
+        RuntimeEvent::Identity(event)
+    }
+}
+
+impl From<budget::Event<Runtime>> for RuntimeEvent {
+    fn from(event: budget::Event<Runtime>) -> Self {
+        RuntimeEvent::Budget(event)
+    }
+}
+
+impl From<checkpoint::Event<Runtime>> for RuntimeEvent {
+    fn from(event: checkpoint::Event<Runtime>) -> Self {
+        RuntimeEvent::Checkpoint(event)
+    }
+}
+
+impl From<faucet::Event<Runtime>> for RuntimeEvent {
+    fn from(event: faucet::Event<Runtime>) -> Self {
+        RuntimeEvent::Faucet(event)
+    }
+}
+
+impl RuntimeEvent {
+    // Render the event as a sentence, showing accounts by label where known
+    pub fn describe(&self, labels: &impl labels::AccountLabels<AccountId>) -> String {
+        match self {
+            RuntimeEvent::System(event) => event.describe(labels),
+            RuntimeEvent::Balances(event) => event.describe(labels),
+            RuntimeEvent::Staking(event) => event.describe(labels),
+            RuntimeEvent::Governance(event) => event.describe(labels),
+            RuntimeEvent::Inheritance(event) => event.describe(labels),
+            RuntimeEvent::Treasury(event) => event.describe(labels),
+            RuntimeEvent::Council(event) => event.describe(labels),
+            RuntimeEvent::Escrow(event) => event.describe(labels),
+            RuntimeEvent::Payment(event) => event.describe(labels),
+            RuntimeEvent::Identity(event) => event.describe(labels),
+            RuntimeEvent::Budget(event) => event.describe(labels),
+            RuntimeEvent::Checkpoint(event) => event.describe(labels),
+            RuntimeEvent::Faucet(event) => event.describe(labels),
+        }
+    }
+}
+
+// Errors from any pallet, for callers that drive the runtime as a whole
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuntimeError {
+    System(system::SystemError),
+    Balances(balances::BalancesError),
+    Staking(staking::StakingError),
+    Governance(governance::GovernanceError),
+    Inheritance(inheritance::InheritanceError),
+    Treasury(treasury::TreasuryError),
+    Council(council::CouncilError),
+    Escrow(escrow::EscrowError),
+    Payment(payment::PaymentError),
+    Identity(identity::IdentityError),
+    Budget(budget::BudgetError),
+    Checkpoint(checkpoint::CheckpointError),
+    Faucet(faucet::FaucetError),
+}
+
+impl From<system::SystemError> for RuntimeError {
+    fn from(error: system::SystemError) -> Self {
+        RuntimeError::System(error)
+    }
+}
+
+impl From<balances::BalancesError> for RuntimeError {
+    fn from(error: balances::BalancesError) -> Self {
+        RuntimeError::Balances(error)
+    }
+}
+
+impl From<staking::StakingError> for RuntimeError {
+    fn from(error: staking::StakingError) -> Self {
+        RuntimeError::Staking(error)
+    }
+}
+
+impl From<governance::GovernanceError> for RuntimeError {
+    fn from(error: governance::GovernanceError) -> Self {
+        RuntimeError::Governance(error)
+    }
+}
+
+impl From<inheritance::InheritanceError> for RuntimeError {
+    fn from(error: inheritance::InheritanceError) -> Self {
+        RuntimeError::Inheritance(error)
+    }
+}
+
+impl From<treasury::TreasuryError> for RuntimeError {
+    fn from(error: treasury::TreasuryError) -> Self {
+        RuntimeError::Treasury(error)
+    }
+}
+
+impl From<council::CouncilError> for RuntimeError {
+    fn from(error: council::CouncilError) -> Self {
+        RuntimeError::Council(error)
+    }
+}
+
+impl From<escrow::EscrowError> for RuntimeError {
+    fn from(error: escrow::EscrowError) -> Self {
+        RuntimeError::Escrow(error)
+    }
+}
+
+impl From<payment::PaymentError> for RuntimeError {
+    fn from(error: payment::PaymentError) -> Self {
+        RuntimeError::Payment(error)
+    }
+}
+
+impl From<identity::IdentityError> for RuntimeError {
+    fn from(error: identity::IdentityError) -> Self {
+        RuntimeError::Identity(error)
+    }
+}
+
+impl From<budget::BudgetError> for RuntimeError {
+    fn from(error: budget::BudgetError) -> Self {
+        RuntimeError::Budget(error)
+    }
+}
+
+impl From<checkpoint::CheckpointError> for RuntimeError {
+    fn from(error: checkpoint::CheckpointError) -> Self {
+        RuntimeError::Checkpoint(error)
+    }
+}
+
+impl From<faucet::FaucetError> for RuntimeError {
+    fn from(error: faucet::FaucetError) -> Self {
+        RuntimeError::Faucet(error)
+    }
+}
+
+// Stable identifier of an error: the pallet's index in the runtime and the error's
+// index within that pallet. Only the outermost error is encoded, so a staking call
+// failing on a balances check reports the staking pallet's Balances variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ErrorCode {
+    pub pallet: u8,
+    pub error: u8,
+}
+
+// An entry in the runtime's error registry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    pub pallet: &'static str,
+    pub name: &'static str,
+    pub docs: &'static str,
+}
+
+// Pallets in index order; indices are stable, so new pallets go at the end
+const PALLET_ERRORS: &[(&str, &[support::ErrorMetadata])] = &[
+    ("System", <system::SystemError as PalletError>::ERRORS),
+    ("Balances", <balances::BalancesError as PalletError>::ERRORS),
+    ("Staking", <staking::StakingError as PalletError>::ERRORS),
+    (
+        "Governance",
+        <governance::GovernanceError as PalletError>::ERRORS,
+    ),
+    (
+        "Inheritance",
+        <inheritance::InheritanceError as PalletError>::ERRORS,
+    ),
+    ("Treasury", <treasury::TreasuryError as PalletError>::ERRORS),
+    ("Council", <council::CouncilError as PalletError>::ERRORS),
+    ("Escrow", <escrow::EscrowError as PalletError>::ERRORS),
+    ("Payment", <payment::PaymentError as PalletError>::ERRORS),
+    ("Identity", <identity::IdentityError as PalletError>::ERRORS),
+    ("Budget", <budget::BudgetError as PalletError>::ERRORS),
+    (
+        "Checkpoint",
+        <checkpoint::CheckpointError as PalletError>::ERRORS,
+    ),
+    ("Faucet", <faucet::FaucetError as PalletError>::ERRORS),
+];
+
+impl RuntimeError {
+    // Get the stable code for this error
+    pub fn code(&self) -> ErrorCode {
+        let (pallet, error) = match self {
+            RuntimeError::System(error) => (0, error.error_index()),
+            RuntimeError::Balances(error) => (1, error.error_index()),
+            RuntimeError::Staking(error) => (2, error.error_index()),
+            RuntimeError::Governance(error) => (3, error.error_index()),
+            RuntimeError::Inheritance(error) => (4, error.error_index()),
+            RuntimeError::Treasury(error) => (5, error.error_index()),
+            RuntimeError::Council(error) => (6, error.error_index()),
+            RuntimeError::Escrow(error) => (7, error.error_index()),
+            RuntimeError::Payment(error) => (8, error.error_index()),
+            RuntimeError::Identity(error) => (9, error.error_index()),
+            RuntimeError::Budget(error) => (10, error.error_index()),
+            RuntimeError::Checkpoint(error) => (11, error.error_index()),
+            RuntimeError::Faucet(error) => (12, error.error_index()),
+        };
+        ErrorCode { pallet, error }
+    }
+
+    // List every error the runtime can return, ordered by code
+    pub fn registry() -> Vec<ErrorInfo> {
+        PALLET_ERRORS
+            .iter()
+            .zip(0u8..)
+            .flat_map(|((pallet, errors), pallet_index)| {
+                errors.iter().map(move |metadata| ErrorInfo {
+                    code: ErrorCode {
+                        pallet: pallet_index,
+                        error: metadata.index,
+                    },
+                    pallet,
+                    name: metadata.name,
+                    docs: metadata.docs,
+                })
+            })
+            .collect()
+    }
+
+    // Look up the name and docs behind an error code
+    pub fn lookup(code: ErrorCode) -> Option<ErrorInfo> {
+        Self::registry().into_iter().find(|info| info.code == code)
+    }
+}
 
 // Implement specific System configuration for the runtime
 impl SystemConfig for Runtime {
     type AccountId = u64;
+    type BlockNumber = u32;
+    type Nonce = u32;
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeCall = RuntimeCall;
+    const TOKEN_DECIMALS: u8 = 2;
+    const TOKEN_SYMBOL: &'static str = "UNIT";
+}
+
+// Implement specific Balances configuration for the runtime
+impl BalancesConfig for Runtime {
+    type Balance = u64;
+    const EXISTENTIAL_DEPOSIT: u64 = 10;
 }
 
 // Implement specific Staking configuration for the runtime
 impl StakingConfig for Runtime {
-    type Balance = u64;
+    const ERA_LENGTH: u32 = 100;
+    const ERA_REWARD: u64 = 1000;
+    const MAX_VALIDATORS: u32 = 4;
+    const SIGNED_PHASE: u32 = 10;
+    const ROTATION_GRACE_PERIOD: u32 = 5;
+    const SOLUTION_DEPOSIT: u64 = 10;
+    const BONDING_DURATION: u32 = 20;
+    const FINDER_FEE: u32 = 100_000;
+    const VALIDATOR_COMMISSION: u32 = 100_000;
+    const MAX_NOMINATIONS: u32 = 16;
+    const MIN_VALIDATOR_BOND: u64 = 10;
+    const MAX_EXITS_PER_ERA: u32 = 1;
+    const MAX_UNLOCKS_PER_BLOCK: u32 = 16;
+    const VALIDATOR_METADATA_DEPOSIT: u64 = 5;
+    const MAX_PROVIDER_TAGS: u32 = 4;
+    const MAX_METADATA_TAG_LEN: usize = 32;
+    const DIVERSITY_TIE_BREAK: bool = true;
+    const SLASH_ACCOUNT: u64 = <Runtime as TreasuryConfig>::POT_ACCOUNT;
 }
 
 // Implement specific Governance configuration for the runtime
 impl GovernanceConfig for Runtime {
-    // No additional types needed at this level
+    const MAX_LOCALIZATIONS: usize = 8;
+    const MAX_LOCALIZED_DESCRIPTION_LEN: usize = 1024;
+    const LOCALIZATION_DEPOSIT: u64 = 10;
+    const VOTING_PERIOD: u32 = 10;
+    const QUORUM: u32 = 200_000;
+    const APPROVAL_THRESHOLD: u32 = 500_000;
+    const TIE_POLICY: governance::TiePolicy<u32> = governance::TiePolicy::RejectOnTie;
+    const CONVICTION_LOCK_PERIOD: u32 = 20;
+    const OFFENCE_BAR_SEVERITY: u32 = 100_000;
+    const OFFENCE_BAR_ERAS: u32 = 2;
+    const MAX_TAGS: usize = 5;
+    const MAX_TAG_LEN: usize = 32;
+    const ENACTMENT_DELAY: u32 = 5;
+    const MAX_PROPOSAL_CALLS: usize = 8;
+    const MAX_FINALIZATIONS_PER_BLOCK: u32 = 4;
+    const PARTICIPATION_DECAY: u32 = 900_000;
+    type ProposalHooks = ();
+}
+
+// Implement specific Inheritance configuration for the runtime
+impl InheritanceConfig for Runtime {
+    const CHALLENGE_PERIOD: u32 = 10;
+}
+
+// Implement specific Treasury configuration for the runtime
+impl TreasuryConfig for Runtime {
+    // "treasury" in ASCII, well clear of ordinary account ids
+    const POT_ACCOUNT: u64 = u64::from_be_bytes(*b"treasury");
+    const MAX_STAKING_SHARE: u32 = 500_000;
+}
+
+// Implement specific Council configuration for the runtime
+impl CouncilConfig for Runtime {
+    const MAX_MEMBERS: u32 = 7;
+}
+
+// Implement specific Escrow configuration for the runtime
+impl EscrowConfig for Runtime {
+    const DISPUTE_WINDOW: u32 = 20;
+}
+
+// Implement specific Payment configuration for the runtime
+impl PaymentConfig for Runtime {
+    const BASE_FEE: u64 = 1;
+    const FEE_PER_WEIGHT: u64 = 1;
+}
+
+// Implement specific Identity configuration for the runtime
+impl IdentityConfig for Runtime {
+    const IDENTITY_DEPOSIT: u64 = 10;
+    const MAX_DISPLAY_NAME_LEN: usize = 32;
+    const MAX_METADATA_LEN: usize = 256;
+}
+
+// Implement specific Budget configuration for the runtime
+impl BudgetConfig for Runtime {
+    const BUDGET_PERIOD: u32 = 100;
+}
+
+// Implement specific Checkpoint configuration for the runtime
+impl CheckpointConfig for Runtime {
+    const CHECKPOINT_INTERVAL: u32 = 100;
+}
+
+// Implement specific Faucet configuration for the runtime
+impl FaucetConfig for Runtime {
+    const FAUCET_AMOUNT: u64 = 100;
+    const FAUCET_COOLDOWN: u32 = 10;
+    const PARTICIPATION_WINDOW: u32 = 5;
+    const MIN_PARTICIPATION_SCORE: u32 = 250_000;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use governance::ProposalStatus;
+
+    #[test]
+    fn test_dispatch_routes_calls() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        let calls: Vec<(u64, RuntimeCall)> = vec![
+            (
+                alice,
+                balances::Call::Transfer {
+                    to: bob,
+                    amount: 300,
+                }
+                .into(),
+            ),
+            (alice, staking::Call::Stake { amount: 500 }.into()),
+            (bob, staking::Call::Stake { amount: 300 }.into()),
+            (
+                alice,
+                governance::Call::CreateProposal {
+                    description: "Raise the block size".to_string(),
+                }
+                .into(),
+            ),
+            (
+                alice,
+                governance::Call::Vote {
+                    proposal_id: 0,
+                    vote_type: true,
+                    conviction: governance::Conviction::None,
+                }
+                .into(),
+            ),
+            (
+                bob,
+                governance::Call::Vote {
+                    proposal_id: 0,
+                    vote_type: false,
+                    conviction: governance::Conviction::None,
+                }
+                .into(),
+            ),
+        ];
+        for (origin, call) in calls {
+            runtime.dispatch(origin, call).unwrap();
+        }
+        runtime
+            .dispatch_as(
+                Origin::Root,
+                governance::Call::FinalizeProposal { proposal_id: 0 }.into(),
+            )
+            .unwrap();
+
+        assert_eq!(runtime.balances.get_free_balance(alice), 200u64);
+        assert_eq!(runtime.staking.get_staked_balance(bob), 300u64);
+        assert_eq!(
+            runtime.system.events().last(),
+            Some(
+                &governance::Event::ProposalFinalized {
+                    proposal_id: 0,
+                    status: ProposalStatus::Approved,
+                }
+                .into()
+            )
+        );
+
+        // Pallet errors surface as runtime errors
+        let result = runtime.dispatch(bob, staking::Call::Unstake { amount: 301 }.into());
+        assert_eq!(
+            result,
+            Err(RuntimeError::Staking(
+                staking::StakingError::InsufficientStakedBalance
+            ))
+        );
+    }
+
+    #[test]
+    fn test_execute_block() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        let block = Block {
+            header: Header { number: 1 },
+            extrinsics: vec![
+                Extrinsic {
+                    caller: alice,
+                    nonce: 0,
+                    call: staking::Call::Stake { amount: 500 }.into(),
+                },
+                Extrinsic {
+                    caller: bob,
+                    nonce: 0,
+                    call: staking::Call::Stake { amount: 500 }.into(),
+                },
+                Extrinsic {
+                    caller: alice,
+                    nonce: 1,
+                    call: governance::Call::CreateProposal {
+                        description: "Fund the explorer".to_string(),
+                    }
+                    .into(),
+                },
+                Extrinsic {
+                    caller: alice,
+                    nonce: 2,
+                    call: governance::Call::Vote {
+                        proposal_id: 0,
+                        vote_type: true,
+                        conviction: governance::Conviction::None,
+                    }
+                    .into(),
+                },
+            ],
+        };
+
+        // Bob can't pay the fee for his stake, which doesn't stop the rest of the block
+        let results = runtime.execute_block(block).unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(
+            results[1],
+            Err(RuntimeError::Payment(payment::PaymentError::CannotPayFee))
+        );
+        assert_eq!(runtime.system.block_number(), 1);
+        assert_eq!(runtime.staking.get_staked_balance(alice), 500u64);
+
+        // Blocks must come in order
+        let skipped = Block {
+            header: Header { number: 3 },
+            extrinsics: Vec::new(),
+        };
+        assert_eq!(
+            runtime.execute_block(skipped),
+            Err(RuntimeError::System(
+                system::SystemError::InvalidBlockNumber
+            ))
+        );
+
+        // The proposal is finalized at the end of its voting period
+        let end = 1 + <Runtime as GovernanceConfig>::VOTING_PERIOD;
+        for number in 2..=end {
+            let block = Block {
+                header: Header { number },
+                extrinsics: Vec::new(),
+            };
+            runtime.execute_block(block).unwrap();
+        }
+        assert_eq!(
+            runtime.system.events(),
+            &[governance::Event::ProposalFinalized {
+                proposal_id: 0,
+                status: ProposalStatus::Approved,
+            }
+            .into()]
+        );
+    }
+
+    #[test]
+    fn test_extrinsics_cannot_be_replayed() {
+        let alice = 1u64;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        let extrinsic = Extrinsic {
+            caller: alice,
+            nonce: 0,
+            call: staking::Call::Stake { amount: 100 }.into(),
+        };
+        runtime.apply_extrinsic(extrinsic.clone()).unwrap();
+        assert_eq!(runtime.system.get_nonce(alice), 1);
+
+        let result = runtime.apply_extrinsic(extrinsic);
+        assert_eq!(
+            result,
+            Err(RuntimeError::System(system::SystemError::InvalidNonce))
+        );
+        assert_eq!(runtime.staking.get_staked_balance(alice), 100u64);
+
+        // A failed call still uses up its nonce
+        let failing = Extrinsic {
+            caller: alice,
+            nonce: 1,
+            call: staking::Call::Unstake { amount: 500 }.into(),
+        };
+        assert!(runtime.apply_extrinsic(failing).is_err());
+        assert_eq!(runtime.system.get_nonce(alice), 2);
+    }
+
+    #[test]
+    fn test_multisig_dispatches_once_approved() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut runtime = Runtime::new();
+        let multisig = runtime
+            .system
+            .create_multisig(vec![charlie, alice, bob], 2)
+            .unwrap();
+        assert_eq!(
+            runtime.system.multisig(&multisig).unwrap().owners,
+            vec![alice, bob, charlie]
+        );
+        // The id depends only on the owner set and threshold
+        assert_eq!(
+            runtime.system.create_multisig(vec![bob, alice, charlie], 2),
+            Err(system::SystemError::MultisigExists)
+        );
+        assert_eq!(
+            runtime.system.create_multisig(vec![alice, bob], 3),
+            Err(system::SystemError::InvalidThreshold)
+        );
+
+        let proposal: RuntimeCall = governance::Call::CreateProposal {
+            description: "Fund the meetup".to_string(),
+        }
+        .into();
+        let approve = |call: &RuntimeCall| -> RuntimeCall {
+            system::Call::ApproveAsMulti {
+                multisig,
+                call: Box::new(call.clone()),
+            }
+            .into()
+        };
+
+        runtime.dispatch(alice, approve(&proposal)).unwrap();
+        assert!(runtime.governance.get_proposal(0).is_none());
+        assert_eq!(
+            runtime.dispatch(alice, approve(&proposal)),
+            Err(system::SystemError::AlreadyApproved.into())
+        );
+        assert_eq!(
+            runtime.dispatch(4, approve(&proposal)),
+            Err(system::SystemError::NotOwner.into())
+        );
+
+        // The second approval reaches the threshold; the multisig is the creator
+        runtime.dispatch(bob, approve(&proposal)).unwrap();
+        assert!(runtime.system.events().contains(
+            &governance::Event::ProposalCreated {
+                proposal_id: 0,
+                creator: multisig,
+            }
+            .into()
+        ));
+        assert!(runtime.system.approvals(multisig, &proposal).is_empty());
+    }
+
+    #[test]
+    fn test_session_delegate_dispatches_authorized_calls_until_expiry() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        let transfer: RuntimeCall = balances::Call::Transfer {
+            to: charlie,
+            amount: 100,
+        }
+        .into();
+        let stake: RuntimeCall = staking::Call::Stake { amount: 10 }.into();
+        let as_alice = |call: &RuntimeCall| -> RuntimeCall {
+            system::Call::DispatchAsPrincipal {
+                principal: alice,
+                call: Box::new(call.clone()),
+            }
+            .into()
+        };
+
+        assert_eq!(
+            runtime.dispatch(bob, as_alice(&transfer)),
+            Err(system::SystemError::NoSession.into())
+        );
+        runtime
+            .dispatch(
+                alice,
+                system::Call::AuthorizeSession {
+                    delegate: bob,
+                    calls: vec![transfer.call_index()],
+                    expires: 2,
+                }
+                .into(),
+            )
+            .unwrap();
+        runtime.dispatch(bob, as_alice(&transfer)).unwrap();
+        assert_eq!(runtime.balances.get_free_balance(charlie), 100);
+        assert_eq!(
+            runtime.dispatch(bob, as_alice(&stake)),
+            Err(system::SystemError::CallNotAuthorized.into())
+        );
+
+        // The session ends at its expiry block, or earlier when revoked
+        for number in 1..=2 {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+        assert_eq!(
+            runtime.dispatch(bob, as_alice(&transfer)),
+            Err(system::SystemError::SessionExpired.into())
+        );
+        runtime
+            .dispatch(alice, system::Call::RevokeSession { delegate: bob }.into())
+            .unwrap();
+        assert!(runtime.system.session(&alice, &bob).is_none());
+    }
+
+    #[test]
+    fn test_privileged_calls_need_root_or_council() {
+        let (alice, bob) = (1u64, 2u64);
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(pot, 500).unwrap();
+        runtime
+            .dispatch(
+                alice,
+                governance::Call::CreateProposal {
+                    description: "Close early".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        let bad_origin = Err(system::SystemError::BadOrigin.into());
+        let finalize: RuntimeCall = governance::Call::FinalizeProposal { proposal_id: 0 }.into();
+        let spend: RuntimeCall = treasury::Call::ForceSpend {
+            beneficiary: bob,
+            amount: 200,
+        }
+        .into();
+        assert_eq!(runtime.dispatch(alice, finalize.clone()), bad_origin);
+        assert_eq!(runtime.dispatch(alice, spend.clone()), bad_origin);
+        // Ordinary calls still need an account to act for
+        assert_eq!(
+            runtime.dispatch_as(Origin::Root, staking::Call::Stake { amount: 1 }.into()),
+            bad_origin
+        );
+
+        runtime.dispatch_as(Origin::Root, finalize).unwrap();
+        assert_eq!(
+            runtime.governance.get_proposal_status(0),
+            Some(&ProposalStatus::Rejected)
+        );
+        runtime.dispatch_as(Origin::Council, spend).unwrap();
+        assert_eq!(runtime.balances.get_free_balance(bob), 200u64);
+        assert_eq!(
+            runtime.system.events().last(),
+            Some(
+                &treasury::Event::SpendForced {
+                    beneficiary: bob,
+                    amount: 200,
+                }
+                .into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_extrinsics_round_trip_through_their_versioned_encoding() {
+        let (alice, bob) = (1u64, 2u64);
+        let extrinsic = Extrinsic {
+            caller: alice,
+            nonce: 0,
+            call: balances::Call::Transfer {
+                to: bob,
+                amount: 10,
+            }
+            .into(),
+        };
+        let bytes = extrinsic.encode();
+        assert_eq!(bytes[0], support::EXTRINSIC_VERSION);
+
+        // Built now, submitted from the bytes later
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 100).unwrap();
+        runtime
+            .apply_extrinsic(Extrinsic::decode_all(&bytes).unwrap())
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(bob), 10u64);
+
+        let mut other_version = bytes.clone();
+        other_version[0] = 0;
+        assert_eq!(
+            Extrinsic::decode_all(&other_version),
+            Err(codec::CodecError::UnsupportedVersion(0))
+        );
+    }
+
+    #[test]
+    fn test_account_references_list_the_pallets_holding_them() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(bob, 1000).unwrap();
+        assert!(runtime.account_references(alice).is_empty());
+
+        runtime
+            .dispatch(bob, staking::Call::Stake { amount: 500 }.into())
+            .unwrap();
+        runtime
+            .dispatch(
+                alice,
+                staking::Call::Nominate {
+                    targets: vec![bob],
+                    amount: 100,
+                }
+                .into(),
+            )
+            .unwrap();
+        runtime
+            .dispatch(
+                alice,
+                governance::Call::CreateProposal {
+                    description: "Upgrade".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+        runtime
+            .dispatch(
+                alice,
+                identity::Call::SetIdentity {
+                    display_name: "alice".to_string(),
+                    metadata: String::new(),
+                }
+                .into(),
+            )
+            .unwrap();
+        assert_eq!(
+            runtime.account_references(alice),
+            vec!["Staking", "Governance", "Identity"]
+        );
+        assert_eq!(runtime.account_references(bob), vec!["Staking"]);
+
+        // Chilled funds are still unbonding, but a cancelled proposal is closed
+        runtime
+            .dispatch(alice, staking::Call::Chill.into())
+            .unwrap();
+        runtime
+            .dispatch(
+                alice,
+                governance::Call::CancelProposal { proposal_id: 0 }.into(),
+            )
+            .unwrap();
+        runtime
+            .dispatch(alice, identity::Call::ClearIdentity.into())
+            .unwrap();
+        assert_eq!(runtime.account_references(alice), vec!["Staking"]);
+    }
+
+    #[test]
+    fn test_referenced_accounts_cant_be_reaped() {
+        let (alice, bob, charlie, dave) = (1u64, 2u64, 3u64, 4u64);
+        let mut runtime = Runtime::new();
+        for who in [alice, charlie, dave] {
+            runtime.balances.set_balance(who, 5).unwrap();
+        }
+        let reap = |who| -> RuntimeCall { balances::Call::ReapAccount { who }.into() };
+        let referenced = Err(balances::BalancesError::StillReferenced.into());
+
+        // A session and a spending limit are state the account would leave behind
+        runtime
+            .dispatch(
+                alice,
+                system::Call::AuthorizeSession {
+                    delegate: bob,
+                    calls: Vec::new(),
+                    expires: 10,
+                }
+                .into(),
+            )
+            .unwrap();
+        runtime
+            .dispatch(charlie, budget::Call::SetLimit { limit: 5 }.into())
+            .unwrap();
+        assert_eq!(runtime.account_references(alice), vec!["System"]);
+        assert_eq!(runtime.account_references(bob), vec!["System"]);
+        assert_eq!(runtime.account_references(charlie), vec!["Budget"]);
+        assert_eq!(runtime.dispatch(dave, reap(alice)), referenced);
+        assert_eq!(runtime.dispatch(dave, reap(charlie)), referenced);
+        assert_eq!(runtime.balances.get_free_balance(alice), 5);
+
+        // Once the session is revoked, alice's dust can be burned
+        runtime
+            .dispatch(alice, system::Call::RevokeSession { delegate: bob }.into())
+            .unwrap();
+        runtime.dispatch(dave, reap(alice)).unwrap();
+        assert_eq!(runtime.balances.get_free_balance(alice), 0);
+        assert_eq!(runtime.balances.total_issuance(), 10);
+        assert_eq!(
+            runtime.system.events().last(),
+            Some(
+                &balances::Event::Reaped {
+                    who: alice,
+                    dust: 5
+                }
+                .into()
+            )
+        );
+
+        // Only dust is reaped, and a faucet claim is a reference too
+        runtime.balances.set_balance(dave, 50).unwrap();
+        assert_eq!(
+            runtime.dispatch(alice, reap(dave)),
+            Err(balances::BalancesError::NotDust.into())
+        );
+        runtime.dispatch(dave, faucet::Call::Claim.into()).unwrap();
+        assert_eq!(runtime.account_references(dave), vec!["Faucet"]);
+        assert_eq!(runtime.dispatch(alice, reap(dave)), referenced);
+    }
+
+    #[test]
+    fn test_set_code_upgrades_the_runtime() {
+        let mut runtime = Runtime::new();
+        let set_code = |code: &[u8]| -> RuntimeCall {
+            system::Call::SetCode {
+                code: code.to_vec(),
+            }
+            .into()
+        };
+        let bad_origin = Err(system::SystemError::BadOrigin.into());
+        assert_eq!(runtime.dispatch(1, set_code(b"v1")), bad_origin);
+        assert_eq!(
+            runtime.dispatch_as(Origin::Council, set_code(b"v1")),
+            bad_origin
+        );
+        assert_eq!(
+            runtime.dispatch_as(Origin::Root, set_code(b"")),
+            Err(system::SystemError::EmptyCode.into())
+        );
+        assert_eq!(runtime.system.spec_version(), 0);
+
+        runtime.dispatch_as(Origin::Root, set_code(b"v1")).unwrap();
+        assert_eq!(runtime.system.spec_version(), 1);
+        assert_eq!(runtime.system.code(), b"v1".to_vec());
+        assert_eq!(
+            runtime.system.events().last(),
+            Some(&system::Event::CodeUpdated { spec_version: 1 }.into())
+        );
+
+        // The code and version survive a state export
+        let restored = Runtime::import_state(&runtime.export_state()).unwrap();
+        assert_eq!(restored.system.spec_version(), 1);
+        assert_eq!(restored.system.code(), b"v1".to_vec());
+    }
+
+    #[test]
+    fn test_approved_proposals_are_enacted() {
+        let (alice, bob) = (1u64, 2u64);
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(pot, 500).unwrap();
+
+        let spend: RuntimeCall = treasury::Call::ForceSpend {
+            beneficiary: bob,
+            amount: 200,
+        }
+        .into();
+        // Calls round-trip through the codec, so they can be stored in proposals
+        assert_eq!(RuntimeCall::decode_all(&spend.encode()), Ok(spend.clone()));
+        let calls: Vec<RuntimeCall> = vec![
+            staking::Call::Stake { amount: 500 }.into(),
+            governance::Call::CreateProposalWithCall {
+                description: "Pay bob".to_string(),
+                call: Box::new(spend),
+            }
+            .into(),
+            // Enactment uses a Council origin, so a signed-only call fails
+            governance::Call::CreateProposalWithCall {
+                description: "Stake for nobody".to_string(),
+                call: Box::new(staking::Call::Stake { amount: 1 }.into()),
+            }
+            .into(),
+        ];
+        for call in calls {
+            runtime.dispatch(alice, call).unwrap();
+        }
+        for proposal_id in [0, 1] {
+            runtime
+                .dispatch(
+                    alice,
+                    governance::Call::Vote {
+                        proposal_id,
+                        vote_type: true,
+                        conviction: governance::Conviction::None,
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        // Approved at the end of the voting period, enacted ENACTMENT_DELAY later
+        let voting_end = <Runtime as GovernanceConfig>::VOTING_PERIOD;
+        let enactment = voting_end + <Runtime as GovernanceConfig>::ENACTMENT_DELAY;
+        for number in 1..enactment {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+        assert_eq!(runtime.balances.get_free_balance(bob), 0u64);
+        runtime
+            .execute_block(Block {
+                header: Header { number: enactment },
+                extrinsics: Vec::new(),
+            })
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(bob), 200u64);
+        assert_eq!(
+            runtime.system.events()[2..],
+            [
+                governance::Event::ProposalEnacted {
+                    proposal_id: 0,
+                    failed_call: None,
+                }
+                .into(),
+                governance::Event::ProposalEnacted {
+                    proposal_id: 1,
+                    failed_call: Some(0),
+                }
+                .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_omnibus_proposals_are_enacted_atomically() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(pot, 500).unwrap();
+
+        let spend = |beneficiary, amount| -> RuntimeCall {
+            treasury::Call::ForceSpend {
+                beneficiary,
+                amount,
+            }
+            .into()
+        };
+        let calls: Vec<RuntimeCall> = vec![
+            staking::Call::Stake { amount: 500 }.into(),
+            governance::Call::CreateProposalWithCalls {
+                description: "Pay bob and charlie".to_string(),
+                calls: vec![spend(bob, 100), spend(charlie, 100)],
+            }
+            .into(),
+            // The pot can't cover the second spend, so the first is rolled back too
+            governance::Call::CreateProposalWithCalls {
+                description: "Overspend".to_string(),
+                calls: vec![spend(bob, 200), spend(charlie, 1000)],
+            }
+            .into(),
+        ];
+        for call in calls {
+            runtime.dispatch(alice, call).unwrap();
+        }
+        assert_eq!(
+            runtime.dispatch(
+                alice,
+                governance::Call::CreateProposalWithCalls {
+                    description: "Nothing".to_string(),
+                    calls: Vec::new(),
+                }
+                .into(),
+            ),
+            Err(governance::GovernanceError::InvalidCallCount.into())
+        );
+        for proposal_id in [0, 1] {
+            runtime
+                .dispatch(
+                    alice,
+                    governance::Call::Vote {
+                        proposal_id,
+                        vote_type: true,
+                        conviction: governance::Conviction::None,
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        let voting_end = <Runtime as GovernanceConfig>::VOTING_PERIOD;
+        let enactment = voting_end + <Runtime as GovernanceConfig>::ENACTMENT_DELAY;
+        for number in 1..=enactment {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+        assert_eq!(runtime.balances.get_free_balance(bob), 100u64);
+        assert_eq!(runtime.balances.get_free_balance(charlie), 100u64);
+        assert_eq!(runtime.balances.get_free_balance(pot), 300u64);
+        assert_eq!(
+            runtime.system.events()[runtime.system.events().len() - 2..],
+            [
+                governance::Event::ProposalEnacted {
+                    proposal_id: 0,
+                    failed_call: None,
+                }
+                .into(),
+                governance::Event::ProposalEnacted {
+                    proposal_id: 1,
+                    failed_call: Some(1),
+                }
+                .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependent_proposals_wait_for_their_dependency_to_be_enacted() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(pot, 500).unwrap();
+
+        let spend = |beneficiary, amount| -> RuntimeCall {
+            treasury::Call::ForceSpend {
+                beneficiary,
+                amount,
+            }
+            .into()
+        };
+        let dependent = |depends_on, calls| -> RuntimeCall {
+            governance::Call::CreateDependentProposalWithCalls {
+                description: "Follow-up".to_string(),
+                depends_on,
+                calls,
+            }
+            .into()
+        };
+        let calls: Vec<RuntimeCall> = vec![
+            staking::Call::Stake { amount: 500 }.into(),
+            // More than the pot holds, so its enactment fails
+            governance::Call::CreateProposalWithCall {
+                description: "Overspend".to_string(),
+                call: Box::new(spend(bob, 1000)),
+            }
+            .into(),
+            dependent(0, vec![spend(charlie, 100)]),
+            governance::Call::CreateProposalWithCall {
+                description: "Pay bob".to_string(),
+                call: Box::new(spend(bob, 100)),
+            }
+            .into(),
+            dependent(2, vec![spend(charlie, 50)]),
+        ];
+        for call in calls {
+            runtime.dispatch(alice, call).unwrap();
+        }
+        for proposal_id in 0..4 {
+            runtime
+                .dispatch(
+                    alice,
+                    governance::Call::Vote {
+                        proposal_id,
+                        vote_type: true,
+                        conviction: governance::Conviction::None,
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        // Every proposal is due in the same block, but the dependents wait for the
+        // outcome of their dependency's calls
+        let voting_end = <Runtime as GovernanceConfig>::VOTING_PERIOD;
+        let enactment = voting_end + <Runtime as GovernanceConfig>::ENACTMENT_DELAY;
+        for number in 1..=enactment {
+            runtime
+                .execute_block(Block {
+                    header: Header { number },
+                    extrinsics: Vec::new(),
+                })
+                .unwrap();
+        }
+        assert_eq!(runtime.governance.get_enactment_outcome(0), Some(false));
+        assert_eq!(runtime.governance.get_enactment_outcome(1), None);
+        assert_eq!(runtime.governance.get_enactment_outcome(2), Some(true));
+        assert_eq!(runtime.governance.get_enactment_outcome(3), None);
+        assert_eq!(runtime.balances.get_free_balance(charlie), 0u64);
+
+        runtime
+            .execute_block(Block {
+                header: Header {
+                    number: enactment + 1,
+                },
+                extrinsics: Vec::new(),
+            })
+            .unwrap();
+        // The first dependent is dropped with its dependency's failure, the second
+        // enacted after its dependency paid bob
+        assert_eq!(runtime.governance.get_enactment_outcome(1), Some(false));
+        assert_eq!(runtime.governance.get_enactment_outcome(3), Some(true));
+        assert_eq!(runtime.balances.get_free_balance(bob), 100u64);
+        assert_eq!(runtime.balances.get_free_balance(charlie), 50u64);
+        assert_eq!(runtime.balances.get_free_balance(pot), 350u64);
+        let events = runtime.system.events();
+        assert!(events.contains(
+            &governance::Event::EnactmentCancelled {
+                proposal_id: 1,
+                dependency: 0,
+            }
+            .into()
+        ));
+        assert_eq!(
+            events.last(),
+            Some(
+                &governance::Event::ProposalEnacted {
+                    proposal_id: 3,
+                    failed_call: None,
+                }
+                .into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_offenders_are_barred_from_governance() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut runtime = Runtime::new();
+        for who in [alice, bob, charlie] {
+            runtime.balances.set_balance(who, 1000).unwrap();
+            runtime
+                .dispatch(who, staking::Call::Stake { amount: 500 }.into())
+                .unwrap();
+        }
+        let propose = |description: &str| -> RuntimeCall {
+            governance::Call::CreateProposal {
+                description: description.to_string(),
+            }
+            .into()
+        };
+        runtime.dispatch(charlie, propose("Upgrade")).unwrap();
+        let delegate = |to| -> RuntimeCall { governance::Call::Delegate { to }.into() };
+        runtime.dispatch(alice, delegate(charlie)).unwrap();
+
+        let report = |offender, severity| -> RuntimeCall {
+            staking::Call::ReportOffence {
+                reporter: bob,
+                offender,
+                severity,
+            }
+            .into()
+        };
+        // Anyone could claim an offence, so a signed report is refused
+        assert_eq!(
+            runtime.dispatch(bob, report(alice, 1_000_000)),
+            Err(system::SystemError::BadOrigin.into())
+        );
+        assert_eq!(runtime.staking.get_staked_balance(alice), 500);
+
+        // A minor offence is slashed but doesn't bar
+        runtime
+            .dispatch_as(Origin::Council, report(charlie, 50_000))
+            .unwrap();
+        runtime
+            .dispatch_as(Origin::Council, report(alice, 200_000))
+            .unwrap();
+        assert_eq!(runtime.governance.barred_until(&charlie), None);
+        assert_eq!(runtime.governance.barred_until(&alice), Some(3));
+
+        let barred = Err(governance::GovernanceError::ParticipationBarred.into());
+        assert_eq!(runtime.dispatch(alice, propose("Spare me")), barred);
+        let vote: RuntimeCall = governance::Call::Vote {
+            proposal_id: 0,
+            vote_type: false,
+            conviction: governance::Conviction::None,
+        }
+        .into();
+        assert_eq!(runtime.dispatch(alice, vote.clone()), barred);
+        assert_eq!(runtime.dispatch(alice, delegate(bob)), barred);
+
+        // Nor does the delegation alice set up before the offence add her stake
+        runtime.dispatch(charlie, vote).unwrap();
+        assert_eq!(
+            runtime.governance.get_proposal(0).unwrap().tally().1,
+            runtime.staking.get_staked_balance(charlie)
+        );
+        assert_eq!(runtime.governance.vote_carried_by(alice, 0), None);
+
+        // The bar covers the rest of era 0 and two more eras
+        for era_end in [100, 200] {
+            runtime.on_finalize(era_end);
+            assert_eq!(runtime.governance.barred_until(&alice), Some(3));
+        }
+        runtime.on_finalize(300);
+        assert_eq!(runtime.governance.barred_until(&alice), None);
+        assert!(runtime
+            .system
+            .events()
+            .contains(&governance::Event::ParticipationRestored { who: alice }.into()));
+        runtime.dispatch(alice, propose("Back again")).unwrap();
+    }
+
+    #[test]
+    fn test_export_and_import_state() {
+        let mut runtime = Runtime::new();
+        for who in 1..=3u64 {
+            runtime.balances.set_balance(who, 1_000).unwrap();
+        }
+        runtime.system.initialize(1).unwrap();
+        let calls: [(AccountId, RuntimeCall); 7] = [
+            (1, staking::Call::Stake { amount: 500 }.into()),
+            (2, staking::Call::Stake { amount: 300 }.into()),
+            (2, staking::Call::Unbond { amount: 100 }.into()),
+            (
+                1,
+                governance::Call::CreateProposal {
+                    description: "Raise the era reward".to_string(),
+                }
+                .into(),
+            ),
+            (
+                1,
+                governance::Call::SetLocalizedDescription {
+                    proposal_id: 0,
+                    lang: "fr".to_string(),
+                    description: "Augmenter la récompense".to_string(),
+                }
+                .into(),
+            ),
+            (
+                2,
+                governance::Call::Vote {
+                    proposal_id: 0,
+                    vote_type: true,
+                    conviction: governance::Conviction::Locked3x,
+                }
+                .into(),
+            ),
+            (
+                3,
+                inheritance::Call::SetHeir {
+                    beneficiary: 1,
+                    timeout: 50,
+                }
+                .into(),
+            ),
+        ];
+        for (caller, call) in calls {
+            runtime.dispatch(caller, call).unwrap();
+        }
+        runtime.system.take_events();
+
+        let bytes = runtime.export_state();
+        let restored = Runtime::import_state(&bytes).unwrap();
+        assert_eq!(restored, runtime);
+        // Equal state always gives equal bytes, whatever order the maps iterate in
+        assert_eq!(restored.export_state(), bytes);
+
+        assert_eq!(
+            Runtime::import_state(&bytes[..bytes.len() - 1]),
+            Err(codec::CodecError::UnexpectedEnd)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            Runtime::import_state(&trailing),
+            Err(codec::CodecError::TrailingBytes)
+        );
+        let mut future = bytes;
+        future[0] = STATE_VERSION + 1;
+        assert_eq!(
+            Runtime::import_state(&future),
+            Err(codec::CodecError::UnsupportedVersion(STATE_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn test_runtime_recovers_its_state_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("runtime-{}.journal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1_000).unwrap();
+        runtime.balances.set_balance(bob, 1_000).unwrap();
+        let mut runtime = runtime.with_journal(storage::Journal::open(&path).unwrap().0);
+
+        // Each in its own block, with the caller's nonce
+        let calls: [(AccountId, _, RuntimeCall); 4] = [
+            (alice, 0, staking::Call::Stake { amount: 500 }.into()),
+            (bob, 0, staking::Call::Stake { amount: 300 }.into()),
+            (
+                alice,
+                1,
+                governance::Call::CreateProposal {
+                    description: "Fund the explorer".to_string(),
+                }
+                .into(),
+            ),
+            (
+                bob,
+                1,
+                governance::Call::Vote {
+                    proposal_id: 0,
+                    vote_type: true,
+                    conviction: governance::Conviction::Locked2x,
+                }
+                .into(),
+            ),
+        ];
+        for ((caller, nonce, call), number) in calls.into_iter().zip(1..) {
+            let block = Block {
+                header: Header { number },
+                extrinsics: vec![Extrinsic {
+                    caller,
+                    nonce,
+                    call,
+                }],
+            };
+            assert!(runtime.execute_block(block).unwrap()[0].is_ok());
+        }
+        let root = runtime.state_root();
+        // Kept in memory only, to check the recovered chain against
+        let mut expected = Runtime::import_state(&runtime.export_state()).unwrap();
+        drop(runtime);
+
+        // Restarting from the log recovers every pallet's state as of block 4, and
+        // compacts the log down to that block's frame
+        let mut recovered = Runtime::open(&path).unwrap();
+        assert_eq!(recovered.state_root(), root);
+        assert_eq!(recovered.system.block_number(), 4);
+        assert_eq!(recovered.staking.get_staked_balance(bob), 300);
+        let compacted = std::fs::read(&path).unwrap();
+
+        // The recovered chain goes on committing blocks
+        let block = Block {
+            header: Header { number: 5 },
+            extrinsics: vec![Extrinsic {
+                caller: alice,
+                nonce: 2,
+                call: balances::Call::Transfer {
+                    to: bob,
+                    amount: 10,
+                }
+                .into(),
+            }],
+        };
+        expected.execute_block(block.clone()).unwrap();
+        assert!(recovered.execute_block(block).unwrap()[0].is_ok());
+        assert_eq!(recovered.state_root(), expected.state_root());
+
+        // A crash while block 5 is being written loses that block and nothing before
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..compacted.len() + 9]).unwrap();
+        let restarted = Runtime::open(&path).unwrap();
+        assert_eq!(restarted.state_root(), root);
+        assert_eq!(std::fs::read(&path).unwrap(), compacted);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_error_codes_are_stable() {
+        let mut runtime = Runtime::new();
+        let error = runtime
+            .dispatch(1, staking::Call::Unstake { amount: 1 }.into())
+            .unwrap_err();
+
+        // These codes are relied on by clients, so they must never change
+        let code = error.code();
+        assert_eq!(
+            code,
+            ErrorCode {
+                pallet: 2,
+                error: 0
+            }
+        );
+        let info = RuntimeError::lookup(code).unwrap();
+        assert_eq!(
+            (info.pallet, info.name),
+            ("Staking", "InsufficientStakedBalance")
+        );
+        assert_eq!(
+            RuntimeError::from(governance::GovernanceError::Balances(
+                balances::BalancesError::InsufficientBalance
+            ))
+            .code(),
+            ErrorCode {
+                pallet: 3,
+                error: 18
+            }
+        );
+
+        // Every code is listed once, and each table is in index order
+        let registry = RuntimeError::registry();
+        let codes: std::collections::BTreeSet<_> = registry.iter().map(|info| info.code).collect();
+        assert_eq!(codes.len(), registry.len());
+        for (_, errors) in PALLET_ERRORS {
+            assert!(errors
+                .iter()
+                .zip(0u8..)
+                .all(|(error, index)| error.index == index));
+        }
+        assert_eq!(
+            RuntimeError::lookup(ErrorCode {
+                pallet: 13,
+                error: 0
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_storage_report_tracks_growth() {
+        let mut runtime = Runtime::new();
+        let usage = |runtime: &Runtime, pallet: &str| {
+            runtime
+                .storage_report()
+                .into_iter()
+                .find(|(name, _)| *name == pallet)
+                .map(|(_, usage)| usage)
+                .unwrap()
+        };
+        let before = usage(&runtime, "Governance");
+
+        for who in 1..=5u64 {
+            runtime.balances.set_balance(who, 100).unwrap();
+            runtime
+                .dispatch(
+                    who,
+                    governance::Call::CreateProposal {
+                        description: "x".repeat(100),
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        // Five more proposals, with their text counted in the byte estimate
+        let after = usage(&runtime, "Governance");
+        assert_eq!(after.items, before.items + 5);
+        assert!(after.bytes >= before.bytes + 500);
+        assert_eq!(usage(&runtime, "Balances").items, 1 + 5);
+        // Only the era counters, no bonds
+        assert_eq!(usage(&runtime, "Staking").items, 1);
+    }
 }
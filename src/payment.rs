@@ -0,0 +1,350 @@
+use crate::balances::BalancesPallet;
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::StorageMap;
+pub use crate::support::CallIndex;
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use crate::treasury::TreasuryConfig;
+use num::traits::Zero;
+
+// Transaction fees, similar to `pallet_transaction_payment`. Every signed extrinsic
+// pays BASE_FEE plus FEE_PER_WEIGHT for each unit of its call's weight before the call
+// runs, and the fee goes to the treasury pot. Governance can discount the fee of
+// specific calls, such as claims or feeds that should stay free to submit.
+pub trait PaymentConfig: TreasuryConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Paid by every extrinsic, whatever its call
+    const BASE_FEE: Self::Balance;
+    // Paid for each unit of the call's weight
+    const FEE_PER_WEIGHT: Self::Balance;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaymentError {
+    // The sender's spendable balance doesn't cover the fee
+    CannotPayFee,
+    Overflow,
+    // Discounts are in parts per million, so at most 1_000_000
+    InvalidDiscount,
+}
+
+pallet_error!(PaymentError {
+    0 => CannotPayFee: "The sender's spendable balance doesn't cover the fee",
+    1 => Overflow: "An arithmetic operation overflowed",
+    2 => InvalidDiscount: "Discounts are in parts per million, so at most 1_000_000",
+});
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: PaymentConfig> {
+    FeePaid { who: T::AccountId, fee: T::Balance },
+    // A discount of zero means the call pays the full fee again
+    FeeDiscountSet { call: CallIndex, discount: u32 },
+}
+
+impl<T: PaymentConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice paid a fee of 0.0003 UNIT"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::FeePaid { who, fee } => format!(
+                "{} paid a fee of {}",
+                display_account(labels, who),
+                format_balance::<T>(*fee)
+            ),
+            Event::FeeDiscountSet {
+                call: (pallet, call),
+                discount,
+            } => format!(
+                "fees for call {}/{} are now discounted by {} parts per million",
+                pallet, call, discount
+            ),
+        }
+    }
+}
+
+// Discount on every call's fee in parts per million: 1_000_000 exempts the call
+pub const FULL_DISCOUNT: u32 = 1_000_000;
+
+// Calls that only a Root or Council origin may submit to the payment pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+    // Set the discount on a call's fee, or clear it with a discount of zero
+    SetFeeDiscount { call: CallIndex, discount: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaymentPallet<T: PaymentConfig> {
+    // Fee discount by call, in parts per million
+    discounts: StorageMap<CallIndex, u32>,
+    _config: std::marker::PhantomData<T>,
+}
+
+impl<T: PaymentConfig> Default for PaymentPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PaymentConfig> PaymentPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            discounts: StorageMap::new(),
+            _config: std::marker::PhantomData,
+        }
+    }
+
+    // Route a call to the matching method. The runtime only routes these calls for
+    // a Root or Council origin.
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        call: Call,
+    ) -> Result<(), PaymentError> {
+        match call {
+            Call::SetFeeDiscount { call, discount } => {
+                self.set_fee_discount(system, call, discount)
+            }
+        }
+    }
+
+    // Set the discount on a call's fee, in parts per million
+    pub fn set_fee_discount(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        call: CallIndex,
+        discount: u32,
+    ) -> Result<(), PaymentError> {
+        if discount > FULL_DISCOUNT {
+            return Err(PaymentError::InvalidDiscount);
+        }
+        if discount == 0 {
+            self.discounts.remove(&call);
+        } else {
+            self.discounts.insert(call, discount);
+        }
+        system.deposit_event(Event::FeeDiscountSet { call, discount });
+
+        Ok(())
+    }
+
+    // Get the discount on a call's fee, in parts per million
+    pub fn fee_discount(&self, call: CallIndex) -> u32 {
+        self.discounts.get(&call).copied().unwrap_or(0)
+    }
+
+    // The fee for a call of the given weight, after its discount
+    pub fn fee_for(&self, call: CallIndex, weight: Weight) -> Result<T::Balance, PaymentError> {
+        let fee: u128 = compute_fee::<T>(weight)?.into();
+        let discount = fee
+            .checked_mul(u128::from(self.fee_discount(call)))
+            .and_then(|scaled| scaled.checked_div(u128::from(FULL_DISCOUNT)))
+            .ok_or(PaymentError::Overflow)?;
+        T::Balance::try_from(fee.saturating_sub(discount)).map_err(|_| PaymentError::Overflow)
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.discounts.storage_usage()
+    }
+}
+
+// The full fee for a call of the given weight
+pub fn compute_fee<T: PaymentConfig>(weight: Weight) -> Result<T::Balance, PaymentError> {
+    let per_weight: u128 = T::FEE_PER_WEIGHT.into();
+    let base: u128 = T::BASE_FEE.into();
+    let fee = per_weight
+        .checked_mul(u128::from(weight))
+        .and_then(|fee| fee.checked_add(base))
+        .ok_or(PaymentError::Overflow)?;
+    T::Balance::try_from(fee).map_err(|_| PaymentError::Overflow)
+}
+
+// Move a fee, as given by `PaymentPallet::fee_for`, from `who` to the treasury pot
+pub fn charge_fee<T: PaymentConfig>(
+    system: &mut SystemPallet<T>,
+    balances: &mut BalancesPallet<T>,
+    who: T::AccountId,
+    fee: T::Balance,
+) -> Result<T::Balance, PaymentError> {
+    if fee.is_zero() {
+        return Ok(fee);
+    }
+
+    balances
+        .transfer(system, who.clone(), T::POT_ACCOUNT, fee)
+        .map_err(|_| PaymentError::CannotPayFee)?;
+    system.deposit_event(Event::FeePaid { who, fee });
+
+    Ok(fee)
+}
+
+impl GetWeight for Call {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::SetFeeDiscount { .. } => 1,
+        }
+    }
+}
+
+impl Encode for Call {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::SetFeeDiscount { call, discount } => {
+                0u8.encode_to(dest);
+                call.encode_to(dest);
+                discount.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl Decode for Call {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::SetFeeDiscount {
+                call: Decode::decode(input)?,
+                discount: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: PaymentConfig> Encode for PaymentPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.discounts.encode_to(dest);
+    }
+}
+
+impl<T: PaymentConfig> Decode for PaymentPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            discounts: Decode::decode(input)?,
+            _config: std::marker::PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances, faucet, staking, Extrinsic, GetWeight, Origin, Runtime, RuntimeCall, RuntimeError,
+    };
+
+    #[test]
+    fn test_fees_are_charged_before_dispatch() {
+        let (alice, bob) = (1u64, 2u64);
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(bob, 2).unwrap();
+
+        let call: crate::RuntimeCall = staking::Call::Stake { amount: 500 }.into();
+        let fee = compute_fee::<Runtime>(call.weight()).unwrap();
+        // BASE_FEE plus a weight of 3 at FEE_PER_WEIGHT
+        assert_eq!(fee, 4);
+        runtime
+            .apply_extrinsic(Extrinsic {
+                caller: alice,
+                nonce: 0,
+                call: call.clone(),
+            })
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(alice), 1000 - 500 - fee);
+        assert_eq!(runtime.balances.get_free_balance(pot), fee);
+        assert_eq!(
+            runtime.system.events()[1],
+            Event::FeePaid { who: alice, fee }.into()
+        );
+
+        // The fee is kept even if the call then fails
+        let failing = Extrinsic {
+            caller: alice,
+            nonce: 1,
+            call: staking::Call::Unstake { amount: 1000 }.into(),
+        };
+        assert!(runtime.apply_extrinsic(failing).is_err());
+        assert_eq!(runtime.balances.get_free_balance(pot), fee + fee);
+
+        // bob can't afford the fee, so the call is rejected and the nonce kept
+        let transfer = Extrinsic {
+            caller: bob,
+            nonce: 0,
+            call: balances::Call::Transfer {
+                to: alice,
+                amount: 1,
+            }
+            .into(),
+        };
+        assert_eq!(
+            runtime.apply_extrinsic(transfer),
+            Err(RuntimeError::Payment(PaymentError::CannotPayFee))
+        );
+        assert_eq!(runtime.balances.get_free_balance(bob), 2u64);
+        assert_eq!(runtime.system.get_nonce(bob), 0);
+    }
+
+    #[test]
+    fn test_governance_can_discount_call_fees() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+
+        let claim: RuntimeCall = faucet::Call::Claim.into();
+        let exempt: RuntimeCall = Call::SetFeeDiscount {
+            call: claim.call_index(),
+            discount: FULL_DISCOUNT,
+        }
+        .into();
+        assert_eq!(
+            runtime.dispatch(alice, exempt.clone()),
+            Err(crate::system::SystemError::BadOrigin.into())
+        );
+        runtime.dispatch_as(Origin::Council, exempt).unwrap();
+
+        // bob has nothing, but claiming is free
+        runtime
+            .apply_extrinsic(Extrinsic {
+                caller: bob,
+                nonce: 0,
+                call: claim,
+            })
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(bob), 100u64);
+
+        // Half off a transfer's fee of 3, with the discount rounded down
+        let transfer: RuntimeCall = balances::Call::Transfer {
+            to: bob,
+            amount: 10,
+        }
+        .into();
+        runtime
+            .dispatch_as(
+                Origin::Council,
+                Call::SetFeeDiscount {
+                    call: transfer.call_index(),
+                    discount: 500_000,
+                }
+                .into(),
+            )
+            .unwrap();
+        assert_eq!(
+            runtime
+                .payment
+                .fee_for(transfer.call_index(), transfer.weight()),
+            Ok(2)
+        );
+        assert_eq!(
+            runtime.dispatch_as(
+                Origin::Council,
+                Call::SetFeeDiscount {
+                    call: transfer.call_index(),
+                    discount: FULL_DISCOUNT + 1,
+                }
+                .into(),
+            ),
+            Err(RuntimeError::Payment(PaymentError::InvalidDiscount))
+        );
+    }
+}
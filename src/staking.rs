@@ -1,143 +1,2748 @@
-use crate::system::SystemConfig;
-use num::traits::{CheckedAdd, CheckedSub, Zero};
-use std::collections::HashMap;
+use crate::balances::{BalancesConfig, BalancesError, BalancesPallet, HoldReason};
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::{StorageMap, StorageValue};
+use crate::support::{pallet_error, weight_of, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::{CheckedAdd, CheckedSub, One, Zero};
+use std::collections::{BTreeMap, BTreeSet};
 
-pub trait StakingConfig: SystemConfig {
-    // Define the Balance type with ability to perform checked arithmetic operations
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy + PartialOrd;
+// Staking keeps bonded funds on hold in the balances pallet
+pub trait StakingConfig: BalancesConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Number of blocks in an era
+    const ERA_LENGTH: Self::BlockNumber;
+    // Amount minted at the end of each era and shared among stakers by stake
+    const ERA_REWARD: Self::Balance;
+    // Most validators an election can pick
+    const MAX_VALIDATORS: u32;
+    // Blocks before the election during which solutions are accepted
+    const SIGNED_PHASE: Self::BlockNumber;
+    // Blocks between the election and the end of the era, during which the next
+    // validator set is queued so nodes can prepare for the handoff
+    const ROTATION_GRACE_PERIOD: Self::BlockNumber;
+    // Held from each solution submitter; slashed if the solution turns out to be invalid
+    const SOLUTION_DEPOSIT: Self::Balance;
+    // Blocks unbonded funds stay locked up before they can be withdrawn
+    const BONDING_DURATION: Self::BlockNumber;
+    // Share of a slash paid to whoever reported the offence, in parts per million
+    const FINDER_FEE: u32;
+    // Share of a validator's era reward it keeps before splitting the rest with its
+    // nominators by backing, in parts per million
+    const VALIDATOR_COMMISSION: u32;
+    // Most validators a nominator can back
+    const MAX_NOMINATIONS: u32;
+    // Least own stake an account needs to stand for election
+    const MIN_VALIDATOR_BOND: Self::Balance;
+    // Most active validators that may drop below MIN_VALIDATOR_BOND in one era; later
+    // unbonds wait in the exit queue
+    const MAX_EXITS_PER_ERA: u32;
+    // Most expired stake locks released at the start of a block; the rest wait for
+    // the next blocks
+    const MAX_UNLOCKS_PER_BLOCK: u32;
+    // Deposit held for a validator's declared region and again for each provider tag
+    const VALIDATOR_METADATA_DEPOSIT: Self::Balance;
+    // Most provider tags a validator can declare
+    const MAX_PROVIDER_TAGS: u32;
+    // Maximum length in bytes of a region or provider tag
+    const MAX_METADATA_TAG_LEN: usize;
+    // Whether the fallback election breaks stake ties by diversity of region and provider
+    const DIVERSITY_TIE_BREAK: bool;
+    // Account credited with what a slash takes beyond the finder's fee
+    const SLASH_ACCOUNT: Self::AccountId;
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StakingError {
+    InsufficientStakedBalance,
+    // Solutions are only accepted during the signed phase
+    ElectionClosed,
+    AlreadySubmitted,
+    TooManyWinners,
+    // Severity is a share in parts per million, so at most 1_000_000
+    InvalidSeverity,
+    SelfReport,
+    // Part of the stake is locked, e.g. by a conviction vote
+    StakeLocked,
+    // Targets are between one and MAX_NOMINATIONS distinct accounts other than the nominator
+    InvalidTargets,
+    NominationTooSmall,
+    NotNominating,
+    // This era's validator exits are used up; unbond to join the exit queue
+    ExitCapReached,
+    AlreadyInExitQueue,
+    // Regions and provider tags are non-empty and at most MAX_METADATA_TAG_LEN bytes
+    InvalidMetadataTag,
+    TooManyProviderTags,
+    NoValidatorMetadata,
+    Overflow,
+    Underflow,
+    Balances(BalancesError),
+}
+
+pallet_error!(StakingError {
+    0 => InsufficientStakedBalance: "Less is staked than requested",
+    1 => Overflow: "An arithmetic operation overflowed",
+    2 => Underflow: "An arithmetic operation underflowed",
+    3 => Balances(_): "A balances operation failed",
+    4 => ElectionClosed: "Solutions are only accepted during the signed phase",
+    5 => AlreadySubmitted: "The account already submitted a solution this era",
+    6 => TooManyWinners: "The solution elects more than MAX_VALIDATORS",
+    7 => InvalidSeverity: "Severity is a share in parts per million, so at most 1_000_000",
+    8 => SelfReport: "Accounts cannot report their own offences",
+    9 => StakeLocked: "Part of the stake is locked, e.g. by a conviction vote",
+    10 => InvalidTargets: "Targets are between one and MAX_NOMINATIONS distinct accounts other than the nominator",
+    11 => NominationTooSmall: "The amount must give every target a non-zero share",
+    12 => NotNominating: "The account is not nominating anyone",
+    13 => ExitCapReached: "This era's validator exits are used up; unbond to join the exit queue",
+    14 => AlreadyInExitQueue: "The validator is already waiting in the exit queue",
+    15 => InvalidMetadataTag: "Regions and provider tags are non-empty and at most MAX_METADATA_TAG_LEN bytes",
+    16 => TooManyProviderTags: "The validator declares more than MAX_PROVIDER_TAGS provider tags",
+    17 => NoValidatorMetadata: "The account has not declared any validator metadata",
+});
+
+impl From<BalancesError> for StakingError {
+    fn from(error: BalancesError) -> Self {
+        StakingError::Balances(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: StakingConfig> {
+    Staked {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    Unstaked {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    Unbonded {
+        who: T::AccountId,
+        amount: T::Balance,
+        release_at: T::BlockNumber,
+    },
+    Withdrawn {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    // The unbond goes through once earlier exits have been let out
+    ExitQueued {
+        who: T::AccountId,
+        amount: T::Balance,
+        position: u32,
+    },
+    StakeLocked {
+        who: T::AccountId,
+        amount: T::Balance,
+        until: T::BlockNumber,
+    },
+    StakeUnlocked {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    Slashed {
+        offender: T::AccountId,
+        amount: T::Balance,
+        reporter: T::AccountId,
+        finder_fee: T::Balance,
+    },
+    Nominated {
+        nominator: T::AccountId,
+        targets: Vec<T::AccountId>,
+        amount: T::Balance,
+    },
+    Chilled {
+        nominator: T::AccountId,
+        amount: T::Balance,
+        release_at: T::BlockNumber,
+    },
+    // Nominators lose the same share of their backing as the validator they back
+    NominatorSlashed {
+        nominator: T::AccountId,
+        validator: T::AccountId,
+        amount: T::Balance,
+    },
+    Rewarded {
+        era: u32,
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    EraEnded {
+        era: u32,
+        total_reward: T::Balance,
+    },
+    SolutionSubmitted {
+        who: T::AccountId,
+        score: u128,
+    },
+    SolutionRejected {
+        who: T::AccountId,
+    },
+    ValidatorsElected {
+        era: u32,
+        validators: Vec<T::AccountId>,
+        compute: ElectionCompute,
+    },
+    ValidatorMetadataSet {
+        who: T::AccountId,
+        region: String,
+        providers: Vec<String>,
+    },
+    ValidatorMetadataCleared {
+        who: T::AccountId,
+    },
+}
+
+impl<T: StakingConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice staked 4 UNIT"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::Staked { who, amount } => format!(
+                "{} staked {}",
+                display_account(labels, who),
+                format_balance::<T>(*amount)
+            ),
+            Event::Unstaked { who, amount } => format!(
+                "{} unstaked {}",
+                display_account(labels, who),
+                format_balance::<T>(*amount)
+            ),
+            Event::Unbonded {
+                who,
+                amount,
+                release_at,
+            } => format!(
+                "{} unbonded {}, withdrawable at block {:?}",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                release_at
+            ),
+            Event::Withdrawn { who, amount } => format!(
+                "{} withdrew {} of unbonded stake",
+                display_account(labels, who),
+                format_balance::<T>(*amount)
+            ),
+            Event::ExitQueued {
+                who,
+                amount,
+                position,
+            } => format!(
+                "{} queued to unbond {} as validator exit {} in line",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                position
+            ),
+            Event::StakeLocked { who, amount, until } => format!(
+                "{} had {} of stake locked until block {:?}",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                until
+            ),
+            Event::StakeUnlocked { who, amount } => format!(
+                "{} had a lock on {} of stake released",
+                display_account(labels, who),
+                format_balance::<T>(*amount)
+            ),
+            Event::Slashed {
+                offender,
+                amount,
+                reporter,
+                finder_fee,
+            } => format!(
+                "{} was slashed {}, paying {} to {}",
+                display_account(labels, offender),
+                format_balance::<T>(*amount),
+                format_balance::<T>(*finder_fee),
+                display_account(labels, reporter)
+            ),
+            Event::Nominated {
+                nominator,
+                targets,
+                amount,
+            } => format!(
+                "{} nominated {} with {}",
+                display_account(labels, nominator),
+                targets
+                    .iter()
+                    .map(|who| display_account(labels, who))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                format_balance::<T>(*amount)
+            ),
+            Event::Chilled {
+                nominator,
+                amount,
+                release_at,
+            } => format!(
+                "{} stopped nominating, unbonding {} until block {:?}",
+                display_account(labels, nominator),
+                format_balance::<T>(*amount),
+                release_at
+            ),
+            Event::NominatorSlashed {
+                nominator,
+                validator,
+                amount,
+            } => format!(
+                "{} was slashed {} for backing {}",
+                display_account(labels, nominator),
+                format_balance::<T>(*amount),
+                display_account(labels, validator)
+            ),
+            Event::Rewarded { era, who, amount } => format!(
+                "{} earned {} in era {}",
+                display_account(labels, who),
+                format_balance::<T>(*amount),
+                era
+            ),
+            Event::EraEnded { era, total_reward } => format!(
+                "era {} ended, paying out {}",
+                era,
+                format_balance::<T>(*total_reward)
+            ),
+            Event::SolutionSubmitted { who, score } => format!(
+                "{} submitted an election solution scoring {}",
+                display_account(labels, who),
+                score
+            ),
+            Event::SolutionRejected { who } => format!(
+                "{}'s election solution was rejected",
+                display_account(labels, who)
+            ),
+            Event::ValidatorsElected {
+                era,
+                validators,
+                compute,
+            } => format!(
+                "{} elected as validators for era {} by {:?}",
+                validators
+                    .iter()
+                    .map(|who| display_account(labels, who))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                era,
+                compute
+            ),
+            Event::ValidatorMetadataSet {
+                who,
+                region,
+                providers,
+            } => format!(
+                "{} runs in {} on {}",
+                display_account(labels, who),
+                region,
+                if providers.is_empty() {
+                    "undeclared providers".to_string()
+                } else {
+                    providers.join(", ")
+                }
+            ),
+            Event::ValidatorMetadataCleared { who } => format!(
+                "{} cleared its validator metadata",
+                display_account(labels, who)
+            ),
+        }
+    }
+}
+
+// Calls an account can submit to the staking pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: StakingConfig> {
+    Stake {
+        amount: T::Balance,
+    },
+    Unstake {
+        amount: T::Balance,
+    },
+    Unbond {
+        amount: T::Balance,
+    },
+    WithdrawUnbonded,
+    // Slash a proven offender; needs a Root or Council origin, which stands in for
+    // checking the evidence. `reporter` receives the finder's fee.
+    ReportOffence {
+        reporter: T::AccountId,
+        offender: T::AccountId,
+        severity: u32,
+    },
+    SubmitSolution {
+        winners: Vec<T::AccountId>,
+        score: u128,
+    },
+    Nominate {
+        targets: Vec<T::AccountId>,
+        amount: T::Balance,
+    },
+    Chill,
+    // Declare the caller's region and infrastructure providers, replacing any earlier
+    // declaration; a deposit is held per entry
+    SetValidatorMetadata {
+        region: String,
+        providers: Vec<String>,
+    },
+    ClearValidatorMetadata,
+}
+
+// How the validator set for an era was chosen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElectionCompute {
+    // The best valid solution submitted during the signed phase
+    Signed,
+    // Computed on-chain because no valid solution was submitted
+    Fallback,
+}
+
+// An election solution waiting to be checked at the end of the era
+#[derive(Clone, Debug, PartialEq)]
+pub struct Submission<T: StakingConfig> {
+    pub who: T::AccountId,
+    pub winners: Vec<T::AccountId>,
+    // Claimed total stake backing the winners
+    pub score: u128,
+}
+
+// Funds waiting out the bonding duration, as (release block, amount) oldest first
+pub type UnlockChunks<T> = Vec<(
+    <T as SystemConfig>::BlockNumber,
+    <T as BalancesConfig>::Balance,
+)>;
+
+// Validators a nominator backs, as (validator, amount) ordered by validator
+pub type Nominations<T> = Vec<(
+    <T as SystemConfig>::AccountId,
+    <T as BalancesConfig>::Balance,
+)>;
+
+// What reporting an offence would take and from whom, as worked out by
+// `StakingPallet::simulate_slash`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlashReport<T: StakingConfig> {
+    // Taken from the offender's bond, active stake first
+    pub validator_loss: T::Balance,
+    // Taken from each nominator's backing of the offender, ordered by nominator
+    pub nominator_losses: Vec<(T::AccountId, T::Balance)>,
+    // Minted to the reporter
+    pub finder_fee: T::Balance,
+    // Everything slashed beyond the finder's fee, credited to SLASH_ACCOUNT
+    pub treasury_gain: T::Balance,
+    // Validators the fallback election would pick before the slash but not after,
+    // and the other way round
+    pub leaving: Vec<T::AccountId>,
+    pub joining: Vec<T::AccountId>,
+}
+
+// Where a validator says it runs; self-declared, so only as honest as the deposit
+// makes it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatorMetadata<T: StakingConfig> {
+    pub region: String,
+    pub providers: Vec<String>,
+    // Held under HoldReason::ValidatorMetadata
+    pub deposit: T::Balance,
+}
+
+// Stake that can't be unstaked or unbonded before a block, e.g. after a conviction vote
+#[derive(Clone, Debug, PartialEq)]
+pub struct StakeLock<T: StakingConfig> {
+    pub amount: T::Balance,
+    pub until: T::BlockNumber,
+}
+
+// A way the staking ledger has drifted from the funds actually bonded in balances
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inconsistency<AccountId, Balance> {
+    // The ledger plus unbonding chunks record a different stake than the StakingBond hold
+    BondMismatch {
+        who: AccountId,
+        ledger: Balance,
+        held: Balance,
+    },
+    // A zero stake is stored instead of being removed
+    EmptyLedgerEntry {
+        who: AccountId,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct StakingPallet<T: StakingConfig> {
-    // Track free balances for each account
-    pub free_balances: HashMap<T::AccountId, T::Balance>,
-    // Track staked balances for each account
-    pub staked_balances: HashMap<T::AccountId, T::Balance>,
+    // Track staked balances for each account; the funds themselves sit on hold in balances
+    ledger: StorageMap<T::AccountId, T::Balance>,
+    // Unbonding chunks per account; the funds are still held in balances
+    unlocking: StorageMap<T::AccountId, UnlockChunks<T>>,
+    // Locks keeping part of the stake bonded; they overlap rather than stack
+    stake_locks: StorageMap<T::AccountId, Vec<StakeLock<T>>>,
+    // Stake locks by expiry, as (until, account) in block order, so they can be
+    // released without the account doing anything
+    lock_expiries: Vec<(T::BlockNumber, T::AccountId)>,
+    // Stake each nominator backs validators with; the funds are held in balances
+    // alongside the nominator's own stake
+    nominations: StorageMap<T::AccountId, Nominations<T>>,
+    // Index of the era in progress
+    current_era: StorageValue<u32>,
+    // Block the current era started at
+    era_start: StorageValue<T::BlockNumber>,
+    // Total reward paid out at the end of each era
+    era_rewards: StorageMap<u32, T::Balance>,
+    // Rewards each account earned, as (era, amount) in era order
+    payouts: StorageMap<T::AccountId, Vec<(u32, T::Balance)>>,
+    // Stakes the next election is run against, taken when the signed phase opens
+    election_snapshot: Option<Vec<(T::AccountId, T::Balance)>>,
+    // Solutions submitted during the signed phase, in submission order
+    submissions: Vec<Submission<T>>,
+    // Validators elected for the current era
+    validators: Vec<T::AccountId>,
+    // Validators elected for the next era, from the election until the era ends
+    queued_validators: Option<Vec<T::AccountId>>,
+    // Active validators that dropped below MIN_VALIDATOR_BOND this era
+    exits_this_era: StorageValue<u32>,
+    // Validator unbonds waiting for a free exit slot, as (validator, amount) in order
+    exit_queue: Vec<(T::AccountId, T::Balance)>,
+    // Regions and providers declared by validators
+    validator_metadata: StorageMap<T::AccountId, ValidatorMetadata<T>>,
+}
+
+impl<T: StakingConfig> Default for StakingPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: StakingConfig> StakingPallet<T> {
     pub fn new() -> Self {
         Self {
-            free_balances: HashMap::new(),
-            staked_balances: HashMap::new(),
+            ledger: StorageMap::new(),
+            unlocking: StorageMap::new(),
+            stake_locks: StorageMap::new(),
+            lock_expiries: Vec::new(),
+            nominations: StorageMap::new(),
+            current_era: StorageValue::new(0),
+            era_start: StorageValue::new(T::BlockNumber::zero()),
+            era_rewards: StorageMap::new(),
+            payouts: StorageMap::new(),
+            election_snapshot: None,
+            submissions: Vec::new(),
+            validators: Vec::new(),
+            queued_validators: None,
+            exits_this_era: StorageValue::new(0),
+            exit_queue: Vec::new(),
+            validator_metadata: StorageMap::new(),
         }
     }
 
-    // Set free balance for an account
-    pub fn set_balance(&mut self, who: T::AccountId, amount: T::Balance) {
-        self.free_balances.insert(who, amount);
+    // Route a call signed by `origin` to the matching method
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), StakingError> {
+        match call {
+            Call::Stake { amount } => self.stake(system, balances, origin, amount),
+            Call::Unstake { amount } => self.unstake(system, balances, origin, amount),
+            Call::Unbond { amount } => self.unbond(system, origin, amount),
+            Call::WithdrawUnbonded => self.withdraw_unbonded(system, balances, origin),
+            Call::ReportOffence {
+                reporter,
+                offender,
+                severity,
+            } => self.report_offence(system, balances, reporter, offender, severity),
+            Call::SubmitSolution { winners, score } => {
+                self.submit_solution(system, balances, origin, winners, score)
+            }
+            Call::Nominate { targets, amount } => {
+                self.nominate(system, balances, origin, targets, amount)
+            }
+            Call::Chill => self.chill(system, origin),
+            Call::SetValidatorMetadata { region, providers } => {
+                self.set_validator_metadata(system, balances, origin, region, providers)
+            }
+            Call::ClearValidatorMetadata => self.clear_validator_metadata(system, balances, origin),
+        }
     }
 
     // Stake tokens (move from free to staked)
-    pub fn stake(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
-        let free_balance = *self.free_balances.get(&who).unwrap_or(&T::Balance::zero());
-        if free_balance < amount {
-            return Err("Not enough free balance to stake");
-        }
-
-        let new_free_balance = free_balance.checked_sub(&amount).unwrap();
+    pub fn stake(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), StakingError> {
         let new_staked_balance = self
-            .staked_balances
-            .get(&who)
-            .unwrap_or(&T::Balance::zero())
+            .get_staked_balance(who.clone())
             .checked_add(&amount)
-            .unwrap();
+            .ok_or(StakingError::Overflow)?;
 
-        self.free_balances.insert(who.clone(), new_free_balance);
-        self.staked_balances.insert(who, new_staked_balance);
+        balances.hold(system, HoldReason::StakingBond, who.clone(), amount)?;
+        self.write_ledger(who.clone(), new_staked_balance);
+        system.deposit_event(Event::Staked { who, amount });
 
         Ok(())
     }
 
     // Unstake tokens (move from staked to free)
-    pub fn unstake(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
-        let staked_balance = *self
-            .staked_balances
-            .get(&who)
-            .unwrap_or(&T::Balance::zero());
+    pub fn unstake(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), StakingError> {
+        let staked_balance = self.get_staked_balance(who.clone());
         if staked_balance < amount {
-            return Err("Not enough staked balance to unstake");
+            return Err(StakingError::InsufficientStakedBalance);
         }
 
-        let new_staked_balance = staked_balance.checked_sub(&amount).unwrap();
-        let new_free_balance = self
-            .free_balances
-            .get(&who)
-            .unwrap_or(&T::Balance::zero())
-            .checked_add(&amount)
-            .unwrap();
+        let new_staked_balance = staked_balance
+            .checked_sub(&amount)
+            .ok_or(StakingError::Underflow)?;
+        if new_staked_balance < self.get_locked_stake(who.clone(), system.block_number()) {
+            return Err(StakingError::StakeLocked);
+        }
+        let exiting = self.is_exit(&who, staked_balance, new_staked_balance);
+        if exiting && self.exits_this_era.get() >= T::MAX_EXITS_PER_ERA {
+            return Err(StakingError::ExitCapReached);
+        }
 
-        self.staked_balances.insert(who.clone(), new_staked_balance);
-        self.free_balances.insert(who, new_free_balance);
+        balances.release(system, HoldReason::StakingBond, who.clone(), amount)?;
+        self.write_ledger(who.clone(), new_staked_balance);
+        if exiting {
+            self.note_exit();
+        }
+        system.deposit_event(Event::Unstaked { who, amount });
 
         Ok(())
     }
 
-    // Get free balance for an account
-    pub fn get_free_balance(&self, who: T::AccountId) -> T::Balance {
-        *self.free_balances.get(&who).unwrap_or(&T::Balance::zero())
+    // Stop staking `amount`; it stays held until BONDING_DURATION blocks have passed.
+    // An active validator dropping below MIN_VALIDATOR_BOND once this era's exits are
+    // used up joins the exit queue instead, and is unbonded in a later era.
+    pub fn unbond(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), StakingError> {
+        let staked_balance = self.get_staked_balance(who.clone());
+        let new_staked_balance = staked_balance
+            .checked_sub(&amount)
+            .ok_or(StakingError::InsufficientStakedBalance)?;
+        let exiting = self.is_exit(&who, staked_balance, new_staked_balance);
+        if exiting && self.exits_this_era.get() >= T::MAX_EXITS_PER_ERA {
+            return self.queue_exit(system, who, amount);
+        }
+
+        self.do_unbond(system, who, amount)?;
+        if exiting {
+            self.note_exit();
+        }
+
+        Ok(())
+    }
+
+    fn do_unbond(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), StakingError> {
+        let staked_balance = self.get_staked_balance(who.clone());
+        if staked_balance < amount {
+            return Err(StakingError::InsufficientStakedBalance);
+        }
+
+        let new_staked_balance = staked_balance
+            .checked_sub(&amount)
+            .ok_or(StakingError::Underflow)?;
+        if new_staked_balance < self.get_locked_stake(who.clone(), system.block_number()) {
+            return Err(StakingError::StakeLocked);
+        }
+        let release_at = system
+            .block_number()
+            .checked_add(&T::BONDING_DURATION)
+            .ok_or(StakingError::Overflow)?;
+
+        self.push_unlock_chunk(who.clone(), release_at, amount)?;
+        self.write_ledger(who.clone(), new_staked_balance);
+        system.deposit_event(Event::Unbonded {
+            who,
+            amount,
+            release_at,
+        });
+
+        Ok(())
+    }
+
+    // Whether going from `staked` to `remaining` takes an active validator below
+    // MIN_VALIDATOR_BOND
+    fn is_exit(&self, who: &T::AccountId, staked: T::Balance, remaining: T::Balance) -> bool {
+        self.validators.contains(who)
+            && staked >= T::MIN_VALIDATOR_BOND
+            && remaining < T::MIN_VALIDATOR_BOND
+    }
+
+    fn note_exit(&mut self) {
+        self.exits_this_era
+            .mutate(|exits| *exits = exits.saturating_add(1));
+    }
+
+    fn queue_exit(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), StakingError> {
+        if self.exit_queue_position(who.clone()).is_some() {
+            return Err(StakingError::AlreadyInExitQueue);
+        }
+        let position = u32::try_from(self.exit_queue.len()).map_err(|_| StakingError::Overflow)?;
+
+        self.exit_queue.push((who.clone(), amount));
+        system.deposit_event(Event::ExitQueued {
+            who,
+            amount,
+            position,
+        });
+
+        Ok(())
+    }
+
+    // Let queued validators out, oldest first, while this era has exits to spare.
+    // An unbond that no longer goes through (e.g. the stake was slashed) is dropped.
+    fn process_exit_queue(&mut self, system: &mut SystemPallet<T>) {
+        while self.exits_this_era.get() < T::MAX_EXITS_PER_ERA && !self.exit_queue.is_empty() {
+            let (who, amount) = self.exit_queue.remove(0);
+            if self.do_unbond(system, who, amount).is_ok() {
+                self.note_exit();
+            }
+        }
+    }
+
+    // Get a validator's place in the exit queue; 0 is let out next
+    pub fn exit_queue_position(&self, who: T::AccountId) -> Option<u32> {
+        self.exit_queue
+            .iter()
+            .position(|(queued, _)| *queued == who)
+            .and_then(|position| u32::try_from(position).ok())
+    }
+
+    // Chunks unbonded in the same block share a release block
+    fn push_unlock_chunk(
+        &mut self,
+        who: T::AccountId,
+        release_at: T::BlockNumber,
+        amount: T::Balance,
+    ) -> Result<(), StakingError> {
+        let chunks = self.unlocking.get_or_insert_with(who, Vec::new);
+        match chunks.last_mut() {
+            Some((at, chunk)) if *at == release_at => {
+                *chunk = chunk.checked_add(&amount).ok_or(StakingError::Overflow)?;
+            }
+            _ => chunks.push((release_at, amount)),
+        }
+        Ok(())
+    }
+
+    // Back `targets` with `amount`, split evenly with the remainder going to the
+    // first target. Nominating again adds to the existing backing.
+    pub fn nominate(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        nominator: T::AccountId,
+        targets: Vec<T::AccountId>,
+        amount: T::Balance,
+    ) -> Result<(), StakingError> {
+        let distinct: BTreeSet<_> = targets.iter().collect();
+        let too_many = u32::try_from(targets.len()).map_or(true, |len| len > T::MAX_NOMINATIONS);
+        if targets.is_empty()
+            || too_many
+            || distinct.len() != targets.len()
+            || distinct.contains(&nominator)
+        {
+            return Err(StakingError::InvalidTargets);
+        }
+
+        let total: u128 = amount.into();
+        let count = u128::try_from(targets.len()).map_err(|_| StakingError::Overflow)?;
+        let (share, remainder) = total
+            .checked_div(count)
+            .zip(total.checked_rem(count))
+            .ok_or(StakingError::Overflow)?;
+        if share == 0 {
+            return Err(StakingError::NominationTooSmall);
+        }
+
+        let mut backing = self.nominations_of(nominator.clone()).to_vec();
+        for (index, target) in targets.iter().enumerate() {
+            let extra = if index == 0 {
+                share.checked_add(remainder).ok_or(StakingError::Overflow)?
+            } else {
+                share
+            };
+            let extra = T::Balance::try_from(extra).map_err(|_| StakingError::Overflow)?;
+            match backing.binary_search_by(|(validator, _)| validator.cmp(target)) {
+                Ok(position) => {
+                    let (_, backed) = &mut backing[position];
+                    *backed = backed.checked_add(&extra).ok_or(StakingError::Overflow)?;
+                }
+                Err(position) => backing.insert(position, (target.clone(), extra)),
+            }
+        }
+
+        balances.hold(system, HoldReason::StakingBond, nominator.clone(), amount)?;
+        self.nominations.insert(nominator.clone(), backing);
+        system.deposit_event(Event::Nominated {
+            nominator,
+            targets,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Stop nominating; the backing unbonds and can be withdrawn after BONDING_DURATION
+    pub fn chill(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        nominator: T::AccountId,
+    ) -> Result<(), StakingError> {
+        if !self.nominations.contains_key(&nominator) {
+            return Err(StakingError::NotNominating);
+        }
+        let amount = self
+            .get_nominated_balance(nominator.clone())
+            .ok_or(StakingError::Overflow)?;
+        let release_at = system
+            .block_number()
+            .checked_add(&T::BONDING_DURATION)
+            .ok_or(StakingError::Overflow)?;
+
+        self.push_unlock_chunk(nominator.clone(), release_at, amount)?;
+        self.nominations.remove(&nominator);
+        system.deposit_event(Event::Chilled {
+            nominator,
+            amount,
+            release_at,
+        });
+
+        Ok(())
+    }
+
+    // Release every unbonding chunk whose lock-up has passed, per the current block
+    pub fn withdraw_unbonded(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+    ) -> Result<(), StakingError> {
+        let now = system.block_number();
+        let chunks = self.unbonding(who.clone());
+        let matured = chunks.partition_point(|(at, _)| *at <= now);
+        if matured == 0 {
+            return Ok(());
+        }
+        let amount = chunks
+            .iter()
+            .take(matured)
+            .try_fold(T::Balance::zero(), |total, (_, chunk)| {
+                total.checked_add(chunk)
+            })
+            .ok_or(StakingError::Overflow)?;
+
+        balances.release(system, HoldReason::StakingBond, who.clone(), amount)?;
+        if let Some(chunks) = self.unlocking.get_mut(&who) {
+            chunks.drain(..matured);
+            if chunks.is_empty() {
+                self.unlocking.remove(&who);
+            }
+        }
+        system.deposit_event(Event::Withdrawn { who, amount });
+
+        Ok(())
+    }
+
+    // Slash `severity` parts per million of the offender's bond, unbonding chunks
+    // included, and of each nominator's backing of the offender. FINDER_FEE of the
+    // total goes to the reporter; the rest is credited to SLASH_ACCOUNT.
+    pub fn report_offence(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        reporter: T::AccountId,
+        offender: T::AccountId,
+        severity: u32,
+    ) -> Result<(), StakingError> {
+        let plan = self.slash_plan(&offender, severity)?;
+        if reporter == offender {
+            return Err(StakingError::SelfReport);
+        }
+
+        balances.slash_held(
+            system,
+            HoldReason::StakingBond,
+            offender.clone(),
+            plan.validator_loss,
+        )?;
+        for (nominator, slash) in &plan.nominator_losses {
+            balances.slash_held(system, HoldReason::StakingBond, nominator.clone(), *slash)?;
+            system.deposit_event(Event::NominatorSlashed {
+                nominator: nominator.clone(),
+                validator: offender.clone(),
+                amount: *slash,
+            });
+        }
+        balances.mint(system, reporter.clone(), plan.finder_fee)?;
+        balances.mint(system, T::SLASH_ACCOUNT, plan.treasury_gain)?;
+        self.apply_slash(&offender, &plan)?;
+
+        system.deposit_event(Event::Slashed {
+            offender,
+            amount: plan.validator_loss,
+            reporter,
+            finder_fee: plan.finder_fee,
+        });
+
+        Ok(())
+    }
+
+    // Work out what reporting an offence would do without applying anything: who
+    // loses how much, what the treasury gains, and how the validators the fallback election
+    // would pick change
+    pub fn simulate_slash(
+        &self,
+        offender: T::AccountId,
+        severity: u32,
+    ) -> Result<SlashReport<T>, StakingError>
+    where
+        Self: Clone,
+    {
+        let plan = self.slash_plan(&offender, severity)?;
+        let mut slashed = self.clone();
+        slashed.apply_slash(&offender, &plan)?;
+
+        let before = self.fallback_solution(self.candidates());
+        let after = slashed.fallback_solution(slashed.candidates());
+
+        Ok(SlashReport {
+            leaving: before
+                .iter()
+                .filter(|who| !after.contains(who))
+                .cloned()
+                .collect(),
+            joining: after
+                .iter()
+                .filter(|who| !before.contains(who))
+                .cloned()
+                .collect(),
+            ..plan
+        })
+    }
+
+    // The amounts a slash takes, with the set changes left empty
+    fn slash_plan(
+        &self,
+        offender: &T::AccountId,
+        severity: u32,
+    ) -> Result<SlashReport<T>, StakingError> {
+        if severity > 1_000_000 {
+            return Err(StakingError::InvalidSeverity);
+        }
+
+        let staked = self.get_staked_balance(offender.clone());
+        let bonded: u128 = self
+            .get_unbonding_balance(offender.clone())
+            .and_then(|unbonding| unbonding.checked_add(&staked))
+            .ok_or(StakingError::Overflow)?
+            .into();
+        let validator_loss = Self::fraction(bonded, severity)?;
+        let nominator_losses = self
+            .nominators_of(offender.clone())
+            .into_iter()
+            .map(|(nominator, backed)| Ok((nominator, Self::fraction(backed.into(), severity)?)))
+            .collect::<Result<Vec<_>, StakingError>>()?;
+        let total_slashed = nominator_losses
+            .iter()
+            .try_fold(validator_loss, |total, (_, slash)| total.checked_add(slash))
+            .ok_or(StakingError::Overflow)?;
+        let finder_fee = Self::fraction(total_slashed.into(), T::FINDER_FEE.min(1_000_000))?;
+
+        Ok(SlashReport {
+            validator_loss,
+            nominator_losses,
+            finder_fee,
+            treasury_gain: total_slashed
+                .checked_sub(&finder_fee)
+                .ok_or(StakingError::Underflow)?,
+            leaving: Vec::new(),
+            joining: Vec::new(),
+        })
+    }
+
+    // Take a planned slash out of the offender's ledger and unbonding chunks and the
+    // nominators' backing; the holds are the caller's business
+    fn apply_slash(
+        &mut self,
+        offender: &T::AccountId,
+        plan: &SlashReport<T>,
+    ) -> Result<(), StakingError> {
+        for (nominator, slash) in &plan.nominator_losses {
+            if let Some(backing) = self.nominations.get_mut(nominator) {
+                for (validator, backed) in backing.iter_mut() {
+                    if validator == offender {
+                        *backed = backed.checked_sub(slash).ok_or(StakingError::Underflow)?;
+                    }
+                }
+                backing.retain(|(_, backed)| !backed.is_zero());
+                if backing.is_empty() {
+                    self.nominations.remove(nominator);
+                }
+            }
+        }
+
+        // Take from the active stake first, then from the newest unbonding chunks
+        let amount = plan.validator_loss;
+        let staked = self.get_staked_balance(offender.clone());
+        let from_stake = if staked < amount { staked } else { amount };
+        let mut remaining = amount
+            .checked_sub(&from_stake)
+            .ok_or(StakingError::Underflow)?;
+        let new_staked_balance = staked
+            .checked_sub(&from_stake)
+            .ok_or(StakingError::Underflow)?;
+        self.write_ledger(offender.clone(), new_staked_balance);
+        if let Some(chunks) = self.unlocking.get_mut(offender) {
+            for (_, chunk) in chunks.iter_mut().rev() {
+                let taken = if *chunk < remaining {
+                    *chunk
+                } else {
+                    remaining
+                };
+                *chunk = chunk.checked_sub(&taken).ok_or(StakingError::Underflow)?;
+                remaining = remaining
+                    .checked_sub(&taken)
+                    .ok_or(StakingError::Underflow)?;
+            }
+            chunks.retain(|(_, chunk)| !chunk.is_zero());
+            if chunks.is_empty() {
+                self.unlocking.remove(offender);
+            }
+        }
+
+        Ok(())
+    }
+
+    // `parts_per_million` of `total`, rounded down
+    fn fraction(total: u128, parts_per_million: u32) -> Result<T::Balance, StakingError> {
+        let share = total
+            .checked_mul(u128::from(parts_per_million))
+            .and_then(|scaled| scaled.checked_div(1_000_000))
+            .ok_or(StakingError::Overflow)?;
+        T::Balance::try_from(share).map_err(|_| StakingError::Overflow)
+    }
+
+    // Keep `amount` of the stake bonded until the given block. Expired locks are
+    // dropped here, so they don't pile up.
+    pub fn lock_stake(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+        until: T::BlockNumber,
+    ) {
+        let now = system.block_number();
+        let locks = self.stake_locks.get_or_insert_with(who.clone(), Vec::new);
+        locks.retain(|lock| lock.until > now);
+        locks.push(StakeLock { amount, until });
+        let position = self
+            .lock_expiries
+            .partition_point(|(expiry, _)| *expiry <= until);
+        self.lock_expiries.insert(position, (until, who.clone()));
+        system.deposit_event(Event::StakeLocked { who, amount, until });
+    }
+
+    // Start-of-block hook: release up to MAX_UNLOCKS_PER_BLOCK accounts' expired stake
+    // locks, oldest expiry first. An expired lock no longer binds even before it's
+    // released; releasing drops it from storage and tells the account.
+    pub fn on_initialize(&mut self, system: &mut SystemPallet<T>, now: T::BlockNumber) {
+        let due = self
+            .lock_expiries
+            .partition_point(|(until, _)| *until <= now)
+            .min(usize::try_from(T::MAX_UNLOCKS_PER_BLOCK).unwrap_or(usize::MAX));
+
+        for (_, who) in self.lock_expiries.drain(..due).collect::<Vec<_>>() {
+            let Some(locks) = self.stake_locks.get_mut(&who) else {
+                continue;
+            };
+            let (expired, remaining): (Vec<_>, Vec<_>) =
+                locks.drain(..).partition(|lock| lock.until <= now);
+            *locks = remaining;
+            if locks.is_empty() {
+                self.stake_locks.remove(&who);
+            }
+            for lock in expired {
+                system.deposit_event(Event::StakeUnlocked {
+                    who: who.clone(),
+                    amount: lock.amount,
+                });
+            }
+        }
+    }
+
+    // Get the largest stake lock still in force at `now`
+    pub fn get_locked_stake(&self, who: T::AccountId, now: T::BlockNumber) -> T::Balance {
+        self.stake_locks
+            .get(&who)
+            .into_iter()
+            .flatten()
+            .filter(|lock| lock.until > now)
+            .fold(T::Balance::zero(), |locked, lock| {
+                if lock.amount > locked {
+                    lock.amount
+                } else {
+                    locked
+                }
+            })
+    }
+
+    // Get an account's unbonding chunks as (release block, amount), oldest first
+    pub fn unbonding(&self, who: T::AccountId) -> &[(T::BlockNumber, T::Balance)] {
+        self.unlocking
+            .get(&who)
+            .map_or(&[], |chunks| chunks.as_slice())
+    }
+
+    // Get the total an account has unbonding, or None if it doesn't fit in a balance
+    pub fn get_unbonding_balance(&self, who: T::AccountId) -> Option<T::Balance> {
+        self.unbonding(who)
+            .iter()
+            .try_fold(T::Balance::zero(), |total, (_, chunk)| {
+                total.checked_add(chunk)
+            })
     }
 
     // Get staked balance for an account
     pub fn get_staked_balance(&self, who: T::AccountId) -> T::Balance {
-        *self
-            .staked_balances
+        *self.ledger.get(&who).unwrap_or(&T::Balance::zero())
+    }
+
+    // Get the stake a nominator backs validators with, as (validator, amount)
+    // ordered by validator
+    pub fn nominations_of(&self, nominator: T::AccountId) -> &[(T::AccountId, T::Balance)] {
+        self.nominations
+            .get(&nominator)
+            .map_or(&[], |backing| backing.as_slice())
+    }
+
+    // Get the total a nominator backs validators with, or None if it doesn't fit in a balance
+    pub fn get_nominated_balance(&self, nominator: T::AccountId) -> Option<T::Balance> {
+        self.nominations_of(nominator)
+            .iter()
+            .try_fold(T::Balance::zero(), |total, (_, backed)| {
+                total.checked_add(backed)
+            })
+    }
+
+    // List the nominators backing a validator with their backing, ordered by nominator
+    pub fn nominators_of(&self, validator: T::AccountId) -> Vec<(T::AccountId, T::Balance)> {
+        let mut nominators: Vec<_> = self
+            .nominations
+            .iter()
+            .filter_map(|(nominator, backing)| {
+                backing
+                    .iter()
+                    .find(|(target, _)| *target == validator)
+                    .map(|(_, backed)| (nominator.clone(), *backed))
+            })
+            .collect();
+        nominators.sort_by(|(a, _), (b, _)| a.cmp(b));
+        nominators
+    }
+
+    // List every staker with its own stake plus what nominators back it with,
+    // ordered by account. Nominations of accounts that don't stake are ignored.
+    pub fn backings(&self) -> Vec<(T::AccountId, T::Balance)> {
+        let mut nominated: BTreeMap<&T::AccountId, T::Balance> = BTreeMap::new();
+        for (validator, backed) in self.nominations.values().flatten() {
+            let total = nominated.entry(validator).or_insert_with(T::Balance::zero);
+            *total = total.checked_add(backed).unwrap_or(*total);
+        }
+        self.stakers()
+            .into_iter()
+            .map(|(who, stake)| {
+                let backing = nominated
+                    .get(&who)
+                    .and_then(|backed| stake.checked_add(backed))
+                    .unwrap_or(stake);
+                (who, backing)
+            })
+            .collect()
+    }
+
+    // List the stakers that can stand for election, with their backing, ordered by
+    // account: those with at least MIN_VALIDATOR_BOND of their own
+    pub fn candidates(&self) -> Vec<(T::AccountId, T::Balance)> {
+        let mut candidates = self.backings();
+        candidates.retain(|(who, _)| self.get_staked_balance(who.clone()) >= T::MIN_VALIDATOR_BOND);
+        candidates
+    }
+
+    // List every account with a non-zero stake, ordered by account
+    pub fn stakers(&self) -> Vec<(T::AccountId, T::Balance)> {
+        let mut stakers: Vec<_> = self
+            .ledger
+            .iter()
+            .map(|(who, amount)| (who.clone(), *amount))
+            .collect();
+        stakers.sort_by(|(a, _), (b, _)| a.cmp(b));
+        stakers
+    }
+
+    // Compare the ledger, unbonding chunks and nominations against the StakingBond
+    // holds, ordered by account.
+    // Useful after a hand-written storage migration.
+    pub fn check_consistency(
+        &self,
+        balances: &BalancesPallet<T>,
+    ) -> Vec<Inconsistency<T::AccountId, T::Balance>> {
+        let accounts: BTreeSet<T::AccountId> = self
+            .ledger
+            .keys()
+            .chain(self.unlocking.keys())
+            .chain(self.nominations.keys())
+            .cloned()
+            .chain(
+                balances
+                    .held_balances
+                    .keys()
+                    .filter(|(_, reason)| *reason == HoldReason::StakingBond)
+                    .map(|(who, _)| who.clone()),
+            )
+            .collect();
+
+        let mut issues = Vec::new();
+        for who in accounts {
+            let staked = self.get_staked_balance(who.clone());
+            let held = balances.get_held_balance(HoldReason::StakingBond, who.clone());
+            // A total that overflows can't match any hold, so report it as the stake alone
+            let ledger = self
+                .get_unbonding_balance(who.clone())
+                .zip(self.get_nominated_balance(who.clone()))
+                .and_then(|(unbonding, nominated)| unbonding.checked_add(&nominated))
+                .and_then(|bonded| bonded.checked_add(&staked));
+            if ledger != Some(held) {
+                let ledger = ledger.unwrap_or(staked);
+                issues.push(Inconsistency::BondMismatch { who, ledger, held });
+            } else if staked.is_zero() && self.ledger.contains_key(&who) {
+                issues.push(Inconsistency::EmptyLedgerEntry { who });
+            }
+        }
+        issues
+    }
+
+    // Bring the ledger back in line with the StakingBond holds and return what was fixed.
+    // Only the ledger is touched: the holds are the funds, so they're taken as the truth.
+    pub fn repair(
+        &mut self,
+        balances: &BalancesPallet<T>,
+    ) -> Vec<Inconsistency<T::AccountId, T::Balance>> {
+        let issues = self.check_consistency(balances);
+        for issue in &issues {
+            match issue {
+                Inconsistency::BondMismatch { who, held, .. } => {
+                    // Keep the nominations, then the unbonding chunks, while the hold
+                    // still covers them
+                    let nominated = self
+                        .get_nominated_balance(who.clone())
+                        .and_then(|nominated| held.checked_sub(&nominated));
+                    if nominated.is_none() {
+                        self.nominations.remove(who);
+                    }
+                    let unnominated = nominated.unwrap_or(*held);
+                    let staked = self
+                        .get_unbonding_balance(who.clone())
+                        .and_then(|unbonding| unnominated.checked_sub(&unbonding));
+                    if staked.is_none() {
+                        self.unlocking.remove(who);
+                    }
+                    self.write_ledger(who.clone(), staked.unwrap_or(unnominated));
+                }
+                Inconsistency::EmptyLedgerEntry { who } => {
+                    self.ledger.remove(who);
+                }
+            }
+        }
+        issues
+    }
+
+    // Submit a precomputed election solution against the snapshot. Only cheap checks
+    // happen here; the solution is scored when the era ends.
+    pub fn submit_solution(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        winners: Vec<T::AccountId>,
+        score: u128,
+    ) -> Result<(), StakingError> {
+        if self.election_snapshot.is_none() {
+            return Err(StakingError::ElectionClosed);
+        }
+        if self.submissions.iter().any(|s| s.who == who) {
+            return Err(StakingError::AlreadySubmitted);
+        }
+        if u32::try_from(winners.len()).map_or(true, |len| len > T::MAX_VALIDATORS) {
+            return Err(StakingError::TooManyWinners);
+        }
+
+        balances.hold(
+            system,
+            HoldReason::ElectionDeposit,
+            who.clone(),
+            T::SOLUTION_DEPOSIT,
+        )?;
+        self.submissions.push(Submission {
+            who: who.clone(),
+            winners,
+            score,
+        });
+        system.deposit_event(Event::SolutionSubmitted { who, score });
+
+        Ok(())
+    }
+
+    // End-of-block hook. Each era ends with the signed phase (SIGNED_PHASE blocks),
+    // then the election, whose result stays queued for ROTATION_GRACE_PERIOD blocks
+    // until the era closes after ERA_LENGTH blocks and the new validators take over.
+    pub fn on_finalize(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        now: T::BlockNumber,
+    ) {
+        let Some(era_end) = self.era_start.get().checked_add(&T::ERA_LENGTH) else {
+            return;
+        };
+        let election_due = now
+            .checked_add(&T::ROTATION_GRACE_PERIOD)
+            .is_none_or(|election_end| election_end >= era_end);
+        if election_due && self.queued_validators.is_none() {
+            self.elect(system, balances);
+        }
+        if now >= era_end {
+            self.end_era(system, balances, now);
+            return;
+        }
+
+        let phase_opens = now
+            .checked_add(&T::BlockNumber::one())
+            .and_then(|next_block| next_block.checked_add(&T::SIGNED_PHASE))
+            .and_then(|phase_end| phase_end.checked_add(&T::ROTATION_GRACE_PERIOD))
+            .is_some_and(|phase_end| phase_end >= era_end);
+        if phase_opens && self.election_snapshot.is_none() && self.queued_validators.is_none() {
+            self.election_snapshot = Some(self.candidates());
+        }
+    }
+
+    // Queue the best valid solution for the next era, highest claimed score first.
+    // Submissions that fail the check are slashed; the rest get their deposit back.
+    fn elect(&mut self, system: &mut SystemPallet<T>, balances: &mut BalancesPallet<T>) {
+        let snapshot = self
+            .election_snapshot
+            .take()
+            .unwrap_or_else(|| self.candidates());
+        let mut submissions = std::mem::take(&mut self.submissions);
+        submissions.sort_by_key(|submission| std::cmp::Reverse(submission.score));
+
+        let mut elected = None;
+        for submission in submissions {
+            let Submission { who, winners, .. } = &submission;
+            if elected.is_some() {
+                let _ = balances.release(
+                    system,
+                    HoldReason::ElectionDeposit,
+                    who.clone(),
+                    T::SOLUTION_DEPOSIT,
+                );
+            } else if Self::score_solution(&snapshot, winners) == Some(submission.score) {
+                let _ = balances.release(
+                    system,
+                    HoldReason::ElectionDeposit,
+                    who.clone(),
+                    T::SOLUTION_DEPOSIT,
+                );
+                elected = Some(submission.winners);
+            } else {
+                let _ = balances.slash_held(
+                    system,
+                    HoldReason::ElectionDeposit,
+                    who.clone(),
+                    T::SOLUTION_DEPOSIT,
+                );
+                system.deposit_event(Event::SolutionRejected { who: who.clone() });
+            }
+        }
+
+        let (validators, compute) = match elected {
+            Some(winners) => (winners, ElectionCompute::Signed),
+            None => (self.fallback_solution(snapshot), ElectionCompute::Fallback),
+        };
+        self.queued_validators = Some(validators.clone());
+        system.deposit_event(Event::ValidatorsElected {
+            era: self.current_era.get().saturating_add(1),
+            validators,
+            compute,
+        });
+    }
+
+    // Total snapshot backing behind `winners`, or None if they aren't distinct stakers
+    fn score_solution(
+        snapshot: &[(T::AccountId, T::Balance)],
+        winners: &[T::AccountId],
+    ) -> Option<u128> {
+        let distinct: BTreeSet<_> = winners.iter().collect();
+        if winners.is_empty() || distinct.len() != winners.len() {
+            return None;
+        }
+        winners.iter().try_fold(0u128, |score, winner| {
+            let (_, stake) = snapshot.iter().find(|(who, _)| who == winner)?;
+            score.checked_add((*stake).into())
+        })
+    }
+
+    // The MAX_VALIDATORS best-backed stakers, ties going to the lower account. With
+    // DIVERSITY_TIE_BREAK, a tie first goes to whoever adds the most regions and
+    // providers not yet among those picked.
+    fn fallback_solution(
+        &self,
+        mut snapshot: Vec<(T::AccountId, T::Balance)>,
+    ) -> Vec<T::AccountId> {
+        snapshot.sort_by(|(a, a_stake), (b, b_stake)| {
+            b_stake
+                .partial_cmp(a_stake)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.cmp(b))
+        });
+        let max = usize::try_from(T::MAX_VALIDATORS).unwrap_or(usize::MAX);
+        let mut validators: Vec<T::AccountId> = Vec::new();
+        if T::DIVERSITY_TIE_BREAK {
+            let mut remaining = snapshot;
+            while validators.len() < max && !remaining.is_empty() {
+                let top = remaining[0].1;
+                let tied = remaining
+                    .iter()
+                    .take_while(|(_, stake)| *stake == top)
+                    .count();
+                // The first best score wins, so equal scores still go to the lower account
+                let mut best = 0;
+                let mut best_score = None;
+                for (index, (who, _)) in remaining.iter().take(tied).enumerate() {
+                    let score = self.diversity_score(who, &validators);
+                    if best_score.is_none_or(|best_score| score > best_score) {
+                        best = index;
+                        best_score = Some(score);
+                    }
+                }
+                let (who, _) = remaining.remove(best);
+                validators.push(who);
+            }
+        } else {
+            validators = snapshot.into_iter().take(max).map(|(who, _)| who).collect();
+        }
+        validators.sort();
+        validators
+    }
+
+    // Hand over to the queued validators, then mint the era reward and share it among
+    // stakers by backing. Each staker keeps VALIDATOR_COMMISSION of its share and
+    // splits the rest with its nominators by stake. Rewards are bonded, so they
+    // compound; they're rounded down and the remainder is never minted.
+    fn end_era(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        now: T::BlockNumber,
+    ) {
+        if let Some(validators) = self.queued_validators.take() {
+            self.validators = validators;
+        }
+
+        let era = self.current_era.get();
+        let backings = self.backings();
+        let total_backing = backings.iter().try_fold(0u128, |total, (_, backing)| {
+            total.checked_add((*backing).into())
+        });
+        let reward: u128 = T::ERA_REWARD.into();
+
+        let mut total_reward = T::Balance::zero();
+        for (validator, backing) in backings {
+            let backing: u128 = backing.into();
+            let Some(share) = total_backing
+                .and_then(|total_backing| backing.checked_mul(reward)?.checked_div(total_backing))
+            else {
+                continue;
+            };
+            let commission =
+                Self::fraction(share, T::VALIDATOR_COMMISSION.min(1_000_000)).map_or(0, Into::into);
+            let rest = share.saturating_sub(commission);
+            // Each backer's cut of what's left after commission
+            let cut = |stake: T::Balance| {
+                let stake: u128 = stake.into();
+                rest.checked_mul(stake)?.checked_div(backing)
+            };
+
+            let own = cut(self.get_staked_balance(validator.clone()))
+                .and_then(|own| own.checked_add(commission));
+            if let Some(amount) = own.and_then(|own| T::Balance::try_from(own).ok()) {
+                if self.reward(system, balances, era, validator.clone(), amount, None) {
+                    total_reward = total_reward.checked_add(&amount).unwrap_or(total_reward);
+                }
+            }
+            for (nominator, backed) in self.nominators_of(validator.clone()) {
+                let Some(amount) = cut(backed).and_then(|cut| T::Balance::try_from(cut).ok())
+                else {
+                    continue;
+                };
+                if self.reward(
+                    system,
+                    balances,
+                    era,
+                    nominator,
+                    amount,
+                    Some(validator.clone()),
+                ) {
+                    total_reward = total_reward.checked_add(&amount).unwrap_or(total_reward);
+                }
+            }
+        }
+
+        self.era_rewards.insert(era, total_reward);
+        system.deposit_event(Event::EraEnded { era, total_reward });
+        self.current_era.put(era.saturating_add(1));
+        self.era_start.put(now);
+        self.exits_this_era.put(0);
+        self.process_exit_queue(system);
+    }
+
+    // Mint a reward and bond it, as stake or, for a nominator, as more backing of the
+    // validator it came from. Returns whether anything was paid.
+    fn reward(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        era: u32,
+        who: T::AccountId,
+        amount: T::Balance,
+        backing: Option<T::AccountId>,
+    ) -> bool {
+        if amount.is_zero() || balances.mint(system, who.clone(), amount).is_err() {
+            return false;
+        }
+        // If locks keep the reward from being bonded it stays in the free balance
+        match backing {
+            None => {
+                let _ = self.stake(system, balances, who.clone(), amount);
+            }
+            Some(validator) => {
+                let bonded = balances.hold(system, HoldReason::StakingBond, who.clone(), amount);
+                if let (Ok(()), Some(backing)) = (bonded, self.nominations.get_mut(&who)) {
+                    for (target, backed) in backing.iter_mut() {
+                        if *target == validator {
+                            *backed = backed.checked_add(&amount).unwrap_or(*backed);
+                        }
+                    }
+                }
+            }
+        }
+
+        let payouts = self.payouts.get_or_insert_with(who.clone(), Vec::new);
+        match payouts.last_mut() {
+            Some((last_era, paid)) if *last_era == era => {
+                *paid = paid.checked_add(&amount).unwrap_or(*paid);
+            }
+            _ => payouts.push((era, amount)),
+        }
+        system.deposit_event(Event::Rewarded { era, who, amount });
+        true
+    }
+
+    // Get the index of the era in progress
+    pub fn current_era(&self) -> u32 {
+        self.current_era.get()
+    }
+
+    // Declare the validator's region and providers. The deposit covers the region and
+    // each provider tag; replacing a declaration holds or releases the difference.
+    pub fn set_validator_metadata(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+        region: String,
+        providers: Vec<String>,
+    ) -> Result<(), StakingError> {
+        let too_many =
+            u32::try_from(providers.len()).map_or(true, |len| len > T::MAX_PROVIDER_TAGS);
+        if too_many {
+            return Err(StakingError::TooManyProviderTags);
+        }
+        let valid = |tag: &String| !tag.trim().is_empty() && tag.len() <= T::MAX_METADATA_TAG_LEN;
+        if !valid(&region) || !providers.iter().all(valid) {
+            return Err(StakingError::InvalidMetadataTag);
+        }
+
+        let entries = u128::try_from(providers.len())
+            .ok()
+            .and_then(|tags| tags.checked_add(1))
+            .ok_or(StakingError::Overflow)?;
+        let deposit = entries
+            .checked_mul(T::VALIDATOR_METADATA_DEPOSIT.into())
+            .and_then(|deposit| T::Balance::try_from(deposit).ok())
+            .ok_or(StakingError::Overflow)?;
+        let held = self
+            .validator_metadata
+            .get(&who)
+            .map_or_else(T::Balance::zero, |metadata| metadata.deposit);
+        if deposit > held {
+            let extra = deposit.checked_sub(&held).ok_or(StakingError::Underflow)?;
+            balances.hold(system, HoldReason::ValidatorMetadata, who.clone(), extra)?;
+        } else if held > deposit {
+            let refund = held.checked_sub(&deposit).ok_or(StakingError::Underflow)?;
+            balances.release(system, HoldReason::ValidatorMetadata, who.clone(), refund)?;
+        }
+
+        self.validator_metadata.insert(
+            who.clone(),
+            ValidatorMetadata {
+                region: region.clone(),
+                providers: providers.clone(),
+                deposit,
+            },
+        );
+        system.deposit_event(Event::ValidatorMetadataSet {
+            who,
+            region,
+            providers,
+        });
+
+        Ok(())
+    }
+
+    // Remove the validator's declaration and release its deposit
+    pub fn clear_validator_metadata(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        who: T::AccountId,
+    ) -> Result<(), StakingError> {
+        let deposit = self
+            .validator_metadata
             .get(&who)
-            .unwrap_or(&T::Balance::zero())
+            .ok_or(StakingError::NoValidatorMetadata)?
+            .deposit;
+
+        balances.release(system, HoldReason::ValidatorMetadata, who.clone(), deposit)?;
+        self.validator_metadata.remove(&who);
+        system.deposit_event(Event::ValidatorMetadataCleared { who });
+
+        Ok(())
+    }
+
+    // Get the region and providers a validator declared, if any
+    pub fn validator_metadata(&self, who: &T::AccountId) -> Option<&ValidatorMetadata<T>> {
+        self.validator_metadata.get(who)
+    }
+
+    // How many of the candidate's region and provider tags none of `others` shares.
+    // Candidates without metadata score 0.
+    pub fn diversity_score(&self, candidate: &T::AccountId, others: &[T::AccountId]) -> u32 {
+        let Some(metadata) = self.validator_metadata.get(candidate) else {
+            return 0;
+        };
+        let taken: BTreeSet<&String> = others
+            .iter()
+            .filter_map(|other| self.validator_metadata.get(other))
+            .flat_map(|other| std::iter::once(&other.region).chain(&other.providers))
+            .collect();
+        let tags: BTreeSet<&String> = std::iter::once(&metadata.region)
+            .chain(&metadata.providers)
+            .collect();
+        let fresh = tags.iter().filter(|tag| !taken.contains(*tag)).count();
+        u32::try_from(fresh).unwrap_or(u32::MAX)
+    }
+
+    // Get the validators elected for the current era, ordered by account
+    pub fn validators(&self) -> &[T::AccountId] {
+        &self.validators
+    }
+
+    // Get the validators taking over at the end of the current era, once elected
+    pub fn queued_validators(&self) -> Option<&[T::AccountId]> {
+        self.queued_validators.as_deref()
+    }
+
+    // Get the stakes the next election runs against, once the signed phase is open
+    pub fn election_snapshot(&self) -> Option<&[(T::AccountId, T::Balance)]> {
+        self.election_snapshot.as_deref()
+    }
+
+    // Get the solutions submitted so far, in submission order
+    pub fn submissions(&self) -> &[Submission<T>] {
+        &self.submissions
+    }
+
+    // Get the total reward paid out at the end of an era
+    pub fn era_reward(&self, era: u32) -> Option<T::Balance> {
+        self.era_rewards.get(&era).copied()
+    }
+
+    // Get the rewards an account earned, as (era, amount) in era order
+    pub fn payout_history(&self, who: T::AccountId) -> &[(u32, T::Balance)] {
+        self.payouts
+            .get(&who)
+            .map_or(&[], |payouts| payouts.as_slice())
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.ledger
+            .storage_usage()
+            .saturating_add(StorageUsage::value::<(u32, T::BlockNumber, u32)>(0))
+            .saturating_add(self.era_rewards.storage_usage())
+            .saturating_add(self.payouts.values().fold(
+                StorageUsage::default(),
+                |usage, payouts| {
+                    usage.saturating_add(StorageUsage {
+                        items: payouts.len(),
+                        bytes: payouts
+                            .len()
+                            .saturating_mul(std::mem::size_of::<(u32, T::Balance)>()),
+                    })
+                },
+            ))
+            .saturating_add(self.unlocking.values().fold(
+                StorageUsage::default(),
+                |usage, chunks| {
+                    usage.saturating_add(StorageUsage {
+                        items: chunks.len(),
+                        bytes: chunks
+                            .len()
+                            .saturating_mul(std::mem::size_of::<(T::BlockNumber, T::Balance)>()),
+                    })
+                },
+            ))
+            .saturating_add(self.stake_locks.values().fold(
+                StorageUsage::default(),
+                |usage, locks| {
+                    usage.saturating_add(StorageUsage {
+                        items: locks.len(),
+                        bytes: locks
+                            .len()
+                            .saturating_mul(std::mem::size_of::<StakeLock<T>>()),
+                    })
+                },
+            ))
+            .saturating_add(self.nominations.values().fold(
+                StorageUsage::default(),
+                |usage, backing| {
+                    usage.saturating_add(StorageUsage {
+                        items: backing.len(),
+                        bytes: backing
+                            .len()
+                            .saturating_mul(std::mem::size_of::<(T::AccountId, T::Balance)>()),
+                    })
+                },
+            ))
+            .saturating_add(self.election_usage())
+            .saturating_add(StorageUsage {
+                items: self.lock_expiries.len(),
+                bytes: self
+                    .lock_expiries
+                    .len()
+                    .saturating_mul(std::mem::size_of::<(T::BlockNumber, T::AccountId)>()),
+            })
+            .saturating_add(StorageUsage {
+                items: self.exit_queue.len(),
+                bytes: self
+                    .exit_queue
+                    .len()
+                    .saturating_mul(std::mem::size_of::<(T::AccountId, T::Balance)>()),
+            })
+            .saturating_add(self.validator_metadata.storage_usage())
+    }
+
+    // Approximate state held for elections: the snapshot, submissions and validator sets
+    fn election_usage(&self) -> StorageUsage {
+        let account = std::mem::size_of::<T::AccountId>();
+        let entry = std::mem::size_of::<(T::AccountId, T::Balance)>();
+        let snapshot = self
+            .election_snapshot
+            .as_ref()
+            .map_or_else(StorageUsage::default, |snapshot| {
+                StorageUsage::value::<()>(snapshot.len().saturating_mul(entry))
+            });
+        let submissions =
+            self.submissions
+                .iter()
+                .fold(StorageUsage::default(), |usage, submission| {
+                    usage.saturating_add(StorageUsage::value::<Submission<T>>(
+                        submission.winners.len().saturating_mul(account),
+                    ))
+                });
+        let queued = self.queued_validators.as_deref().unwrap_or_default();
+        let validators = StorageUsage {
+            items: self.validators.len().saturating_add(queued.len()),
+            bytes: self
+                .validators
+                .len()
+                .saturating_add(queued.len())
+                .saturating_mul(account),
+        };
+        snapshot
+            .saturating_add(submissions)
+            .saturating_add(validators)
+    }
+
+    fn write_ledger(&mut self, who: T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.ledger.remove(&who);
+        } else {
+            self.ledger.insert(who, amount);
+        }
+    }
+}
+
+impl<T: StakingConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::Stake { .. } | Call::Unstake { .. } | Call::Unbond { .. } => 3,
+            Call::WithdrawUnbonded | Call::Chill | Call::ClearValidatorMetadata => 3,
+            Call::SetValidatorMetadata { providers, .. } => {
+                weight_of(providers.len(), 1).saturating_add(3)
+            }
+            Call::ReportOffence { .. } => 10,
+            Call::SubmitSolution { winners, .. } => weight_of(winners.len(), 1).saturating_add(3),
+            Call::Nominate { targets, .. } => weight_of(targets.len(), 1).saturating_add(3),
+        }
+    }
+}
+
+impl<T: StakingConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::Stake { amount } => {
+                0u8.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::Unstake { amount } => {
+                1u8.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::Unbond { amount } => {
+                2u8.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::WithdrawUnbonded => 3u8.encode_to(dest),
+            Call::ReportOffence {
+                reporter,
+                offender,
+                severity,
+            } => {
+                4u8.encode_to(dest);
+                reporter.encode_to(dest);
+                offender.encode_to(dest);
+                severity.encode_to(dest);
+            }
+            Call::SubmitSolution { winners, score } => {
+                5u8.encode_to(dest);
+                winners.encode_to(dest);
+                score.encode_to(dest);
+            }
+            Call::Nominate { targets, amount } => {
+                6u8.encode_to(dest);
+                targets.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::Chill => 7u8.encode_to(dest),
+            Call::SetValidatorMetadata { region, providers } => {
+                8u8.encode_to(dest);
+                region.encode_to(dest);
+                providers.encode_to(dest);
+            }
+            Call::ClearValidatorMetadata => 9u8.encode_to(dest),
+        }
+    }
+}
+
+impl<T: StakingConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::Stake {
+                amount: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::Unstake {
+                amount: Decode::decode(input)?,
+            }),
+            2 => Ok(Call::Unbond {
+                amount: Decode::decode(input)?,
+            }),
+            3 => Ok(Call::WithdrawUnbonded),
+            4 => Ok(Call::ReportOffence {
+                reporter: Decode::decode(input)?,
+                offender: Decode::decode(input)?,
+                severity: Decode::decode(input)?,
+            }),
+            5 => Ok(Call::SubmitSolution {
+                winners: Decode::decode(input)?,
+                score: Decode::decode(input)?,
+            }),
+            6 => Ok(Call::Nominate {
+                targets: Decode::decode(input)?,
+                amount: Decode::decode(input)?,
+            }),
+            7 => Ok(Call::Chill),
+            8 => Ok(Call::SetValidatorMetadata {
+                region: Decode::decode(input)?,
+                providers: Decode::decode(input)?,
+            }),
+            9 => Ok(Call::ClearValidatorMetadata),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: StakingConfig> Encode for StakeLock<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.amount.encode_to(dest);
+        self.until.encode_to(dest);
+    }
+}
+
+impl<T: StakingConfig> Decode for StakeLock<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            amount: Decode::decode(input)?,
+            until: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: StakingConfig> Encode for ValidatorMetadata<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.region.encode_to(dest);
+        self.providers.encode_to(dest);
+        self.deposit.encode_to(dest);
+    }
+}
+
+impl<T: StakingConfig> Decode for ValidatorMetadata<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            region: Decode::decode(input)?,
+            providers: Decode::decode(input)?,
+            deposit: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: StakingConfig> Encode for Submission<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.who.encode_to(dest);
+        self.winners.encode_to(dest);
+        self.score.encode_to(dest);
+    }
+}
+
+impl<T: StakingConfig> Decode for Submission<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            who: Decode::decode(input)?,
+            winners: Decode::decode(input)?,
+            score: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: StakingConfig> Encode for StakingPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.ledger.encode_to(dest);
+        self.unlocking.encode_to(dest);
+        self.stake_locks.encode_to(dest);
+        self.lock_expiries.encode_to(dest);
+        self.nominations.encode_to(dest);
+        self.current_era.encode_to(dest);
+        self.era_start.encode_to(dest);
+        self.era_rewards.encode_to(dest);
+        self.payouts.encode_to(dest);
+        self.election_snapshot.encode_to(dest);
+        self.submissions.encode_to(dest);
+        self.validators.encode_to(dest);
+        self.queued_validators.encode_to(dest);
+        self.exits_this_era.encode_to(dest);
+        self.exit_queue.encode_to(dest);
+        self.validator_metadata.encode_to(dest);
+    }
+}
+
+impl<T: StakingConfig> Decode for StakingPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            ledger: Decode::decode(input)?,
+            unlocking: Decode::decode(input)?,
+            stake_locks: Decode::decode(input)?,
+            lock_expiries: Decode::decode(input)?,
+            nominations: Decode::decode(input)?,
+            current_era: Decode::decode(input)?,
+            era_start: Decode::decode(input)?,
+            era_rewards: Decode::decode(input)?,
+            payouts: Decode::decode(input)?,
+            election_snapshot: Decode::decode(input)?,
+            submissions: Decode::decode(input)?,
+            validators: Decode::decode(input)?,
+            queued_validators: Decode::decode(input)?,
+            exits_this_era: Decode::decode(input)?,
+            exit_queue: Decode::decode(input)?,
+            validator_metadata: Decode::decode(input)?,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Runtime;
+    use crate::balances;
+    use crate::{Runtime, TreasuryConfig};
 
     #[test]
     fn test_staking_should_work() {
         let alice = 1u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
         let mut staking = StakingPallet::<Runtime>::new();
 
         // Set initial balance
-        staking.set_balance(alice, 1000);
+        balances.set_balance(alice, 1000).unwrap();
 
         // Check free balance
-        assert_eq!(staking.get_free_balance(alice), 1000u64);
+        assert_eq!(balances.get_free_balance(alice), 1000u64);
         assert_eq!(staking.get_staked_balance(alice), 0u64);
 
         // Stake tokens
-        let result = staking.stake(alice, 400);
+        let result = staking.stake(&mut system, &mut balances, alice, 400);
         assert!(result.is_ok());
 
         // Check balances after staking
-        assert_eq!(staking.get_free_balance(alice), 600u64);
+        assert_eq!(balances.get_free_balance(alice), 600u64);
         assert_eq!(staking.get_staked_balance(alice), 400u64);
+        assert_eq!(
+            balances.get_held_balance(HoldReason::StakingBond, alice),
+            400u64
+        );
 
         // Unstake tokens
-        let result = staking.unstake(alice, 100);
+        let result = staking.unstake(&mut system, &mut balances, alice, 100);
         assert!(result.is_ok());
 
         // Check balances after unstaking
-        assert_eq!(staking.get_free_balance(alice), 700u64);
+        assert_eq!(balances.get_free_balance(alice), 700u64);
         assert_eq!(staking.get_staked_balance(alice), 300u64);
     }
 
     #[test]
     fn test_staking_errors() {
         let bob = 2u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
         let mut staking = StakingPallet::<Runtime>::new();
 
         // Set initial balance
-        staking.set_balance(bob, 500);
+        balances.set_balance(bob, 500).unwrap();
 
         // Try to stake more than available
-        let result = staking.stake(bob, 600);
-        assert!(result.is_err());
+        let result = staking.stake(&mut system, &mut balances, bob, 600);
+        assert_eq!(
+            result,
+            Err(StakingError::Balances(BalancesError::InsufficientBalance))
+        );
 
         // Stake valid amount
-        let result = staking.stake(bob, 300);
+        let result = staking.stake(&mut system, &mut balances, bob, 300);
         assert!(result.is_ok());
 
         // Try to unstake more than staked
-        let result = staking.unstake(bob, 400);
-        assert!(result.is_err());
+        let result = staking.unstake(&mut system, &mut balances, bob, 400);
+        assert_eq!(result, Err(StakingError::InsufficientStakedBalance));
+
+        // Locked funds can't be staked
+        balances.set_lock(&mut system, *b"vesting ", bob, 200);
+        let result = staking.stake(&mut system, &mut balances, bob, 100);
+        assert_eq!(
+            result,
+            Err(StakingError::Balances(BalancesError::LiquidityRestrictions))
+        );
+    }
+
+    #[test]
+    fn test_staking_emits_events() {
+        let alice = 1u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+        staking
+            .stake(&mut system, &mut balances, alice, 400)
+            .unwrap();
+        staking
+            .unstake(&mut system, &mut balances, alice, 100)
+            .unwrap();
+
+        // Failed calls don't emit anything
+        let _ = staking.unstake(&mut system, &mut balances, alice, 1000);
+
+        assert_eq!(
+            system.events(),
+            &[
+                balances::Event::Held {
+                    reason: HoldReason::StakingBond,
+                    who: alice,
+                    amount: 400,
+                }
+                .into(),
+                Event::Staked {
+                    who: alice,
+                    amount: 400,
+                }
+                .into(),
+                balances::Event::Released {
+                    reason: HoldReason::StakingBond,
+                    who: alice,
+                    amount: 100,
+                }
+                .into(),
+                Event::Unstaked {
+                    who: alice,
+                    amount: 100,
+                }
+                .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unbonding_waits_for_bonding_duration() {
+        let alice = 1u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+        staking
+            .stake(&mut system, &mut balances, alice, 500)
+            .unwrap();
+        let result = staking.unbond(&mut system, alice, 600);
+        assert_eq!(result, Err(StakingError::InsufficientStakedBalance));
+
+        // Two chunks, unbonded at blocks 0 and 5
+        staking.unbond(&mut system, alice, 100).unwrap();
+        for number in 1..=5 {
+            system.initialize(number).unwrap();
+        }
+        staking.unbond(&mut system, alice, 50).unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 350u64);
+        assert_eq!(staking.unbonding(alice), &[(20, 100), (25, 50)]);
+        assert!(staking.check_consistency(&balances).is_empty());
+
+        // Nothing has matured yet, so withdrawing is a no-op
+        staking
+            .withdraw_unbonded(&mut system, &mut balances, alice)
+            .unwrap();
+        assert_eq!(balances.get_free_balance(alice), 500u64);
+
+        // Only the first chunk is released at block 20
+        for number in 6..=20 {
+            system.initialize(number).unwrap();
+        }
+        staking
+            .withdraw_unbonded(&mut system, &mut balances, alice)
+            .unwrap();
+        assert_eq!(balances.get_free_balance(alice), 600u64);
+        assert_eq!(staking.unbonding(alice), &[(25, 50)]);
+        assert_eq!(
+            system.events().last(),
+            Some(
+                &Event::Withdrawn {
+                    who: alice,
+                    amount: 100,
+                }
+                .into()
+            )
+        );
+        assert!(staking.check_consistency(&balances).is_empty());
+    }
+
+    #[test]
+    fn test_expired_stake_locks_are_released_in_bulk() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let cap = <Runtime as StakingConfig>::MAX_UNLOCKS_PER_BLOCK as u64;
+        for who in 1..=cap + 1 {
+            staking.lock_stake(&mut system, who, 100, 5);
+        }
+        staking.lock_stake(&mut system, 1, 50, 8);
+        system.take_events();
+
+        // Nothing is due yet
+        staking.on_initialize(&mut system, 4);
+        assert!(system.events().is_empty());
+
+        // The cap leaves the last account for the next block
+        staking.on_initialize(&mut system, 5);
+        assert_eq!(system.take_events().len(), cap as usize);
+        assert_eq!(staking.stake_locks.get(&1).map(Vec::len), Some(1));
+        staking.on_initialize(&mut system, 6);
+        assert_eq!(
+            system.take_events(),
+            vec![Event::StakeUnlocked {
+                who: cap + 1,
+                amount: 100
+            }
+            .into()]
+        );
+
+        staking.on_initialize(&mut system, 8);
+        assert_eq!(system.take_events().len(), 1);
+        assert!(staking.stake_locks.is_empty());
+        assert!(staking.lock_expiries.is_empty());
+    }
+
+    #[test]
+    fn test_report_offence_slashes_unbonding_chunks() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+        staking
+            .stake(&mut system, &mut balances, alice, 500)
+            .unwrap();
+        let result = staking.report_offence(&mut system, &mut balances, bob, alice, 1_000_001);
+        assert_eq!(result, Err(StakingError::InvalidSeverity));
+        let result = staking.report_offence(&mut system, &mut balances, alice, alice, 1);
+        assert_eq!(result, Err(StakingError::SelfReport));
+
+        // alice tries to escape by unbonding almost everything first
+        staking.unbond(&mut system, alice, 450).unwrap();
+
+        // Half of the bond goes: all 50 staked, then 200 of the chunk
+        staking
+            .report_offence(&mut system, &mut balances, bob, alice, 500_000)
+            .unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 0u64);
+        assert_eq!(staking.unbonding(alice), &[(20, 250)]);
+        assert_eq!(balances.get_free_balance(bob), 25u64);
+        // The rest goes to the treasury rather than being burned
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        assert_eq!(balances.get_free_balance(pot), 225u64);
+        assert_eq!(balances.total_issuance(), 1000u64);
+        assert_eq!(
+            system.events().last(),
+            Some(
+                &Event::Slashed {
+                    offender: alice,
+                    amount: 250,
+                    reporter: bob,
+                    finder_fee: 25,
+                }
+                .into()
+            )
+        );
+        assert!(staking.check_consistency(&balances).is_empty());
+    }
+
+    #[test]
+    fn test_check_consistency_and_repair() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        balances.set_balance(alice, 1000).unwrap();
+        balances.set_balance(bob, 1000).unwrap();
+        staking
+            .stake(&mut system, &mut balances, alice, 400)
+            .unwrap();
+        assert!(staking.check_consistency(&balances).is_empty());
+
+        // Simulate a botched migration
+        staking.ledger.insert(alice, 500);
+        staking.ledger.insert(charlie, 0);
+        balances
+            .hold(&mut system, HoldReason::StakingBond, bob, 100)
+            .unwrap();
+
+        let expected = vec![
+            Inconsistency::BondMismatch {
+                who: alice,
+                ledger: 500,
+                held: 400,
+            },
+            Inconsistency::BondMismatch {
+                who: bob,
+                ledger: 0,
+                held: 100,
+            },
+            Inconsistency::EmptyLedgerEntry { who: charlie },
+        ];
+        assert_eq!(staking.check_consistency(&balances), expected);
+
+        assert_eq!(staking.repair(&balances), expected);
+        assert!(staking.check_consistency(&balances).is_empty());
+        assert_eq!(staking.stakers(), vec![(alice, 400), (bob, 100)]);
+    }
+
+    #[test]
+    fn test_era_rewards_compound() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in [alice, bob, charlie] {
+            balances.set_balance(who, 1000).unwrap();
+        }
+        staking
+            .stake(&mut system, &mut balances, alice, 300)
+            .unwrap();
+        staking.stake(&mut system, &mut balances, bob, 100).unwrap();
+
+        // Nothing happens until the era is over
+        staking.on_finalize(&mut system, &mut balances, 99);
+        assert_eq!(staking.current_era(), 0);
+
+        // The 1000 reward is split 3:1 and bonded
+        staking.on_finalize(&mut system, &mut balances, 100);
+        assert_eq!(staking.current_era(), 1);
+        assert_eq!(staking.era_reward(0), Some(1000));
+        assert_eq!(staking.get_staked_balance(alice), 1050u64);
+        assert_eq!(staking.get_staked_balance(bob), 350u64);
+
+        // Era 1 pays on the compounded stake alongside a newcomer
+        staking
+            .stake(&mut system, &mut balances, charlie, 600)
+            .unwrap();
+        staking.on_finalize(&mut system, &mut balances, 200);
+        assert_eq!(staking.payout_history(alice), &[(0, 750), (1, 525)]);
+        assert_eq!(staking.payout_history(charlie), &[(1, 300)]);
+        assert_eq!(balances.total_issuance(), 3000 + 2000);
+        assert!(staking.check_consistency(&balances).is_empty());
+    }
+
+    #[test]
+    fn test_nominators_share_rewards_and_slashes() {
+        let (alice, bob, charlie) = (1u64, 2u64, 3u64);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in [alice, bob, charlie] {
+            balances.set_balance(who, 1000).unwrap();
+        }
+        staking
+            .stake(&mut system, &mut balances, alice, 300)
+            .unwrap();
+        staking.stake(&mut system, &mut balances, bob, 100).unwrap();
+
+        for targets in [vec![], vec![charlie], vec![alice, alice]] {
+            let result = staking.nominate(&mut system, &mut balances, charlie, targets, 100);
+            assert_eq!(result, Err(StakingError::InvalidTargets));
+        }
+        let result = staking.nominate(&mut system, &mut balances, charlie, vec![alice, bob], 1);
+        assert_eq!(result, Err(StakingError::NominationTooSmall));
+        staking
+            .nominate(&mut system, &mut balances, charlie, vec![alice], 100)
+            .unwrap();
+        assert_eq!(staking.backings(), vec![(alice, 400), (bob, 100)]);
+        assert_eq!(staking.nominators_of(alice), vec![(charlie, 100)]);
+
+        // alice's 800 share: 80 commission, then 720 split 3:1 with charlie
+        staking.on_finalize(&mut system, &mut balances, 100);
+        assert_eq!(staking.validators(), &[alice, bob]);
+        assert_eq!(staking.payout_history(alice), &[(0, 620)]);
+        assert_eq!(staking.payout_history(bob), &[(0, 200)]);
+        assert_eq!(staking.payout_history(charlie), &[(0, 180)]);
+        assert_eq!(staking.nominations_of(charlie), &[(alice, 280)]);
+        assert_eq!(staking.era_reward(0), Some(1000));
+
+        // Half of alice's bond goes, and half of charlie's backing with it
+        staking
+            .report_offence(&mut system, &mut balances, bob, alice, 500_000)
+            .unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 460u64);
+        assert_eq!(staking.nominations_of(charlie), &[(alice, 140)]);
+        assert_eq!(balances.get_free_balance(bob), 960u64);
+        assert!(system.events().contains(
+            &Event::NominatorSlashed {
+                nominator: charlie,
+                validator: alice,
+                amount: 140,
+            }
+            .into()
+        ));
+        assert!(staking.check_consistency(&balances).is_empty());
+
+        staking.chill(&mut system, charlie).unwrap();
+        assert_eq!(staking.nominations_of(charlie), &[]);
+        assert_eq!(staking.unbonding(charlie), &[(20, 140)]);
+        assert_eq!(
+            staking.chill(&mut system, charlie),
+            Err(StakingError::NotNominating)
+        );
+        assert!(staking.check_consistency(&balances).is_empty());
+    }
+
+    #[test]
+    fn test_simulate_slash_reports_without_applying() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for (who, stake) in [(1u64, 100), (2, 100), (3, 100), (4, 100), (5, 60)] {
+            balances.set_balance(who, 1000).unwrap();
+            staking
+                .stake(&mut system, &mut balances, who, stake)
+                .unwrap();
+        }
+        balances.set_balance(6, 1000).unwrap();
+        staking
+            .nominate(&mut system, &mut balances, 6, vec![1], 50)
+            .unwrap();
+
+        // 1 drops to a backing of 30, below 5's 60
+        let report = staking.simulate_slash(1, 800_000).unwrap();
+        assert_eq!(
+            report,
+            SlashReport {
+                validator_loss: 80,
+                nominator_losses: vec![(6, 40)],
+                finder_fee: 12,
+                treasury_gain: 108,
+                leaving: vec![1],
+                joining: vec![5],
+            }
+        );
+        assert_eq!(staking.get_staked_balance(1), 100u64);
+        assert_eq!(staking.nominations_of(6), &[(1, 50)]);
+        assert_eq!(
+            staking.simulate_slash(1, 1_000_001),
+            Err(StakingError::InvalidSeverity)
+        );
+
+        // The real slash takes what the report said
+        staking
+            .report_offence(&mut system, &mut balances, 2, 1, 800_000)
+            .unwrap();
+        assert_eq!(staking.get_staked_balance(1), 20u64);
+        assert_eq!(staking.nominations_of(6), &[(1, 10)]);
+        assert_eq!(balances.get_free_balance(2), 912u64);
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        assert_eq!(balances.get_free_balance(pot), report.treasury_gain);
+    }
+
+    #[test]
+    fn test_best_valid_solution_is_elected() {
+        let (alice, bob, charlie, dave) = (1u64, 2u64, 3u64, 4u64);
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in [alice, bob, charlie, dave] {
+            balances.set_balance(who, 1000).unwrap();
+        }
+        staking
+            .stake(&mut system, &mut balances, alice, 300)
+            .unwrap();
+        staking.stake(&mut system, &mut balances, bob, 100).unwrap();
+
+        // Too early: the signed phase opens after block 84
+        let result = staking.submit_solution(&mut system, &mut balances, charlie, vec![alice], 300);
+        assert_eq!(result, Err(StakingError::ElectionClosed));
+        staking.on_finalize(&mut system, &mut balances, 84);
+        assert_eq!(
+            staking.election_snapshot(),
+            Some(&[(alice, 300), (bob, 100)][..])
+        );
+
+        // Stake added after the snapshot doesn't count towards the election
+        staking
+            .stake(&mut system, &mut balances, dave, 500)
+            .unwrap();
+
+        // charlie inflates the score, bob submits a weaker but honest solution
+        staking
+            .submit_solution(&mut system, &mut balances, charlie, vec![alice, dave], 800)
+            .unwrap();
+        staking
+            .submit_solution(&mut system, &mut balances, bob, vec![bob], 100)
+            .unwrap();
+        staking
+            .submit_solution(&mut system, &mut balances, alice, vec![alice, bob], 400)
+            .unwrap();
+        let result = staking.submit_solution(&mut system, &mut balances, bob, vec![alice], 300);
+        assert_eq!(result, Err(StakingError::AlreadySubmitted));
+
+        staking.on_finalize(&mut system, &mut balances, 100);
+        assert_eq!(staking.validators(), &[alice, bob]);
+        assert!(system.events().contains(
+            &Event::ValidatorsElected {
+                era: 1,
+                validators: vec![alice, bob],
+                compute: ElectionCompute::Signed,
+            }
+            .into()
+        ));
+
+        // Only the invalid submission loses its deposit
+        assert_eq!(balances.get_free_balance(charlie), 990u64);
+        assert_eq!(balances.get_free_balance(bob), 900u64);
+        assert_eq!(
+            balances.get_held_balance(HoldReason::ElectionDeposit, alice),
+            0u64
+        );
+        assert!(staking.submissions().is_empty());
+        assert_eq!(staking.election_snapshot(), None);
+    }
+
+    #[test]
+    fn test_election_falls_back_to_largest_stakers() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for (who, stake) in [(1u64, 50u64), (2, 20), (3, 40), (4, 20), (5, 30)] {
+            balances.set_balance(who, 1000).unwrap();
+            staking
+                .stake(&mut system, &mut balances, who, stake)
+                .unwrap();
+        }
+
+        // No solutions at all, so the top four are picked on-chain
+        staking.on_finalize(&mut system, &mut balances, 100);
+        assert_eq!(staking.validators(), &[1, 2, 3, 5]);
+        assert!(system.events().contains(
+            &Event::ValidatorsElected {
+                era: 1,
+                validators: vec![1, 2, 3, 5],
+                compute: ElectionCompute::Fallback,
+            }
+            .into()
+        ));
+    }
+
+    #[test]
+    fn test_next_validators_are_queued_before_the_handoff() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in [1u64, 2] {
+            balances.set_balance(who, 1000).unwrap();
+        }
+        staking.stake(&mut system, &mut balances, 1, 100).unwrap();
+        staking.on_finalize(&mut system, &mut balances, 100);
+        assert_eq!(staking.validators(), &[1]);
+
+        // The election for era 2 runs ROTATION_GRACE_PERIOD blocks before era 1 ends
+        staking.stake(&mut system, &mut balances, 2, 100).unwrap();
+        staking.on_finalize(&mut system, &mut balances, 194);
+        assert_eq!(staking.queued_validators(), None);
+        staking.on_finalize(&mut system, &mut balances, 195);
+        assert_eq!(staking.queued_validators(), Some(&[1, 2][..]));
+        assert_eq!(staking.validators(), &[1]);
+        assert!(system.events().contains(
+            &Event::ValidatorsElected {
+                era: 2,
+                validators: vec![1, 2],
+                compute: ElectionCompute::Fallback,
+            }
+            .into()
+        ));
+
+        // Too late to submit; the queued set is final
+        let result = staking.submit_solution(&mut system, &mut balances, 2, vec![2], 100);
+        assert_eq!(result, Err(StakingError::ElectionClosed));
+        staking.on_finalize(&mut system, &mut balances, 199);
+        assert_eq!(staking.election_snapshot(), None);
+
+        staking.on_finalize(&mut system, &mut balances, 200);
+        assert_eq!(staking.validators(), &[1, 2]);
+        assert_eq!(staking.queued_validators(), None);
+        assert_eq!(staking.current_era(), 2);
+    }
+
+    #[test]
+    fn test_validator_exits_are_capped_per_era() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in [1u64, 2, 3, 4] {
+            balances.set_balance(who, 1000).unwrap();
+            staking.stake(&mut system, &mut balances, who, 100).unwrap();
+        }
+        staking.on_finalize(&mut system, &mut balances, 100);
+        assert_eq!(staking.validators(), &[1, 2, 3, 4]);
+
+        // One exit per era: 1 leaves now, 2 and 3 have to wait
+        let stake = staking.get_staked_balance(1);
+        staking.unbond(&mut system, 1, stake).unwrap();
+        assert_eq!(staking.get_staked_balance(1), 0u64);
+        staking.unbond(&mut system, 2, stake).unwrap();
+        staking.unbond(&mut system, 3, stake - 5).unwrap();
+        assert_eq!(staking.get_staked_balance(2), stake);
+        assert_eq!(staking.exit_queue_position(2), Some(0));
+        assert_eq!(staking.exit_queue_position(3), Some(1));
+        assert_eq!(
+            staking.unbond(&mut system, 3, stake),
+            Err(StakingError::AlreadyInExitQueue)
+        );
+        assert_eq!(
+            staking.unstake(&mut system, &mut balances, 4, stake),
+            Err(StakingError::ExitCapReached)
+        );
+        // Unbonding that keeps the minimum bond isn't an exit
+        staking.unbond(&mut system, 4, 50).unwrap();
+
+        // Each new era lets the next queued validator out
+        staking.on_finalize(&mut system, &mut balances, 200);
+        assert_eq!(staking.exit_queue_position(2), None);
+        assert_eq!(staking.exit_queue_position(3), Some(0));
+        assert_eq!(staking.unbonding(2), &[(20, stake)]);
+        staking.on_finalize(&mut system, &mut balances, 300);
+        assert_eq!(staking.exit_queue_position(3), None);
+        assert_eq!(staking.unbonding(3), &[(20, stake - 5)]);
+        assert!(staking.check_consistency(&balances).is_empty());
+    }
+
+    #[test]
+    fn test_random_calls_never_panic() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let amounts = [0u64, 1, 500, u64::MAX - 1, u64::MAX];
+
+        // Simple LCG so the sequence is reproducible
+        let mut seed = 0x5eed_u64;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+
+        for _ in 0..10_000 {
+            let who = (next() % 3) as u64;
+            let amount = amounts[next() % amounts.len()];
+            let _ = match next() % 9 {
+                0 => balances
+                    .set_balance(who, amount)
+                    .map_err(StakingError::from),
+                1 => staking.stake(&mut system, &mut balances, who, amount),
+                2 => staking.unstake(&mut system, &mut balances, who, amount),
+                3 => staking.unbond(&mut system, who, amount),
+                4 => staking.withdraw_unbonded(&mut system, &mut balances, who),
+                5 => {
+                    let severity = [0, 1, 500_000, 1_000_000][next() % 4];
+                    staking.report_offence(&mut system, &mut balances, who, (who + 1) % 3, severity)
+                }
+                6 => staking.nominate(&mut system, &mut balances, who, vec![(who + 1) % 3], amount),
+                7 => staking.chill(&mut system, who),
+                _ => system
+                    .initialize(system.block_number() + 1)
+                    .map_err(|_| StakingError::Overflow),
+            };
+
+            // The ledger and the staking hold never drift apart
+            assert!(staking.check_consistency(&balances).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_validator_metadata_breaks_election_ties() {
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        for who in 1..=6u64 {
+            balances.set_balance(who, 1000).unwrap();
+            let stake = if who <= 2 { 200 } else { 100 };
+            staking
+                .stake(&mut system, &mut balances, who, stake)
+                .unwrap();
+        }
+        let declarations: [(u64, &str, &[&str]); 5] = [
+            (1, "eu", &["hetzner"]),
+            (2, "eu", &[]),
+            (3, "eu", &["hetzner"]),
+            (4, "us", &["hetzner"]),
+            (6, "asia", &["aws", "ovh"]),
+        ];
+        for (who, region, providers) in declarations {
+            staking
+                .set_validator_metadata(
+                    &mut system,
+                    &mut balances,
+                    who,
+                    region.to_string(),
+                    tags(providers),
+                )
+                .unwrap();
+        }
+        // The deposit covers the region and each provider tag
+        assert_eq!(
+            balances.get_held_balance(HoldReason::ValidatorMetadata, 6),
+            15u64
+        );
+        assert_eq!(
+            staking.set_validator_metadata(&mut system, &mut balances, 5, String::new(), vec![]),
+            Err(StakingError::InvalidMetadataTag)
+        );
+        assert_eq!(
+            staking.set_validator_metadata(
+                &mut system,
+                &mut balances,
+                5,
+                "us".to_string(),
+                tags(&["a", "b", "c", "d", "e"])
+            ),
+            Err(StakingError::TooManyProviderTags)
+        );
+        assert_eq!(staking.diversity_score(&3, &[1, 2]), 0);
+        assert_eq!(staking.diversity_score(&6, &[1, 2]), 3);
+
+        // 3, 4, 5 and 6 tie for the last two seats: 6 adds a new region and two
+        // providers, then 4 a new region, while 3 duplicates 1 and 5 declared nothing
+        staking.on_finalize(&mut system, &mut balances, 100);
+        assert_eq!(staking.validators(), &[1, 2, 4, 6]);
+
+        // Shrinking the declaration releases the difference, clearing releases the rest
+        staking
+            .set_validator_metadata(&mut system, &mut balances, 6, "asia".to_string(), vec![])
+            .unwrap();
+        assert_eq!(
+            balances.get_held_balance(HoldReason::ValidatorMetadata, 6),
+            5u64
+        );
+        staking
+            .clear_validator_metadata(&mut system, &mut balances, 6)
+            .unwrap();
+        assert_eq!(
+            balances.get_held_balance(HoldReason::ValidatorMetadata, 6),
+            0u64
+        );
+        assert_eq!(
+            staking.clear_validator_metadata(&mut system, &mut balances, 6),
+            Err(StakingError::NoValidatorMetadata)
+        );
     }
 }
@@ -0,0 +1,587 @@
+// Typed storage items for pallets, modelled on FRAME's `StorageMap` and `StorageValue`.
+// Pallets only see these types, so where map entries live is up to the backend.
+use crate::codec::{encode_unordered_map, Codec, CodecError, Decode, Encode};
+use crate::support::StorageUsage;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+// Where a `StorageMap` keeps its entries
+pub trait StorageBackend<K, V> {
+    fn get(&self, key: &K) -> Option<&V>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    fn get_or_insert_with(&mut self, key: K, default: impl FnOnce() -> V) -> &mut V;
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    // Entries in no particular order
+    fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_>;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// The default backend: entries live in memory only
+impl<K: Eq + Hash, V> StorageBackend<K, V> for HashMap<K, V> {
+    fn get(&self, key: &K) -> Option<&V> {
+        HashMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        HashMap::get_mut(self, key)
+    }
+
+    fn get_or_insert_with(&mut self, key: K, default: impl FnOnce() -> V) -> &mut V {
+        self.entry(key).or_insert_with(default)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        HashMap::insert(self, key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        HashMap::remove(self, key)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+        Box::new(HashMap::iter(self))
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageError {
+    Io(std::io::ErrorKind),
+    // The file holds something other than journal records
+    Codec(CodecError),
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(error: std::io::Error) -> Self {
+        StorageError::Io(error.kind())
+    }
+}
+
+impl From<CodecError> for StorageError {
+    fn from(error: CodecError) -> Self {
+        StorageError::Codec(error)
+    }
+}
+
+// Backend persisting entries to a journal file. Reads are served from memory; writes
+// are collected until `sync` appends them to the file, so an I/O failure is reported
+// there instead of being lost inside a pallet call.
+//
+// The journal is a write-ahead log: each sync appends one frame holding its records
+// and a checksum, flushed to disk before `sync` returns. Syncing once per block makes
+// a block's changes to this map land all at once, and a crash part-way through writing
+// a frame leaves a torn tail that `open` drops, recovering the map as of its last sync.
+// Each map has its own journal, so a crash between two maps' syncs can leave them a
+// block apart; state spanning several maps goes in a `Journal` instead.
+#[derive(Debug)]
+pub struct FileBackend<K, V> {
+    path: PathBuf,
+    entries: HashMap<K, V>,
+    // Keys written or removed since the last sync
+    dirty: HashSet<K>,
+}
+
+impl<K: Codec + Eq + Hash + Clone, V: Codec> FileBackend<K, V> {
+    // Open the journal at `path`, replaying its complete frames if the file exists.
+    // A torn last frame is cut off the file; a damaged frame before it is an error.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let path = path.into();
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        // Each record is a key and its new value, or none if it was removed
+        let mut entries = HashMap::new();
+        let mut input = &bytes[..];
+        let mut committed = 0;
+        while let Some(mut records) = read_frame(&mut input)? {
+            while !records.is_empty() {
+                match <(K, Option<V>)>::decode(&mut records)? {
+                    (key, Some(value)) => entries.insert(key, value),
+                    (key, None) => entries.remove(&key),
+                };
+            }
+            committed = bytes.len().saturating_sub(input.len());
+        }
+        if committed < bytes.len() {
+            let len = u64::try_from(committed).map_err(|_| CodecError::InvalidValue)?;
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(len)?;
+        }
+
+        Ok(Self {
+            path,
+            entries,
+            dirty: HashSet::new(),
+        })
+    }
+
+    // Append every change since the last sync to the journal as one frame
+    pub fn sync(&mut self) -> Result<(), StorageError> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+        let mut records = Vec::new();
+        for key in &self.dirty {
+            key.encode_to(&mut records);
+            self.entries.get(key).encode_to(&mut records);
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&frame(&records)?)?;
+        file.sync_data()?;
+        self.dirty.clear();
+        Ok(())
+    }
+}
+
+// Log of the whole runtime state, shared by every pallet's maps. Each commit appends
+// one frame holding a full state export, so a block's changes to all maps land
+// together: a crash mid-commit leaves a torn tail and `open` recovers the state as of
+// the last block committed. Opening also compacts the log down to that one frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    // Open the log at `path`, with the last state committed to it if there is one
+    pub fn open(path: impl Into<PathBuf>) -> Result<(Self, Option<Vec<u8>>), StorageError> {
+        let path = path.into();
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut input = &bytes[..];
+        let mut state = None;
+        while let Some(records) = read_frame(&mut input)? {
+            state = Some(records);
+        }
+        // Rewrite the log as just its last frame, swapped in whole so a crash while
+        // compacting leaves the old log in place
+        let compacted = state.map(frame).transpose()?.unwrap_or_default();
+        if compacted != bytes {
+            let staging = path.with_extension("compact");
+            let mut file = std::fs::File::create(&staging)?;
+            file.write_all(&compacted)?;
+            file.sync_data()?;
+            std::fs::rename(&staging, &path)?;
+        }
+
+        Ok((Self { path }, state.map(<[u8]>::to_vec)))
+    }
+
+    // Append a state export to the log, flushed to disk before returning
+    pub fn commit(&mut self, state: &[u8]) -> Result<(), StorageError> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&frame(state)?)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+// Frame records for the log: their length, the records and their checksum
+fn frame(records: &[u8]) -> Result<Vec<u8>, StorageError> {
+    let len = u32::try_from(records.len()).map_err(|_| CodecError::InvalidValue)?;
+    let mut frame = len.encode();
+    frame.extend_from_slice(records);
+    crate::system::fnv1a(records).encode_to(&mut frame);
+    Ok(frame)
+}
+
+// Take the next frame's records off the journal: a length, the records and their
+// checksum. None at the end, or for a frame cut short at the end by a crash.
+fn read_frame<'a>(input: &mut &'a [u8]) -> Result<Option<&'a [u8]>, StorageError> {
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let Some((records, checksum, rest)) = split_frame(input) else {
+        // Cut short, which a crash can only do to the last frame. A complete frame
+        // further on means the length itself was damaged.
+        if (1..input.len()).any(|start| is_valid_frame(&input[start..])) {
+            return Err(StorageError::Codec(CodecError::InvalidValue));
+        }
+        return Ok(None);
+    };
+    if checksum != crate::system::fnv1a(records) {
+        // Only the last frame can have been torn by a crash
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        return Err(StorageError::Codec(CodecError::InvalidValue));
+    }
+
+    *input = rest;
+    Ok(Some(records))
+}
+
+// Split a frame into its records, its stored checksum and what follows it, or None
+// if the input ends before the frame does
+fn split_frame(input: &[u8]) -> Option<(&[u8], u64, &[u8])> {
+    let mut rest = input;
+    let len = usize::try_from(u32::decode(&mut rest).ok()?).ok()?;
+    if rest.len() < len {
+        return None;
+    }
+    let (records, mut rest) = rest.split_at(len);
+    let checksum = u64::decode(&mut rest).ok()?;
+    Some((records, checksum, rest))
+}
+
+fn is_valid_frame(input: &[u8]) -> bool {
+    split_frame(input)
+        .is_some_and(|(records, checksum, _)| checksum == crate::system::fnv1a(records))
+}
+
+impl<K: Eq + Hash + Clone, V> StorageBackend<K, V> for FileBackend<K, V> {
+    fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let value = self.entries.get_mut(key)?;
+        self.dirty.insert(key.clone());
+        Some(value)
+    }
+
+    fn get_or_insert_with(&mut self, key: K, default: impl FnOnce() -> V) -> &mut V {
+        self.dirty.insert(key.clone());
+        self.entries.entry(key).or_insert_with(default)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.dirty.insert(key.clone());
+        self.entries.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let value = self.entries.remove(key)?;
+        self.dirty.insert(key.clone());
+        Some(value)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+        Box::new(self.entries.iter())
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// A map in pallet storage, kept in memory unless another backend is given
+pub struct StorageMap<K, V, B = HashMap<K, V>> {
+    backend: B,
+    _entries: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, B: StorageBackend<K, V>> StorageMap<K, V, B> {
+    pub fn new() -> Self
+    where
+        B: Default,
+    {
+        Self::with_backend(B::default())
+    }
+
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            _entries: PhantomData,
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.backend.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.backend.get_mut(key)
+    }
+
+    // Get the entry for `key`, inserting `default()` first if there's none
+    pub fn get_or_insert_with(&mut self, key: K, default: impl FnOnce() -> V) -> &mut V {
+        self.backend.get_or_insert_with(key, default)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.backend.get(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.backend.insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.backend.remove(key)
+    }
+
+    // Entries in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.backend.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.backend.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.backend.is_empty()
+    }
+
+    // Approximate state held by the map, ignoring heap bytes owned by the entries
+    pub fn storage_usage(&self) -> StorageUsage {
+        StorageUsage {
+            items: self.len(),
+            bytes: self.len().saturating_mul(std::mem::size_of::<(K, V)>()),
+        }
+    }
+}
+
+impl<'a, K, V, B: StorageBackend<K, V>> IntoIterator for &'a StorageMap<K, V, B> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Box<dyn Iterator<Item = (&'a K, &'a V)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.backend.iter()
+    }
+}
+
+impl<K, V, B: StorageBackend<K, V> + Default> Default for StorageMap<K, V, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, B: Clone> Clone for StorageMap<K, V, B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            _entries: PhantomData,
+        }
+    }
+}
+
+impl<K, V, B: Debug> Debug for StorageMap<K, V, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.backend.fmt(f)
+    }
+}
+
+impl<K, V, B: PartialEq> PartialEq for StorageMap<K, V, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.backend == other.backend
+    }
+}
+
+// Encoded like a `HashMap`, whatever the backend
+impl<K: Encode, V: Encode, B: StorageBackend<K, V>> Encode for StorageMap<K, V, B> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_unordered_map(self.iter(), dest);
+    }
+}
+
+impl<K: Decode + Eq + Hash, V: Decode, B: StorageBackend<K, V> + Default> Decode
+    for StorageMap<K, V, B>
+{
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let mut map = Self::new();
+        for (key, value) in HashMap::<K, V>::decode(input)? {
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+// A single value in pallet storage
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StorageValue<V> {
+    value: V,
+}
+
+impl<V> StorageValue<V> {
+    pub fn new(value: V) -> Self {
+        Self { value }
+    }
+
+    pub fn get(&self) -> V
+    where
+        V: Clone,
+    {
+        self.value.clone()
+    }
+
+    pub fn put(&mut self, value: V) {
+        self.value = value;
+    }
+
+    pub fn mutate<R>(&mut self, f: impl FnOnce(&mut V) -> R) -> R {
+        f(&mut self.value)
+    }
+
+    pub fn storage_usage(&self) -> StorageUsage {
+        StorageUsage::value::<V>(0)
+    }
+}
+
+impl<V: Encode> Encode for StorageValue<V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.value.encode_to(dest);
+    }
+}
+
+impl<V: Decode> Decode for StorageValue<V> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self::new(V::decode(input)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_backend_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("storage-map-{}.journal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut map: StorageMap<u64, String, FileBackend<u64, String>> =
+            StorageMap::with_backend(FileBackend::open(&path).unwrap());
+        map.insert(1, "alice".to_string());
+        map.insert(2, "bob".to_string());
+        map.backend_mut().sync().unwrap();
+
+        map.get_mut(&1).unwrap().push_str(" smith");
+        map.remove(&2);
+        map.get_or_insert_with(3, || "charlie".to_string());
+        map.backend_mut().sync().unwrap();
+        // Not synced, so lost on reopening
+        map.insert(4, "dave".to_string());
+
+        let reopened: StorageMap<u64, String, FileBackend<u64, String>> =
+            StorageMap::with_backend(FileBackend::open(&path).unwrap());
+        let mut entries: Vec<_> = reopened.iter().collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                (&1, &"alice smith".to_string()),
+                (&3, &"charlie".to_string())
+            ]
+        );
+
+        // Both backends encode the same entries to the same bytes
+        let mut memory: StorageMap<u64, String> = StorageMap::new();
+        memory.insert(3, "charlie".to_string());
+        memory.insert(1, "alice smith".to_string());
+        assert_eq!(memory.encode(), reopened.encode());
+
+        // A frame whose checksum holds but whose records don't decode is corruption
+        let mut frame = 3u32.encode();
+        frame.extend_from_slice(&[1, 2, 3]);
+        crate::system::fnv1a(&[1, 2, 3]).encode_to(&mut frame);
+        std::fs::write(&path, frame).unwrap();
+        assert_eq!(
+            FileBackend::<u64, String>::open(&path).map(|_| ()),
+            Err(StorageError::Codec(CodecError::UnexpectedEnd))
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_file_backend_recovers_from_a_crash_mid_sync() {
+        let path = std::env::temp_dir().join(format!("storage-wal-{}.journal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let open = || -> StorageMap<u64, u64, FileBackend<u64, u64>> {
+            StorageMap::with_backend(FileBackend::open(&path).unwrap())
+        };
+
+        // Block 1 is committed; the process dies while block 2 is being written
+        let mut map = open();
+        map.insert(1, 10);
+        map.insert(2, 20);
+        map.backend_mut().sync().unwrap();
+        let committed = std::fs::metadata(&path).unwrap().len();
+        map.insert(1, 11);
+        map.remove(&2);
+        map.insert(3, 30);
+        map.backend_mut().sync().unwrap();
+        drop(map);
+        let bytes = std::fs::read(&path).unwrap();
+        let torn = usize::try_from(committed).unwrap() + 9;
+        std::fs::write(&path, &bytes[..torn]).unwrap();
+
+        // Restarting recovers block 1 in full and none of block 2
+        let mut map = open();
+        let mut entries: Vec<_> = map.iter().map(|(key, value)| (*key, *value)).collect();
+        entries.sort();
+        assert_eq!(entries, vec![(1, 10), (2, 20)]);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), committed);
+
+        // The journal carries on from there
+        map.insert(4, 40);
+        map.backend_mut().sync().unwrap();
+        assert_eq!(open().get(&4), Some(&40));
+
+        // Damage before the last frame can't be a torn write, so it is reported
+        let bytes = std::fs::read(&path).unwrap();
+        let mut damaged = bytes.clone();
+        damaged[5] ^= 0xff;
+        std::fs::write(&path, damaged).unwrap();
+        assert_eq!(
+            FileBackend::<u64, u64>::open(&path).map(|_| ()),
+            Err(StorageError::Codec(CodecError::InvalidValue))
+        );
+
+        // So is a first frame whose length runs past the end of the file: the frames
+        // after it are complete, and nothing is cut off
+        let mut damaged = bytes.clone();
+        damaged[..4].copy_from_slice(&u32::MAX.encode());
+        std::fs::write(&path, &damaged).unwrap();
+        assert_eq!(
+            FileBackend::<u64, u64>::open(&path).map(|_| ()),
+            Err(StorageError::Codec(CodecError::InvalidValue))
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), damaged);
+        let _ = std::fs::remove_file(&path);
+    }
+}
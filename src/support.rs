@@ -0,0 +1,162 @@
+// Building blocks shared by the runtime, modelled on Substrate's block format
+
+use crate::codec::{CodecError, Decode, Encode};
+
+// The part of a block that identifies it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header<BlockNumber> {
+    pub number: BlockNumber,
+}
+
+// A call together with the account that signed it and the signer's nonce
+#[derive(Clone, Debug, PartialEq)]
+pub struct Extrinsic<Caller, Nonce, Call> {
+    pub caller: Caller,
+    pub nonce: Nonce,
+    pub call: Call,
+}
+
+// Version byte leading an encoded extrinsic. An extrinsic can be built on one machine
+// and submitted later from another, so bump this whenever the layout changes and
+// older bytes are rejected rather than misread.
+pub const EXTRINSIC_VERSION: u8 = 2;
+
+impl<Caller: Encode, Nonce: Encode, Call: Encode> Encode for Extrinsic<Caller, Nonce, Call> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        EXTRINSIC_VERSION.encode_to(dest);
+        self.caller.encode_to(dest);
+        self.nonce.encode_to(dest);
+        self.call.encode_to(dest);
+    }
+}
+
+impl<Caller: Decode, Nonce: Decode, Call: Decode> Decode for Extrinsic<Caller, Nonce, Call> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let version = u8::decode(input)?;
+        if version != EXTRINSIC_VERSION {
+            return Err(CodecError::UnsupportedVersion(version));
+        }
+        Ok(Self {
+            caller: Decode::decode(input)?,
+            nonce: Decode::decode(input)?,
+            call: Decode::decode(input)?,
+        })
+    }
+}
+
+// Who a call is dispatched as. Signed calls come from an account; privileged calls
+// (e.g. force-finalizing a proposal) need Root, or the Council acting on an
+// approved governance referendum.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin<AccountId> {
+    Signed(AccountId),
+    Root,
+    Council,
+}
+
+// Rough cost of executing a call, counted in storage reads and writes
+pub type Weight = u64;
+
+// Calls declare their weight up front, so fees can be charged before they run
+pub trait GetWeight {
+    fn weight(&self) -> Weight;
+}
+
+// Identifies a call by its pallet's index and its own index within the pallet, the
+// tags leading its encoding
+pub type CallIndex = (u8, u8);
+
+// The call index of an encoded runtime call
+pub fn call_index_of(call: &impl Encode) -> CallIndex {
+    let bytes = call.encode();
+    (
+        bytes.first().copied().unwrap_or_default(),
+        bytes.get(1).copied().unwrap_or_default(),
+    )
+}
+
+// Weight of a variable number of items, each costing `per_item`
+pub fn weight_of(count: usize, per_item: Weight) -> Weight {
+    Weight::try_from(count)
+        .unwrap_or(Weight::MAX)
+        .saturating_mul(per_item)
+}
+
+// A header and the extrinsics to execute, in order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block<Header, Extrinsic> {
+    pub header: Header,
+    pub extrinsics: Vec<Extrinsic>,
+}
+
+// Approximate state held by a pallet: stored items and their in-memory size in bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub items: usize,
+    pub bytes: usize,
+}
+
+impl StorageUsage {
+    // Usage of a single value, plus any heap bytes it owns (e.g. string contents)
+    pub fn value<V>(heap_bytes: usize) -> Self {
+        Self {
+            items: 1,
+            bytes: std::mem::size_of::<V>().saturating_add(heap_bytes),
+        }
+    }
+
+    // Usage of every entry in a map, ignoring heap bytes owned by the entries
+    pub fn map<K, V>(map: &std::collections::HashMap<K, V>) -> Self {
+        Self {
+            items: map.len(),
+            bytes: map.len().saturating_mul(std::mem::size_of::<(K, V)>()),
+        }
+    }
+
+    pub fn saturating_add(self, other: Self) -> Self {
+        Self {
+            items: self.items.saturating_add(other.items),
+            bytes: self.bytes.saturating_add(other.bytes),
+        }
+    }
+}
+
+// Name and docs of one error variant, as listed in the runtime's error registry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorMetadata {
+    pub index: u8,
+    pub name: &'static str,
+    pub docs: &'static str,
+}
+
+// An error enum whose variants have stable indices within their pallet
+pub trait PalletError {
+    // Every variant, in index order
+    const ERRORS: &'static [ErrorMetadata];
+
+    fn error_index(&self) -> u8;
+}
+
+// Implement `PalletError` from a table of `index => Variant: "docs"` entries.
+// Indices are part of the public interface: never renumber or reuse one, and give
+// new variants the next free index. Wrapping variants are written `Variant(_)`.
+macro_rules! pallet_error {
+    ($error:ident { $($index:literal => $variant:ident $(($wildcard:tt))?: $docs:literal,)* }) => {
+        impl $crate::support::PalletError for $error {
+            const ERRORS: &'static [$crate::support::ErrorMetadata] = &[
+                $($crate::support::ErrorMetadata {
+                    index: $index,
+                    name: stringify!($variant),
+                    docs: $docs,
+                },)*
+            ];
+
+            fn error_index(&self) -> u8 {
+                match self {
+                    $($error::$variant $(($wildcard))? => $index,)*
+                }
+            }
+        }
+    };
+}
+pub(crate) use pallet_error;
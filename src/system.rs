@@ -1,6 +1,773 @@
+use crate::codec::{Codec, CodecError, Decode, Encode};
+use crate::labels::{display_account, AccountLabels};
+use crate::storage::{StorageMap, StorageValue};
+use crate::support::{
+    call_index_of, pallet_error, weight_of, CallIndex, GetWeight, StorageUsage, Weight,
+};
+use num::traits::{CheckedAdd, One, Zero};
+use std::collections::BTreeSet;
+use std::fmt::Debug;
 use std::hash::Hash;
 
-pub trait SystemConfig {
+pub trait SystemConfig: Sized {
     // Define the account identifier type
-    type AccountId: Eq + Hash + Clone;
+    type AccountId: Eq + Hash + Ord + Clone + Debug + Codec;
+    // Define the block number type
+    type BlockNumber: Zero + One + CheckedAdd + Copy + Ord + Debug + Codec;
+    // Define the type counting the transactions an account has sent
+    type Nonce: Zero + One + CheckedAdd + Copy + Eq + Debug + Codec;
+    // Aggregated event type of the runtime, built from each pallet's events
+    type RuntimeEvent: Clone + Debug + PartialEq + From<Event<Self>>;
+    // Aggregated call type of the runtime, so multisig accounts can wrap any call
+    type RuntimeCall: Clone + Debug + PartialEq + Codec + GetWeight;
+    // Number of decimal places used when displaying balances
+    const TOKEN_DECIMALS: u8;
+    // Ticker shown next to formatted balances
+    const TOKEN_SYMBOL: &'static str;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemError {
+    // Blocks must be executed one after another
+    InvalidBlockNumber,
+    // The extrinsic's nonce doesn't match the account's next nonce
+    InvalidNonce,
+    Overflow,
+    // A multisig needs a threshold between 1 and the number of owners
+    InvalidThreshold,
+    DuplicateOwner,
+    // A multisig with these owners and threshold already exists
+    MultisigExists,
+    UnknownMultisig,
+    NotOwner,
+    // The owner already approved this call
+    AlreadyApproved,
+    // The derived multisig id doesn't decode as an account id
+    InvalidAccountId,
+    // The call needs a different origin, e.g. Root for a privileged call
+    BadOrigin,
+    // A runtime upgrade needs a non-empty code blob
+    EmptyCode,
+    // A session must expire after the current block
+    InvalidExpiry,
+    // The principal hasn't authorized a session for this delegate
+    NoSession,
+    SessionExpired,
+    // The session doesn't cover this call
+    CallNotAuthorized,
+    // The block's state couldn't be written to the runtime's journal
+    CommitFailed,
+}
+
+pallet_error!(SystemError {
+    0 => InvalidBlockNumber: "Blocks must be executed one after another",
+    1 => InvalidNonce: "The extrinsic's nonce doesn't match the account's next nonce",
+    2 => Overflow: "An arithmetic operation overflowed",
+    3 => InvalidThreshold: "A multisig needs a threshold between 1 and the number of owners",
+    4 => DuplicateOwner: "An account is listed as a multisig owner more than once",
+    5 => MultisigExists: "A multisig with these owners and threshold already exists",
+    6 => UnknownMultisig: "No multisig exists with this account id",
+    7 => NotOwner: "The caller isn't an owner of the multisig",
+    8 => AlreadyApproved: "The owner already approved this call",
+    9 => InvalidAccountId: "The derived multisig id doesn't decode as an account id",
+    10 => BadOrigin: "The call needs a different origin, e.g. Root for a privileged call",
+    11 => EmptyCode: "A runtime upgrade needs a non-empty code blob",
+    12 => InvalidExpiry: "A session must expire after the current block",
+    13 => NoSession: "The principal hasn't authorized a session for this delegate",
+    14 => SessionExpired: "The delegate's session has expired",
+    15 => CallNotAuthorized: "The session doesn't cover this call",
+    16 => CommitFailed: "The block's state couldn't be written to the runtime's journal",
+});
+
+// An account controlled jointly by its owners
+#[derive(Clone, Debug, PartialEq)]
+pub struct Multisig<T: SystemConfig> {
+    // Sorted by account
+    pub owners: Vec<T::AccountId>,
+    // Approvals a call needs before it is dispatched
+    pub threshold: u32,
+}
+
+// Lets a delegate dispatch some of the principal's calls until a block, like a
+// short-lived session key
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session<T: SystemConfig> {
+    // Calls the delegate may dispatch as the principal
+    pub calls: Vec<CallIndex>,
+    // First block the session can no longer be used at
+    pub expires: T::BlockNumber,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: SystemConfig> {
+    CreateMultisig {
+        owners: Vec<T::AccountId>,
+        threshold: u32,
+    },
+    // Approve `call` on behalf of `multisig`; the approval that reaches the
+    // threshold dispatches it with the multisig as origin
+    ApproveAsMulti {
+        multisig: T::AccountId,
+        call: Box<T::RuntimeCall>,
+    },
+    // Replace the runtime code and bump the spec version; needs a Root origin
+    SetCode {
+        code: Vec<u8>,
+    },
+    // Let `delegate` dispatch `calls` as the signer until block `expires`, replacing
+    // any session it already has
+    AuthorizeSession {
+        delegate: T::AccountId,
+        calls: Vec<CallIndex>,
+        expires: T::BlockNumber,
+    },
+    RevokeSession {
+        delegate: T::AccountId,
+    },
+    // Dispatch `call` as `principal`, under a session it authorized for the signer
+    DispatchAsPrincipal {
+        principal: T::AccountId,
+        call: Box<T::RuntimeCall>,
+    },
+}
+
+// A call to dispatch with a multisig account as origin
+pub type AsMulti<T> = (
+    <T as SystemConfig>::AccountId,
+    <T as SystemConfig>::RuntimeCall,
+);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: SystemConfig> {
+    MultisigCreated {
+        multisig: T::AccountId,
+        owners: Vec<T::AccountId>,
+        threshold: u32,
+    },
+    MultisigApproval {
+        multisig: T::AccountId,
+        owner: T::AccountId,
+        approvals: u32,
+    },
+    MultisigExecuted {
+        multisig: T::AccountId,
+    },
+    CodeUpdated {
+        spec_version: u32,
+    },
+    SessionAuthorized {
+        principal: T::AccountId,
+        delegate: T::AccountId,
+        expires: T::BlockNumber,
+    },
+    SessionRevoked {
+        principal: T::AccountId,
+        delegate: T::AccountId,
+    },
+}
+
+impl<T: SystemConfig> Event<T> {
+    // Render the event as a sentence, e.g. "alice approved a call for account 7 (1 so far)"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::MultisigCreated {
+                multisig,
+                owners,
+                threshold,
+            } => format!(
+                "{} created as a {}-of-{} multisig of {}",
+                display_account(labels, multisig),
+                threshold,
+                owners.len(),
+                owners
+                    .iter()
+                    .map(|owner| display_account(labels, owner))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Event::MultisigApproval {
+                multisig,
+                owner,
+                approvals,
+            } => format!(
+                "{} approved a call for {} ({} so far)",
+                display_account(labels, owner),
+                display_account(labels, multisig),
+                approvals
+            ),
+            Event::MultisigExecuted { multisig } => format!(
+                "{} dispatched a call after enough approvals",
+                display_account(labels, multisig)
+            ),
+            Event::CodeUpdated { spec_version } => {
+                format!("the runtime was upgraded to spec version {}", spec_version)
+            }
+            Event::SessionAuthorized {
+                principal,
+                delegate,
+                expires,
+            } => format!(
+                "{} authorized {} to act for it until block {:?}",
+                display_account(labels, principal),
+                display_account(labels, delegate),
+                expires
+            ),
+            Event::SessionRevoked {
+                principal,
+                delegate,
+            } => format!(
+                "{} revoked the session of {}",
+                display_account(labels, principal),
+                display_account(labels, delegate)
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemPallet<T: SystemConfig> {
+    // Number of the block being executed, or last executed
+    block_number: StorageValue<T::BlockNumber>,
+    // Number of extrinsics each account has had applied
+    nonces: StorageMap<T::AccountId, T::Nonce>,
+    // Events deposited by pallets, in the order they happened
+    events: Vec<T::RuntimeEvent>,
+    multisigs: StorageMap<T::AccountId, Multisig<T>>,
+    // Owners that approved each pending call, keyed by (multisig, call hash)
+    approvals: StorageMap<(T::AccountId, u64), Vec<T::AccountId>>,
+    // Runtime code set by the last upgrade; empty until the first one
+    code: StorageValue<Vec<u8>>,
+    // Bumped by every runtime upgrade
+    spec_version: StorageValue<u32>,
+    // Sessions keyed by (principal, delegate); expired ones stay until revoked or
+    // replaced
+    sessions: StorageMap<(T::AccountId, T::AccountId), Session<T>>,
+}
+
+impl<T: SystemConfig> Default for SystemPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: SystemConfig> SystemPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            block_number: StorageValue::new(T::BlockNumber::zero()),
+            nonces: StorageMap::new(),
+            events: Vec::new(),
+            multisigs: StorageMap::new(),
+            approvals: StorageMap::new(),
+            code: StorageValue::new(Vec::new()),
+            spec_version: StorageValue::new(0),
+            sessions: StorageMap::new(),
+        }
+    }
+
+    // Handle a call signed by `origin`. Returns the call to dispatch next, and the
+    // account to dispatch it as, once a multisig call has enough approvals or a
+    // session delegate's call is authorized.
+    pub fn dispatch(
+        &mut self,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<Option<AsMulti<T>>, SystemError> {
+        match call {
+            Call::CreateMultisig { owners, threshold } => {
+                self.create_multisig(owners, threshold)?;
+            }
+            Call::ApproveAsMulti { multisig, call } => {
+                if self.approve_as_multi(origin, multisig.clone(), &call)? {
+                    return Ok(Some((multisig, *call)));
+                }
+            }
+            Call::SetCode { code } => {
+                self.set_code(code)?;
+            }
+            Call::AuthorizeSession {
+                delegate,
+                calls,
+                expires,
+            } => self.authorize_session(origin, delegate, calls, expires)?,
+            Call::RevokeSession { delegate } => self.revoke_session(origin, delegate)?,
+            Call::DispatchAsPrincipal { principal, call } => {
+                self.ensure_session_allows(&principal, &origin, &call)?;
+                return Ok(Some((principal, *call)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Store a new runtime code blob and bump the spec version. Returns the version
+    // upgraded from, so the runtime can run its migrations.
+    pub fn set_code(&mut self, code: Vec<u8>) -> Result<u32, SystemError> {
+        if code.is_empty() {
+            return Err(SystemError::EmptyCode);
+        }
+        let from = self.spec_version.get();
+        let spec_version = from.checked_add(1).ok_or(SystemError::Overflow)?;
+
+        self.code.put(code);
+        self.spec_version.put(spec_version);
+        self.deposit_event(Event::CodeUpdated { spec_version });
+
+        Ok(from)
+    }
+
+    // Get the spec version of the current runtime code
+    pub fn spec_version(&self) -> u32 {
+        self.spec_version.get()
+    }
+
+    // Get the runtime code set by the last upgrade
+    pub fn code(&self) -> Vec<u8> {
+        self.code.get()
+    }
+
+    // Start a new block: it must directly follow the current one. Events from the
+    // previous block are cleared, as each block reports its own.
+    pub fn initialize(&mut self, number: T::BlockNumber) -> Result<(), SystemError> {
+        let expected = self
+            .block_number
+            .get()
+            .checked_add(&T::BlockNumber::one())
+            .ok_or(SystemError::Overflow)?;
+        if number != expected {
+            return Err(SystemError::InvalidBlockNumber);
+        }
+
+        self.block_number.put(number);
+        self.events.clear();
+
+        Ok(())
+    }
+
+    // Get the current block number
+    pub fn block_number(&self) -> T::BlockNumber {
+        self.block_number.get()
+    }
+
+    // Get the nonce the account's next extrinsic must carry
+    pub fn get_nonce(&self, who: T::AccountId) -> T::Nonce {
+        *self.nonces.get(&who).unwrap_or(&T::Nonce::zero())
+    }
+
+    // Increase an account's nonce by one
+    pub fn inc_nonce(&mut self, who: T::AccountId) -> Result<(), SystemError> {
+        let nonce = self
+            .get_nonce(who.clone())
+            .checked_add(&T::Nonce::one())
+            .ok_or(SystemError::Overflow)?;
+        self.nonces.insert(who, nonce);
+
+        Ok(())
+    }
+
+    // Replay protection: accept only the account's next nonce
+    pub fn check_nonce(&self, who: T::AccountId, nonce: T::Nonce) -> Result<(), SystemError> {
+        if nonce != self.get_nonce(who) {
+            return Err(SystemError::InvalidNonce);
+        }
+
+        Ok(())
+    }
+
+    // Accept only the account's next nonce, then move past it
+    pub fn check_and_increment_nonce(
+        &mut self,
+        who: T::AccountId,
+        nonce: T::Nonce,
+    ) -> Result<(), SystemError> {
+        self.check_nonce(who.clone(), nonce)?;
+        self.inc_nonce(who)
+    }
+
+    // Record an event from any pallet
+    pub fn deposit_event(&mut self, event: impl Into<T::RuntimeEvent>) {
+        self.events.push(event.into());
+    }
+
+    // Get all events recorded so far
+    pub fn events(&self) -> &[T::RuntimeEvent] {
+        &self.events
+    }
+
+    // Remove and return all recorded events
+    pub fn take_events(&mut self) -> Vec<T::RuntimeEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Register a multisig account. Its id is derived from the owners and threshold,
+    // so the same set can always work out the id again.
+    pub fn create_multisig(
+        &mut self,
+        mut owners: Vec<T::AccountId>,
+        threshold: u32,
+    ) -> Result<T::AccountId, SystemError> {
+        owners.sort();
+        let distinct: BTreeSet<_> = owners.iter().collect();
+        if distinct.len() != owners.len() {
+            return Err(SystemError::DuplicateOwner);
+        }
+        if threshold == 0 || usize::try_from(threshold).map_or(true, |t| t > owners.len()) {
+            return Err(SystemError::InvalidThreshold);
+        }
+
+        let multisig = Self::multisig_account(&owners, threshold)?;
+        if self.multisigs.contains_key(&multisig) {
+            return Err(SystemError::MultisigExists);
+        }
+        self.multisigs.insert(
+            multisig.clone(),
+            Multisig {
+                owners: owners.clone(),
+                threshold,
+            },
+        );
+        self.deposit_event(Event::MultisigCreated {
+            multisig: multisig.clone(),
+            owners,
+            threshold,
+        });
+
+        Ok(multisig)
+    }
+
+    // Add `owner`'s approval of `call`. Returns true once the threshold is reached;
+    // the approvals are then cleared and the caller must dispatch the call as
+    // `multisig`.
+    pub fn approve_as_multi(
+        &mut self,
+        owner: T::AccountId,
+        multisig: T::AccountId,
+        call: &T::RuntimeCall,
+    ) -> Result<bool, SystemError> {
+        let threshold = {
+            let info = self
+                .multisigs
+                .get(&multisig)
+                .ok_or(SystemError::UnknownMultisig)?;
+            if info.owners.binary_search(&owner).is_err() {
+                return Err(SystemError::NotOwner);
+            }
+            info.threshold
+        };
+
+        let key = (multisig.clone(), call_hash(call));
+        let approvals = self.approvals.get_or_insert_with(key.clone(), Vec::new);
+        if approvals.contains(&owner) {
+            return Err(SystemError::AlreadyApproved);
+        }
+        approvals.push(owner.clone());
+        let count = u32::try_from(approvals.len()).unwrap_or(u32::MAX);
+
+        self.deposit_event(Event::MultisigApproval {
+            multisig: multisig.clone(),
+            owner,
+            approvals: count,
+        });
+        if count < threshold {
+            return Ok(false);
+        }
+
+        self.approvals.remove(&key);
+        self.deposit_event(Event::MultisigExecuted { multisig });
+        Ok(true)
+    }
+
+    // Get a multisig's owners and threshold
+    pub fn multisig(&self, multisig: &T::AccountId) -> Option<&Multisig<T>> {
+        self.multisigs.get(multisig)
+    }
+
+    // Get the owners that approved a pending multisig call
+    pub fn approvals(&self, multisig: T::AccountId, call: &T::RuntimeCall) -> &[T::AccountId] {
+        self.approvals
+            .get(&(multisig, call_hash(call)))
+            .map_or(&[], |approvals| approvals.as_slice())
+    }
+
+    // Let `delegate` dispatch the listed calls as `principal` until block `expires`
+    pub fn authorize_session(
+        &mut self,
+        principal: T::AccountId,
+        delegate: T::AccountId,
+        calls: Vec<CallIndex>,
+        expires: T::BlockNumber,
+    ) -> Result<(), SystemError> {
+        if expires <= self.block_number() {
+            return Err(SystemError::InvalidExpiry);
+        }
+
+        self.sessions.insert(
+            (principal.clone(), delegate.clone()),
+            Session { calls, expires },
+        );
+        self.deposit_event(Event::SessionAuthorized {
+            principal,
+            delegate,
+            expires,
+        });
+
+        Ok(())
+    }
+
+    // End a session before it expires
+    pub fn revoke_session(
+        &mut self,
+        principal: T::AccountId,
+        delegate: T::AccountId,
+    ) -> Result<(), SystemError> {
+        self.sessions
+            .remove(&(principal.clone(), delegate.clone()))
+            .ok_or(SystemError::NoSession)?;
+        self.deposit_event(Event::SessionRevoked {
+            principal,
+            delegate,
+        });
+
+        Ok(())
+    }
+
+    // Check that `delegate` may dispatch `call` as `principal` at the current block
+    pub fn ensure_session_allows(
+        &self,
+        principal: &T::AccountId,
+        delegate: &T::AccountId,
+        call: &T::RuntimeCall,
+    ) -> Result<(), SystemError> {
+        let session = self
+            .sessions
+            .get(&(principal.clone(), delegate.clone()))
+            .ok_or(SystemError::NoSession)?;
+        if session.expires <= self.block_number() {
+            return Err(SystemError::SessionExpired);
+        }
+        if !session.calls.contains(&call_index_of(call)) {
+            return Err(SystemError::CallNotAuthorized);
+        }
+
+        Ok(())
+    }
+
+    // Get the session `principal` authorized for `delegate`
+    pub fn session(
+        &self,
+        principal: &T::AccountId,
+        delegate: &T::AccountId,
+    ) -> Option<&Session<T>> {
+        self.sessions.get(&(principal.clone(), delegate.clone()))
+    }
+
+    // Whether the account is, owns or approved a call for a multisig, or is either
+    // side of a session, expired ones included
+    pub fn is_referenced(&self, who: &T::AccountId) -> bool {
+        self.multisigs
+            .iter()
+            .any(|(multisig, details)| multisig == who || details.owners.contains(who))
+            || self
+                .approvals
+                .iter()
+                .any(|((multisig, _), approvals)| multisig == who || approvals.contains(who))
+            || self
+                .sessions
+                .keys()
+                .any(|(principal, delegate)| principal == who || delegate == who)
+    }
+
+    // Account id of the multisig with these sorted owners and threshold
+    fn multisig_account(
+        owners: &[T::AccountId],
+        threshold: u32,
+    ) -> Result<T::AccountId, SystemError> {
+        let mut seed = b"multisig".to_vec();
+        owners.to_vec().encode_to(&mut seed);
+        threshold.encode_to(&mut seed);
+
+        // Stretch the hash so account ids of up to 32 bytes can be decoded from it
+        let mut bytes = Vec::new();
+        for round in 0u8..4 {
+            seed.push(round);
+            bytes.extend_from_slice(&fnv1a(&seed).to_le_bytes());
+            seed.pop();
+        }
+        T::AccountId::decode(&mut &bytes[..]).map_err(|_| SystemError::InvalidAccountId)
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        self.block_number
+            .storage_usage()
+            .saturating_add(self.nonces.storage_usage())
+            .saturating_add(StorageUsage {
+                items: self.events.len(),
+                bytes: self
+                    .events
+                    .len()
+                    .saturating_mul(std::mem::size_of::<T::RuntimeEvent>()),
+            })
+            .saturating_add(self.multisigs.storage_usage())
+            .saturating_add(self.approvals.storage_usage())
+            .saturating_add(StorageUsage::value::<Vec<u8>>(self.code().len()))
+            .saturating_add(self.spec_version.storage_usage())
+            .saturating_add(self.sessions.storage_usage())
+    }
+}
+
+// Identifies a pending multisig call by its encoding
+fn call_hash(call: &impl Encode) -> u64 {
+    fnv1a(&call.encode())
+}
+
+// 64-bit FNV-1a: stable across builds, unlike std's hasher
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl<T: SystemConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::CreateMultisig { owners, .. } => weight_of(owners.len(), 1).saturating_add(1),
+            // The approval reaching the threshold dispatches the call too
+            Call::ApproveAsMulti { call, .. } => call.weight().saturating_add(3),
+            Call::SetCode { code } => weight_of(code.len(), 1).saturating_add(10),
+            Call::AuthorizeSession { calls, .. } => weight_of(calls.len(), 1).saturating_add(1),
+            Call::RevokeSession { .. } => 1,
+            Call::DispatchAsPrincipal { call, .. } => call.weight().saturating_add(1),
+        }
+    }
+}
+
+impl<T: SystemConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::CreateMultisig { owners, threshold } => {
+                0u8.encode_to(dest);
+                owners.encode_to(dest);
+                threshold.encode_to(dest);
+            }
+            Call::ApproveAsMulti { multisig, call } => {
+                1u8.encode_to(dest);
+                multisig.encode_to(dest);
+                call.encode_to(dest);
+            }
+            Call::SetCode { code } => {
+                2u8.encode_to(dest);
+                code.encode_to(dest);
+            }
+            Call::AuthorizeSession {
+                delegate,
+                calls,
+                expires,
+            } => {
+                3u8.encode_to(dest);
+                delegate.encode_to(dest);
+                calls.encode_to(dest);
+                expires.encode_to(dest);
+            }
+            Call::RevokeSession { delegate } => {
+                4u8.encode_to(dest);
+                delegate.encode_to(dest);
+            }
+            Call::DispatchAsPrincipal { principal, call } => {
+                5u8.encode_to(dest);
+                principal.encode_to(dest);
+                call.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: SystemConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::CreateMultisig {
+                owners: Decode::decode(input)?,
+                threshold: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::ApproveAsMulti {
+                multisig: Decode::decode(input)?,
+                call: Decode::decode(input)?,
+            }),
+            2 => Ok(Call::SetCode {
+                code: Decode::decode(input)?,
+            }),
+            3 => Ok(Call::AuthorizeSession {
+                delegate: Decode::decode(input)?,
+                calls: Decode::decode(input)?,
+                expires: Decode::decode(input)?,
+            }),
+            4 => Ok(Call::RevokeSession {
+                delegate: Decode::decode(input)?,
+            }),
+            5 => Ok(Call::DispatchAsPrincipal {
+                principal: Decode::decode(input)?,
+                call: Decode::decode(input)?,
+            }),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: SystemConfig> Encode for Multisig<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.owners.encode_to(dest);
+        self.threshold.encode_to(dest);
+    }
+}
+
+impl<T: SystemConfig> Decode for Multisig<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            owners: Decode::decode(input)?,
+            threshold: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: SystemConfig> Encode for Session<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.calls.encode_to(dest);
+        self.expires.encode_to(dest);
+    }
+}
+
+impl<T: SystemConfig> Decode for Session<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            calls: Decode::decode(input)?,
+            expires: Decode::decode(input)?,
+        })
+    }
+}
+
+// Events are left out: they only describe the block being executed
+impl<T: SystemConfig> Encode for SystemPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block_number.encode_to(dest);
+        self.nonces.encode_to(dest);
+        self.multisigs.encode_to(dest);
+        self.approvals.encode_to(dest);
+        self.code.encode_to(dest);
+        self.spec_version.encode_to(dest);
+        self.sessions.encode_to(dest);
+    }
+}
+
+impl<T: SystemConfig> Decode for SystemPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            block_number: Decode::decode(input)?,
+            nonces: Decode::decode(input)?,
+            events: Vec::new(),
+            multisigs: Decode::decode(input)?,
+            approvals: Decode::decode(input)?,
+            code: Decode::decode(input)?,
+            spec_version: Decode::decode(input)?,
+            sessions: Decode::decode(input)?,
+        })
+    }
 }
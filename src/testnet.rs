@@ -0,0 +1,246 @@
+// In-process local testnet for end-to-end tests: N nodes start from the same genesis,
+// gossip extrinsics and blocks to the nodes they can reach, and take turns authoring
+// blocks. There is no real networking or consensus; delivery is instant and the run
+// is fully deterministic, so partitions and their healing can be scripted in tests.
+// Built with the `testnet` feature.
+
+use crate::genesis::{GenesisConfig, GenesisError};
+use crate::{Block, Extrinsic, Header, Runtime, RuntimeError};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub runtime: Runtime,
+    // Blocks this node imported, in order
+    pub chain: Vec<Block>,
+    // Extrinsics waiting to go into a block
+    pool: Vec<Extrinsic>,
+}
+
+impl Node {
+    pub fn pool(&self) -> &[Extrinsic] {
+        &self.pool
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestnetError {
+    Genesis(GenesisError),
+    // No node has this index
+    UnknownNode,
+    Runtime(RuntimeError),
+}
+
+impl From<GenesisError> for TestnetError {
+    fn from(error: GenesisError) -> Self {
+        TestnetError::Genesis(error)
+    }
+}
+
+impl From<RuntimeError> for TestnetError {
+    fn from(error: RuntimeError) -> Self {
+        TestnetError::Runtime(error)
+    }
+}
+
+pub struct Testnet {
+    nodes: Vec<Node>,
+    // Partition group of each node; nodes only reach the nodes in their own group
+    groups: Vec<usize>,
+}
+
+impl Testnet {
+    // Start `count` connected nodes from the same genesis
+    pub fn new(count: usize, genesis: &GenesisConfig<Runtime>) -> Result<Self, TestnetError> {
+        let runtime = Runtime::from_genesis(genesis)?;
+        let node = Node {
+            runtime,
+            chain: Vec::new(),
+            pool: Vec::new(),
+        };
+        Ok(Self {
+            nodes: vec![node; count],
+            groups: vec![0; count],
+        })
+    }
+
+    pub fn node(&self, index: usize) -> Option<&Node> {
+        self.nodes.get(index)
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    // Submit an extrinsic to a node, which gossips it to every node it can reach
+    pub fn submit(&mut self, to: usize, extrinsic: Extrinsic) -> Result<(), TestnetError> {
+        let group = *self.groups.get(to).ok_or(TestnetError::UnknownNode)?;
+        for index in self.reachable(group) {
+            if let Some(node) = self.nodes.get_mut(index) {
+                node.pool.push(extrinsic.clone());
+            }
+        }
+        Ok(())
+    }
+
+    // Split the network: each listed set of nodes only reaches its own members, and
+    // unlisted nodes end up together in one more group
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        let rest = groups.len();
+        self.groups = vec![rest; self.nodes.len()];
+        for (group, members) in groups.iter().enumerate() {
+            for index in *members {
+                if let Some(slot) = self.groups.get_mut(*index) {
+                    *slot = group;
+                }
+            }
+        }
+    }
+
+    // Reconnect every node. Nodes then follow the longest chain, ties going to the
+    // lowest node index, dropping the blocks of the other forks.
+    pub fn heal(&mut self) {
+        self.groups = vec![0; self.nodes.len()];
+        let best = self
+            .nodes
+            .iter()
+            .enumerate()
+            .max_by(|(a, x), (b, y)| x.chain.len().cmp(&y.chain.len()).then(b.cmp(a)))
+            .map(|(_, node)| node.clone());
+        if let Some(best) = best {
+            for node in &mut self.nodes {
+                let pool = std::mem::take(&mut node.pool);
+                *node = Node {
+                    pool,
+                    ..best.clone()
+                };
+            }
+        }
+    }
+
+    // Produce the next block in every partition group: the group's members take turns
+    // by block number, the author includes its whole pool, and the other members
+    // import the block
+    pub fn produce_block(&mut self) -> Result<(), TestnetError> {
+        let mut groups = self.groups.clone();
+        groups.sort_unstable();
+        groups.dedup();
+        for group in groups {
+            let members = self.reachable(group);
+            let Some(first) = members.first().and_then(|index| self.nodes.get(*index)) else {
+                continue;
+            };
+            let number = first
+                .runtime
+                .system
+                .block_number()
+                .checked_add(1)
+                .ok_or(RuntimeError::System(crate::system::SystemError::Overflow))?;
+            let turn = usize::try_from(number).unwrap_or_default();
+            let author = members
+                .get(turn.checked_rem(members.len()).unwrap_or_default())
+                .copied()
+                .ok_or(TestnetError::UnknownNode)?;
+            let extrinsics = self
+                .nodes
+                .get(author)
+                .map(|node| node.pool.clone())
+                .unwrap_or_default();
+            let block = Block {
+                header: Header { number },
+                extrinsics,
+            };
+            for index in members {
+                if let Some(node) = self.nodes.get_mut(index) {
+                    node.runtime.execute_block(block.clone())?;
+                    node.pool
+                        .retain(|extrinsic| !block.extrinsics.contains(extrinsic));
+                    node.chain.push(block.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Produce `count` blocks in a row
+    pub fn produce_blocks(&mut self, count: usize) -> Result<(), TestnetError> {
+        for _ in 0..count {
+            self.produce_block()?;
+        }
+        Ok(())
+    }
+
+    // Whether every node has the same state
+    pub fn in_sync(&self) -> bool {
+        let mut roots = self.nodes.iter().map(|node| node.runtime.state_root());
+        let first = roots.next();
+        roots.all(|root| Some(root) == first)
+    }
+
+    fn reachable(&self, group: usize) -> Vec<usize> {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(_, member_of)| **member_of == group)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::balances;
+    use crate::genesis::GenesisAccount;
+
+    fn transfer(nonce: u32, amount: u64) -> Extrinsic {
+        Extrinsic {
+            caller: 1,
+            nonce,
+            call: balances::Call::Transfer { to: 2, amount }.into(),
+        }
+    }
+
+    #[test]
+    fn test_nodes_converge_after_a_partition_heals() {
+        let genesis = GenesisConfig {
+            accounts: vec![GenesisAccount {
+                who: 1,
+                balance: 1000,
+                bond: 0,
+            }],
+        };
+        let mut testnet = Testnet::new(3, &genesis).unwrap();
+        testnet.submit(0, transfer(0, 100)).unwrap();
+        testnet.produce_blocks(2).unwrap();
+        assert!(testnet.in_sync());
+        assert!(testnet.nodes().iter().all(|node| node.pool().is_empty()));
+        assert_eq!(
+            testnet
+                .node(2)
+                .unwrap()
+                .runtime
+                .balances
+                .get_free_balance(2),
+            100u64
+        );
+
+        // Node 2 is cut off, so its transfer only lands on its own fork
+        testnet.partition(&[&[0, 1]]);
+        testnet.submit(2, transfer(1, 50)).unwrap();
+        testnet.produce_blocks(1).unwrap();
+        assert!(!testnet.in_sync());
+        testnet.submit(0, transfer(1, 10)).unwrap();
+        testnet.produce_blocks(1).unwrap();
+
+        // Both forks are four blocks long, so the lowest node's fork wins
+        testnet.heal();
+        assert!(testnet.in_sync());
+        let node = testnet.node(2).unwrap();
+        assert_eq!(node.chain.len(), 4);
+        assert_eq!(node.runtime.balances.get_free_balance(2), 110u64);
+        assert_eq!(
+            testnet.submit(3, transfer(2, 1)),
+            Err(TestnetError::UnknownNode)
+        );
+    }
+}
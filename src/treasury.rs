@@ -0,0 +1,666 @@
+use crate::balances::{BalancesError, BalancesPallet};
+use crate::codec::{CodecError, Decode, Encode};
+use crate::currency::format_balance;
+use crate::governance::{
+    GovernanceConfig, GovernanceError, GovernancePallet, ProposalStatus, ProposalTemplate,
+};
+use crate::labels::{display_account, AccountLabels};
+use crate::staking::{StakingError, StakingPallet};
+use crate::support::{pallet_error, GetWeight, StorageUsage, Weight};
+use crate::system::{SystemConfig, SystemPallet};
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+use std::collections::HashMap;
+
+// Spends from the treasury pot go through a governance proposal, and are paid out
+// only once that proposal is approved
+pub trait TreasuryConfig: GovernanceConfig + SystemConfig<RuntimeEvent: From<Event<Self>>> {
+    // Account holding the treasury's funds
+    const POT_ACCOUNT: Self::AccountId;
+    // Most of the treasury's funds, pot and investments together, that may be
+    // nominated through the staking strategy, in parts per million
+    const MAX_STAKING_SHARE: u32;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreasuryError {
+    SpendNotFound,
+    ProposalNotApproved,
+    AlreadyPaid,
+    Overflow,
+    // Creating the spend proposal failed
+    Governance(GovernanceError),
+    // Moving funds out of the pot failed
+    Balances(BalancesError),
+    // The investment would take the strategy over its share of the treasury
+    StrategyCapExceeded,
+    // The treasury has nothing invested to divest
+    NotInvested,
+    // Nominating or unbonding the pot's funds failed
+    Staking(StakingError),
+}
+
+pallet_error!(TreasuryError {
+    0 => SpendNotFound: "No spend has this id",
+    1 => ProposalNotApproved: "The spend's proposal has not been approved",
+    2 => AlreadyPaid: "The spend has already been paid out",
+    3 => Overflow: "An arithmetic operation overflowed",
+    4 => Governance(_): "Creating the spend proposal failed",
+    5 => Balances(_): "Moving funds out of the pot failed",
+    6 => StrategyCapExceeded: "The investment would take the strategy over its share of the treasury",
+    7 => NotInvested: "The treasury has nothing invested to divest",
+    8 => Staking(_): "Nominating or unbonding the pot's funds failed",
+});
+
+impl From<GovernanceError> for TreasuryError {
+    fn from(error: GovernanceError) -> Self {
+        TreasuryError::Governance(error)
+    }
+}
+
+impl From<BalancesError> for TreasuryError {
+    fn from(error: BalancesError) -> Self {
+        TreasuryError::Balances(error)
+    }
+}
+
+impl From<StakingError> for TreasuryError {
+    fn from(error: StakingError) -> Self {
+        TreasuryError::Staking(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<T: TreasuryConfig> {
+    SpendProposed {
+        spend_id: u32,
+        proposal_id: u32,
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    },
+    SpendPaid {
+        spend_id: u32,
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    },
+    SpendForced {
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    },
+    Invested {
+        targets: Vec<T::AccountId>,
+        amount: T::Balance,
+    },
+    // `amount` is unbonding back into the pot, `returns` of it earned by the strategy
+    Divested {
+        amount: T::Balance,
+        returns: T::Balance,
+    },
+}
+
+impl<T: TreasuryConfig> Event<T> {
+    // Render the event as a sentence, e.g. "treasury paid 4 UNIT to alice (spend 0)"
+    pub fn describe(&self, labels: &impl AccountLabels<T::AccountId>) -> String {
+        match self {
+            Event::SpendProposed {
+                spend_id,
+                proposal_id,
+                beneficiary,
+                amount,
+            } => format!(
+                "spend {} of {} to {} awaits proposal {}",
+                spend_id,
+                format_balance::<T>(*amount),
+                display_account(labels, beneficiary),
+                proposal_id
+            ),
+            Event::SpendPaid {
+                spend_id,
+                beneficiary,
+                amount,
+            } => format!(
+                "treasury paid {} to {} (spend {})",
+                format_balance::<T>(*amount),
+                display_account(labels, beneficiary),
+                spend_id
+            ),
+            Event::SpendForced {
+                beneficiary,
+                amount,
+            } => format!(
+                "treasury paid {} to {} without a proposal",
+                format_balance::<T>(*amount),
+                display_account(labels, beneficiary)
+            ),
+            Event::Invested { targets, amount } => format!(
+                "treasury nominated {} with {}",
+                targets
+                    .iter()
+                    .map(|target| display_account(labels, target))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                format_balance::<T>(*amount)
+            ),
+            Event::Divested { amount, returns } => format!(
+                "treasury divested {}, {} of it returns",
+                format_balance::<T>(*amount),
+                format_balance::<T>(*returns)
+            ),
+        }
+    }
+}
+
+// Calls an account can submit to the treasury pallet
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T: TreasuryConfig> {
+    ProposeSpend {
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    },
+    Payout {
+        spend_id: u32,
+    },
+    // Pay from the pot straight away; needs a Root or Council origin
+    ForceSpend {
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    },
+    // Nominate `targets` with pot funds, within MAX_STAKING_SHARE; needs a Root or
+    // Council origin
+    Invest {
+        targets: Vec<T::AccountId>,
+        amount: T::Balance,
+    },
+    // Unbond everything invested straight away; needs a Root or Council origin
+    Divest,
+    // Return unbonded investments to the pot; anyone may trigger it
+    Reclaim,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spend<T: TreasuryConfig> {
+    pub proposer: T::AccountId,
+    pub beneficiary: T::AccountId,
+    pub amount: T::Balance,
+    // Governance proposal that has to approve the spend
+    pub proposal_id: u32,
+    pub paid: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreasuryPallet<T: TreasuryConfig> {
+    pub spends: HashMap<u32, Spend<T>>,
+    next_spend_id: u32,
+    // Principal currently nominated through the staking strategy
+    invested: T::Balance,
+    // Everything the strategy earned on top of the principal, counted at divestment
+    returns: T::Balance,
+}
+
+impl<T: TreasuryConfig> Default for TreasuryPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: TreasuryConfig> TreasuryPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            spends: HashMap::new(),
+            next_spend_id: 0,
+            invested: T::Balance::zero(),
+            returns: T::Balance::zero(),
+        }
+    }
+
+    // Route a call signed by `origin` to the matching method
+    pub fn dispatch(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        staking: &mut StakingPallet<T>,
+        governance: &mut GovernancePallet<T>,
+        origin: T::AccountId,
+        call: Call<T>,
+    ) -> Result<(), TreasuryError> {
+        match call {
+            Call::ProposeSpend {
+                beneficiary,
+                amount,
+            } => {
+                self.propose_spend(system, governance, origin, beneficiary, amount)?;
+            }
+            Call::Payout { spend_id } => self.payout(system, balances, governance, spend_id)?,
+            Call::ForceSpend {
+                beneficiary,
+                amount,
+            } => Self::force_spend(system, balances, beneficiary, amount)?,
+            Call::Invest { targets, amount } => {
+                self.invest(system, balances, staking, targets, amount)?
+            }
+            Call::Divest => self.divest(system, staking)?,
+            Call::Reclaim => Self::reclaim(system, balances, staking)?,
+        }
+
+        Ok(())
+    }
+
+    // Request a spend; this opens a treasury spend proposal in governance
+    pub fn propose_spend(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        governance: &mut GovernancePallet<T>,
+        proposer: T::AccountId,
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<u32, TreasuryError> {
+        let spend_id = self.next_spend_id;
+        let next_spend_id = spend_id.checked_add(1).ok_or(TreasuryError::Overflow)?;
+
+        let proposal_id = governance.create_proposal_from_template(
+            system,
+            proposer.clone(),
+            ProposalTemplate::TreasurySpend {
+                to: beneficiary.clone(),
+                amount,
+            },
+        )?;
+
+        self.next_spend_id = next_spend_id;
+        self.spends.insert(
+            spend_id,
+            Spend {
+                proposer,
+                beneficiary: beneficiary.clone(),
+                amount,
+                proposal_id,
+                paid: false,
+            },
+        );
+        system.deposit_event(Event::SpendProposed {
+            spend_id,
+            proposal_id,
+            beneficiary,
+            amount,
+        });
+
+        Ok(spend_id)
+    }
+
+    // Pay an approved spend from the pot; anyone may trigger it
+    pub fn payout(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        governance: &GovernancePallet<T>,
+        spend_id: u32,
+    ) -> Result<(), TreasuryError> {
+        let spend = self
+            .spends
+            .get_mut(&spend_id)
+            .ok_or(TreasuryError::SpendNotFound)?;
+        if spend.paid {
+            return Err(TreasuryError::AlreadyPaid);
+        }
+        if governance.get_proposal_status(spend.proposal_id) != Some(&ProposalStatus::Approved) {
+            return Err(TreasuryError::ProposalNotApproved);
+        }
+
+        balances.transfer(
+            system,
+            T::POT_ACCOUNT,
+            spend.beneficiary.clone(),
+            spend.amount,
+        )?;
+        spend.paid = true;
+        system.deposit_event(Event::SpendPaid {
+            spend_id,
+            beneficiary: spend.beneficiary.clone(),
+            amount: spend.amount,
+        });
+
+        Ok(())
+    }
+
+    // Pay from the pot without a proposal. The runtime only routes this for a Root
+    // or Council origin, which stands in for the referendum.
+    pub fn force_spend(
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), TreasuryError> {
+        balances.transfer(system, T::POT_ACCOUNT, beneficiary.clone(), amount)?;
+        system.deposit_event(Event::SpendForced {
+            beneficiary,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Nominate `targets` with `amount` from the pot. The runtime only routes this for
+    // a Root or Council origin, which stands in for the referendum.
+    pub fn invest(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        staking: &mut StakingPallet<T>,
+        targets: Vec<T::AccountId>,
+        amount: T::Balance,
+    ) -> Result<(), TreasuryError> {
+        let nominated = staking
+            .get_nominated_balance(T::POT_ACCOUNT)
+            .ok_or(TreasuryError::Overflow)?;
+        let total: u128 = Self::pot(balances)
+            .checked_add(&nominated)
+            .ok_or(TreasuryError::Overflow)?
+            .into();
+        let cap = total
+            .checked_mul(u128::from(T::MAX_STAKING_SHARE.min(1_000_000)))
+            .and_then(|scaled| scaled.checked_div(1_000_000))
+            .ok_or(TreasuryError::Overflow)?;
+        let after: u128 = nominated
+            .checked_add(&amount)
+            .ok_or(TreasuryError::Overflow)?
+            .into();
+        if after > cap {
+            return Err(TreasuryError::StrategyCapExceeded);
+        }
+        let invested = self
+            .invested
+            .checked_add(&amount)
+            .ok_or(TreasuryError::Overflow)?;
+
+        staking.nominate(system, balances, T::POT_ACCOUNT, targets.clone(), amount)?;
+        self.invested = invested;
+        system.deposit_event(Event::Invested { targets, amount });
+
+        Ok(())
+    }
+
+    // Unbond the whole staking position, rewards included, and book whatever it
+    // holds beyond the principal as returns. A slashed position books none.
+    pub fn divest(
+        &mut self,
+        system: &mut SystemPallet<T>,
+        staking: &mut StakingPallet<T>,
+    ) -> Result<(), TreasuryError> {
+        if staking.nominations_of(T::POT_ACCOUNT).is_empty() {
+            return Err(TreasuryError::NotInvested);
+        }
+        let amount = staking
+            .get_nominated_balance(T::POT_ACCOUNT)
+            .ok_or(TreasuryError::Overflow)?;
+        let returns = amount
+            .checked_sub(&self.invested)
+            .unwrap_or_else(T::Balance::zero);
+        let total_returns = self
+            .returns
+            .checked_add(&returns)
+            .ok_or(TreasuryError::Overflow)?;
+
+        staking.chill(system, T::POT_ACCOUNT)?;
+        self.invested = T::Balance::zero();
+        self.returns = total_returns;
+        system.deposit_event(Event::Divested { amount, returns });
+
+        Ok(())
+    }
+
+    // Move divested funds whose unbonding period is over back into the pot
+    pub fn reclaim(
+        system: &mut SystemPallet<T>,
+        balances: &mut BalancesPallet<T>,
+        staking: &mut StakingPallet<T>,
+    ) -> Result<(), TreasuryError> {
+        staking.withdraw_unbonded(system, balances, T::POT_ACCOUNT)?;
+        Ok(())
+    }
+
+    // Get the principal currently invested
+    pub fn invested(&self) -> T::Balance {
+        self.invested
+    }
+
+    // Get the returns booked by divestments so far
+    pub fn returns(&self) -> T::Balance {
+        self.returns
+    }
+
+    // Get the funds available in the pot
+    pub fn pot(balances: &BalancesPallet<T>) -> T::Balance {
+        balances.get_usable_balance(T::POT_ACCOUNT)
+    }
+
+    // Approximate state held by this pallet
+    pub fn storage_usage(&self) -> StorageUsage {
+        StorageUsage::value::<u32>(0)
+            .saturating_add(StorageUsage::value::<T::Balance>(0))
+            .saturating_add(StorageUsage::value::<T::Balance>(0))
+            .saturating_add(StorageUsage::map(&self.spends))
+    }
+}
+
+impl<T: TreasuryConfig> GetWeight for Call<T> {
+    fn weight(&self) -> Weight {
+        match self {
+            Call::ProposeSpend { .. } | Call::ForceSpend { .. } | Call::Divest => 3,
+            Call::Payout { .. } | Call::Invest { .. } | Call::Reclaim => 4,
+        }
+    }
+}
+
+impl<T: TreasuryConfig> Encode for Call<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Call::ProposeSpend {
+                beneficiary,
+                amount,
+            } => {
+                0u8.encode_to(dest);
+                beneficiary.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::Payout { spend_id } => {
+                1u8.encode_to(dest);
+                spend_id.encode_to(dest);
+            }
+            Call::ForceSpend {
+                beneficiary,
+                amount,
+            } => {
+                2u8.encode_to(dest);
+                beneficiary.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::Invest { targets, amount } => {
+                3u8.encode_to(dest);
+                targets.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Call::Divest => 4u8.encode_to(dest),
+            Call::Reclaim => 5u8.encode_to(dest),
+        }
+    }
+}
+
+impl<T: TreasuryConfig> Decode for Call<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(Call::ProposeSpend {
+                beneficiary: Decode::decode(input)?,
+                amount: Decode::decode(input)?,
+            }),
+            1 => Ok(Call::Payout {
+                spend_id: Decode::decode(input)?,
+            }),
+            2 => Ok(Call::ForceSpend {
+                beneficiary: Decode::decode(input)?,
+                amount: Decode::decode(input)?,
+            }),
+            3 => Ok(Call::Invest {
+                targets: Decode::decode(input)?,
+                amount: Decode::decode(input)?,
+            }),
+            4 => Ok(Call::Divest),
+            5 => Ok(Call::Reclaim),
+            _ => Err(CodecError::InvalidTag),
+        }
+    }
+}
+
+impl<T: TreasuryConfig> Encode for Spend<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.proposer.encode_to(dest);
+        self.beneficiary.encode_to(dest);
+        self.amount.encode_to(dest);
+        self.proposal_id.encode_to(dest);
+        self.paid.encode_to(dest);
+    }
+}
+
+impl<T: TreasuryConfig> Decode for Spend<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            proposer: Decode::decode(input)?,
+            beneficiary: Decode::decode(input)?,
+            amount: Decode::decode(input)?,
+            proposal_id: Decode::decode(input)?,
+            paid: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<T: TreasuryConfig> Encode for TreasuryPallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.spends.encode_to(dest);
+        self.next_spend_id.encode_to(dest);
+        self.invested.encode_to(dest);
+        self.returns.encode_to(dest);
+    }
+}
+
+impl<T: TreasuryConfig> Decode for TreasuryPallet<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self {
+            spends: Decode::decode(input)?,
+            next_spend_id: Decode::decode(input)?,
+            invested: Decode::decode(input)?,
+            returns: Decode::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::balances::BalancesPallet;
+    use crate::{staking, Runtime, RuntimeError};
+
+    #[test]
+    fn test_spend_needs_approval() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(alice, 1000).unwrap();
+        runtime.balances.set_balance(pot, 500).unwrap();
+        runtime
+            .dispatch(alice, staking::Call::Stake { amount: 1000 }.into())
+            .unwrap();
+
+        runtime
+            .dispatch(
+                alice,
+                Call::ProposeSpend {
+                    beneficiary: bob,
+                    amount: 200,
+                }
+                .into(),
+            )
+            .unwrap();
+        let proposal_id = runtime.treasury.spends[&0].proposal_id;
+
+        // Nothing moves while the proposal is open
+        let result = runtime.dispatch(bob, Call::Payout { spend_id: 0 }.into());
+        assert_eq!(
+            result,
+            Err(RuntimeError::Treasury(TreasuryError::ProposalNotApproved))
+        );
+
+        runtime
+            .dispatch(
+                alice,
+                crate::governance::Call::Vote {
+                    proposal_id,
+                    vote_type: true,
+                    conviction: crate::governance::Conviction::None,
+                }
+                .into(),
+            )
+            .unwrap();
+        runtime
+            .dispatch_as(
+                crate::Origin::Council,
+                crate::governance::Call::FinalizeProposal { proposal_id }.into(),
+            )
+            .unwrap();
+
+        runtime
+            .dispatch(bob, Call::Payout { spend_id: 0 }.into())
+            .unwrap();
+        assert_eq!(runtime.balances.get_free_balance(bob), 200u64);
+        assert_eq!(TreasuryPallet::<Runtime>::pot(&runtime.balances), 300u64);
+
+        // A spend is paid only once
+        let result = runtime.dispatch(bob, Call::Payout { spend_id: 0 }.into());
+        assert_eq!(
+            result,
+            Err(RuntimeError::Treasury(TreasuryError::AlreadyPaid))
+        );
+    }
+
+    #[test]
+    fn test_invest_and_divest_pot_funds() {
+        let alice = 1u64;
+        let pot = <Runtime as TreasuryConfig>::POT_ACCOUNT;
+        let mut system = SystemPallet::<Runtime>::new();
+        let mut balances = BalancesPallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut treasury = TreasuryPallet::<Runtime>::new();
+        balances.set_balance(alice, 1000).unwrap();
+        balances.set_balance(pot, 1000).unwrap();
+        staking
+            .stake(&mut system, &mut balances, alice, 1000)
+            .unwrap();
+
+        // Half the treasury may be invested
+        let result = treasury.invest(&mut system, &mut balances, &mut staking, vec![alice], 600);
+        assert_eq!(result, Err(TreasuryError::StrategyCapExceeded));
+        treasury
+            .invest(&mut system, &mut balances, &mut staking, vec![alice], 500)
+            .unwrap();
+        assert_eq!(treasury.invested(), 500u64);
+        assert_eq!(TreasuryPallet::<Runtime>::pot(&balances), 500u64);
+
+        // An era's rewards compound into the position and come back as returns
+        staking.on_finalize(&mut system, &mut balances, 100);
+        let position = staking.get_nominated_balance(pot).unwrap();
+        assert!(position > 500);
+        treasury.divest(&mut system, &mut staking).unwrap();
+        assert_eq!(treasury.invested(), 0u64);
+        assert_eq!(treasury.returns(), position - 500);
+        assert_eq!(
+            treasury.divest(&mut system, &mut staking),
+            Err(TreasuryError::NotInvested)
+        );
+
+        // The funds reach the pot once unbonded
+        let bonding = <Runtime as crate::staking::StakingConfig>::BONDING_DURATION;
+        for number in 1..=bonding {
+            system.initialize(number).unwrap();
+        }
+        TreasuryPallet::reclaim(&mut system, &mut balances, &mut staking).unwrap();
+        assert_eq!(TreasuryPallet::<Runtime>::pot(&balances), 500 + position);
+    }
+}